@@ -189,7 +189,7 @@ pub async fn spawn_agent(
     let mut request_data = HashMap::new();
     request_data.insert("type".to_string(), agent_type.to_string());
     let capabilities_json = serde_json::to_string(&capabilities).map_err(|e| {
-        crate::errors::NeuralBridgeError::api(format!("Failed to serialize capabilities: {}", e))
+        crate::errors::NeuralBridgeError::api(format!("Failed to serialize capabilities: {}", e), None)
     })?;
     request_data.insert("capabilities".to_string(), capabilities_json);
     request_data.insert("swarmId".to_string(), swarm_id.to_string());
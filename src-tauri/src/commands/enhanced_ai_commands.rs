@@ -7,10 +7,16 @@ use crate::orchestration::{
     get_enhanced_orchestration_info, ClaudeFlowService, CodexService, EnhancedOrchestrationConfig,
     ExecutionRequest, OrchestrationService,
 };
+use crate::orchestration::enhanced::{
+    AdaptiveContextManager, AdaptiveWorkflowConfig, AdaptiveWorkflowOrchestrator,
+    AdvancedExecutionRequest, AdvancedExecutionResponse, ContextData, ContextManagerConfig,
+    OpenRouterService, WorkflowBackends, WorkflowWatcher,
+};
+use crate::plugins::global_shortcuts::{register_shortcut_action, ShortcutAction, ShortcutScope};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use tauri::{command, State};
+use tauri::{command, AppHandle, Runtime, State};
 use uuid::Uuid;
 
 /// Simplified types for enhanced orchestration
@@ -65,6 +71,12 @@ pub struct WorkflowExecution {
 /// Enhanced AI orchestration state
 pub struct EnhancedAiState {
     pub orchestration: Arc<Mutex<OrchestrationService>>,
+    pub openrouter: Arc<OpenRouterService>,
+    /// Conversation histories that OpenRouter requests continue via `context_id`
+    pub context_manager: Arc<AdaptiveContextManager>,
+    /// Adaptive workflows, with the patterns defined in `config.workflow_directory`
+    pub workflows: Arc<AdaptiveWorkflowOrchestrator>,
+    workflow_watcher: Mutex<Option<WorkflowWatcher>>,
     pub config: EnhancedOrchestrationConfig,
 }
 
@@ -75,15 +87,74 @@ impl Default for EnhancedAiState {
         // Create base orchestration service
         let claude_flow = ClaudeFlowService::new();
         let codex = CodexService::new();
-        let orchestration = OrchestrationService::new(claude_flow, codex);
+        let orchestration = OrchestrationService::new(claude_flow.clone(), codex.clone());
+
+        let context_manager = Arc::new(AdaptiveContextManager::new(ContextManagerConfig::default()));
+        let openrouter = Arc::new(
+            OpenRouterService::new(config.openrouter_api_key.clone())
+                .with_context_manager(context_manager.clone()),
+        );
+
+        let workflows = AdaptiveWorkflowOrchestrator::new(AdaptiveWorkflowConfig::default())
+            .with_backends(WorkflowBackends {
+                claude_flow: Some(claude_flow),
+                codex: Some(codex),
+                openrouter: Some(openrouter.clone()),
+            });
 
         Self {
             orchestration: Arc::new(Mutex::new(orchestration)),
+            openrouter,
+            context_manager,
+            workflows: Arc::new(workflows),
+            workflow_watcher: Mutex::new(None),
             config,
         }
     }
 }
 
+impl EnhancedAiState {
+    /// Register the workflow definitions in `config.workflow_directory` and follow changes to them
+    pub async fn load_workflow_definitions(&self) -> Result<(), String> {
+        let directory = &self.config.workflow_directory;
+        tokio::fs::create_dir_all(directory)
+            .await
+            .map_err(|e| format!("Failed to create workflow directory {}: {}", directory, e))?;
+
+        let (report, watcher) = self
+            .workflows
+            .load_workflow_definitions(directory)
+            .await
+            .map_err(|e| format!("Failed to load workflow definitions: {}", e))?;
+        for error in &report.errors {
+            tracing::warn!("Skipped workflow definition {}", error);
+        }
+
+        *self.workflow_watcher.lock().map_err(|e| e.to_string())? = Some(watcher);
+        Ok(())
+    }
+
+    /// Offer orchestration actions for keyboard shortcuts
+    pub fn register_shortcut_actions<R: Runtime>(&self, app: &AppHandle<R>) -> Result<(), String> {
+        let openrouter = self.openrouter.clone();
+        register_shortcut_action(
+            app,
+            ShortcutAction {
+                id: "orchestration.cancel_streams".to_string(),
+                label: "Cancel all streaming AI requests".to_string(),
+                source: "orchestration".to_string(),
+                default_accelerator: Some("CmdOrCtrl+Shift+Period".to_string()),
+                default_scope: ShortcutScope::App,
+            },
+            Some(Arc::new(move || {
+                let cancelled = openrouter.cancel_all_streams();
+                tracing::info!("Cancelled {} streaming requests from a shortcut", cancelled);
+                Ok(())
+            })),
+        )
+    }
+}
+
 // All the enhanced AI orchestration commands with simplified implementations
 
 #[command]
@@ -361,3 +432,69 @@ pub async fn test_enhanced_orchestration(
         Err(e) => Err(format!("Enhanced orchestration test failed: {}", e)),
     }
 }
+
+/// Run a request through OpenRouter, routing it when no model is given
+///
+/// With `generation.stream` set, deltas are forwarded to the frontend as
+/// progress events while the request runs.
+#[command]
+pub async fn execute_openrouter_request(
+    app: tauri::AppHandle,
+    model_id: Option<String>,
+    request: AdvancedExecutionRequest,
+    state: State<'_, EnhancedAiState>,
+) -> Result<AdvancedExecutionResponse, String> {
+    let openrouter = state.openrouter.clone();
+
+    let model_id = match model_id {
+        Some(model_id) => model_id,
+        None => {
+            openrouter
+                .route_request(&request)
+                .await
+                .map_err(|e| format!("Failed to route OpenRouter request: {}", e))?
+                .selected_model
+        }
+    };
+
+    let result = if request.generation.stream {
+        openrouter
+            .execute_request_streaming_to_app(&app, &model_id, &request)
+            .await
+    } else {
+        openrouter.execute_request(&model_id, &request).await
+    };
+
+    result.map_err(|e| format!("OpenRouter request failed: {}", e))
+}
+
+/// Cancel a streaming OpenRouter request, returning whether it was still running
+#[command]
+pub async fn cancel_openrouter_stream(
+    request_id: String,
+    state: State<'_, EnhancedAiState>,
+) -> Result<bool, String> {
+    Ok(state.openrouter.cancel_stream(&request_id).await)
+}
+
+/// Start a managed conversation for OpenRouter requests, returning its `context_id`
+#[command]
+pub async fn create_openrouter_context(
+    system_prompt: Option<String>,
+    state: State<'_, EnhancedAiState>,
+) -> Result<String, String> {
+    let initial_data = ContextData {
+        messages: Default::default(),
+        system_prompt: system_prompt.unwrap_or_default(),
+        variables: HashMap::new(),
+        attachments: Vec::new(),
+        total_tokens: 0,
+        compressed_segments: Vec::new(),
+    };
+
+    state
+        .context_manager
+        .create_context(Uuid::new_v4().to_string(), initial_data)
+        .await
+        .map_err(|e| format!("Failed to create context: {}", e))
+}
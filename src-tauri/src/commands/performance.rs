@@ -280,6 +280,20 @@ pub async fn stop_performance_profiling() -> Result<String, String> {
     }
 }
 
+#[tauri::command]
+pub async fn export_flame_graph() -> Result<String, String> {
+    performance::profiler::export_flame_graph()
+        .await
+        .map_err(|e| format!("Failed to export flame graph: {}", e))
+}
+
+#[tauri::command]
+pub async fn export_flame_graph_svg() -> Result<String, String> {
+    performance::profiler::export_flame_graph_svg()
+        .await
+        .map_err(|e| format!("Failed to render flame graph: {}", e))
+}
+
 #[tauri::command]
 pub async fn optimize_memory_usage() -> Result<String, String> {
     info!("Running memory optimization");
@@ -371,7 +385,8 @@ pub async fn execute_database_query_optimized(
 ) -> Result<String, String> {
     let params: Vec<&str> = parameters.iter().map(|s| s.as_str()).collect();
 
-    match performance::database::execute_optimized_query(&query, &params).await {
+    // Exposed to the webview, so only reads are allowed
+    match performance::database::execute_optimized_read_query(&query, &params).await {
         Ok(result) => Ok(result),
         Err(e) => Err(format!("Database query failed: {}", e)),
     }
@@ -1,10 +1,36 @@
 // AutoDev-AI Neural Bridge Platform - Database Backup
 //! Database backup and restore functionality
-
+//!
+//! Full backups are page-for-page snapshots taken with SQLite's online backup
+//! API, so they are consistent even while the application keeps writing.
+//! Incremental backups store only the pages that differ from the previous
+//! backup, full or incremental, which keeps nightly backups of a large, mostly
+//! append-only history database small. Restoring one replays its chain of
+//! incrementals on top of the full backup the chain starts from, so a backup
+//! cannot be deleted while a later one is based on it. Either kind can be
+//! gzip-compressed.
+//!
+//! Each backup `<name>` consists of a data file (`<name>.db`, `<name>.inc` or
+//! `<name>.sql`, with a `.gz` suffix when compressed) and a `<name>.json`
+//! metadata file. Names are limited to ASCII letters, digits, `_` and `-`.
+
+use super::{global_database, Database};
 use crate::errors::{NeuralBridgeError, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use tokio::fs;
-use tracing::{error, info, warn};
+use tracing::{info, warn};
+
+/// Magic header of incremental backup files
+const INCREMENTAL_MAGIC: &[u8; 8] = b"NBINC\x00\x00\x01";
+
+/// Header every SQLite database file starts with
+const SQLITE_MAGIC: &[u8; 16] = b"SQLite format 3\0";
 
 /// Backup configuration
 #[derive(Debug, Clone)]
@@ -31,86 +57,484 @@ impl Default for BackupConfig {
 pub struct BackupMetadata {
     pub name: String,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Size of the stored (possibly compressed) backup file
     pub size_bytes: u64,
+    /// SHA-256 of the stored backup file
     pub checksum: String,
     pub database_version: String,
     pub backup_type: BackupType,
+    /// Name of the stored backup file inside the backup directory
+    #[serde(default)]
+    pub file_name: String,
+    #[serde(default)]
+    pub compressed: bool,
+    /// Backup an incremental backup was taken against
+    #[serde(default)]
+    pub base_backup: Option<String>,
+    /// Size of the database the backup restores to
+    #[serde(default)]
+    pub content_size: u64,
+    /// SHA-256 of the database the backup restores to
+    #[serde(default)]
+    pub content_checksum: String,
+    #[serde(default)]
+    pub page_size: u32,
 }
 
 /// Backup type
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum BackupType {
     Full,
     Incremental,
     Schema,
 }
 
-/// Create a backup of the database
-pub async fn create_backup(backup_path: Option<String>) -> Result<String> {
-    info!("Creating database backup");
+impl BackupType {
+    fn extension(&self) -> &'static str {
+        match self {
+            BackupType::Full => "db",
+            BackupType::Incremental => "inc",
+            BackupType::Schema => "sql",
+        }
+    }
+}
 
-    let config = BackupConfig::default();
+/// Creates, restores and prunes backups of a database
+pub struct BackupManager {
+    database: Database,
+    config: BackupConfig,
+}
 
-    // Ensure backup directory exists
-    fs::create_dir_all(&config.backup_dir).await.map_err(|e| {
-        NeuralBridgeError::database(format!("Failed to create backup directory: {}", e))
-    })?;
+impl BackupManager {
+    /// Create a backup manager for the given database
+    pub fn new(database: Database, config: BackupConfig) -> Self {
+        Self { database, config }
+    }
 
-    // Generate backup filename if not provided
-    let backup_filename = match backup_path {
-        Some(path) => PathBuf::from(path),
-        None => {
-            let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
-            config
+    /// Backup configuration
+    pub fn config(&self) -> &BackupConfig {
+        &self.config
+    }
+
+    /// Create a backup, choosing incremental when enabled and a previous backup exists
+    pub async fn create_backup(&self, name: Option<String>) -> Result<BackupMetadata> {
+        let backup_type =
+            if self.config.incremental_backups && self.latest_database_backup().await?.is_some() {
+                BackupType::Incremental
+            } else {
+                BackupType::Full
+            };
+
+        self.create_backup_of_type(name, backup_type).await
+    }
+
+    /// Create a backup of a specific type
+    pub async fn create_backup_of_type(
+        &self,
+        name: Option<String>,
+        backup_type: BackupType,
+    ) -> Result<BackupMetadata> {
+        info!("Creating {:?} database backup", backup_type);
+
+        // Ensure backup directory exists
+        fs::create_dir_all(&self.config.backup_dir)
+            .await
+            .map_err(|e| {
+                NeuralBridgeError::database(format!("Failed to create backup directory: {}", e))
+            })?;
+
+        // Generate backup name if not provided
+        let name = name.unwrap_or_else(|| {
+            let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S_%6f");
+            format!("neural_bridge_backup_{}", timestamp)
+        });
+
+        if self.metadata_path(&name)?.exists() {
+            return Err(NeuralBridgeError::database(format!(
+                "Backup already exists: {}",
+                name
+            )));
+        }
+
+        let chain = match backup_type {
+            BackupType::Incremental => {
+                let base = self.latest_database_backup().await?.ok_or_else(|| {
+                    NeuralBridgeError::database("Incremental backup requires an existing backup")
+                })?;
+                self.backup_chain(base).await?
+            }
+            _ => Vec::new(),
+        };
+
+        let metadata = match backup_type {
+            BackupType::Schema => self.create_schema_backup(&name).await?,
+            _ => self.create_database_backup(&name, chain).await?,
+        };
+
+        // Create metadata file
+        save_backup_metadata(&self.metadata_path(&name)?, &metadata).await?;
+
+        // Cleanup old backups if needed
+        self.cleanup_old_backups().await?;
+
+        info!(
+            "Backup created successfully: {} ({} bytes)",
+            metadata.file_name, metadata.size_bytes
+        );
+        Ok(metadata)
+    }
+
+    /// Restore the database from a named backup
+    ///
+    /// The backup, together with the backups it is based on, is rebuilt into
+    /// a temporary file next to the live database
+    /// and integrity-checked before it replaces the live file with a rename,
+    /// so a failed restore leaves the current database untouched.
+    pub async fn restore_backup(&self, name: &str) -> Result<()> {
+        info!("Restoring database from backup: {}", name);
+
+        let metadata = self.verified_metadata(name).await?;
+        if metadata.backup_type == BackupType::Schema {
+            return Err(NeuralBridgeError::database(
+                "Schema-only backups cannot be restored as a database",
+            ));
+        }
+
+        let chain = self.backup_chain(metadata.clone()).await?;
+
+        info!(
+            "Restoring backup: {} (created: {})",
+            metadata.name, metadata.created_at
+        );
+
+        let live_path = self.database.config().sqlite_path()?;
+        let staging_path = match &live_path {
+            Some(path) => PathBuf::from(format!("{}.restore", path.display())),
+            None => self
+                .config
                 .backup_dir
-                .join(format!("neural_bridge_backup_{}.db", timestamp))
+                .join(format!(".{}.restore", metadata.name)),
+        };
+
+        let backup_dir = self.config.backup_dir.clone();
+        let staging = staging_path.clone();
+        let rebuilt = run_blocking(move || {
+            rebuild_database(&backup_dir, &chain, &staging)?;
+            check_database_file(&staging)
+        })
+        .await;
+
+        if let Err(e) = rebuilt {
+            let _ = fs::remove_file(&staging_path).await;
+            return Err(e);
         }
-    };
 
-    // Create backup
-    let backup_result = create_database_backup(&backup_filename, &config).await?;
+        match live_path {
+            Some(live_path) => {
+                self.database.close();
+                for suffix in ["-wal", "-shm"] {
+                    let sidecar = PathBuf::from(format!("{}{}", live_path.display(), suffix));
+                    if let Err(e) = fs::remove_file(&sidecar).await {
+                        if e.kind() != io::ErrorKind::NotFound {
+                            warn!("Failed to remove {}: {}", sidecar.display(), e);
+                        }
+                    }
+                }
+
+                let swapped = fs::rename(&staging_path, &live_path).await;
+                // Reconnect regardless so a failed swap keeps the old database usable
+                self.database.initialize().await?;
+                swapped.map_err(|e| {
+                    NeuralBridgeError::database(format!(
+                        "Failed to swap in restored database: {}",
+                        e
+                    ))
+                })?;
+            }
+            None => {
+                let staging = staging_path.clone();
+                let restored = self
+                    .database
+                    .with_connection(move |conn| {
+                        conn.restore(DatabaseName::Main, &staging, None::<fn(_)>)
+                    })
+                    .await;
+                let _ = fs::remove_file(&staging_path).await;
+                restored?;
+            }
+        }
 
-    // Create metadata file
-    let metadata_path = backup_filename.with_extension("json");
-    save_backup_metadata(&metadata_path, &backup_result).await?;
+        info!("Database restored successfully from backup");
+        Ok(())
+    }
 
-    // Cleanup old backups if needed
-    cleanup_old_backups(&config).await?;
+    /// List available backups, newest first
+    pub async fn list_backups(&self) -> Result<Vec<BackupMetadata>> {
+        list_backups_in(&self.config.backup_dir).await
+    }
 
-    info!("Backup created successfully: {}", backup_filename.display());
-    Ok(backup_filename.to_string_lossy().to_string())
-}
+    /// Delete a specific backup
+    ///
+    /// Backups that later incremental backups are based on are refused, as
+    /// those could no longer be restored.
+    pub async fn delete_backup(&self, name: &str) -> Result<()> {
+        info!("Deleting backup: {}", name);
+
+        let metadata_path = self.metadata_path(name)?;
+        let dependents: Vec<String> = self
+            .list_backups()
+            .await?
+            .into_iter()
+            .filter(|b| b.base_backup.as_deref() == Some(name))
+            .map(|b| b.name)
+            .collect();
+        if !dependents.is_empty() {
+            return Err(NeuralBridgeError::database(format!(
+                "Backup {} is the base of {}; delete those first",
+                name,
+                dependents.join(", ")
+            )));
+        }
 
-/// Restore database from backup
-pub async fn restore_backup(backup_path: &str) -> Result<()> {
-    info!("Restoring database from backup: {}", backup_path);
+        let mut files = Vec::new();
 
-    let backup_file = Path::new(backup_path);
+        if let Ok(metadata) = load_backup_metadata(&metadata_path).await {
+            if !metadata.file_name.is_empty() {
+                files.push(self.config.backup_dir.join(&metadata.file_name));
+            }
+        }
+        // Older metadata did not record the file name
+        files.push(self.config.backup_dir.join(format!("{}.db", name)));
+        files.push(metadata_path);
+
+        for path in files {
+            if path.exists() {
+                fs::remove_file(&path).await.map_err(|e| {
+                    NeuralBridgeError::database(format!(
+                        "Failed to delete backup file {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+            }
+        }
 
-    if !backup_file.exists() {
-        return Err(NeuralBridgeError::database(format!(
-            "Backup file not found: {}",
-            backup_path
-        )));
+        info!("Backup deleted successfully: {}", name);
+        Ok(())
     }
 
-    // Validate backup integrity
-    validate_backup_integrity(backup_file).await?;
+    /// Verify a backup's checksum and size against its metadata
+    pub async fn verify_backup(&self, name: &str) -> Result<bool> {
+        let metadata_path = self.metadata_path(name)?;
+        if !metadata_path.exists() {
+            return Ok(false);
+        }
 
-    // Load backup metadata
-    let metadata_path = backup_file.with_extension("json");
-    let metadata = load_backup_metadata(&metadata_path).await?;
+        let metadata = load_backup_metadata(&metadata_path).await?;
+        validate_backup_integrity(&self.config.backup_dir.join(&metadata.file_name), &metadata)
+            .await?;
 
-    info!(
-        "Restoring backup: {} (created: {})",
-        metadata.name, metadata.created_at
-    );
+        info!("Backup integrity verification passed: {}", name);
+        Ok(true)
+    }
 
-    // Perform restore
-    restore_database_from_backup(backup_file, &metadata).await?;
+    fn metadata_path(&self, name: &str) -> Result<PathBuf> {
+        validate_backup_name(name)?;
+        Ok(self.config.backup_dir.join(format!("{}.json", name)))
+    }
 
-    info!("Database restored successfully from backup");
-    Ok(())
+    async fn verified_metadata(&self, name: &str) -> Result<BackupMetadata> {
+        let metadata_path = self.metadata_path(name)?;
+        if !metadata_path.exists() {
+            return Err(NeuralBridgeError::database(format!(
+                "Backup not found: {}",
+                name
+            )));
+        }
+
+        let metadata = load_backup_metadata(&metadata_path).await?;
+        validate_backup_integrity(&self.config.backup_dir.join(&metadata.file_name), &metadata)
+            .await?;
+        Ok(metadata)
+    }
+
+    async fn latest_database_backup(&self) -> Result<Option<BackupMetadata>> {
+        Ok(self
+            .list_backups()
+            .await?
+            .into_iter()
+            .find(|b| b.backup_type != BackupType::Schema))
+    }
+
+    /// The verified backups needed to rebuild `backup`, starting with its full backup
+    async fn backup_chain(&self, backup: BackupMetadata) -> Result<Vec<BackupMetadata>> {
+        let mut chain = vec![backup];
+        let mut seen = HashSet::new();
+
+        while let Some(base_name) = chain.last().and_then(|b| b.base_backup.clone()) {
+            if !seen.insert(base_name.clone()) {
+                return Err(NeuralBridgeError::database(format!(
+                    "Backup chain of {} loops at {}",
+                    chain[0].name, base_name
+                )));
+            }
+            chain.push(self.verified_metadata(&base_name).await?);
+        }
+
+        chain.reverse();
+        if chain[0].backup_type != BackupType::Full {
+            return Err(NeuralBridgeError::database(format!(
+                "Backup chain of {} does not start with a full backup",
+                chain[chain.len() - 1].name
+            )));
+        }
+        Ok(chain)
+    }
+
+    async fn create_database_backup(
+        &self,
+        name: &str,
+        chain: Vec<BackupMetadata>,
+    ) -> Result<BackupMetadata> {
+        let snapshot_path = self.config.backup_dir.join(format!(".{}.snapshot", name));
+
+        let snapshot = snapshot_path.clone();
+        self.database
+            .with_connection(move |conn| conn.backup(DatabaseName::Main, &snapshot, None))
+            .await?;
+
+        let backup_dir = self.config.backup_dir.clone();
+        let compressed = self.config.compression_enabled;
+        let name = name.to_string();
+        let snapshot = snapshot_path.clone();
+        let result = run_blocking(move || {
+            write_database_backup(&backup_dir, &name, &snapshot, &chain, compressed)
+        })
+        .await;
+
+        let _ = fs::remove_file(&snapshot_path).await;
+        result
+    }
+
+    async fn create_schema_backup(&self, name: &str) -> Result<BackupMetadata> {
+        let statements: Vec<String> = self
+            .database
+            .with_connection(|conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT sql FROM sqlite_master WHERE sql IS NOT NULL ORDER BY rowid",
+                )?;
+                let rows = stmt.query_map([], |row| row.get(0))?;
+                rows.collect()
+            })
+            .await?;
+
+        let schema = statements
+            .iter()
+            .map(|sql| format!("{};\n", sql))
+            .collect::<String>();
+
+        let backup_dir = self.config.backup_dir.clone();
+        let compressed = self.config.compression_enabled;
+        let name = name.to_string();
+        run_blocking(move || {
+            let file_name = stored_file_name(&name, &BackupType::Schema, compressed);
+            let path = backup_dir.join(&file_name);
+            write_atomically(&path, compressed, |out| out.write_all(schema.as_bytes()))?;
+            let (size_bytes, checksum) = hash_file(&path)?;
+
+            Ok(BackupMetadata {
+                name,
+                created_at: chrono::Utc::now(),
+                size_bytes,
+                checksum,
+                database_version: env!("CARGO_PKG_VERSION").to_string(),
+                backup_type: BackupType::Schema,
+                file_name,
+                compressed,
+                base_backup: None,
+                content_size: schema.len() as u64,
+                content_checksum: calculate_checksum(schema.as_bytes()),
+                page_size: 0,
+            })
+        })
+        .await
+    }
+
+    /// Remove backups beyond `max_backups`, keeping the chains that
+    /// retained incremental backups still depend on
+    async fn cleanup_old_backups(&self) -> Result<()> {
+        let backups = self.list_backups().await?;
+        let max_backups = self.config.max_backups as usize;
+
+        if backups.len() <= max_backups {
+            return Ok(());
+        }
+
+        info!("Cleaning up old backups (keeping {})", max_backups);
+
+        let bases: std::collections::HashMap<&str, &str> = backups
+            .iter()
+            .filter_map(|b| Some((b.name.as_str(), b.base_backup.as_deref()?)))
+            .collect();
+        let mut required_bases = HashSet::new();
+        for backup in &backups[..max_backups] {
+            let mut name = backup.name.as_str();
+            while let Some(base) = bases.get(name) {
+                if !required_bases.insert(*base) {
+                    break;
+                }
+                name = base;
+            }
+        }
+
+        // Newest first, so incrementals go before the backups they are based on
+        for backup in &backups[max_backups..] {
+            if required_bases.contains(backup.name.as_str()) {
+                continue;
+            }
+            if let Err(e) = self.delete_backup(&backup.name).await {
+                warn!("Failed to delete old backup {}: {}", backup.name, e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Create a backup of the database
+pub async fn create_backup(backup_path: Option<String>) -> Result<String> {
+    let mut config = BackupConfig::default();
+
+    // An explicit path selects both the backup directory and the backup name
+    let name = backup_path.map(|path| {
+        let path = PathBuf::from(path);
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            config.backup_dir = parent.to_path_buf();
+        }
+        backup_name_from_path(&path)
+    });
+
+    let manager = BackupManager::new(global_database()?, config);
+    let metadata = manager.create_backup(name).await?;
+
+    Ok(manager
+        .config
+        .backup_dir
+        .join(&metadata.file_name)
+        .to_string_lossy()
+        .to_string())
+}
+
+/// Restore database from backup
+pub async fn restore_backup(backup_path: &str) -> Result<()> {
+    let (config, name) = config_for_backup_path(backup_path)?;
+    // The performance optimizer keeps its own connections to the file being replaced
+    crate::performance::database::reset_database_connections().await;
+    let restored = BackupManager::new(global_database()?, config)
+        .restore_backup(&name)
+        .await;
+    crate::performance::database::reset_database_connections().await;
+    restored
 }
 
 /// List available backups
@@ -118,13 +542,42 @@ pub async fn list_backups() -> Result<Vec<BackupMetadata>> {
     info!("Listing available backups");
 
     let config = BackupConfig::default();
+    let backups = list_backups_in(&config.backup_dir).await?;
 
-    if !config.backup_dir.exists() {
+    info!("Found {} backups", backups.len());
+    Ok(backups)
+}
+
+/// Delete a specific backup
+pub async fn delete_backup(backup_name: &str) -> Result<()> {
+    BackupManager::new(global_database()?, BackupConfig::default())
+        .delete_backup(backup_name)
+        .await
+}
+
+/// Verify backup integrity
+pub async fn verify_backup(backup_path: &str) -> Result<bool> {
+    info!("Verifying backup integrity: {}", backup_path);
+
+    if !Path::new(backup_path).exists() {
+        return Ok(false);
+    }
+
+    let (config, name) = config_for_backup_path(backup_path)?;
+    BackupManager::new(global_database()?, config)
+        .verify_backup(&name)
+        .await
+}
+
+// Internal helper functions
+
+async fn list_backups_in(backup_dir: &Path) -> Result<Vec<BackupMetadata>> {
+    if !backup_dir.exists() {
         return Ok(Vec::new());
     }
 
     let mut backups = Vec::new();
-    let mut entries = fs::read_dir(&config.backup_dir).await.map_err(|e| {
+    let mut entries = fs::read_dir(&backup_dir).await.map_err(|e| {
         NeuralBridgeError::database(format!("Failed to read backup directory: {}", e))
     })?;
 
@@ -143,140 +596,412 @@ pub async fn list_backups() -> Result<Vec<BackupMetadata>> {
     }
 
     // Sort by creation date (newest first)
-    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    backups.sort_by_key(|b| std::cmp::Reverse(b.created_at));
 
-    info!("Found {} backups", backups.len());
     Ok(backups)
 }
 
-/// Delete a specific backup
-pub async fn delete_backup(backup_name: &str) -> Result<()> {
-    info!("Deleting backup: {}", backup_name);
-
-    let config = BackupConfig::default();
-    let backup_path = config.backup_dir.join(format!("{}.db", backup_name));
-    let metadata_path = config.backup_dir.join(format!("{}.json", backup_name));
-
-    // Delete backup file
-    if backup_path.exists() {
-        fs::remove_file(&backup_path).await.map_err(|e| {
-            NeuralBridgeError::database(format!("Failed to delete backup file: {}", e))
-        })?;
+fn config_for_backup_path(backup_path: &str) -> Result<(BackupConfig, String)> {
+    let path = Path::new(backup_path);
+    if !path.exists() {
+        return Err(NeuralBridgeError::database(format!(
+            "Backup file not found: {}",
+            backup_path
+        )));
     }
 
-    // Delete metadata file
-    if metadata_path.exists() {
-        fs::remove_file(&metadata_path).await.map_err(|e| {
-            NeuralBridgeError::database(format!("Failed to delete metadata file: {}", e))
-        })?;
+    let mut config = BackupConfig::default();
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        config.backup_dir = parent.to_path_buf();
     }
 
-    info!("Backup deleted successfully: {}", backup_name);
-    Ok(())
+    Ok((config, backup_name_from_path(path)))
 }
 
-/// Verify backup integrity
-pub async fn verify_backup(backup_path: &str) -> Result<bool> {
-    info!("Verifying backup integrity: {}", backup_path);
+/// Strip the data or metadata extensions from a backup file name
+fn backup_name_from_path(path: &Path) -> String {
+    let mut name = path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown")
+        .to_string();
 
-    let backup_file = Path::new(backup_path);
-
-    if !backup_file.exists() {
-        return Ok(false);
+    if let Some(stripped) = name.strip_suffix(".gz") {
+        name = stripped.to_string();
     }
+    for extension in [".db", ".inc", ".sql", ".json"] {
+        if let Some(stripped) = name.strip_suffix(extension) {
+            return stripped.to_string();
+        }
+    }
+    name
+}
 
-    validate_backup_integrity(backup_file).await?;
+/// Backup names become file names, so only `[A-Za-z0-9_-]` is accepted
+fn validate_backup_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        return Err(NeuralBridgeError::validation(format!(
+            "Invalid backup name {:?}: use only letters, digits, '_' and '-'",
+            name
+        )));
+    }
+    Ok(())
+}
 
-    info!("Backup integrity verification passed");
-    Ok(true)
+fn stored_file_name(name: &str, backup_type: &BackupType, compressed: bool) -> String {
+    format!(
+        "{}.{}{}",
+        name,
+        backup_type.extension(),
+        if compressed { ".gz" } else { "" }
+    )
 }
 
-// Internal helper functions
+async fn run_blocking<F, R>(f: F) -> Result<R>
+where
+    F: FnOnce() -> Result<R> + Send + 'static,
+    R: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| NeuralBridgeError::internal(format!("Backup task failed: {}", e)))?
+}
 
-async fn create_database_backup(
-    backup_path: &Path,
-    config: &BackupConfig,
+fn write_database_backup(
+    backup_dir: &Path,
+    name: &str,
+    snapshot_path: &Path,
+    chain: &[BackupMetadata],
+    compressed: bool,
 ) -> Result<BackupMetadata> {
-    info!("Creating backup at: {}", backup_path.display());
-
-    // In a real implementation, you would:
-    // 1. Connect to the database
-    // 2. Create a consistent snapshot
-    // 3. Copy the data to the backup file
-    // 4. Optionally compress the backup
+    let page_size = read_page_size(snapshot_path)?;
+    let (content_size, content_checksum) = hash_file(snapshot_path)?;
+
+    let base = chain.last().filter(|b| {
+        let usable = b.page_size == page_size;
+        if !usable {
+            warn!(
+                "Page size changed since backup {}; taking a full backup instead",
+                b.name
+            );
+        }
+        usable
+    });
 
-    // Placeholder implementation
-    let backup_data = b"BACKUP_PLACEHOLDER_DATA";
-    fs::write(backup_path, backup_data)
-        .await
-        .map_err(|e| NeuralBridgeError::database(format!("Failed to write backup: {}", e)))?;
+    let backup_type = if base.is_some() {
+        BackupType::Incremental
+    } else {
+        BackupType::Full
+    };
+    let file_name = stored_file_name(name, &backup_type, compressed);
+    let path = backup_dir.join(&file_name);
+
+    match base {
+        Some(base) => {
+            // The pages of the previous backup, as the database it restores to
+            let base_path = backup_dir.join(format!(".{}.base", name));
+            let written = rebuild_database(backup_dir, chain, &base_path).and_then(|()| {
+                let mut changed_pages = 0u64;
+                write_atomically(&path, compressed, |out| {
+                    changed_pages = write_page_diff(
+                        out,
+                        &mut BufReader::new(File::open(&base_path)?),
+                        &mut BufReader::new(File::open(snapshot_path)?),
+                        page_size,
+                        content_size,
+                    )?;
+                    Ok(())
+                })?;
+                Ok(changed_pages)
+            });
+            let _ = std::fs::remove_file(&base_path);
+            let changed_pages = written?;
+            info!(
+                "Incremental backup against {} stores {} changed pages",
+                base.name, changed_pages
+            );
+        }
+        None => {
+            write_atomically(&path, compressed, |out| {
+                io::copy(&mut BufReader::new(File::open(snapshot_path)?), out).map(|_| ())
+            })?;
+        }
+    }
 
-    // Calculate checksum
-    let checksum = calculate_checksum(backup_data);
+    let (size_bytes, checksum) = hash_file(&path)?;
 
-    let metadata = BackupMetadata {
-        name: backup_path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("unknown")
-            .to_string(),
+    Ok(BackupMetadata {
+        name: name.to_string(),
         created_at: chrono::Utc::now(),
-        size_bytes: backup_data.len() as u64,
+        size_bytes,
         checksum,
         database_version: env!("CARGO_PKG_VERSION").to_string(),
-        backup_type: BackupType::Full,
-    };
+        backup_type,
+        file_name,
+        compressed,
+        base_backup: base.map(|b| b.name.clone()),
+        content_size,
+        content_checksum,
+        page_size,
+    })
+}
 
-    Ok(metadata)
+/// Write every page of `current` that differs from `base`
+///
+/// Layout: magic, page size (u32), database size (u64), then one
+/// `index (u32), length (u32), bytes` record per changed page. All integers
+/// are little-endian.
+fn write_page_diff(
+    out: &mut dyn Write,
+    base: &mut dyn Read,
+    current: &mut dyn Read,
+    page_size: u32,
+    total_size: u64,
+) -> io::Result<u64> {
+    out.write_all(INCREMENTAL_MAGIC)?;
+    out.write_all(&page_size.to_le_bytes())?;
+    out.write_all(&total_size.to_le_bytes())?;
+
+    let mut page = vec![0u8; page_size as usize];
+    let mut base_page = vec![0u8; page_size as usize];
+    let mut changed = 0u64;
+    let mut index = 0u32;
+
+    loop {
+        let len = read_full(current, &mut page)?;
+        if len == 0 {
+            break;
+        }
+        let base_len = read_full(base, &mut base_page)?;
+
+        if len != base_len || page[..len] != base_page[..base_len] {
+            out.write_all(&index.to_le_bytes())?;
+            out.write_all(&(len as u32).to_le_bytes())?;
+            out.write_all(&page[..len])?;
+            changed += 1;
+        }
+        index += 1;
+    }
+
+    Ok(changed)
 }
 
-async fn restore_database_from_backup(backup_path: &Path, metadata: &BackupMetadata) -> Result<()> {
-    info!("Restoring from backup: {}", backup_path.display());
+/// Apply an incremental page diff on top of a database file
+fn apply_page_diff(diff: &mut dyn Read, target: &mut File) -> Result<()> {
+    let mut magic = [0u8; 8];
+    diff.read_exact(&mut magic)?;
+    if &magic != INCREMENTAL_MAGIC {
+        return Err(NeuralBridgeError::database(
+            "Not an incremental backup file",
+        ));
+    }
 
-    // In a real implementation, you would:
-    // 1. Stop all database connections
-    // 2. Restore the backup data
-    // 3. Verify data integrity
-    // 4. Restart database connections
+    let mut word = [0u8; 4];
+    let mut long = [0u8; 8];
+    diff.read_exact(&mut word)?;
+    let page_size = u32::from_le_bytes(word) as u64;
+    diff.read_exact(&mut long)?;
+    let total_size = u64::from_le_bytes(long);
+
+    let mut page = vec![0u8; page_size as usize];
+    loop {
+        match diff.read_exact(&mut word) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        let index = u32::from_le_bytes(word) as u64;
+        diff.read_exact(&mut word)?;
+        let len = u32::from_le_bytes(word) as usize;
+        if len > page.len() {
+            return Err(NeuralBridgeError::database(
+                "Corrupt incremental backup: page larger than page size",
+            ));
+        }
 
-    // Placeholder implementation
-    let _backup_data = fs::read(backup_path)
-        .await
-        .map_err(|e| NeuralBridgeError::database(format!("Failed to read backup: {}", e)))?;
+        diff.read_exact(&mut page[..len])?;
+        target.seek(SeekFrom::Start(index * page_size))?;
+        target.write_all(&page[..len])?;
+    }
 
-    info!("Backup restored: {} bytes", metadata.size_bytes);
+    target.set_len(total_size)?;
     Ok(())
 }
 
-async fn validate_backup_integrity(backup_path: &Path) -> Result<()> {
-    info!("Validating backup integrity: {}", backup_path.display());
-
-    // Read backup file
-    let backup_data = fs::read(backup_path)
-        .await
-        .map_err(|e| NeuralBridgeError::database(format!("Failed to read backup file: {}", e)))?;
-
-    // Load metadata
-    let metadata_path = backup_path.with_extension("json");
-    if metadata_path.exists() {
-        let metadata = load_backup_metadata(&metadata_path).await?;
+/// Rebuild the raw database file the last backup of `chain` describes at `target`
+///
+/// `chain` starts with a full backup, followed by the incremental backups
+/// each based on the one before it.
+fn rebuild_database(backup_dir: &Path, chain: &[BackupMetadata], target: &Path) -> Result<()> {
+    let (full, incrementals) = chain
+        .split_first()
+        .ok_or_else(|| NeuralBridgeError::database("Empty backup chain"))?;
+    {
+        let mut out = BufWriter::new(File::create(target)?);
+        io::copy(
+            &mut open_stored(&backup_dir.join(&full.file_name), full.compressed)?,
+            &mut out,
+        )?;
+        out.flush()?;
+    }
 
-        // Verify checksum
-        let calculated_checksum = calculate_checksum(&backup_data);
-        if calculated_checksum != metadata.checksum {
-            return Err(NeuralBridgeError::database(
-                "Backup checksum mismatch - file may be corrupted",
-            ));
+    if !incrementals.is_empty() {
+        let mut file = OpenOptions::new().read(true).write(true).open(target)?;
+        for incremental in incrementals {
+            apply_page_diff(
+                &mut open_stored(&backup_dir.join(&incremental.file_name), incremental.compressed)?,
+                &mut file,
+            )?;
         }
+        file.sync_all()?;
+    }
 
-        // Verify size
-        if backup_data.len() as u64 != metadata.size_bytes {
+    let metadata = &chain[chain.len() - 1];
+    if !metadata.content_checksum.is_empty() {
+        let (size, checksum) = hash_file(target)?;
+        if size != metadata.content_size || checksum != metadata.content_checksum {
             return Err(NeuralBridgeError::database(
-                "Backup size mismatch - file may be corrupted",
+                "Restored database does not match the backup checksum",
             ));
         }
+    }
+
+    Ok(())
+}
+
+/// Run SQLite's integrity check on a database file
+fn check_database_file(path: &Path) -> Result<()> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+    let result: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    conn.close().map_err(|(_, e)| NeuralBridgeError::from(e))?;
+
+    if result != "ok" {
+        return Err(NeuralBridgeError::database(format!(
+            "Restored database failed integrity check: {}",
+            result
+        )));
+    }
+    Ok(())
+}
+
+fn read_page_size(path: &Path) -> Result<u32> {
+    let mut header = [0u8; 100];
+    File::open(path)?.read_exact(&mut header)?;
+    if &header[..16] != SQLITE_MAGIC {
+        return Err(NeuralBridgeError::database(
+            "Snapshot is not a SQLite database",
+        ));
+    }
+
+    // Stored big-endian; the value 1 stands for 65536
+    Ok(match u16::from_be_bytes([header[16], header[17]]) {
+        1 => 65536,
+        size => size as u32,
+    })
+}
+
+fn open_stored(path: &Path, compressed: bool) -> io::Result<Box<dyn Read>> {
+    let file = BufReader::new(File::open(path)?);
+    Ok(if compressed {
+        Box::new(GzDecoder::new(file))
     } else {
-        warn!("No metadata file found for backup - skipping integrity checks");
+        Box::new(file)
+    })
+}
+
+/// Write a file through a temporary sibling and rename it into place
+fn write_atomically<F>(path: &Path, compressed: bool, write: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> io::Result<()>,
+{
+    let partial = PathBuf::from(format!("{}.partial", path.display()));
+    let result = (|| -> io::Result<()> {
+        let file = BufWriter::new(File::create(&partial)?);
+        let file = if compressed {
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            write(&mut encoder)?;
+            encoder.finish()?
+        } else {
+            let mut file = file;
+            write(&mut file)?;
+            file
+        };
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        std::fs::rename(&partial, path)
+    })();
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial);
+        return Err(NeuralBridgeError::database(format!(
+            "Failed to write backup {}: {}",
+            path.display(),
+            e
+        )));
+    }
+    Ok(())
+}
+
+fn read_full(reader: &mut dyn Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Size and SHA-256 of a file, streamed
+fn hash_file(path: &Path) -> io::Result<(u64, String)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    let mut size = 0u64;
+
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+
+    Ok((size, hex::encode(hasher.finalize())))
+}
+
+async fn validate_backup_integrity(backup_path: &Path, metadata: &BackupMetadata) -> Result<()> {
+    info!("Validating backup integrity: {}", backup_path.display());
+
+    if !backup_path.exists() {
+        return Err(NeuralBridgeError::database(format!(
+            "Backup file not found: {}",
+            backup_path.display()
+        )));
+    }
+
+    let path = backup_path.to_path_buf();
+    let (size, checksum) = run_blocking(move || hash_file(&path).map_err(Into::into)).await?;
+
+    // Verify checksum
+    if checksum != metadata.checksum {
+        return Err(NeuralBridgeError::database(
+            "Backup checksum mismatch - file may be corrupted",
+        ));
+    }
+
+    // Verify size
+    if size != metadata.size_bytes {
+        return Err(NeuralBridgeError::database(
+            "Backup size mismatch - file may be corrupted",
+        ));
     }
 
     Ok(())
@@ -298,32 +1023,29 @@ async fn load_backup_metadata(metadata_path: &Path) -> Result<BackupMetadata> {
         .await
         .map_err(|e| NeuralBridgeError::database(format!("Failed to read metadata: {}", e)))?;
 
-    let metadata: BackupMetadata = serde_json::from_str(&metadata_json)
+    let mut metadata: BackupMetadata = serde_json::from_str(&metadata_json)
         .map_err(|e| NeuralBridgeError::database(format!("Failed to parse metadata: {}", e)))?;
 
-    Ok(metadata)
-}
-
-async fn cleanup_old_backups(config: &BackupConfig) -> Result<()> {
-    let backups = list_backups().await?;
-
-    if backups.len() > config.max_backups as usize {
-        info!("Cleaning up old backups (keeping {})", config.max_backups);
-
-        let backups_to_delete = &backups[config.max_backups as usize..];
+    // Metadata written before file names were recorded
+    if metadata.file_name.is_empty() {
+        metadata.file_name = format!("{}.db", metadata.name);
+    }
 
-        for backup in backups_to_delete {
-            if let Err(e) = delete_backup(&backup.name).await {
-                warn!("Failed to delete old backup {}: {}", backup.name, e);
-            }
-        }
+    validate_backup_name(&metadata.name)?;
+    if let Some(base) = &metadata.base_backup {
+        validate_backup_name(base)?;
+    }
+    if Path::new(&metadata.file_name).file_name() != Some(metadata.file_name.as_ref()) {
+        return Err(NeuralBridgeError::database(format!(
+            "Backup {} points outside the backup directory",
+            metadata.name
+        )));
     }
 
-    Ok(())
+    Ok(metadata)
 }
 
 fn calculate_checksum(data: &[u8]) -> String {
-    use sha2::{Digest, Sha256};
     let mut hasher = Sha256::new();
     hasher.update(data);
     hex::encode(hasher.finalize())
@@ -332,6 +1054,37 @@ fn calculate_checksum(data: &[u8]) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::database::models::{Project, Task, TaskStatus, User};
+    use crate::database::DatabaseConfig;
+
+    async fn file_database(dir: &Path) -> Database {
+        let db = Database::new(DatabaseConfig::for_path(dir.join("live.db")));
+        db.initialize().await.unwrap();
+        db
+    }
+
+    fn backup_config(dir: &Path) -> BackupConfig {
+        BackupConfig {
+            backup_dir: dir.join("backups"),
+            ..BackupConfig::default()
+        }
+    }
+
+    async fn insert_tasks(db: &Database, count: usize) {
+        let owner = User::new(
+            format!("user-{}", uuid::Uuid::new_v4()),
+            format!("{}@example.com", uuid::Uuid::new_v4()),
+            "hash".to_string(),
+        );
+        let project = Project::new("Project".to_string(), None, owner.id);
+        db.save(&owner).await.unwrap();
+        db.save(&project).await.unwrap();
+
+        let tasks: Vec<Task> = (0..count)
+            .map(|i| Task::new(project.id, format!("Task {}", i), "x".repeat(200)))
+            .collect();
+        db.save_all(&tasks).await.unwrap();
+    }
 
     #[test]
     fn test_backup_config_default() {
@@ -348,6 +1101,16 @@ mod tests {
         assert_eq!(checksum.len(), 64); // SHA-256 hex string length
     }
 
+    #[test]
+    fn test_backup_name_from_path() {
+        assert_eq!(
+            backup_name_from_path(Path::new("/b/nightly.db.gz")),
+            "nightly"
+        );
+        assert_eq!(backup_name_from_path(Path::new("nightly.inc")), "nightly");
+        assert_eq!(backup_name_from_path(Path::new("nightly.json")), "nightly");
+    }
+
     #[tokio::test]
     async fn test_backup_metadata_serialization() {
         let metadata = BackupMetadata {
@@ -357,6 +1120,12 @@ mod tests {
             checksum: "test_checksum".to_string(),
             database_version: "1.0.0".to_string(),
             backup_type: BackupType::Full,
+            file_name: "test_backup.db".to_string(),
+            compressed: false,
+            base_backup: None,
+            content_size: 1024,
+            content_checksum: "test_checksum".to_string(),
+            page_size: 4096,
         };
 
         let json = serde_json::to_string(&metadata).unwrap();
@@ -365,4 +1134,187 @@ mod tests {
         assert_eq!(metadata.name, deserialized.name);
         assert_eq!(metadata.size_bytes, deserialized.size_bytes);
     }
+
+    #[tokio::test]
+    async fn test_full_backup_restore_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = file_database(dir.path()).await;
+        insert_tasks(&db, 10).await;
+
+        let manager = BackupManager::new(db.clone(), backup_config(dir.path()));
+        let metadata = manager
+            .create_backup(Some("nightly".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(metadata.backup_type, BackupType::Full);
+        assert!(metadata.compressed);
+        assert!(metadata.size_bytes < metadata.content_size);
+        assert!(manager.verify_backup("nightly").await.unwrap());
+
+        db.execute("DELETE FROM tasks").await.unwrap();
+        assert!(db.fetch_all::<Task>().await.unwrap().is_empty());
+
+        manager.restore_backup("nightly").await.unwrap();
+        assert_eq!(db.fetch_all::<Task>().await.unwrap().len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_incremental_backup_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = file_database(dir.path()).await;
+        insert_tasks(&db, 500).await;
+
+        let config = BackupConfig {
+            compression_enabled: false,
+            incremental_backups: true,
+            ..backup_config(dir.path())
+        };
+        let manager = BackupManager::new(db.clone(), config);
+
+        let full = manager
+            .create_backup(Some("full".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(full.backup_type, BackupType::Full);
+
+        insert_tasks(&db, 5).await;
+        db.execute("UPDATE tasks SET status = 'Completed' WHERE rowid <= 100")
+            .await
+            .unwrap();
+
+        let incremental = manager
+            .create_backup(Some("inc".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(incremental.backup_type, BackupType::Incremental);
+        assert_eq!(incremental.base_backup.as_deref(), Some("full"));
+        assert!(incremental.size_bytes < full.size_bytes / 2);
+
+        // The next incremental only holds what changed since the previous one
+        insert_tasks(&db, 5).await;
+        let second = manager
+            .create_backup(Some("inc2".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(second.base_backup.as_deref(), Some("inc"));
+        assert!(second.size_bytes < incremental.size_bytes);
+
+        db.execute("DELETE FROM tasks").await.unwrap();
+        manager.restore_backup("inc").await.unwrap();
+
+        let tasks = db.fetch_all::<Task>().await.unwrap();
+        assert_eq!(tasks.len(), 505);
+        assert!(tasks
+            .iter()
+            .any(|t| t.title == "Task 0" && matches!(t.status, TaskStatus::Completed)));
+
+        manager.restore_backup("inc2").await.unwrap();
+        assert_eq!(db.fetch_all::<Task>().await.unwrap().len(), 510);
+    }
+
+    #[tokio::test]
+    async fn test_bases_of_other_backups_are_not_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = file_database(dir.path()).await;
+        let config = BackupConfig {
+            incremental_backups: true,
+            ..backup_config(dir.path())
+        };
+        let manager = BackupManager::new(db.clone(), config);
+
+        for name in ["full", "inc"] {
+            insert_tasks(&db, 1).await;
+            manager.create_backup(Some(name.to_string())).await.unwrap();
+        }
+
+        let err = manager.delete_backup("full").await.unwrap_err();
+        assert!(err.message().contains("inc"));
+        assert!(manager.verify_backup("full").await.unwrap());
+
+        manager.delete_backup("inc").await.unwrap();
+        manager.delete_backup("full").await.unwrap();
+        assert!(manager.list_backups().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_backup_names_are_validated() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = file_database(dir.path()).await;
+        let manager = BackupManager::new(db, backup_config(dir.path()));
+
+        for name in ["../outside", "a/b", "", "nightly.db", "..\\x"] {
+            assert!(manager.create_backup(Some(name.to_string())).await.is_err());
+            assert!(manager.restore_backup(name).await.is_err());
+            assert!(manager.delete_backup(name).await.is_err());
+            assert!(manager.verify_backup(name).await.is_err());
+        }
+        assert!(!dir.path().join("outside.json").exists());
+
+        manager
+            .create_backup(Some("Nightly_2024-01".to_string()))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retention_keeps_required_base() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = file_database(dir.path()).await;
+
+        let config = BackupConfig {
+            max_backups: 2,
+            incremental_backups: true,
+            ..backup_config(dir.path())
+        };
+        let manager = BackupManager::new(db.clone(), config);
+
+        let names = || async {
+            manager
+                .list_backups()
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|b| b.name)
+                .collect::<Vec<String>>()
+        };
+
+        for name in ["full", "inc1", "inc2", "inc3"] {
+            insert_tasks(&db, 1).await;
+            manager.create_backup(Some(name.to_string())).await.unwrap();
+        }
+        assert_eq!(names().await, vec!["inc3", "inc2", "inc1", "full"]);
+
+        // A new full backup starts a new chain, and the old one can go
+        insert_tasks(&db, 1).await;
+        manager
+            .create_backup_of_type(Some("full2".to_string()), BackupType::Full)
+            .await
+            .unwrap();
+        insert_tasks(&db, 1).await;
+        manager.create_backup(Some("inc4".to_string())).await.unwrap();
+        assert_eq!(names().await, vec!["inc4", "full2"]);
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_backup_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = file_database(dir.path()).await;
+        insert_tasks(&db, 3).await;
+
+        let manager = BackupManager::new(db.clone(), backup_config(dir.path()));
+        let metadata = manager
+            .create_backup(Some("nightly".to_string()))
+            .await
+            .unwrap();
+
+        let path = manager.config().backup_dir.join(&metadata.file_name);
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+
+        assert!(manager.verify_backup("nightly").await.is_err());
+        assert!(manager.restore_backup("nightly").await.is_err());
+        assert_eq!(db.fetch_all::<Task>().await.unwrap().len(), 3);
+    }
 }
@@ -6,9 +6,16 @@ pub mod models;
 pub mod schema;
 
 use crate::errors::{NeuralBridgeError, Result};
+use models::Record;
+use parking_lot::Mutex;
+use rusqlite::{params_from_iter, types::Value, Connection, OpenFlags};
 use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::fs;
-use tracing::{error, info};
+use tracing::{debug, error, info};
+
+/// In-memory database URL, mainly useful for tests
+pub const MEMORY_DATABASE_URL: &str = "sqlite::memory:";
 
 /// Database configuration
 #[derive(Debug, Clone)]
@@ -28,27 +35,86 @@ impl Default for DatabaseConfig {
     }
 }
 
+impl DatabaseConfig {
+    /// Configuration for a private in-memory database
+    pub fn in_memory() -> Self {
+        Self {
+            database_url: MEMORY_DATABASE_URL.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Configuration for a database file at the given path
+    pub fn for_path(path: impl Into<PathBuf>) -> Self {
+        Self {
+            database_url: format!("sqlite://{}", path.into().display()),
+            ..Self::default()
+        }
+    }
+
+    /// Filesystem path of the database, or `None` for in-memory databases
+    pub fn sqlite_path(&self) -> Result<Option<PathBuf>> {
+        if self.database_url == MEMORY_DATABASE_URL {
+            return Ok(None);
+        }
+
+        self.database_url
+            .strip_prefix("sqlite://")
+            .map(|path| Some(PathBuf::from(path)))
+            .ok_or_else(|| {
+                NeuralBridgeError::database(format!(
+                    "Unsupported database URL: {}",
+                    self.database_url
+                ))
+            })
+    }
+}
+
 /// Database connection manager
+///
+/// Wraps a single SQLite connection. Clones share the same connection, and
+/// all statements run on the blocking thread pool so callers never stall the
+/// async runtime.
+#[derive(Clone)]
 pub struct Database {
     config: DatabaseConfig,
+    connection: Arc<Mutex<Option<Connection>>>,
 }
 
 impl Database {
     /// Create a new database instance
     pub fn new(config: DatabaseConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            connection: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Database configuration
+    pub fn config(&self) -> &DatabaseConfig {
+        &self.config
     }
 
     /// Initialize the database
     pub async fn initialize(&self) -> Result<()> {
         info!("Initializing database: {}", self.config.database_url);
 
-        // Create database directory if it doesn't exist
-        if self.config.database_url.starts_with("sqlite://") {
-            let path = self.config.database_url.strip_prefix("sqlite://").unwrap();
-            let db_path = PathBuf::from(path);
+        self.connect().await?;
+
+        // Initialize schema
+        self.create_tables().await?;
+
+        info!("Database initialized successfully");
+        Ok(())
+    }
 
-            if let Some(parent) = db_path.parent() {
+    /// Open the connection without applying schema migrations
+    pub async fn connect(&self) -> Result<()> {
+        let db_path = self.config.sqlite_path()?;
+
+        // Create database directory if it doesn't exist
+        if let Some(parent) = db_path.as_ref().and_then(|path| path.parent()) {
+            if !parent.as_os_str().is_empty() {
                 fs::create_dir_all(parent).await.map_err(|e| {
                     NeuralBridgeError::database(format!(
                         "Failed to create database directory: {}",
@@ -58,67 +124,232 @@ impl Database {
             }
         }
 
-        // Initialize schema
-        self.create_tables().await?;
+        let timeout = self.config.connection_timeout;
+        let connection = tokio::task::spawn_blocking(move || open_connection(db_path, timeout))
+            .await
+            .map_err(|e| NeuralBridgeError::internal(format!("Database task failed: {}", e)))??;
 
-        info!("Database initialized successfully");
+        *self.connection.lock() = Some(connection);
         Ok(())
     }
 
+    /// Close the underlying connection
+    pub fn close(&self) {
+        if let Some(connection) = self.connection.lock().take() {
+            if let Err((_, e)) = connection.close() {
+                error!("Failed to close database connection: {}", e);
+            }
+        }
+    }
+
     /// Create database tables
     async fn create_tables(&self) -> Result<()> {
         info!("Creating database tables");
+        self.create_sqlite_tables().await
+    }
 
-        // This is a placeholder - in a real implementation, you would use
-        // a proper database migration system or ORM
-        if self.config.database_url.starts_with("sqlite://") {
-            self.create_sqlite_tables().await?;
-        }
+    /// Create SQLite tables by applying any pending schema migrations
+    async fn create_sqlite_tables(&self) -> Result<()> {
+        let version = schema::SchemaManager::new().migrate_up(self).await?;
 
+        info!("SQLite tables ready at schema version {}", version);
         Ok(())
     }
 
-    /// Create SQLite tables
-    async fn create_sqlite_tables(&self) -> Result<()> {
-        // Placeholder for SQLite table creation
-        // In a real implementation, you would use sqlx or another SQLite library
+    /// Run a closure against the open connection on the blocking thread pool
+    pub async fn with_connection<F, R>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut Connection) -> rusqlite::Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let connection = self.connection.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut guard = connection.lock();
+            let conn = guard
+                .as_mut()
+                .ok_or_else(|| NeuralBridgeError::database("Database not initialized"))?;
+            f(conn).map_err(NeuralBridgeError::from)
+        })
+        .await
+        .map_err(|e| NeuralBridgeError::internal(format!("Database task failed: {}", e)))?
+    }
 
-        info!("SQLite tables created");
-        Ok(())
+    /// Run a closure against the open connection on the calling thread
+    ///
+    /// For callers that are already off the async runtime, such as background writers.
+    pub fn with_connection_blocking<F, R>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut Connection) -> rusqlite::Result<R>,
+    {
+        let mut guard = self.connection.lock();
+        let conn = guard
+            .as_mut()
+            .ok_or_else(|| NeuralBridgeError::database("Database not initialized"))?;
+        f(conn).map_err(NeuralBridgeError::from)
     }
 
     /// Execute a query
     pub async fn execute(&self, query: &str) -> Result<u64> {
-        info!("Executing query: {}", query);
+        self.execute_with_params(query, Vec::new()).await
+    }
 
-        // Placeholder implementation
-        // In a real implementation, you would use a proper database driver
+    /// Execute a query with positional parameters
+    pub async fn execute_with_params(&self, query: &str, params: Vec<Value>) -> Result<u64> {
+        debug!("Executing query: {}", query);
 
-        Ok(1)
+        let query = query.to_string();
+        self.with_connection(move |conn| {
+            conn.execute(&query, params_from_iter(params.iter()))
+                .map(|changed| changed as u64)
+        })
+        .await
+    }
+
+    /// Execute several statements separated by semicolons
+    pub async fn execute_batch(&self, sql: &str) -> Result<()> {
+        let sql = sql.to_string();
+        self.with_connection(move |conn| conn.execute_batch(&sql))
+            .await
     }
 
     /// Fetch records
-    pub async fn fetch<T>(&self, query: &str) -> Result<Vec<T>>
-    where
-        T: Send + 'static,
-    {
-        info!("Fetching records with query: {}", query);
+    pub async fn fetch<T: Record>(&self, query: &str) -> Result<Vec<T>> {
+        self.fetch_with_params(query, Vec::new()).await
+    }
+
+    /// Fetch records with positional parameters
+    pub async fn fetch_with_params<T: Record>(
+        &self,
+        query: &str,
+        params: Vec<Value>,
+    ) -> Result<Vec<T>> {
+        debug!("Fetching records with query: {}", query);
+
+        let query = query.to_string();
+        self.with_connection(move |conn| {
+            let mut stmt = conn.prepare(&query)?;
+            let rows = stmt.query_map(params_from_iter(params.iter()), |row| T::from_row(row))?;
+            rows.collect()
+        })
+        .await
+    }
 
-        // Placeholder implementation
-        // In a real implementation, you would use a proper database driver
+    /// Insert a record, updating the existing row with the same id in place
+    pub async fn save<T: Record>(&self, record: &T) -> Result<()> {
+        self.execute_with_params(&upsert_sql::<T>(), record.to_values()).await?;
+        Ok(())
+    }
 
-        Ok(Vec::new())
+    /// Insert several records in one transaction
+    pub async fn save_all<T: Record>(&self, records: &[T]) -> Result<()> {
+        let sql = upsert_sql::<T>();
+        let rows: Vec<Vec<Value>> = records.iter().map(Record::to_values).collect();
+
+        self.with_connection(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut stmt = tx.prepare(&sql)?;
+                for values in &rows {
+                    stmt.execute(params_from_iter(values.iter()))?;
+                }
+            }
+            tx.commit()
+        })
+        .await
+    }
+
+    /// Load a record by primary key
+    pub async fn find_by_id<T: Record>(&self, id: uuid::Uuid) -> Result<Option<T>> {
+        let sql = format!("SELECT * FROM {} WHERE id = ?1", T::TABLE);
+        let mut records = self
+            .fetch_with_params::<T>(&sql, vec![models::uuid_value(id)])
+            .await?;
+        Ok(records.pop())
+    }
+
+    /// Load every record in the table
+    pub async fn fetch_all<T: Record>(&self) -> Result<Vec<T>> {
+        self.fetch::<T>(&format!("SELECT * FROM {}", T::TABLE))
+            .await
+    }
+
+    /// Delete a record by primary key, returning whether a row was removed
+    pub async fn delete_by_id<T: Record>(&self, id: uuid::Uuid) -> Result<bool> {
+        let sql = format!("DELETE FROM {} WHERE id = ?1", T::TABLE);
+        let changed = self
+            .execute_with_params(&sql, vec![models::uuid_value(id)])
+            .await?;
+        Ok(changed > 0)
     }
 
     /// Health check
     pub async fn health_check(&self) -> Result<bool> {
-        info!("Performing database health check");
+        debug!("Performing database health check");
+
+        let result = self
+            .with_connection(|conn| conn.query_row("SELECT 1", [], |row| row.get::<_, i64>(0)))
+            .await;
+
+        match result {
+            Ok(1) => Ok(true),
+            Ok(_) => Ok(false),
+            Err(e) => {
+                error!("Database health check failed: {}", e);
+                Ok(false)
+            }
+        }
+    }
+}
 
-        // Placeholder implementation
-        // In a real implementation, you would execute a simple query
+pub(crate) fn open_connection(path: Option<PathBuf>, busy_timeout: std::time::Duration) -> Result<Connection> {
+    let connection = match path {
+        Some(path) => Connection::open_with_flags(
+            &path,
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+                | OpenFlags::SQLITE_OPEN_CREATE
+                | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?,
+        None => Connection::open_in_memory()?,
+    };
+
+    connection.busy_timeout(busy_timeout)?;
+    connection.execute_batch(
+        "PRAGMA journal_mode = WAL;
+         PRAGMA synchronous = NORMAL;
+         PRAGMA foreign_keys = ON;",
+    )?;
+
+    Ok(connection)
+}
 
-        Ok(true)
-    }
+/// Insert statement for `T` that updates a conflicting row rather than replacing it
+///
+/// `INSERT OR REPLACE` deletes the old row first, which cascades to every row
+/// referencing it while foreign keys are enforced.
+fn upsert_sql<T: Record>() -> String {
+    let (key, columns) = T::COLUMNS.split_first().expect("records have a primary key column");
+    let updates = columns
+        .iter()
+        .map(|column| format!("{} = excluded.{}", column, column))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT({}) DO UPDATE SET {}",
+        T::TABLE,
+        T::COLUMNS.join(", "),
+        placeholders(T::COLUMNS.len()),
+        key,
+        updates
+    )
+}
+
+fn placeholders(count: usize) -> String {
+    (1..=count)
+        .map(|i| format!("?{}", i))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 // Global database instance
@@ -132,15 +363,26 @@ pub fn get_database() -> Result<std::sync::Arc<std::sync::Mutex<Option<Database>
         .clone())
 }
 
-/// Initialize the global database
-pub async fn initialize_database(config: Option<DatabaseConfig>) -> Result<()> {
+/// Clone the global database handle without holding the lock across awaits
+pub(crate) fn global_database() -> Result<Database> {
     let db_mutex = get_database()?;
-    let mut db_guard = db_mutex.lock().unwrap();
+    let db_guard = db_mutex.lock().unwrap();
+
+    db_guard
+        .clone()
+        .ok_or_else(|| NeuralBridgeError::database("Database not initialized"))
+}
 
+/// Initialize the global database
+pub async fn initialize_database(config: Option<DatabaseConfig>) -> Result<()> {
     let database = Database::new(config.unwrap_or_default());
     database.initialize().await?;
 
-    *db_guard = Some(database);
+    let db_mutex = get_database()?;
+    let previous = db_mutex.lock().unwrap().replace(database);
+    if let Some(previous) = previous {
+        previous.close();
+    }
 
     info!("Global database initialized");
     Ok(())
@@ -148,42 +390,22 @@ pub async fn initialize_database(config: Option<DatabaseConfig>) -> Result<()> {
 
 /// Execute a query on the global database
 pub async fn execute_query(query: &str) -> Result<u64> {
-    let db_mutex = get_database()?;
-    let db_guard = db_mutex.lock().unwrap();
-
-    match db_guard.as_ref() {
-        Some(db) => db.execute(query).await,
-        None => Err(NeuralBridgeError::database("Database not initialized")),
-    }
+    global_database()?.execute(query).await
 }
 
 /// Fetch records from the global database
-pub async fn fetch_records<T>(query: &str) -> Result<Vec<T>>
-where
-    T: Send + 'static,
-{
-    let db_mutex = get_database()?;
-    let db_guard = db_mutex.lock().unwrap();
-
-    match db_guard.as_ref() {
-        Some(db) => db.fetch(query).await,
-        None => Err(NeuralBridgeError::database("Database not initialized")),
-    }
+pub async fn fetch_records<T: Record>(query: &str) -> Result<Vec<T>> {
+    global_database()?.fetch(query).await
 }
 
 /// Check database health
 pub async fn check_database_health() -> Result<bool> {
-    let db_mutex = get_database()?;
-    let db_guard = db_mutex.lock().unwrap();
-
-    match db_guard.as_ref() {
-        Some(db) => db.health_check().await,
-        None => Err(NeuralBridgeError::database("Database not initialized")),
-    }
+    global_database()?.health_check().await
 }
 
 #[cfg(test)]
 mod tests {
+    use super::models::*;
     use super::*;
 
     #[test]
@@ -204,4 +426,116 @@ mod tests {
         let result = initialize_database(None).await;
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_sqlite_path_parsing() {
+        let config = DatabaseConfig::default();
+        assert_eq!(
+            config.sqlite_path().unwrap(),
+            Some(PathBuf::from("/tmp/neural_bridge.db"))
+        );
+        assert_eq!(DatabaseConfig::in_memory().sqlite_path().unwrap(), None);
+
+        let config = DatabaseConfig {
+            database_url: "postgres://localhost/db".to_string(),
+            ..DatabaseConfig::default()
+        };
+        assert!(config.sqlite_path().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_record_round_trip() {
+        let db = Database::new(DatabaseConfig::in_memory());
+        db.initialize().await.unwrap();
+        assert!(db.health_check().await.unwrap());
+
+        let user = User::new(
+            "testuser".to_string(),
+            "test@example.com".to_string(),
+            "hashed_password".to_string(),
+        );
+        let project = Project::new("Project".to_string(), None, user.id);
+        let mut task = Task::new(project.id, "Task".to_string(), "Desc".to_string());
+        task.status = TaskStatus::InProgress;
+        task.tags = vec!["backend".to_string()];
+        task.metadata = serde_json::json!({ "attempt": 2 });
+
+        db.save(&user).await.unwrap();
+        db.save(&project).await.unwrap();
+        db.save(&task).await.unwrap();
+
+        let loaded: Task = db.find_by_id(task.id).await.unwrap().unwrap();
+        assert_eq!(loaded.title, "Task");
+        assert!(matches!(loaded.status, TaskStatus::InProgress));
+        assert_eq!(loaded.tags, vec!["backend".to_string()]);
+        assert_eq!(loaded.metadata["attempt"], 2);
+        assert_eq!(loaded.created_at, task.created_at);
+
+        let tasks: Vec<Task> = db
+            .fetch_with_params(
+                "SELECT * FROM tasks WHERE project_id = ?1",
+                vec![models::uuid_value(project.id)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(tasks.len(), 1);
+
+        assert!(db.delete_by_id::<Task>(task.id).await.unwrap());
+        assert!(db.find_by_id::<Task>(task.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_saving_a_parent_keeps_its_children() {
+        let db = Database::new(DatabaseConfig::in_memory());
+        db.initialize().await.unwrap();
+
+        let user = User::new(
+            "testuser".to_string(),
+            "test@example.com".to_string(),
+            "hashed_password".to_string(),
+        );
+        let mut project = Project::new("Project".to_string(), None, user.id);
+        let task = Task::new(project.id, "Task".to_string(), "Desc".to_string());
+        db.save(&user).await.unwrap();
+        db.save(&project).await.unwrap();
+        db.save(&task).await.unwrap();
+
+        project.name = "Renamed".to_string();
+        db.save(&project).await.unwrap();
+        db.save_all(&[project.clone()]).await.unwrap();
+
+        let loaded: Project = db.find_by_id(project.id).await.unwrap().unwrap();
+        assert_eq!(loaded.name, "Renamed");
+        assert!(db.find_by_id::<Task>(task.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_records_survive_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DatabaseConfig::for_path(dir.path().join("data/neural_bridge.db"));
+
+        let log = AuditLog {
+            id: uuid::Uuid::new_v4(),
+            user_id: None,
+            entity_type: "session".to_string(),
+            entity_id: uuid::Uuid::new_v4(),
+            action: AuditAction::Login,
+            details: serde_json::json!({ "source": "test" }),
+            ip_address: "127.0.0.1".to_string(),
+            user_agent: "tests".to_string(),
+            created_at: chrono::Utc::now(),
+        };
+
+        let db = Database::new(config.clone());
+        db.initialize().await.unwrap();
+        db.save(&log).await.unwrap();
+        db.close();
+
+        let reopened = Database::new(config);
+        reopened.initialize().await.unwrap();
+        let logs: Vec<AuditLog> = reopened.fetch_all().await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].id, log.id);
+        assert!(matches!(logs[0].action, AuditAction::Login));
+    }
 }
@@ -1,9 +1,33 @@
 // AutoDev-AI Neural Bridge Platform - Database Models
 //! Data models for database entities
 
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::types::{Type, Value};
+use rusqlite::Row;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use uuid::Uuid;
 
+/// A model that maps onto a single SQLite table
+///
+/// Columns are read by name, so `from_row` works for any `SELECT *` on the
+/// table regardless of column order. `to_values` must return one value per
+/// entry in `COLUMNS`, in the same order.
+pub trait Record: Sized + Send + 'static {
+    /// Table the record is stored in
+    const TABLE: &'static str;
+    /// Column names, the first being the primary key
+    const COLUMNS: &'static [&'static str];
+
+    /// Primary key of this record
+    fn id(&self) -> Uuid;
+
+    /// Build the record from a result row
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self>;
+
+    /// Values to bind for `COLUMNS` when inserting or updating the record
+    fn to_values(&self) -> Vec<Value>;
+}
+
 /// User account model
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
@@ -422,6 +446,633 @@ impl Task {
     }
 }
 
+// SQLite value conversions
+//
+// UUIDs are stored as hyphenated text, timestamps as RFC 3339 text with
+// fixed nanosecond precision (so they sort lexically and round-trip exactly),
+// nested structures as JSON text and enums as their bare variant name where
+// possible.
+
+pub(crate) fn uuid_value(id: Uuid) -> Value {
+    Value::Text(id.to_string())
+}
+
+pub(crate) fn opt_uuid_value(id: Option<Uuid>) -> Value {
+    id.map(uuid_value).unwrap_or(Value::Null)
+}
+
+pub(crate) fn time_value(time: DateTime<Utc>) -> Value {
+    Value::Text(time.to_rfc3339_opts(SecondsFormat::Nanos, true))
+}
+
+pub(crate) fn opt_time_value(time: Option<DateTime<Utc>>) -> Value {
+    time.map(time_value).unwrap_or(Value::Null)
+}
+
+pub(crate) fn json_value<T: Serialize>(value: &T) -> Value {
+    Value::Text(serde_json::to_string(value).unwrap_or_else(|_| "null".to_string()))
+}
+
+/// Unit variants are stored as their name, data-carrying variants as JSON
+pub(crate) fn enum_value<T: Serialize>(value: &T) -> Value {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => Value::Text(name),
+        Ok(other) => Value::Text(other.to_string()),
+        Err(_) => Value::Null,
+    }
+}
+
+/// Conversion failure for `column`, or the error looking the column up
+fn conversion_error(
+    row: &Row<'_>,
+    column: &str,
+    err: impl std::error::Error + Send + Sync + 'static,
+) -> rusqlite::Error {
+    match row.as_ref().column_index(column) {
+        Ok(idx) => rusqlite::Error::FromSqlConversionFailure(idx, Type::Text, Box::new(err)),
+        Err(lookup) => lookup,
+    }
+}
+
+pub(crate) fn row_uuid(row: &Row<'_>, column: &str) -> rusqlite::Result<Uuid> {
+    let text: String = row.get(column)?;
+    Uuid::parse_str(&text).map_err(|e| conversion_error(row, column, e))
+}
+
+pub(crate) fn row_opt_uuid(row: &Row<'_>, column: &str) -> rusqlite::Result<Option<Uuid>> {
+    let text: Option<String> = row.get(column)?;
+    text.map(|t| Uuid::parse_str(&t).map_err(|e| conversion_error(row, column, e)))
+        .transpose()
+}
+
+pub(crate) fn row_time(row: &Row<'_>, column: &str) -> rusqlite::Result<DateTime<Utc>> {
+    let text: String = row.get(column)?;
+    parse_time(&text).map_err(|e| conversion_error(row, column, e))
+}
+
+pub(crate) fn row_opt_time(row: &Row<'_>, column: &str) -> rusqlite::Result<Option<DateTime<Utc>>> {
+    let text: Option<String> = row.get(column)?;
+    text.map(|t| parse_time(&t).map_err(|e| conversion_error(row, column, e)))
+        .transpose()
+}
+
+fn parse_time(text: &str) -> std::result::Result<DateTime<Utc>, chrono::ParseError> {
+    DateTime::parse_from_rfc3339(text).map(|t| t.with_timezone(&Utc))
+}
+
+pub(crate) fn row_json<T: DeserializeOwned>(row: &Row<'_>, column: &str) -> rusqlite::Result<T> {
+    let text: String = row.get(column)?;
+    serde_json::from_str(&text).map_err(|e| conversion_error(row, column, e))
+}
+
+pub(crate) fn row_enum<T: DeserializeOwned>(row: &Row<'_>, column: &str) -> rusqlite::Result<T> {
+    let text: String = row.get(column)?;
+    let value = if text.starts_with('{') {
+        serde_json::from_str(&text)
+    } else {
+        serde_json::from_value(serde_json::Value::String(text))
+    };
+    value.map_err(|e| conversion_error(row, column, e))
+}
+
+// Table mappings
+
+impl Record for User {
+    const TABLE: &'static str = "users";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "username",
+        "email",
+        "password_hash",
+        "is_active",
+        "created_at",
+        "updated_at",
+        "last_login",
+        "settings",
+    ];
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row_uuid(row, "id")?,
+            username: row.get("username")?,
+            email: row.get("email")?,
+            password_hash: row.get("password_hash")?,
+            is_active: row.get("is_active")?,
+            created_at: row_time(row, "created_at")?,
+            updated_at: row_time(row, "updated_at")?,
+            last_login: row_opt_time(row, "last_login")?,
+            settings: row_json(row, "settings")?,
+        })
+    }
+
+    fn to_values(&self) -> Vec<Value> {
+        vec![
+            uuid_value(self.id),
+            Value::Text(self.username.clone()),
+            Value::Text(self.email.clone()),
+            Value::Text(self.password_hash.clone()),
+            Value::Integer(self.is_active as i64),
+            time_value(self.created_at),
+            time_value(self.updated_at),
+            opt_time_value(self.last_login),
+            json_value(&self.settings),
+        ]
+    }
+}
+
+impl Record for Session {
+    const TABLE: &'static str = "sessions";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "user_id",
+        "session_token",
+        "created_at",
+        "expires_at",
+        "ip_address",
+        "user_agent",
+        "is_active",
+    ];
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row_uuid(row, "id")?,
+            user_id: row_uuid(row, "user_id")?,
+            session_token: row.get("session_token")?,
+            created_at: row_time(row, "created_at")?,
+            expires_at: row_time(row, "expires_at")?,
+            ip_address: row.get("ip_address")?,
+            user_agent: row.get("user_agent")?,
+            is_active: row.get("is_active")?,
+        })
+    }
+
+    fn to_values(&self) -> Vec<Value> {
+        vec![
+            uuid_value(self.id),
+            uuid_value(self.user_id),
+            Value::Text(self.session_token.clone()),
+            time_value(self.created_at),
+            time_value(self.expires_at),
+            Value::Text(self.ip_address.clone()),
+            Value::Text(self.user_agent.clone()),
+            Value::Integer(self.is_active as i64),
+        ]
+    }
+}
+
+impl Record for Project {
+    const TABLE: &'static str = "projects";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "description",
+        "owner_id",
+        "created_at",
+        "updated_at",
+        "is_archived",
+        "project_type",
+        "settings",
+    ];
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row_uuid(row, "id")?,
+            name: row.get("name")?,
+            description: row.get("description")?,
+            owner_id: row_uuid(row, "owner_id")?,
+            created_at: row_time(row, "created_at")?,
+            updated_at: row_time(row, "updated_at")?,
+            is_archived: row.get("is_archived")?,
+            project_type: row_enum(row, "project_type")?,
+            settings: row_json(row, "settings")?,
+        })
+    }
+
+    fn to_values(&self) -> Vec<Value> {
+        vec![
+            uuid_value(self.id),
+            Value::Text(self.name.clone()),
+            self.description
+                .clone()
+                .map(Value::Text)
+                .unwrap_or(Value::Null),
+            uuid_value(self.owner_id),
+            time_value(self.created_at),
+            time_value(self.updated_at),
+            Value::Integer(self.is_archived as i64),
+            enum_value(&self.project_type),
+            json_value(&self.settings),
+        ]
+    }
+}
+
+impl Record for Swarm {
+    const TABLE: &'static str = "swarms";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "project_id",
+        "name",
+        "topology",
+        "max_agents",
+        "created_at",
+        "status",
+        "configuration",
+    ];
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row_uuid(row, "id")?,
+            project_id: row_uuid(row, "project_id")?,
+            name: row.get("name")?,
+            topology: row_enum(row, "topology")?,
+            max_agents: row.get("max_agents")?,
+            created_at: row_time(row, "created_at")?,
+            status: row_enum(row, "status")?,
+            configuration: row_json(row, "configuration")?,
+        })
+    }
+
+    fn to_values(&self) -> Vec<Value> {
+        vec![
+            uuid_value(self.id),
+            uuid_value(self.project_id),
+            Value::Text(self.name.clone()),
+            enum_value(&self.topology),
+            Value::Integer(self.max_agents as i64),
+            time_value(self.created_at),
+            enum_value(&self.status),
+            json_value(&self.configuration),
+        ]
+    }
+}
+
+impl Record for Agent {
+    const TABLE: &'static str = "agents";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "swarm_id",
+        "agent_type",
+        "name",
+        "capabilities",
+        "status",
+        "created_at",
+        "last_active",
+        "performance_metrics",
+    ];
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row_uuid(row, "id")?,
+            swarm_id: row_uuid(row, "swarm_id")?,
+            agent_type: row_enum(row, "agent_type")?,
+            name: row.get("name")?,
+            capabilities: row_json(row, "capabilities")?,
+            status: row_enum(row, "status")?,
+            created_at: row_time(row, "created_at")?,
+            last_active: row_time(row, "last_active")?,
+            performance_metrics: row_json(row, "performance_metrics")?,
+        })
+    }
+
+    fn to_values(&self) -> Vec<Value> {
+        vec![
+            uuid_value(self.id),
+            uuid_value(self.swarm_id),
+            enum_value(&self.agent_type),
+            Value::Text(self.name.clone()),
+            json_value(&self.capabilities),
+            enum_value(&self.status),
+            time_value(self.created_at),
+            time_value(self.last_active),
+            json_value(&self.performance_metrics),
+        ]
+    }
+}
+
+impl Record for Task {
+    const TABLE: &'static str = "tasks";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "project_id",
+        "swarm_id",
+        "assigned_agent_id",
+        "title",
+        "description",
+        "priority",
+        "status",
+        "created_at",
+        "started_at",
+        "completed_at",
+        "estimated_duration",
+        "actual_duration",
+        "dependencies",
+        "tags",
+        "metadata",
+    ];
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row_uuid(row, "id")?,
+            project_id: row_uuid(row, "project_id")?,
+            swarm_id: row_opt_uuid(row, "swarm_id")?,
+            assigned_agent_id: row_opt_uuid(row, "assigned_agent_id")?,
+            title: row.get("title")?,
+            description: row.get("description")?,
+            priority: row_enum(row, "priority")?,
+            status: row_enum(row, "status")?,
+            created_at: row_time(row, "created_at")?,
+            started_at: row_opt_time(row, "started_at")?,
+            completed_at: row_opt_time(row, "completed_at")?,
+            estimated_duration: row.get("estimated_duration")?,
+            actual_duration: row.get("actual_duration")?,
+            dependencies: row_json(row, "dependencies")?,
+            tags: row_json(row, "tags")?,
+            metadata: row_json(row, "metadata")?,
+        })
+    }
+
+    fn to_values(&self) -> Vec<Value> {
+        vec![
+            uuid_value(self.id),
+            uuid_value(self.project_id),
+            opt_uuid_value(self.swarm_id),
+            opt_uuid_value(self.assigned_agent_id),
+            Value::Text(self.title.clone()),
+            Value::Text(self.description.clone()),
+            enum_value(&self.priority),
+            enum_value(&self.status),
+            time_value(self.created_at),
+            opt_time_value(self.started_at),
+            opt_time_value(self.completed_at),
+            self.estimated_duration
+                .map(|d| Value::Integer(d as i64))
+                .unwrap_or(Value::Null),
+            self.actual_duration
+                .map(|d| Value::Integer(d as i64))
+                .unwrap_or(Value::Null),
+            json_value(&self.dependencies),
+            json_value(&self.tags),
+            json_value(&self.metadata),
+        ]
+    }
+}
+
+impl Record for AuditLog {
+    const TABLE: &'static str = "audit_logs";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "user_id",
+        "entity_type",
+        "entity_id",
+        "action",
+        "details",
+        "ip_address",
+        "user_agent",
+        "created_at",
+    ];
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row_uuid(row, "id")?,
+            user_id: row_opt_uuid(row, "user_id")?,
+            entity_type: row.get("entity_type")?,
+            entity_id: row_uuid(row, "entity_id")?,
+            action: row_enum(row, "action")?,
+            details: row_json(row, "details")?,
+            ip_address: row.get("ip_address")?,
+            user_agent: row.get("user_agent")?,
+            created_at: row_time(row, "created_at")?,
+        })
+    }
+
+    fn to_values(&self) -> Vec<Value> {
+        vec![
+            uuid_value(self.id),
+            opt_uuid_value(self.user_id),
+            Value::Text(self.entity_type.clone()),
+            uuid_value(self.entity_id),
+            enum_value(&self.action),
+            json_value(&self.details),
+            Value::Text(self.ip_address.clone()),
+            Value::Text(self.user_agent.clone()),
+            time_value(self.created_at),
+        ]
+    }
+}
+
+impl Record for Configuration {
+    const TABLE: &'static str = "configurations";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "key",
+        "value",
+        "description",
+        "is_sensitive",
+        "created_at",
+        "updated_at",
+        "updated_by",
+    ];
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row_uuid(row, "id")?,
+            key: row.get("key")?,
+            value: row_json(row, "value")?,
+            description: row.get("description")?,
+            is_sensitive: row.get("is_sensitive")?,
+            created_at: row_time(row, "created_at")?,
+            updated_at: row_time(row, "updated_at")?,
+            updated_by: row_uuid(row, "updated_by")?,
+        })
+    }
+
+    fn to_values(&self) -> Vec<Value> {
+        vec![
+            uuid_value(self.id),
+            Value::Text(self.key.clone()),
+            json_value(&self.value),
+            self.description
+                .clone()
+                .map(Value::Text)
+                .unwrap_or(Value::Null),
+            Value::Integer(self.is_sensitive as i64),
+            time_value(self.created_at),
+            time_value(self.updated_at),
+            uuid_value(self.updated_by),
+        ]
+    }
+}
+
+impl Record for FileStorage {
+    const TABLE: &'static str = "file_storage";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "project_id",
+        "filename",
+        "file_path",
+        "file_size",
+        "mime_type",
+        "checksum",
+        "uploaded_by",
+        "created_at",
+        "is_deleted",
+    ];
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row_uuid(row, "id")?,
+            project_id: row_opt_uuid(row, "project_id")?,
+            filename: row.get("filename")?,
+            file_path: row.get("file_path")?,
+            file_size: row.get::<_, i64>("file_size")? as u64,
+            mime_type: row.get("mime_type")?,
+            checksum: row.get("checksum")?,
+            uploaded_by: row_uuid(row, "uploaded_by")?,
+            created_at: row_time(row, "created_at")?,
+            is_deleted: row.get("is_deleted")?,
+        })
+    }
+
+    fn to_values(&self) -> Vec<Value> {
+        vec![
+            uuid_value(self.id),
+            opt_uuid_value(self.project_id),
+            Value::Text(self.filename.clone()),
+            Value::Text(self.file_path.clone()),
+            Value::Integer(self.file_size as i64),
+            Value::Text(self.mime_type.clone()),
+            Value::Text(self.checksum.clone()),
+            uuid_value(self.uploaded_by),
+            time_value(self.created_at),
+            Value::Integer(self.is_deleted as i64),
+        ]
+    }
+}
+
+impl Record for PerformanceMetrics {
+    const TABLE: &'static str = "performance_metrics";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "entity_type",
+        "entity_id",
+        "metric_name",
+        "metric_value",
+        "unit",
+        "timestamp",
+        "tags",
+    ];
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row_uuid(row, "id")?,
+            entity_type: row.get("entity_type")?,
+            entity_id: row_uuid(row, "entity_id")?,
+            metric_name: row.get("metric_name")?,
+            metric_value: row.get("metric_value")?,
+            unit: row.get("unit")?,
+            timestamp: row_time(row, "timestamp")?,
+            tags: row_json(row, "tags")?,
+        })
+    }
+
+    fn to_values(&self) -> Vec<Value> {
+        vec![
+            uuid_value(self.id),
+            Value::Text(self.entity_type.clone()),
+            uuid_value(self.entity_id),
+            Value::Text(self.metric_name.clone()),
+            Value::Real(self.metric_value),
+            Value::Text(self.unit.clone()),
+            time_value(self.timestamp),
+            json_value(&self.tags),
+        ]
+    }
+}
+
+impl Record for MemoryStore {
+    const TABLE: &'static str = "memory_store";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "namespace",
+        "key",
+        "value",
+        "ttl",
+        "created_at",
+        "updated_at",
+        "access_count",
+    ];
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row_uuid(row, "id")?,
+            namespace: row.get("namespace")?,
+            key: row.get("key")?,
+            value: row_json(row, "value")?,
+            ttl: row_opt_time(row, "ttl")?,
+            created_at: row_time(row, "created_at")?,
+            updated_at: row_time(row, "updated_at")?,
+            access_count: row.get("access_count")?,
+        })
+    }
+
+    fn to_values(&self) -> Vec<Value> {
+        vec![
+            uuid_value(self.id),
+            Value::Text(self.namespace.clone()),
+            Value::Text(self.key.clone()),
+            json_value(&self.value),
+            opt_time_value(self.ttl),
+            time_value(self.created_at),
+            time_value(self.updated_at),
+            Value::Integer(self.access_count as i64),
+        ]
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -499,4 +1150,29 @@ mod tests {
             assert_eq!(format!("{:?}", agent_type), format!("{:?}", deserialized));
         }
     }
+
+    #[test]
+    fn test_record_columns_match_values() {
+        let user = User::new(
+            "testuser".to_string(),
+            "test@example.com".to_string(),
+            "hashed_password".to_string(),
+        );
+        assert_eq!(user.to_values().len(), User::COLUMNS.len());
+
+        let task = Task::new(Uuid::new_v4(), "Task".to_string(), "Desc".to_string());
+        assert_eq!(task.to_values().len(), Task::COLUMNS.len());
+    }
+
+    #[test]
+    fn test_enum_value_encoding() {
+        assert_eq!(
+            enum_value(&TaskStatus::InProgress),
+            Value::Text("InProgress".to_string())
+        );
+        assert_eq!(
+            enum_value(&SwarmStatus::Error("boom".to_string())),
+            Value::Text(r#"{"Error":"boom"}"#.to_string())
+        );
+    }
 }
@@ -1,11 +1,22 @@
 // AutoDev-AI Neural Bridge Platform - Database Schema
 //! Database schema definitions and migrations
+//!
+//! Applied migrations are recorded in the `schema_migrations` table together
+//! with a checksum of their SQL. Each migration runs in its own transaction,
+//! and a database whose recorded checksums no longer match the migrations
+//! compiled into the application is refused rather than silently migrated.
 
+use super::Database;
 use crate::errors::{NeuralBridgeError, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
 use tracing::{info, warn};
 
 /// Database schema version
-pub const SCHEMA_VERSION: u32 = 1;
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Table recording applied migrations
+pub const MIGRATIONS_TABLE: &str = "schema_migrations";
 
 /// Schema migration
 #[derive(Debug, Clone)]
@@ -16,6 +27,27 @@ pub struct Migration {
     pub down_sql: String,
 }
 
+impl Migration {
+    /// SHA-256 checksum over the migration's up and down SQL
+    pub fn checksum(&self) -> String {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        hasher.update(self.up_sql.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(self.down_sql.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// A migration as recorded in the `schema_migrations` table
+#[derive(Debug, Clone)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: chrono::DateTime<chrono::Utc>,
+}
+
 /// Database schema manager
 pub struct SchemaManager {
     migrations: Vec<Migration>,
@@ -24,97 +56,77 @@ pub struct SchemaManager {
 impl SchemaManager {
     /// Create a new schema manager
     pub fn new() -> Self {
-        Self {
-            migrations: create_migrations(),
-        }
+        Self::with_migrations(create_migrations())
     }
 
-    /// Apply all pending migrations
-    pub async fn migrate_up(&self) -> Result<()> {
-        info!("Applying database migrations");
+    /// Create a schema manager for a custom migration set
+    pub fn with_migrations(mut migrations: Vec<Migration>) -> Self {
+        migrations.sort_by_key(|m| m.version);
+        Self { migrations }
+    }
 
-        let current_version = self.get_current_schema_version().await?;
+    /// Apply all pending migrations, returning the resulting schema version
+    pub async fn migrate_up(&self, db: &Database) -> Result<u32> {
+        info!("Applying database migrations");
 
-        for migration in &self.migrations {
-            if migration.version > current_version {
-                info!(
-                    "Applying migration: {} (v{})",
-                    migration.name, migration.version
-                );
-                self.apply_migration(migration, true).await?;
-            }
-        }
+        let migrations = self.migrations.clone();
+        let version = db
+            .with_connection(move |conn| Ok(migrate_up_blocking(conn, &migrations)))
+            .await??;
 
         info!("All migrations applied successfully");
-        Ok(())
+        Ok(version)
     }
 
     /// Rollback to a specific schema version
-    pub async fn migrate_down(&self, target_version: u32) -> Result<()> {
+    pub async fn migrate_down(&self, db: &Database, target_version: u32) -> Result<()> {
         info!("Rolling back migrations to version {}", target_version);
 
-        let current_version = self.get_current_schema_version().await?;
-
-        for migration in self.migrations.iter().rev() {
-            if migration.version > target_version && migration.version <= current_version {
-                info!(
-                    "Rolling back migration: {} (v{})",
-                    migration.name, migration.version
-                );
-                self.apply_migration(migration, false).await?;
-            }
-        }
+        let migrations = self.migrations.clone();
+        db.with_connection(move |conn| {
+            Ok(migrate_down_blocking(conn, &migrations, target_version))
+        })
+        .await??;
 
         info!("Rollback completed successfully");
         Ok(())
     }
 
     /// Get current schema version
-    async fn get_current_schema_version(&self) -> Result<u32> {
-        // In a real implementation, this would query the database
-        // for the current schema version from a metadata table
-        Ok(0)
+    pub async fn get_current_schema_version(&self, db: &Database) -> Result<u32> {
+        db.with_connection(|conn| {
+            ensure_migrations_table(conn)?;
+            current_version(conn)
+        })
+        .await
     }
 
-    /// Apply a single migration
-    async fn apply_migration(&self, migration: &Migration, up: bool) -> Result<()> {
-        let sql = if up {
-            &migration.up_sql
-        } else {
-            &migration.down_sql
-        };
-
-        // In a real implementation, this would execute the SQL
-        info!("Executing SQL: {}", sql);
-
-        // Update schema version in metadata table
-        if up {
-            self.update_schema_version(migration.version).await?;
-        } else {
-            self.update_schema_version(migration.version - 1).await?;
-        }
-
-        Ok(())
-    }
-
-    /// Update schema version in metadata table
-    async fn update_schema_version(&self, version: u32) -> Result<()> {
-        // In a real implementation, this would update the schema version
-        info!("Updated schema version to: {}", version);
-        Ok(())
+    /// List migrations recorded in the version table, oldest first
+    pub async fn applied_migrations(&self, db: &Database) -> Result<Vec<AppliedMigration>> {
+        db.with_connection(|conn| {
+            ensure_migrations_table(conn)?;
+            load_applied(conn)
+        })
+        .await
     }
 
     /// Validate schema integrity
-    pub async fn validate_schema(&self) -> Result<bool> {
+    ///
+    /// Returns `false` when migrations are still pending; fails when an
+    /// applied migration has been modified or is unknown to this build.
+    pub async fn validate_schema(&self, db: &Database) -> Result<bool> {
         info!("Validating database schema");
 
-        // In a real implementation, this would:
-        // 1. Check if all required tables exist
-        // 2. Validate table structures
-        // 3. Check foreign key constraints
-        // 4. Verify indexes exist
+        let applied = self.applied_migrations(db).await?;
+        verify_checksums(&self.migrations, &applied)?;
+
+        let latest = self.migrations.last().map(|m| m.version).unwrap_or(0);
+        let current = applied.last().map(|m| m.version).unwrap_or(0);
+        if current < latest {
+            warn!("Schema is at v{} but v{} is available", current, latest);
+        }
 
-        Ok(true)
+        Ok(current == latest)
     }
 
     /// Generate schema documentation
@@ -199,6 +211,161 @@ impl SchemaManager {
     }
 }
 
+fn ensure_migrations_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS {} (
+            version INTEGER PRIMARY KEY NOT NULL,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        MIGRATIONS_TABLE
+    ))
+}
+
+fn current_version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.query_row(
+        &format!("SELECT MAX(version) FROM {}", MIGRATIONS_TABLE),
+        [],
+        |row| row.get::<_, Option<u32>>(0),
+    )
+    .map(|version| version.unwrap_or(0))
+}
+
+fn load_applied(conn: &Connection) -> rusqlite::Result<Vec<AppliedMigration>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT version, name, checksum, applied_at FROM {} ORDER BY version",
+        MIGRATIONS_TABLE
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        Ok(AppliedMigration {
+            version: row.get(0)?,
+            name: row.get(1)?,
+            checksum: row.get(2)?,
+            applied_at: super::models::row_time(row, "applied_at")?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Ensure every recorded migration is known and unchanged
+fn verify_checksums(migrations: &[Migration], applied: &[AppliedMigration]) -> Result<()> {
+    let known: HashMap<u32, &Migration> = migrations.iter().map(|m| (m.version, m)).collect();
+
+    for record in applied {
+        match known.get(&record.version) {
+            Some(migration) => {
+                let expected = migration.checksum();
+                if expected != record.checksum {
+                    return Err(NeuralBridgeError::database(format!(
+                        "Checksum mismatch for applied migration v{} ({}): recorded {}, expected {}",
+                        record.version, record.name, record.checksum, expected
+                    )));
+                }
+            }
+            None => {
+                return Err(NeuralBridgeError::database(format!(
+                    "Database has migration v{} ({}) applied which is unknown to this build",
+                    record.version, record.name
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn migrate_up_blocking(conn: &mut Connection, migrations: &[Migration]) -> Result<u32> {
+    ensure_migrations_table(conn)?;
+    verify_checksums(migrations, &load_applied(conn)?)?;
+
+    let mut version = current_version(conn)?;
+    let pending: Vec<&Migration> = migrations.iter().filter(|m| m.version > version).collect();
+
+    for migration in pending {
+        info!(
+            "Applying migration: {} (v{})",
+            migration.name, migration.version
+        );
+
+        let tx = conn.transaction()?;
+        tx.execute_batch(&migration.up_sql).map_err(|e| {
+            NeuralBridgeError::database(format!(
+                "Migration v{} ({}) failed: {}",
+                migration.version, migration.name, e
+            ))
+        })?;
+        tx.execute(
+            &format!(
+                "INSERT INTO {} (version, name, checksum, applied_at) VALUES (?1, ?2, ?3, ?4)",
+                MIGRATIONS_TABLE
+            ),
+            params![
+                migration.version,
+                migration.name,
+                migration.checksum(),
+                super::models::time_value(chrono::Utc::now()),
+            ],
+        )?;
+        tx.commit()?;
+
+        version = migration.version;
+    }
+
+    Ok(version)
+}
+
+fn migrate_down_blocking(
+    conn: &mut Connection,
+    migrations: &[Migration],
+    target_version: u32,
+) -> Result<()> {
+    ensure_migrations_table(conn)?;
+    verify_checksums(migrations, &load_applied(conn)?)?;
+
+    for migration in migrations.iter().rev() {
+        let applied = conn
+            .query_row(
+                &format!("SELECT 1 FROM {} WHERE version = ?1", MIGRATIONS_TABLE),
+                [migration.version],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+
+        if migration.version <= target_version || !applied {
+            continue;
+        }
+
+        info!(
+            "Rolling back migration: {} (v{})",
+            migration.name, migration.version
+        );
+
+        let tx = conn.transaction()?;
+        tx.execute_batch(&migration.down_sql).map_err(|e| {
+            NeuralBridgeError::database(format!(
+                "Rollback of migration v{} ({}) failed: {}",
+                migration.version, migration.name, e
+            ))
+        })?;
+        tx.execute(
+            &format!("DELETE FROM {} WHERE version = ?1", MIGRATIONS_TABLE),
+            [migration.version],
+        )?;
+        tx.commit()?;
+    }
+
+    if current_version(conn)? > target_version {
+        warn!(
+            "Schema is still above version {} after rollback",
+            target_version
+        );
+    }
+
+    Ok(())
+}
+
 /// Create all database migrations
 fn create_migrations() -> Vec<Migration> {
     vec![
@@ -207,178 +374,169 @@ fn create_migrations() -> Vec<Migration> {
             name: "Initial schema".to_string(),
             up_sql: r#"
                 -- Users table
-                CREATE TABLE users (
-                    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-                    username VARCHAR(255) UNIQUE NOT NULL,
-                    email VARCHAR(255) UNIQUE NOT NULL,
-                    password_hash VARCHAR(255) NOT NULL,
-                    is_active BOOLEAN DEFAULT true,
-                    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                    last_login TIMESTAMP WITH TIME ZONE,
-                    settings JSONB DEFAULT '{}'
+                CREATE TABLE IF NOT EXISTS users (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    username TEXT UNIQUE NOT NULL,
+                    email TEXT UNIQUE NOT NULL,
+                    password_hash TEXT NOT NULL,
+                    is_active INTEGER NOT NULL DEFAULT 1,
+                    created_at TEXT NOT NULL,
+                    updated_at TEXT NOT NULL,
+                    last_login TEXT,
+                    settings TEXT NOT NULL DEFAULT '{}'
                 );
 
                 -- Sessions table
-                CREATE TABLE sessions (
-                    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-                    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
-                    session_token VARCHAR(255) UNIQUE NOT NULL,
-                    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
-                    ip_address INET,
-                    user_agent TEXT,
-                    is_active BOOLEAN DEFAULT true
+                CREATE TABLE IF NOT EXISTS sessions (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                    session_token TEXT UNIQUE NOT NULL,
+                    created_at TEXT NOT NULL,
+                    expires_at TEXT NOT NULL,
+                    ip_address TEXT NOT NULL DEFAULT '',
+                    user_agent TEXT NOT NULL DEFAULT '',
+                    is_active INTEGER NOT NULL DEFAULT 1
                 );
 
                 -- Projects table
-                CREATE TABLE projects (
-                    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-                    name VARCHAR(255) NOT NULL,
+                CREATE TABLE IF NOT EXISTS projects (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    name TEXT NOT NULL,
                     description TEXT,
-                    owner_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
-                    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                    is_archived BOOLEAN DEFAULT false,
-                    project_type VARCHAR(50) DEFAULT 'Other',
-                    settings JSONB DEFAULT '{}'
+                    owner_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                    created_at TEXT NOT NULL,
+                    updated_at TEXT NOT NULL,
+                    is_archived INTEGER NOT NULL DEFAULT 0,
+                    project_type TEXT NOT NULL DEFAULT 'Other',
+                    settings TEXT NOT NULL DEFAULT '{}'
                 );
 
                 -- Swarms table
-                CREATE TABLE swarms (
-                    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-                    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
-                    name VARCHAR(255) NOT NULL,
-                    topology VARCHAR(50) NOT NULL,
-                    max_agents INTEGER DEFAULT 8,
-                    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                    status VARCHAR(50) DEFAULT 'Initializing',
-                    configuration JSONB DEFAULT '{}'
+                CREATE TABLE IF NOT EXISTS swarms (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+                    name TEXT NOT NULL,
+                    topology TEXT NOT NULL,
+                    max_agents INTEGER NOT NULL DEFAULT 8,
+                    created_at TEXT NOT NULL,
+                    status TEXT NOT NULL DEFAULT 'Initializing',
+                    configuration TEXT NOT NULL DEFAULT '{}'
                 );
 
                 -- Agents table
-                CREATE TABLE agents (
-                    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-                    swarm_id UUID NOT NULL REFERENCES swarms(id) ON DELETE CASCADE,
-                    agent_type VARCHAR(100) NOT NULL,
-                    name VARCHAR(255) NOT NULL,
-                    capabilities JSONB DEFAULT '[]',
-                    status VARCHAR(50) DEFAULT 'Idle',
-                    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                    last_active TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                    performance_metrics JSONB DEFAULT '{}'
+                CREATE TABLE IF NOT EXISTS agents (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    swarm_id TEXT NOT NULL REFERENCES swarms(id) ON DELETE CASCADE,
+                    agent_type TEXT NOT NULL,
+                    name TEXT NOT NULL,
+                    capabilities TEXT NOT NULL DEFAULT '[]',
+                    status TEXT NOT NULL DEFAULT 'Idle',
+                    created_at TEXT NOT NULL,
+                    last_active TEXT NOT NULL,
+                    performance_metrics TEXT NOT NULL DEFAULT '{}'
                 );
 
                 -- Tasks table
-                CREATE TABLE tasks (
-                    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-                    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
-                    swarm_id UUID REFERENCES swarms(id) ON DELETE SET NULL,
-                    assigned_agent_id UUID REFERENCES agents(id) ON DELETE SET NULL,
-                    title VARCHAR(255) NOT NULL,
-                    description TEXT,
-                    priority VARCHAR(20) DEFAULT 'Medium',
-                    status VARCHAR(20) DEFAULT 'Pending',
-                    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                    started_at TIMESTAMP WITH TIME ZONE,
-                    completed_at TIMESTAMP WITH TIME ZONE,
+                CREATE TABLE IF NOT EXISTS tasks (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+                    swarm_id TEXT REFERENCES swarms(id) ON DELETE SET NULL,
+                    assigned_agent_id TEXT REFERENCES agents(id) ON DELETE SET NULL,
+                    title TEXT NOT NULL,
+                    description TEXT NOT NULL DEFAULT '',
+                    priority TEXT NOT NULL DEFAULT 'Medium',
+                    status TEXT NOT NULL DEFAULT 'Pending',
+                    created_at TEXT NOT NULL,
+                    started_at TEXT,
+                    completed_at TEXT,
                     estimated_duration INTEGER, -- seconds
                     actual_duration INTEGER,    -- seconds
-                    dependencies JSONB DEFAULT '[]',
-                    tags JSONB DEFAULT '[]',
-                    metadata JSONB DEFAULT '{}'
+                    dependencies TEXT NOT NULL DEFAULT '[]',
+                    tags TEXT NOT NULL DEFAULT '[]',
+                    metadata TEXT NOT NULL DEFAULT 'null'
                 );
 
                 -- Audit logs table
-                CREATE TABLE audit_logs (
-                    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-                    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
-                    entity_type VARCHAR(100) NOT NULL,
-                    entity_id UUID NOT NULL,
-                    action VARCHAR(50) NOT NULL,
-                    details JSONB DEFAULT '{}',
-                    ip_address INET,
-                    user_agent TEXT,
-                    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+                CREATE TABLE IF NOT EXISTS audit_logs (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    user_id TEXT REFERENCES users(id) ON DELETE SET NULL,
+                    entity_type TEXT NOT NULL,
+                    entity_id TEXT NOT NULL,
+                    action TEXT NOT NULL,
+                    details TEXT NOT NULL DEFAULT '{}',
+                    ip_address TEXT NOT NULL DEFAULT '',
+                    user_agent TEXT NOT NULL DEFAULT '',
+                    created_at TEXT NOT NULL
                 );
 
                 -- Configuration table
-                CREATE TABLE configurations (
-                    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-                    key VARCHAR(255) UNIQUE NOT NULL,
-                    value JSONB NOT NULL,
+                CREATE TABLE IF NOT EXISTS configurations (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    key TEXT UNIQUE NOT NULL,
+                    value TEXT NOT NULL,
                     description TEXT,
-                    is_sensitive BOOLEAN DEFAULT false,
-                    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                    updated_by UUID NOT NULL REFERENCES users(id)
+                    is_sensitive INTEGER NOT NULL DEFAULT 0,
+                    created_at TEXT NOT NULL,
+                    updated_at TEXT NOT NULL,
+                    updated_by TEXT NOT NULL
                 );
 
                 -- File storage table
-                CREATE TABLE file_storage (
-                    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-                    project_id UUID REFERENCES projects(id) ON DELETE CASCADE,
-                    filename VARCHAR(255) NOT NULL,
-                    file_path VARCHAR(1000) NOT NULL,
-                    file_size BIGINT NOT NULL,
-                    mime_type VARCHAR(255),
-                    checksum VARCHAR(64),
-                    uploaded_by UUID NOT NULL REFERENCES users(id),
-                    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                    is_deleted BOOLEAN DEFAULT false
+                CREATE TABLE IF NOT EXISTS file_storage (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    project_id TEXT REFERENCES projects(id) ON DELETE CASCADE,
+                    filename TEXT NOT NULL,
+                    file_path TEXT NOT NULL,
+                    file_size INTEGER NOT NULL,
+                    mime_type TEXT NOT NULL DEFAULT '',
+                    checksum TEXT NOT NULL DEFAULT '',
+                    uploaded_by TEXT NOT NULL,
+                    created_at TEXT NOT NULL,
+                    is_deleted INTEGER NOT NULL DEFAULT 0
                 );
 
                 -- Performance metrics table
-                CREATE TABLE performance_metrics (
-                    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-                    entity_type VARCHAR(100) NOT NULL,
-                    entity_id UUID NOT NULL,
-                    metric_name VARCHAR(255) NOT NULL,
-                    metric_value DOUBLE PRECISION NOT NULL,
-                    unit VARCHAR(50),
-                    timestamp TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                    tags JSONB DEFAULT '{}'
+                CREATE TABLE IF NOT EXISTS performance_metrics (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    entity_type TEXT NOT NULL,
+                    entity_id TEXT NOT NULL,
+                    metric_name TEXT NOT NULL,
+                    metric_value REAL NOT NULL,
+                    unit TEXT NOT NULL DEFAULT '',
+                    timestamp TEXT NOT NULL,
+                    tags TEXT NOT NULL DEFAULT '{}'
                 );
 
                 -- Memory store table for swarm coordination
-                CREATE TABLE memory_store (
-                    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-                    namespace VARCHAR(255) DEFAULT 'default',
-                    key VARCHAR(255) NOT NULL,
-                    value JSONB NOT NULL,
-                    ttl TIMESTAMP WITH TIME ZONE,
-                    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-                    access_count INTEGER DEFAULT 0
+                CREATE TABLE IF NOT EXISTS memory_store (
+                    id TEXT PRIMARY KEY NOT NULL,
+                    namespace TEXT NOT NULL DEFAULT 'default',
+                    key TEXT NOT NULL,
+                    value TEXT NOT NULL,
+                    ttl TEXT,
+                    created_at TEXT NOT NULL,
+                    updated_at TEXT NOT NULL,
+                    access_count INTEGER NOT NULL DEFAULT 0
                 );
 
-                -- Schema metadata table
-                CREATE TABLE schema_metadata (
-                    key VARCHAR(255) PRIMARY KEY,
-                    value VARCHAR(255) NOT NULL,
-                    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
-                );
-
-                -- Insert initial schema version
-                INSERT INTO schema_metadata (key, value) VALUES ('schema_version', '1');
-
                 -- Create indexes
-                CREATE INDEX idx_users_email ON users(email);
-                CREATE INDEX idx_users_username ON users(username);
-                CREATE INDEX idx_sessions_user_id ON sessions(user_id);
-                CREATE INDEX idx_sessions_token ON sessions(session_token);
-                CREATE INDEX idx_projects_owner_id ON projects(owner_id);
-                CREATE INDEX idx_swarms_project_id ON swarms(project_id);
-                CREATE INDEX idx_agents_swarm_id ON agents(swarm_id);
-                CREATE INDEX idx_tasks_project_id ON tasks(project_id);
-                CREATE INDEX idx_tasks_swarm_id ON tasks(swarm_id);
-                CREATE INDEX idx_tasks_agent_id ON tasks(assigned_agent_id);
-                CREATE INDEX idx_audit_logs_user_id ON audit_logs(user_id);
-                CREATE INDEX idx_audit_logs_entity ON audit_logs(entity_type, entity_id);
-                CREATE INDEX idx_file_storage_project_id ON file_storage(project_id);
-                CREATE INDEX idx_performance_metrics_entity ON performance_metrics(entity_type, entity_id);
-                CREATE INDEX idx_memory_store_namespace_key ON memory_store(namespace, key);
-                CREATE INDEX idx_memory_store_ttl ON memory_store(ttl) WHERE ttl IS NOT NULL;
+                CREATE INDEX IF NOT EXISTS idx_users_email ON users(email);
+                CREATE INDEX IF NOT EXISTS idx_users_username ON users(username);
+                CREATE INDEX IF NOT EXISTS idx_sessions_user_id ON sessions(user_id);
+                CREATE INDEX IF NOT EXISTS idx_sessions_token ON sessions(session_token);
+                CREATE INDEX IF NOT EXISTS idx_projects_owner_id ON projects(owner_id);
+                CREATE INDEX IF NOT EXISTS idx_swarms_project_id ON swarms(project_id);
+                CREATE INDEX IF NOT EXISTS idx_agents_swarm_id ON agents(swarm_id);
+                CREATE INDEX IF NOT EXISTS idx_tasks_project_id ON tasks(project_id);
+                CREATE INDEX IF NOT EXISTS idx_tasks_swarm_id ON tasks(swarm_id);
+                CREATE INDEX IF NOT EXISTS idx_tasks_agent_id ON tasks(assigned_agent_id);
+                CREATE INDEX IF NOT EXISTS idx_audit_logs_user_id ON audit_logs(user_id);
+                CREATE INDEX IF NOT EXISTS idx_audit_logs_entity ON audit_logs(entity_type, entity_id);
+                CREATE INDEX IF NOT EXISTS idx_audit_logs_created_at ON audit_logs(created_at);
+                CREATE INDEX IF NOT EXISTS idx_file_storage_project_id ON file_storage(project_id);
+                CREATE INDEX IF NOT EXISTS idx_performance_metrics_entity ON performance_metrics(entity_type, entity_id);
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_memory_store_namespace_key ON memory_store(namespace, key);
+                CREATE INDEX IF NOT EXISTS idx_memory_store_ttl ON memory_store(ttl) WHERE ttl IS NOT NULL;
             "#.to_string(),
             down_sql: r#"
                 DROP TABLE IF EXISTS memory_store;
@@ -392,7 +550,23 @@ fn create_migrations() -> Vec<Migration> {
                 DROP TABLE IF EXISTS projects;
                 DROP TABLE IF EXISTS sessions;
                 DROP TABLE IF EXISTS users;
-                DROP TABLE IF EXISTS schema_metadata;
+            "#.to_string(),
+        },
+        Migration {
+            version: 2,
+            name: "Security session records".to_string(),
+            up_sql: r#"
+                -- Encrypted sessions, activity logs, token blacklist and MFA enrollments
+                CREATE TABLE IF NOT EXISTS security_session_records (
+                    kind TEXT NOT NULL,
+                    record_key TEXT NOT NULL,
+                    payload BLOB NOT NULL,
+                    updated_at TEXT NOT NULL,
+                    PRIMARY KEY (kind, record_key)
+                );
+            "#.to_string(),
+            down_sql: r#"
+                DROP TABLE IF EXISTS security_session_records;
             "#.to_string(),
         },
     ]
@@ -407,6 +581,7 @@ impl Default for SchemaManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::database::DatabaseConfig;
 
     #[test]
     fn test_schema_manager_creation() {
@@ -417,7 +592,7 @@ mod tests {
     #[test]
     fn test_migration_structure() {
         let migrations = create_migrations();
-        assert_eq!(migrations.len(), 1);
+        assert_eq!(migrations.len(), SCHEMA_VERSION as usize);
 
         let first_migration = &migrations[0];
         assert_eq!(first_migration.version, 1);
@@ -439,11 +614,143 @@ mod tests {
         assert!(docs.contains("Tasks"));
     }
 
+    async fn memory_database() -> Database {
+        let db = Database::new(DatabaseConfig::in_memory());
+        db.initialize().await.unwrap();
+        db
+    }
+
+    async fn empty_database() -> Database {
+        let db = Database::new(DatabaseConfig::in_memory());
+        db.connect().await.unwrap();
+        db
+    }
+
+    async fn table_exists(db: &Database, table: &str) -> bool {
+        let table = table.to_string();
+        db.with_connection(move |conn| {
+            conn.query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                [&table],
+                |row| row.get::<_, i64>(0),
+            )
+        })
+        .await
+        .unwrap()
+            > 0
+    }
+
+    async fn column_exists(db: &Database, table: &str, column: &str) -> bool {
+        let (table, column) = (table.to_string(), column.to_string());
+        db.with_connection(move |conn| {
+            conn.query_row(
+                "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+                [&table, &column],
+                |row| row.get::<_, i64>(0),
+            )
+        })
+        .await
+        .unwrap()
+            > 0
+    }
+
+    fn test_migrations() -> Vec<Migration> {
+        vec![
+            Migration {
+                version: 1,
+                name: "Create notes".to_string(),
+                up_sql: "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT);".to_string(),
+                down_sql: "DROP TABLE notes;".to_string(),
+            },
+            Migration {
+                version: 2,
+                name: "Add note tags".to_string(),
+                up_sql: "ALTER TABLE notes ADD COLUMN tags TEXT;".to_string(),
+                down_sql: "ALTER TABLE notes DROP COLUMN tags;".to_string(),
+            },
+        ]
+    }
+
     #[tokio::test]
     async fn test_schema_validation() {
+        let db = memory_database().await;
         let manager = SchemaManager::new();
-        let result = manager.validate_schema().await;
+        let result = manager.validate_schema(&db).await;
         assert!(result.is_ok());
         assert!(result.unwrap());
     }
+
+    #[tokio::test]
+    async fn test_migrations_recorded_with_checksums() {
+        let db = memory_database().await;
+        let manager = SchemaManager::new();
+
+        assert_eq!(
+            manager.get_current_schema_version(&db).await.unwrap(),
+            SCHEMA_VERSION
+        );
+
+        let applied = manager.applied_migrations(&db).await.unwrap();
+        assert_eq!(applied.len(), SCHEMA_VERSION as usize);
+        assert_eq!(applied[0].checksum, create_migrations()[0].checksum());
+
+        // Re-running is a no-op
+        assert_eq!(manager.migrate_up(&db).await.unwrap(), SCHEMA_VERSION);
+        assert_eq!(
+            manager.applied_migrations(&db).await.unwrap().len(),
+            SCHEMA_VERSION as usize
+        );
+    }
+
+    #[tokio::test]
+    async fn test_migrate_down_and_up_again() {
+        let db = empty_database().await;
+        let manager = SchemaManager::with_migrations(test_migrations());
+
+        assert_eq!(manager.migrate_up(&db).await.unwrap(), 2);
+        assert!(column_exists(&db, "notes", "tags").await);
+
+        manager.migrate_down(&db, 1).await.unwrap();
+        assert_eq!(manager.get_current_schema_version(&db).await.unwrap(), 1);
+        assert!(table_exists(&db, "notes").await);
+        assert!(!column_exists(&db, "notes", "tags").await);
+
+        manager.migrate_down(&db, 0).await.unwrap();
+        assert_eq!(manager.get_current_schema_version(&db).await.unwrap(), 0);
+        assert!(!table_exists(&db, "notes").await);
+
+        assert_eq!(manager.migrate_up(&db).await.unwrap(), 2);
+        assert!(column_exists(&db, "notes", "tags").await);
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_rolls_back() {
+        let db = empty_database().await;
+        let mut migrations = test_migrations();
+        migrations[1].up_sql =
+            "CREATE TABLE partial (id INTEGER); INSERT INTO missing VALUES (1);".to_string();
+        let manager = SchemaManager::with_migrations(migrations);
+
+        assert!(manager.migrate_up(&db).await.is_err());
+        assert_eq!(manager.get_current_schema_version(&db).await.unwrap(), 1);
+        assert!(table_exists(&db, "notes").await);
+        assert!(!table_exists(&db, "partial").await);
+    }
+
+    #[tokio::test]
+    async fn test_changed_checksum_is_refused() {
+        let db = empty_database().await;
+        SchemaManager::with_migrations(test_migrations())
+            .migrate_up(&db)
+            .await
+            .unwrap();
+
+        let mut modified = test_migrations();
+        modified[0].up_sql = "CREATE TABLE notes (id INTEGER PRIMARY KEY);".to_string();
+        let manager = SchemaManager::with_migrations(modified);
+
+        let err = manager.migrate_up(&db).await.unwrap_err();
+        assert!(err.message().contains("Checksum mismatch"));
+        assert!(manager.validate_schema(&db).await.is_err());
+    }
 }
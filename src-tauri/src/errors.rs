@@ -240,6 +240,12 @@ impl From<reqwest::Error> for NeuralBridgeError {
     }
 }
 
+impl From<rusqlite::Error> for NeuralBridgeError {
+    fn from(err: rusqlite::Error) -> Self {
+        Self::database(err.to_string())
+    }
+}
+
 impl From<tauri::Error> for NeuralBridgeError {
     fn from(err: tauri::Error) -> Self {
         Self::tauri(err.to_string())
@@ -34,6 +34,7 @@ pub enum EventCategory {
     Settings,
     Performance,
     Network,
+    FileSystem,
 }
 
 /// Event source information
@@ -274,6 +275,11 @@ impl EventSystem {
         Ok(())
     }
 
+    /// Receive every event as it is emitted, for subsystems in the backend
+    pub fn subscribe_realtime(&self) -> broadcast::Receiver<Event> {
+        self.broadcast_sender.subscribe()
+    }
+
     /// Get events matching a filter
     pub async fn get_events(&self, filter: EventFilter) -> Vec<Event> {
         let events = self.events.read().await;
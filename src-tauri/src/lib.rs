@@ -42,6 +42,7 @@ mod dev_window;
 mod logging;
 mod orchestration;
 mod performance;
+mod plugins;
 
 // Re-export commonly used types
 pub use errors::{NeuralBridgeError, Result};
@@ -58,10 +59,14 @@ pub const BUILD_TIMESTAMP: &str = env!("BUILD_TIMESTAMP");
 /// This function sets up logging, initializes core components,
 /// and prepares the platform for use.
 pub fn init() -> Result<()> {
+    use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
     tracing_subscriber::fmt()
         .with_max_level(tracing::Level::INFO)
         .with_target(true)
         .with_thread_ids(true)
+        .finish()
+        .with(performance::profiler::span_stack_layer())
         .init();
 
     tracing::info!("Neural Bridge Platform v{} initialized", VERSION);
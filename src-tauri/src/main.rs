@@ -3,20 +3,26 @@
 
 use tauri::Manager;
 use tracing::{debug, info, warn};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 // Module declarations
+mod api;
 mod app;
 mod commands;
+mod database;
 mod dev_window;
+mod errors;
 mod events;
 mod logging;
 mod menu;
 mod monitoring;
 mod orchestration;
 mod performance;
+mod plugins;
 mod security;
 mod settings;
 mod tray;
+mod types;
 mod window_state;
 
 #[tauri::command]
@@ -54,6 +60,8 @@ fn main() {
         .with_max_level(tracing::Level::INFO)
         .with_target(true)
         .with_thread_ids(true)
+        .finish()
+        .with(performance::profiler::span_stack_layer())
         .init();
 
     info!(
@@ -64,11 +72,52 @@ fn main() {
     tauri::Builder::default()
         // Core plugins
         .plugin(tauri_plugin_window_state::Builder::default().build())
+        .plugin(tauri_plugin_notification::init())
         // Setup hook for initializing plugins and state
         .setup(|app| {
             // Initialize AI Orchestration states
             app.manage(commands::ai_orchestration::AiOrchestrationState::default());
             app.manage(commands::enhanced_ai_commands::EnhancedAiState::default());
+
+            // Load the OpenRouter model catalogue used for routing
+            let openrouter = app
+                .state::<commands::enhanced_ai_commands::EnhancedAiState>()
+                .openrouter
+                .clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = openrouter.initialize_models().await {
+                    warn!("Failed to initialize OpenRouter models: {}", e);
+                }
+            });
+
+            // Register the workflow patterns defined in the workflow directory
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let state = handle.state::<commands::enhanced_ai_commands::EnhancedAiState>();
+                if let Err(e) = state.load_workflow_definitions().await {
+                    warn!("{}", e);
+                }
+            });
+
+            // Open the response cache, warm-loading the entries persisted on the last exit
+            tauri::async_runtime::spawn(async {
+                let config = performance::cache::CacheConfig {
+                    persistence_enabled: true,
+                    ..Default::default()
+                };
+                if let Err(e) = performance::cache::initialize_cache(config).await {
+                    warn!("Failed to initialize the response cache: {}", e);
+                }
+            });
+
+            // Create the profiler that samples the span stacks recorded by the subscriber
+            tauri::async_runtime::spawn(async {
+                let config = performance::profiler::ProfilerConfig::default();
+                if let Err(e) = performance::profiler::initialize_profiler(config).await {
+                    warn!("Failed to initialize the profiler: {}", e);
+                }
+            });
+
             info!("Setting up AutoDev-AI Neural Bridge Platform...");
 
             // Get app handle for async operations
@@ -105,6 +154,33 @@ fn main() {
                     warn!("Failed to setup event system: {}", e);
                 }
 
+                // Project file system access
+                if let Err(e) = plugins::file_system::setup_filesystem_plugin(app).await {
+                    warn!("Failed to setup file system plugin: {}", e);
+                }
+
+                // Notification center
+                if let Err(e) = plugins::notifications::setup_notifications_plugin(app).await {
+                    warn!("Failed to setup notifications plugin: {}", e);
+                }
+
+                // Keyboard shortcuts, bound globally where the OS allows it
+                if let Err(e) = plugins::global_shortcuts::setup_shortcuts_plugin(app).await {
+                    warn!("Failed to setup global shortcuts plugin: {}", e);
+                }
+
+                // Open the application database; the security layer keeps its sessions there
+                let database_config =
+                    directories::ProjectDirs::from("com", "autodev-ai", "neural-bridge-platform")
+                        .map(|dirs| {
+                            database::DatabaseConfig::for_path(
+                                dirs.data_dir().join("neural_bridge.db"),
+                            )
+                        });
+                if let Err(e) = database::initialize_database(database_config).await {
+                    warn!("Failed to initialize the database: {}", e);
+                }
+
                 // Initialize app setup (security, updater)
                 if let Err(e) = app::setup::setup_hook(app).await {
                     warn!("Failed to setup app components: {}", e);
@@ -123,6 +199,14 @@ fn main() {
                 }
             });
 
+            // Offer orchestration actions for keyboard shortcuts
+            if let Err(e) = app
+                .state::<commands::enhanced_ai_commands::EnhancedAiState>()
+                .register_shortcut_actions(&app_handle)
+            {
+                warn!("Failed to register orchestration shortcut actions: {}", e);
+            }
+
             // Create application menu
             let menu = menu::create_app_menu(&app_handle)?;
             app.set_menu(menu)?;
@@ -209,12 +293,17 @@ fn main() {
             security::ipc_security::create_security_session,
             security::ipc_security::validate_ipc_command,
             security::ipc_security::get_security_stats,
-            // Enhanced security commands (5 commands)
+            // Enhanced security commands (10 commands)
             security::enhanced_ipc_security::validate_ipc_command_enhanced,
             security::enhanced_ipc_security::create_enhanced_security_session,
             security::enhanced_ipc_security::get_enhanced_security_stats,
             security::enhanced_ipc_security::flush_security_logs,
             security::enhanced_ipc_security::cleanup_security_data,
+            security::enhanced_ipc_security::verify_audit_log,
+            security::enhanced_ipc_security::query_audit_events,
+            security::enhanced_ipc_security::enroll_mfa,
+            security::enhanced_ipc_security::confirm_mfa_enrollment,
+            security::enhanced_ipc_security::verify_mfa_code,
             // App setup and window state commands
             app::setup::get_setup_config,
             app::setup::update_setup_config,
@@ -237,6 +326,42 @@ fn main() {
             events::unsubscribe_from_events,
             events::get_event_stats,
             events::clear_events,
+            // Project file system commands
+            plugins::file_system::set_project_root,
+            plugins::file_system::read_directory,
+            plugins::file_system::read_file,
+            plugins::file_system::write_file,
+            plugins::file_system::create_directory,
+            plugins::file_system::delete_file,
+            plugins::file_system::list_trash,
+            plugins::file_system::restore_from_trash,
+            plugins::file_system::get_file_info_command,
+            plugins::file_system::get_project_structure,
+            plugins::file_system::get_filesystem_config,
+            plugins::file_system::update_filesystem_config,
+            plugins::file_system::search_files,
+            plugins::file_system::search_file_contents,
+            plugins::file_system::cancel_file_search,
+            plugins::file_system::get_search_index_status,
+            // Notification center commands
+            plugins::notifications::get_notifications,
+            plugins::notifications::get_unread_count,
+            plugins::notifications::mark_notification_read,
+            plugins::notifications::mark_all_notifications_read,
+            plugins::notifications::dismiss_notification,
+            plugins::notifications::clear_all_notifications,
+            plugins::notifications::get_notification_config,
+            plugins::notifications::update_notification_config,
+            plugins::notifications::send_test_notification,
+            plugins::notifications::handle_notification_action,
+            // Keyboard shortcut commands
+            plugins::global_shortcuts::get_shortcut_config,
+            plugins::global_shortcuts::update_shortcut_config,
+            plugins::global_shortcuts::get_available_shortcut_actions,
+            plugins::global_shortcuts::register_custom_shortcut,
+            plugins::global_shortcuts::unregister_shortcut,
+            plugins::global_shortcuts::test_shortcut,
+            plugins::global_shortcuts::trigger_shortcut,
             // Enhanced window state management commands
             window_state::save_current_window_state,
             window_state::get_window_states,
@@ -268,6 +393,18 @@ fn main() {
             commands::enhanced_ai_commands::get_enhanced_capabilities,
             commands::enhanced_ai_commands::get_openrouter_models,
             commands::enhanced_ai_commands::test_enhanced_orchestration,
+            commands::enhanced_ai_commands::execute_openrouter_request,
+            commands::enhanced_ai_commands::cancel_openrouter_stream,
+            commands::enhanced_ai_commands::create_openrouter_context,
+            // Performance system commands
+            commands::performance::initialize_performance_system,
+            commands::performance::configure_performance_system,
+            commands::performance::clear_performance_cache,
+            commands::performance::get_cache_statistics,
+            commands::performance::start_performance_profiling,
+            commands::performance::stop_performance_profiling,
+            commands::performance::export_flame_graph,
+            commands::performance::export_flame_graph_svg,
             // Monitoring system commands
             monitoring::logger::get_logging_stats,
             monitoring::logger::flush_logs_command,
@@ -279,6 +416,14 @@ fn main() {
             monitoring::security_integration::record_security_event,
             monitoring::security_integration::get_security_metrics
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running AutoDev-AI Neural Bridge Platform");
+        .build(tauri::generate_context!())
+        .expect("error while building AutoDev-AI Neural Bridge Platform")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                // Keep cached responses for the next start
+                if let Err(e) = tauri::async_runtime::block_on(performance::cache::persist_cache()) {
+                    warn!("Failed to persist the cache on exit: {}", e);
+                }
+            }
+        });
 }
@@ -1,10 +1,15 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 use tauri::{
     menu::{Menu, MenuItem, PredefinedMenuItem, Submenu},
     AppHandle, Emitter, Manager, Wry,
 };
 use tracing::{debug, info, warn};
 
+use crate::plugins::global_shortcuts::{
+    register_shortcut_action, ActionHandler, ShortcutAction, ShortcutScope,
+};
+
 /// Creates the application menu with File menu and native items
 pub fn create_app_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
     info!("Creating application menu");
@@ -24,10 +29,50 @@ pub fn create_app_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
     // Main menu
     let menu = Menu::with_items(app, &[&file_menu, &edit_menu, &view_menu, &help_menu])?;
 
+    if let Err(e) = register_shortcut_actions(app) {
+        warn!("Failed to register menu shortcut actions: {}", e);
+    }
+
     info!("Application menu created successfully");
     Ok(menu)
 }
 
+/// Offers menu actions for keyboard shortcuts; those without a handler are run by the
+/// frontend through the `shortcut-triggered` event
+fn register_shortcut_actions(app: &AppHandle) -> Result<(), String> {
+    let handle = app.clone();
+    let preferences: ActionHandler = Arc::new(move || {
+        show_preferences_dialog(&handle);
+        Ok(())
+    });
+
+    for (id, label, accelerator, handler) in [
+        (
+            "app.command_palette",
+            "Open the command palette",
+            "CmdOrCtrl+Shift+P",
+            None,
+        ),
+        (
+            "app.settings",
+            "Open settings",
+            "CmdOrCtrl+Comma",
+            Some(preferences),
+        ),
+        ("files.search", "Search files", "CmdOrCtrl+P", None),
+    ] {
+        let action = ShortcutAction {
+            id: id.to_string(),
+            label: label.to_string(),
+            source: "menu".to_string(),
+            default_accelerator: Some(accelerator.to_string()),
+            default_scope: ShortcutScope::App,
+        };
+        register_shortcut_action(app, action, handler)?;
+    }
+    Ok(())
+}
+
 /// Creates the File menu with comprehensive file operations
 fn create_file_menu(app: &AppHandle) -> tauri::Result<Submenu<Wry>> {
     info!("Creating File menu");
@@ -13,8 +13,7 @@
 //! - Adaptive AI workflow patterns based on task complexity
 
 // Enhanced orchestration modules - Phase 3 enhancements (modular architecture)
-// Note: Enhanced modules are available as separate components for advanced AI orchestration
-// They can be enabled via feature flags or separate initialization as needed
+pub mod enhanced;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -194,15 +193,21 @@ use std::time::Instant;
 use tokio::process::Command;
 use tracing::{debug, error, info};
 
+/// Environment variable carrying the request id into backend subprocesses
+pub const REQUEST_ID_ENV: &str = "AUTODEV_REQUEST_ID";
+
 #[derive(Debug, Clone)]
 pub struct ClaudeFlowService {
     pub base_path: String,
+    /// Launcher for the claude-flow CLI
+    pub program: String,
 }
 
 impl ClaudeFlowService {
     pub fn new() -> Self {
         Self {
             base_path: ".".to_string(),
+            program: "npx".to_string(),
         }
     }
 
@@ -252,7 +257,7 @@ impl ClaudeFlowService {
         };
 
         // Build Claude Flow command with integrated orchestration
-        let mut cmd = Command::new("npx");
+        let mut cmd = Command::new(&self.program);
 
         // Determine SPARC mode
         let sparc_mode = match request.sparc_mode {
@@ -293,6 +298,10 @@ impl ClaudeFlowService {
         // Set working directory
         cmd.current_dir(&self.base_path);
 
+        // Tag the process tree with the request, and stop it if the caller gives up
+        cmd.env(REQUEST_ID_ENV, &request.id);
+        cmd.kill_on_drop(true);
+
         debug!("Executing command: {:?}", cmd);
 
         // Execute the command
@@ -407,7 +416,7 @@ impl ClaudeFlowService {
             SwarmTopology::Adaptive => "mesh", // Default to mesh for adaptive
         };
 
-        let output = Command::new("npx")
+        let output = Command::new(&self.program)
             .args(["claude-flow@alpha", "swarm", "init", topology_str])
             .env("MAX_AGENTS", config.max_agents.to_string())
             .env("SWARM_ID", &swarm_id)
@@ -444,7 +453,7 @@ impl ClaudeFlowService {
         let agents_json = serde_json::to_string(&command.target_agents)?;
         let payload_json = serde_json::to_string(&command.payload)?;
 
-        let output = Command::new("npx")
+        let output = Command::new(&self.program)
             .args(["claude-flow@alpha", "hive", command_str])
             .env("HIVE_COMMAND_ID", &command.id)
             .env("TARGET_AGENTS", &agents_json)
@@ -468,7 +477,7 @@ impl ClaudeFlowService {
 
     /// Store data in persistent memory layer
     pub async fn store_memory(&self, key: &str, value: &str) -> Result<()> {
-        let output = Command::new("npx")
+        let output = Command::new(&self.program)
             .args(["claude-flow@alpha", "memory", "store", key, value])
             .env("MEMORY_NAMESPACE", "autodev-ai")
             .env("CROSS_SESSION", "true")
@@ -488,7 +497,7 @@ impl ClaudeFlowService {
 
     /// Retrieve data from persistent memory layer
     pub async fn retrieve_memory(&self, key: &str) -> Result<String> {
-        let output = Command::new("npx")
+        let output = Command::new(&self.program)
             .args(["claude-flow@alpha", "memory", "retrieve", key])
             .env("MEMORY_NAMESPACE", "autodev-ai")
             .output()
@@ -506,7 +515,7 @@ impl ClaudeFlowService {
 
     /// Collect swarm performance metrics
     pub async fn collect_swarm_metrics(&self, session_id: &str) -> Result<SwarmMetrics> {
-        let output = Command::new("npx")
+        let output = Command::new(&self.program)
             .args(["claude-flow@alpha", "swarm", "metrics", session_id])
             .output()
             .await?;
@@ -552,7 +561,7 @@ impl ClaudeFlowService {
     }
 
     pub async fn health_check(&self) -> Result<bool> {
-        let output = Command::new("npx")
+        let output = Command::new(&self.program)
             .args(["claude-flow@alpha", "--version"])
             .output()
             .await?;
@@ -595,6 +604,8 @@ impl CodexService {
         if let Some(ref api_key) = self.api_key {
             cmd.env("OPENAI_API_KEY", api_key);
         }
+        cmd.env(REQUEST_ID_ENV, &request.id);
+        cmd.kill_on_drop(true);
 
         debug!("Executing Codex Python script: {}", script_path);
 
@@ -1050,6 +1061,13 @@ pub struct EnhancedOrchestrationConfig {
     pub real_time_monitoring_enabled: bool,
     pub auto_scaling_enabled: bool,
     pub learning_enabled: bool,
+    /// Directory of YAML/JSON workflow definitions registered at startup
+    #[serde(default = "default_workflow_directory")]
+    pub workflow_directory: String,
+}
+
+fn default_workflow_directory() -> String {
+    "./workflows".to_string()
 }
 
 impl Default for EnhancedOrchestrationConfig {
@@ -1066,6 +1084,7 @@ impl Default for EnhancedOrchestrationConfig {
             real_time_monitoring_enabled: true,
             auto_scaling_enabled: true,
             learning_enabled: true,
+            workflow_directory: default_workflow_directory(),
         }
     }
 }
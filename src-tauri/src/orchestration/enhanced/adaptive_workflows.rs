@@ -4,26 +4,46 @@
 //! resource availability, and historical performance data.
 
 use super::*;
+use crate::orchestration::{ClaudeFlowService, CodexService, ExecutionRequest, REQUEST_ID_ENV};
 use anyhow::{anyhow, Result};
+use futures::stream::{FuturesUnordered, StreamExt};
 use serde_json::{json, Value};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
+use sysinfo::{ProcessRefreshKind, System, UpdateKind};
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 
 /// Adaptive workflow orchestrator
 #[derive(Debug)]
 pub struct AdaptiveWorkflowOrchestrator {
-    workflow_patterns: Arc<RwLock<HashMap<String, WorkflowPattern>>>,
+    pattern_library: Arc<PatternLibrary>,
     execution_history: Arc<RwLock<VecDeque<WorkflowExecution>>>,
     adaptation_engine: Arc<AdaptationEngine>,
     pattern_matcher: Arc<PatternMatcher>,
     complexity_analyzer: Arc<ComplexityAnalyzer>,
     resource_optimizer: Arc<ResourceOptimizer>,
+    backends: WorkflowBackends,
     config: AdaptiveWorkflowConfig,
 }
 
+/// Execution backends that workflow stages dispatch to
+#[derive(Debug, Clone, Default)]
+pub struct WorkflowBackends {
+    pub claude_flow: Option<ClaudeFlowService>,
+    pub codex: Option<CodexService>,
+    pub openrouter: Option<Arc<OpenRouterService>>,
+}
+
+/// Registry of workflow patterns available for execution
+#[derive(Debug, Default)]
+pub struct PatternLibrary {
+    patterns: RwLock<HashMap<String, WorkflowPattern>>,
+}
+
 /// Workflow pattern definition
 #[derive(Debug, Clone)]
 pub struct WorkflowPattern {
@@ -37,9 +57,22 @@ pub struct WorkflowPattern {
     pub resource_requirements: ResourceProfile,
     pub success_criteria: Vec<SuccessCriterion>,
     pub performance_metrics: PatternMetrics,
+    /// Overrides the orchestrator's default failure policy
+    pub failure_policy: Option<FailurePolicy>,
 }
 
-#[derive(Debug, Clone)]
+/// How a workflow reacts when a stage fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Cancel running stages and start no new ones
+    FailFast,
+    /// Keep running stages that do not depend on the failed one
+    ContinueIndependent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum WorkflowPatternType {
     Sequential,
     Parallel,
@@ -51,6 +84,13 @@ pub enum WorkflowPatternType {
     Hybrid,
 }
 
+impl WorkflowPatternType {
+    /// Whether each stage implicitly depends on the one listed before it
+    pub fn chains_stages(&self) -> bool {
+        matches!(self, WorkflowPatternType::Sequential | WorkflowPatternType::Pipeline)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ComplexityRange {
     pub min_complexity: f64,
@@ -63,6 +103,7 @@ pub struct WorkflowStage {
     pub stage_id: String,
     pub stage_type: StageType,
     pub description: String,
+    pub backend: StageBackend,
     pub agents_required: Vec<AgentRequirement>,
     pub dependencies: Vec<String>,
     pub timeout: Duration,
@@ -71,7 +112,8 @@ pub struct WorkflowStage {
     pub resource_allocation: ResourceAllocation,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum StageType {
     Analysis,
     Planning,
@@ -82,6 +124,25 @@ pub enum StageType {
     Monitoring,
 }
 
+/// Backend a stage's work is dispatched to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StageBackend {
+    ClaudeFlow,
+    Codex,
+    OpenRouter,
+}
+
+impl StageBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StageBackend::ClaudeFlow => "claude-flow",
+            StageBackend::Codex => "codex",
+            StageBackend::OpenRouter => "openrouter",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AgentRequirement {
     pub agent_type: String,
@@ -95,10 +156,13 @@ pub struct AgentRequirement {
 pub struct RetryPolicy {
     pub max_retries: u32,
     pub backoff_strategy: BackoffStrategy,
+    /// Delay before the first retry; later delays grow per `backoff_strategy`
+    pub base_delay: Duration,
     pub retry_conditions: Vec<RetryCondition>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum BackoffStrategy {
     Fixed,
     Linear,
@@ -119,7 +183,8 @@ pub struct AdaptationHook {
     pub actions: Vec<AdaptationAction>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum HookType {
     PreExecution,
     PostExecution,
@@ -137,7 +202,8 @@ pub struct TriggerCondition {
     pub duration: Option<Duration>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum AdaptationAction {
     ScaleAgents,
     ChangePattern,
@@ -157,7 +223,8 @@ pub struct ResourceAllocation {
     pub priority: ResourcePriority,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ResourcePriority {
     Low = 1,
     Normal = 2,
@@ -247,6 +314,9 @@ pub struct WorkflowExecution {
     pub resource_usage: ResourceUsageRecord,
     pub performance_metrics: ExecutionMetrics,
     pub outcomes: ExecutionOutcomes,
+    pub failure_policy: FailurePolicy,
+    /// Stages never started because a dependency failed or the workflow failed fast
+    pub skipped_stages: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -254,6 +324,8 @@ pub enum ExecutionStatus {
     Pending,
     Running,
     Completed,
+    /// Some stages failed, but the workflow continued with the stages independent of them
+    PartiallyCompleted,
     Failed,
     Timeout,
     Cancelled,
@@ -830,35 +902,90 @@ pub struct AdaptiveWorkflowConfig {
     pub performance_tracking_enabled: bool,
     pub max_pattern_cache_size: usize,
     pub adaptation_history_retention: Duration,
+    /// Upper bound on stages running at the same time
+    pub max_parallel_stages: usize,
+    pub failure_policy: FailurePolicy,
 }
 
 impl AdaptiveWorkflowOrchestrator {
     /// Create a new adaptive workflow orchestrator
     pub fn new(config: AdaptiveWorkflowConfig) -> Self {
         Self {
-            workflow_patterns: Arc::new(RwLock::new(HashMap::new())),
+            pattern_library: Arc::new(PatternLibrary::new()),
             execution_history: Arc::new(RwLock::new(VecDeque::new())),
             adaptation_engine: Arc::new(AdaptationEngine::new()),
             pattern_matcher: Arc::new(PatternMatcher::new()),
             complexity_analyzer: Arc::new(ComplexityAnalyzer::new()),
             resource_optimizer: Arc::new(ResourceOptimizer::new()),
+            backends: WorkflowBackends::default(),
             config,
         }
     }
 
+    /// Configure the backends stages are dispatched to
+    pub fn with_backends(mut self, backends: WorkflowBackends) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    /// Patterns registered for execution, including those loaded from definition files
+    pub fn pattern_library(&self) -> Arc<PatternLibrary> {
+        self.pattern_library.clone()
+    }
+
+    /// Load workflow definitions from `directory` and keep reloading them as files change
+    ///
+    /// Returns the report of the initial load; the watcher stops when dropped.
+    pub async fn load_workflow_definitions(
+        &self,
+        directory: impl Into<PathBuf>,
+    ) -> Result<(WorkflowLoadReport, WorkflowWatcher)> {
+        let mut loader = WorkflowDefinitionLoader::new(directory, self.pattern_library.clone());
+        let report = loader.reload().await?;
+        Ok((report, loader.watch()?))
+    }
+
     /// Execute a workflow with adaptive optimization
     pub async fn execute_workflow(&self, task_description: &str, requirements: HashMap<String, Value>) -> Result<WorkflowExecution> {
-        let execution_id = uuid::Uuid::new_v4().to_string();
-        info!("Starting adaptive workflow execution: {}", execution_id);
-
         // Analyze task complexity
         let complexity = self.complexity_analyzer.analyze_task_complexity(task_description, &requirements).await?;
 
-        // Find best matching pattern
-        let pattern_match = self.pattern_matcher.find_best_pattern(task_description, complexity, &requirements).await?;
+        // Prefer a registered pattern covering this complexity, otherwise adapt the best match
+        let pattern = match self.pattern_library.best_for_complexity(complexity).await {
+            Some(pattern) => pattern,
+            None => {
+                let pattern_match = self.pattern_matcher.find_best_pattern(task_description, complexity, &requirements).await?;
+                self.adaptation_engine.adapt_pattern(&pattern_match.pattern_id, &requirements).await?
+            }
+        };
+
+        self.run_pattern(pattern, complexity, task_description, requirements).await
+    }
+
+    /// Execute a specific registered pattern
+    pub async fn execute_pattern(&self, pattern_id: &str, task_description: &str, requirements: HashMap<String, Value>) -> Result<WorkflowExecution> {
+        let pattern = self.pattern_library
+            .get(pattern_id)
+            .await
+            .ok_or_else(|| anyhow!("Workflow pattern not found: {}", pattern_id))?;
+        let complexity = self.complexity_analyzer.analyze_task_complexity(task_description, &requirements).await?;
+
+        self.run_pattern(pattern, complexity, task_description, requirements).await
+    }
+
+    async fn run_pattern(
+        &self,
+        adapted_pattern: WorkflowPattern,
+        complexity: f64,
+        task_description: &str,
+        requirements: HashMap<String, Value>,
+    ) -> Result<WorkflowExecution> {
+        let execution_id = uuid::Uuid::new_v4().to_string();
+        info!("Starting adaptive workflow execution: {} (pattern {})", execution_id, adapted_pattern.pattern_id);
 
-        // Adapt pattern if necessary
-        let adapted_pattern = self.adaptation_engine.adapt_pattern(&pattern_match.pattern_id, &requirements).await?;
+        let stages = scheduled_stages(&adapted_pattern);
+        validate_stage_graph(&stages)?;
+        let failure_policy = adapted_pattern.failure_policy.unwrap_or(self.config.failure_policy);
 
         // Optimize resource allocation
         let resource_allocation = self.resource_optimizer.optimize_allocation(&adapted_pattern, complexity).await?;
@@ -897,17 +1024,72 @@ impl AdaptiveWorkflowOrchestrator {
                 cost_effectiveness: 0.0,
                 lessons_learned: Vec::new(),
             },
+            failure_policy,
+            skipped_stages: Vec::new(),
         };
 
-        // Execute each stage with monitoring and adaptation
-        for stage in &adapted_pattern.stages {
-            match self.execute_stage(stage, &requirements, &mut execution).await {
-                Ok(stage_execution) => {
-                    execution.stages_completed.push(stage_execution);
+        // Outputs of finished stages, fanned in to the stages that depend on them
+        let mut stage_outputs: HashMap<String, Value> = HashMap::new();
+        let mut pending: Vec<&WorkflowStage> = stages.iter().collect();
+        let mut failed: HashSet<String> = HashSet::new();
+        let mut launched: HashMap<String, SystemTime> = HashMap::new();
+        let mut running = FuturesUnordered::new();
+        let max_parallel = self.config.max_parallel_stages.max(1);
+        let mut halted = false;
+
+        loop {
+            // Stages downstream of a failure can never run
+            pending.retain(|stage| {
+                let blocked = stage.dependencies.iter().any(|dep| failed.contains(dep));
+                if blocked {
+                    debug!("Skipping stage {}: a dependency failed", stage.stage_id);
+                    failed.insert(stage.stage_id.clone());
+                    execution.skipped_stages.push(stage.stage_id.clone());
                 }
-                Err(e) => {
-                    warn!("Stage {} failed: {}", stage.stage_id, e);
-                    execution.status = ExecutionStatus::Failed;
+                !blocked
+            });
+
+            // Start every stage whose dependencies have completed, up to the limit
+            let mut i = 0;
+            while i < pending.len() && running.len() < max_parallel {
+                let stage = pending[i];
+                if stage.dependencies.iter().all(|dep| stage_outputs.contains_key(dep)) {
+                    pending.remove(i);
+                    let inputs: HashMap<String, Value> = stage.dependencies
+                        .iter()
+                        .map(|dep| (dep.clone(), stage_outputs[dep].clone()))
+                        .collect();
+                    launched.insert(stage.stage_id.clone(), SystemTime::now());
+
+                    let requirements = &requirements;
+                    running.push(async move {
+                        let stage_execution = self.execute_stage(stage, task_description, requirements, &inputs).await;
+                        (stage, stage_execution)
+                    });
+                } else {
+                    i += 1;
+                }
+            }
+
+            let Some((stage, stage_execution)) = running.next().await else {
+                break;
+            };
+            launched.remove(&stage.stage_id);
+            let stage_status = stage_execution.status.clone();
+
+            Self::record_stage(&mut execution, &stage_execution);
+            if stage_status == ExecutionStatus::Completed {
+                stage_outputs.insert(stage.stage_id.clone(), Value::Object(stage_execution.outputs.clone().into_iter().collect()));
+            }
+            execution.stages_completed.push(stage_execution);
+
+            if stage_status != ExecutionStatus::Completed {
+                warn!("Stage {} did not complete: {:?}", stage.stage_id, stage_status);
+                execution.status = ExecutionStatus::Failed;
+                failed.insert(stage.stage_id.clone());
+
+                if failure_policy == FailurePolicy::FailFast {
+                    halted = true;
                     break;
                 }
             }
@@ -922,12 +1104,50 @@ impl AdaptiveWorkflowOrchestrator {
             }
         }
 
+        if halted {
+            // Dropping the in-flight futures cancels them
+            drop(running);
+            for (stage_id, start_time) in launched {
+                execution.stages_completed.push(StageExecution {
+                    stage_id,
+                    start_time,
+                    end_time: Some(SystemTime::now()),
+                    status: ExecutionStatus::Cancelled,
+                    agents_used: Vec::new(),
+                    resource_usage: ResourceUsageRecord {
+                        cpu_hours: 0.0,
+                        memory_gb_hours: 0.0,
+                        network_gb: 0.0,
+                        storage_gb_hours: 0.0,
+                        peak_usage: resource_allocation.clone(),
+                        efficiency_score: 0.0,
+                    },
+                    outputs: HashMap::new(),
+                    errors: Vec::new(),
+                });
+            }
+        }
+        execution.skipped_stages.extend(pending.iter().map(|stage| stage.stage_id.clone()));
+
         // Finalize execution
         execution.end_time = Some(SystemTime::now());
         if execution.status == ExecutionStatus::Running {
             execution.status = ExecutionStatus::Completed;
+        } else if !halted && !stage_outputs.is_empty() {
+            execution.status = ExecutionStatus::PartiallyCompleted;
         }
 
+        if let Some(last_stage) = execution.stages_completed.iter().rev().find(|stage| stage.status == ExecutionStatus::Completed) {
+            execution.outcomes.primary_result = last_stage.outputs.get("result").cloned().unwrap_or(Value::Null);
+        }
+        execution.outcomes.secondary_results = stage_outputs;
+        execution.outcomes.quality_score = if execution.stages_completed.is_empty() {
+            0.0
+        } else {
+            execution.stages_completed.iter().map(|stage| stage.resource_usage.efficiency_score).sum::<f64>()
+                / execution.stages_completed.len() as f64
+        };
+
         // Store execution history
         let mut history = self.execution_history.write().await;
         history.push_back(execution.clone());
@@ -942,48 +1162,272 @@ impl AdaptiveWorkflowOrchestrator {
         Ok(execution)
     }
 
-    /// Execute a workflow stage
-    async fn execute_stage(&self, stage: &WorkflowStage, requirements: &HashMap<String, Value>, execution: &mut WorkflowExecution) -> Result<StageExecution> {
+    /// Execute a workflow stage on its backend, retrying per the stage's retry policy
+    ///
+    /// Failures are reported through the returned record's status and errors
+    /// rather than as an `Err`, so the attempt history is never lost.
+    async fn execute_stage(
+        &self,
+        stage: &WorkflowStage,
+        task_description: &str,
+        requirements: &HashMap<String, Value>,
+        inputs: &HashMap<String, Value>,
+    ) -> StageExecution {
         let stage_start = SystemTime::now();
-        debug!("Executing stage: {}", stage.stage_id);
-
-        // Allocate agents for the stage
-        let agents = self.allocate_agents_for_stage(stage).await?;
+        let started = Instant::now();
+        debug!("Executing stage {} on {}", stage.stage_id, stage.backend.as_str());
 
-        // Execute stage logic (simplified)
-        let stage_execution = StageExecution {
+        let mut stage_execution = StageExecution {
             stage_id: stage.stage_id.clone(),
             start_time: stage_start,
-            end_time: Some(SystemTime::now()),
-            status: ExecutionStatus::Completed,
-            agents_used: agents,
+            end_time: None,
+            status: ExecutionStatus::Running,
+            agents_used: self.allocate_agents_for_stage(stage),
             resource_usage: ResourceUsageRecord {
-                cpu_hours: 0.1,
-                memory_gb_hours: 0.5,
-                network_gb: 0.01,
-                storage_gb_hours: 0.05,
+                cpu_hours: 0.0,
+                memory_gb_hours: 0.0,
+                network_gb: 0.0,
+                storage_gb_hours: 0.0,
                 peak_usage: stage.resource_allocation.clone(),
-                efficiency_score: 0.85,
+                efficiency_score: 0.0,
             },
             outputs: HashMap::new(),
             errors: Vec::new(),
         };
 
-        Ok(stage_execution)
+        let request = ExecutionRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            command: stage.stage_id.clone(),
+            prompt: Self::build_stage_prompt(stage, task_description, inputs),
+            language: requirements.get("language").and_then(Value::as_str).map(str::to_string),
+            context: Some(json!({ "requirements": requirements, "inputs": inputs }).to_string()),
+            temperature: requirements.get("temperature").and_then(Value::as_f64).map(|t| t as f32),
+            swarm_config: None,
+            sparc_mode: None,
+            hive_mind_commands: Vec::new(),
+            memory_context: None,
+        };
+
+        let sampler = ResourceSampler::start(request.id.clone());
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+            let attempt = tokio::time::timeout(stage.timeout, self.dispatch(stage.backend, request.clone())).await;
+
+            let error = match attempt {
+                Ok(Ok(response)) if response.success => {
+                    stage_execution.status = ExecutionStatus::Completed;
+                    stage_execution.outputs = Self::stage_outputs(stage, &response, attempts);
+                    break;
+                }
+                Ok(Ok(response)) => ExecutionError {
+                    error_type: "execution_failed".to_string(),
+                    message: response.error.unwrap_or_else(|| "Backend reported failure".to_string()),
+                    timestamp: SystemTime::now(),
+                    recoverable: true,
+                },
+                Ok(Err(e)) => ExecutionError {
+                    error_type: if self.backend_configured(stage.backend) { "backend_error" } else { "backend_unavailable" }.to_string(),
+                    message: e.to_string(),
+                    timestamp: SystemTime::now(),
+                    recoverable: self.backend_configured(stage.backend),
+                },
+                Err(_) => ExecutionError {
+                    error_type: "timeout".to_string(),
+                    message: format!("Stage timed out after {:?}", stage.timeout),
+                    timestamp: SystemTime::now(),
+                    recoverable: true,
+                },
+            };
+
+            warn!("Stage {} attempt {} failed ({}): {}", stage.stage_id, attempts, error.error_type, error.message);
+            let retry = attempts <= stage.retry_policy.max_retries && stage.retry_policy.should_retry(&error);
+            stage_execution.status = if error.error_type == "timeout" { ExecutionStatus::Timeout } else { ExecutionStatus::Failed };
+            stage_execution.errors.push(error);
+
+            if !retry {
+                break;
+            }
+
+            let delay = stage.retry_policy.delay_for_attempt(attempts);
+            debug!("Retrying stage {} in {:?}", stage.stage_id, delay);
+            tokio::time::sleep(delay).await;
+        }
+
+        let samples = sampler.stop().await;
+        let elapsed = started.elapsed();
+        let transferred_bytes = request.prompt.len()
+            + stage_execution.outputs.get("result").and_then(Value::as_str).map_or(0, str::len);
+
+        stage_execution.end_time = Some(SystemTime::now());
+        stage_execution.resource_usage = ResourceUsageRecord {
+            cpu_hours: samples.cpu_seconds / 3600.0,
+            memory_gb_hours: samples.memory_byte_seconds / 1e9 / 3600.0,
+            network_gb: transferred_bytes as f64 / 1e9,
+            storage_gb_hours: 0.0,
+            peak_usage: ResourceAllocation {
+                cpu_cores: samples.peak_cpu_cores,
+                memory_mb: samples.peak_memory_bytes / (1024 * 1024),
+                network_bandwidth: stage.resource_allocation.network_bandwidth,
+                storage_mb: 0,
+                priority: stage.resource_allocation.priority.clone(),
+            },
+            efficiency_score: Self::efficiency_score(&stage_execution.status, attempts, elapsed, stage.timeout),
+        };
+
+        stage_execution
+    }
+
+    /// Send a stage request to the selected backend
+    async fn dispatch(&self, backend: StageBackend, request: ExecutionRequest) -> Result<ExecutionResponse> {
+        match backend {
+            StageBackend::ClaudeFlow => {
+                let service = self.backends.claude_flow.as_ref().ok_or_else(|| anyhow!("Claude-Flow backend is not configured"))?;
+                service.execute(request).await
+            }
+            StageBackend::Codex => {
+                let service = self.backends.codex.as_ref().ok_or_else(|| anyhow!("Codex backend is not configured"))?;
+                service.execute(request).await
+            }
+            StageBackend::OpenRouter => {
+                let service = self.backends.openrouter.as_ref().ok_or_else(|| anyhow!("OpenRouter backend is not configured"))?;
+                let advanced_request = Self::openrouter_request(request);
+                let routing = service.route_request(&advanced_request).await?;
+                let response = service.execute_request(&routing.selected_model, &advanced_request).await?;
+                Ok(response.base_response)
+            }
+        }
+    }
+
+    fn backend_configured(&self, backend: StageBackend) -> bool {
+        match backend {
+            StageBackend::ClaudeFlow => self.backends.claude_flow.is_some(),
+            StageBackend::Codex => self.backends.codex.is_some(),
+            StageBackend::OpenRouter => self.backends.openrouter.is_some(),
+        }
+    }
+
+    /// Wrap a stage request for OpenRouter; retries are handled by the stage's own policy
+    fn openrouter_request(request: ExecutionRequest) -> AdvancedExecutionRequest {
+        AdvancedExecutionRequest {
+            base_request: request,
+            routing_preferences: ModelRoutingPreferences {
+                preferred_models: Vec::new(),
+                avoid_models: Vec::new(),
+                cost_limit: None,
+                latency_requirement: None,
+                quality_preference: QualityPreference::Balanced,
+            },
+            context_optimization: ContextOptimization {
+                enabled: false,
+                compression_ratio: 1.0,
+                preserve_recent: true,
+                adaptive_sizing: false,
+            },
+            performance_requirements: PerformanceRequirements {
+                max_response_time: None,
+                min_success_rate: None,
+                priority: RequestPriority::Normal,
+            },
+            recovery_options: RecoveryOptions {
+                auto_retry: false,
+                fallback_models: Vec::new(),
+                circuit_breaker: true,
+                graceful_degradation: false,
+            },
+            generation: GenerationOptions::default(),
+            context_id: None,
+        }
+    }
+
+    /// Compose the prompt for a stage from the task and the outputs it builds on
+    fn build_stage_prompt(stage: &WorkflowStage, task_description: &str, inputs: &HashMap<String, Value>) -> String {
+        let mut prompt = format!("{:?} stage: {}\n\nTask: {}", stage.stage_type, stage.description, task_description);
+
+        let mut input_ids: Vec<&String> = inputs.keys().collect();
+        input_ids.sort();
+        for stage_id in input_ids {
+            let result = inputs[stage_id].get("result").and_then(Value::as_str).unwrap_or_default();
+            prompt.push_str(&format!("\n\nOutput of stage '{}':\n{}", stage_id, result));
+        }
+
+        prompt
+    }
+
+    fn stage_outputs(stage: &WorkflowStage, response: &ExecutionResponse, attempts: u32) -> HashMap<String, Value> {
+        let mut outputs = HashMap::new();
+        outputs.insert("result".to_string(), json!(response.result.clone().unwrap_or_default()));
+        outputs.insert("backend".to_string(), json!(stage.backend.as_str()));
+        outputs.insert("attempts".to_string(), json!(attempts));
+        outputs.insert("execution_time_ms".to_string(), json!(response.execution_time));
+        if let Some(metadata) = &response.metadata {
+            outputs.insert("metadata".to_string(), metadata.clone());
+        }
+        outputs
+    }
+
+    /// Score a stage by success, retries needed, and how much of its time budget it used
+    fn efficiency_score(status: &ExecutionStatus, attempts: u32, elapsed: Duration, timeout: Duration) -> f64 {
+        if *status != ExecutionStatus::Completed {
+            return 0.0;
+        }
+
+        let budget_used = if timeout.is_zero() {
+            1.0
+        } else {
+            (elapsed.as_secs_f64() / (timeout.as_secs_f64() * attempts as f64)).min(1.0)
+        };
+
+        (1.0 - 0.5 * budget_used) / attempts as f64
     }
 
-    /// Allocate agents for a stage
-    async fn allocate_agents_for_stage(&self, stage: &WorkflowStage) -> Result<Vec<String>> {
-        // Simplified agent allocation
+    /// Fold a finished stage into the workflow's totals
+    fn record_stage(execution: &mut WorkflowExecution, stage: &StageExecution) {
+        let stages = execution.stages_completed.len() as u32;
+        let attempts = stage.errors.len() as u64 + u64::from(stage.status == ExecutionStatus::Completed);
+        let metrics = &mut execution.performance_metrics;
+
+        metrics.total_requests += attempts;
+        metrics.failed_requests += stage.errors.len() as u64;
+        if stage.status == ExecutionStatus::Completed {
+            metrics.successful_requests += 1;
+        }
+
+        let stage_time = stage.end_time
+            .and_then(|end| end.duration_since(stage.start_time).ok())
+            .unwrap_or_default();
+        metrics.avg_response_time = (metrics.avg_response_time * stages + stage_time) / (stages + 1);
+
+        let usage = &mut execution.resource_usage;
+        usage.cpu_hours += stage.resource_usage.cpu_hours;
+        usage.memory_gb_hours += stage.resource_usage.memory_gb_hours;
+        usage.network_gb += stage.resource_usage.network_gb;
+        usage.storage_gb_hours += stage.resource_usage.storage_gb_hours;
+        usage.peak_usage.cpu_cores = usage.peak_usage.cpu_cores.max(stage.resource_usage.peak_usage.cpu_cores);
+        usage.peak_usage.memory_mb = usage.peak_usage.memory_mb.max(stage.resource_usage.peak_usage.memory_mb);
+
+        usage.efficiency_score = (usage.efficiency_score * stages as f64 + stage.resource_usage.efficiency_score) / (stages + 1) as f64;
+    }
+
+    /// Identify the agents serving a stage
+    ///
+    /// Preferred agents are used when named; otherwise the stage is served by
+    /// the backend's own agent of the required type.
+    fn allocate_agents_for_stage(&self, stage: &WorkflowStage) -> Vec<String> {
         let mut agents = Vec::new();
-        
+
         for requirement in &stage.agents_required {
-            // Find suitable agents (simplified)
-            let agent_id = format!("agent_{}_{}", requirement.agent_type, uuid::Uuid::new_v4().simple());
-            agents.push(agent_id);
+            if requirement.preferred_agents.is_empty() {
+                agents.push(format!("{}:{}", stage.backend.as_str(), requirement.agent_type));
+            } else {
+                agents.extend(requirement.preferred_agents.iter().cloned());
+            }
         }
 
-        Ok(agents)
+        let mut seen = HashSet::new();
+        agents.retain(|agent| seen.insert(agent.clone()));
+        agents
     }
 
     /// Check for adaptation opportunities during execution
@@ -1040,7 +1484,7 @@ impl AdaptiveWorkflowOrchestrator {
             "status": format!("{:?}", execution.status),
             "stages_completed": execution.stages_completed.len(),
             "performance": execution.performance_metrics.avg_response_time.as_millis()
-        }).as_object().unwrap().clone();
+        }).as_object().unwrap().clone().into_iter().collect();
 
         // Apply the adaptation (simplified)
         match suggestion.suggestion_type {
@@ -1061,7 +1505,7 @@ impl AdaptiveWorkflowOrchestrator {
             "status": format!("{:?}", execution.status),
             "stages_completed": execution.stages_completed.len(),
             "performance": execution.performance_metrics.avg_response_time.as_millis()
-        }).as_object().unwrap().clone();
+        }).as_object().unwrap().clone().into_iter().collect();
 
         Ok(AppliedAdaptation {
             adaptation_id,
@@ -1082,7 +1526,7 @@ impl AdaptiveWorkflowOrchestrator {
     /// Get workflow execution metrics
     pub async fn get_execution_metrics(&self) -> HashMap<String, Value> {
         let history = self.execution_history.read().await;
-        let patterns = self.workflow_patterns.read().await;
+        let total_patterns = self.pattern_library.len().await;
 
         let total_executions = history.len();
         let successful_executions = history.iter()
@@ -1108,14 +1552,281 @@ impl AdaptiveWorkflowOrchestrator {
             "successful_executions": successful_executions,
             "success_rate": if total_executions > 0 { successful_executions as f64 / total_executions as f64 } else { 0.0 },
             "avg_complexity": avg_complexity,
-            "total_patterns": patterns.len(),
+            "total_patterns": total_patterns,
             "adaptation_rate": adaptation_rate,
             "learning_enabled": self.config.auto_adaptation_enabled,
             "last_updated": chrono::Utc::now().to_rfc3339()
-        }).as_object().unwrap().clone()
+        }).as_object().unwrap().clone().into_iter().collect()
     }
 }
 
+impl PatternLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a pattern, returning the version it replaced
+    pub async fn register(&self, pattern: WorkflowPattern) -> Option<WorkflowPattern> {
+        info!("Registering workflow pattern: {}", pattern.pattern_id);
+        self.patterns.write().await.insert(pattern.pattern_id.clone(), pattern)
+    }
+
+    pub async fn unregister(&self, pattern_id: &str) -> Option<WorkflowPattern> {
+        info!("Unregistering workflow pattern: {}", pattern_id);
+        self.patterns.write().await.remove(pattern_id)
+    }
+
+    pub async fn get(&self, pattern_id: &str) -> Option<WorkflowPattern> {
+        self.patterns.read().await.get(pattern_id).cloned()
+    }
+
+    pub async fn pattern_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.patterns.read().await.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    pub async fn len(&self) -> usize {
+        self.patterns.read().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.patterns.read().await.is_empty()
+    }
+
+    /// Pattern whose complexity range covers `complexity`, preferring the closest optimum
+    pub async fn best_for_complexity(&self, complexity: f64) -> Option<WorkflowPattern> {
+        self.patterns
+            .read()
+            .await
+            .values()
+            .filter(|pattern| {
+                complexity >= pattern.complexity_range.min_complexity
+                    && complexity <= pattern.complexity_range.max_complexity
+            })
+            .min_by(|a, b| {
+                let distance = |p: &WorkflowPattern| (p.complexity_range.optimal_complexity - complexity).abs();
+                distance(a).total_cmp(&distance(b)).then_with(|| a.pattern_id.cmp(&b.pattern_id))
+            })
+            .cloned()
+    }
+}
+
+impl RetryPolicy {
+    /// Upper bound on any single backoff delay
+    const MAX_DELAY: Duration = Duration::from_secs(300);
+
+    /// Delay before retry number `attempt` (1-based)
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let attempt = attempt.max(1);
+        let factor = match self.backoff_strategy {
+            BackoffStrategy::Fixed => 1,
+            BackoffStrategy::Linear => attempt,
+            BackoffStrategy::Exponential => 2u32.saturating_pow(attempt - 1),
+            BackoffStrategy::Fibonacci => {
+                let (mut a, mut b) = (1u32, 1u32);
+                for _ in 1..attempt {
+                    (a, b) = (b, a.saturating_add(b));
+                }
+                a
+            }
+        };
+
+        self.base_delay.saturating_mul(factor).min(Self::MAX_DELAY)
+    }
+
+    /// Whether an error should be retried
+    ///
+    /// A retry condition naming the error type (or `*`) decides; otherwise
+    /// recoverable errors are retried.
+    pub fn should_retry(&self, error: &ExecutionError) -> bool {
+        self.retry_conditions
+            .iter()
+            .find(|condition| condition.error_type == error.error_type || condition.error_type == "*")
+            .map(|condition| condition.should_retry)
+            .unwrap_or(error.recoverable)
+    }
+}
+
+/// Resources consumed by a stage's backend processes while sampling
+#[derive(Debug, Default)]
+struct ResourceSamples {
+    cpu_seconds: f64,
+    memory_byte_seconds: f64,
+    peak_cpu_cores: f64,
+    peak_memory_bytes: u64,
+}
+
+/// Background sampler measuring CPU and memory while a stage runs
+///
+/// Backends tag the subprocesses they launch, and everything those start in
+/// turn, with the request id in `REQUEST_ID_ENV`. Only processes carrying the
+/// stage's request id are counted, so stages running concurrently each see
+/// their own usage. Backends served in-process, like OpenRouter, record none.
+struct ResourceSampler {
+    stop: CancellationToken,
+    handle: tokio::task::JoinHandle<ResourceSamples>,
+}
+
+impl ResourceSampler {
+    const INTERVAL: Duration = Duration::from_millis(500);
+
+    fn start(request_id: String) -> Self {
+        let stop = CancellationToken::new();
+        let token = stop.clone();
+
+        let handle = tokio::spawn(async move {
+            let mut samples = ResourceSamples::default();
+            let tag: Arc<str> = format!("{}={}", REQUEST_ID_ENV, request_id).into();
+            let refresh = ProcessRefreshKind::new()
+                .with_cpu()
+                .with_memory()
+                .with_environ(UpdateKind::OnlyIfNotSet);
+
+            let mut system = System::new();
+            let mut interval = tokio::time::interval(Self::INTERVAL);
+            let mut last_sample = Instant::now();
+
+            loop {
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    _ = interval.tick() => {}
+                }
+
+                // Refreshing reads every process's /proc entries, so it runs off the async workers
+                let tag = tag.clone();
+                let refreshed = tokio::task::spawn_blocking(move || {
+                    system.refresh_processes_specifics(refresh);
+                    let usage = Self::tagged_usage(&system, &tag);
+                    (system, usage)
+                })
+                .await;
+                let Ok((refreshed_system, (cpu_percent, memory_bytes))) = refreshed else {
+                    break;
+                };
+                system = refreshed_system;
+                let dt = last_sample.elapsed().as_secs_f64();
+                last_sample = Instant::now();
+
+                samples.cpu_seconds += cpu_percent / 100.0 * dt;
+                samples.memory_byte_seconds += memory_bytes as f64 * dt;
+                samples.peak_cpu_cores = samples.peak_cpu_cores.max(cpu_percent / 100.0);
+                samples.peak_memory_bytes = samples.peak_memory_bytes.max(memory_bytes);
+            }
+
+            samples
+        });
+
+        Self { stop, handle }
+    }
+
+    async fn stop(mut self) -> ResourceSamples {
+        self.stop.cancel();
+        (&mut self.handle).await.unwrap_or_default()
+    }
+
+    /// Summed CPU percentage and memory of the processes whose environment contains `tag`
+    fn tagged_usage(system: &System, tag: &str) -> (f64, u64) {
+        system
+            .processes()
+            .values()
+            // Threads are listed as tasks of their process; their usage is already included
+            .filter(|process| process.thread_kind().is_none())
+            .filter(|process| process.environ().iter().any(|variable| variable == tag))
+            .fold((0.0, 0), |(cpu_percent, memory_bytes), process| {
+                (cpu_percent + process.cpu_usage() as f64, memory_bytes + process.memory())
+            })
+    }
+}
+
+impl Drop for ResourceSampler {
+    fn drop(&mut self) {
+        // Stops sampling when a stage is cancelled mid-flight
+        self.stop.cancel();
+    }
+}
+
+/// The pattern's stages with the dependencies they are scheduled by
+///
+/// Stages of chaining pattern types also wait for the stage listed before them.
+pub fn scheduled_stages(pattern: &WorkflowPattern) -> Vec<WorkflowStage> {
+    let mut stages = pattern.stages.clone();
+    if pattern.pattern_type.chains_stages() {
+        for i in 1..stages.len() {
+            let previous = stages[i - 1].stage_id.clone();
+            if !stages[i].dependencies.contains(&previous) {
+                stages[i].dependencies.push(previous);
+            }
+        }
+    }
+    stages
+}
+
+/// Check that stage dependencies name existing stages and contain no cycle
+pub fn validate_stage_graph(stages: &[WorkflowStage]) -> Result<()> {
+    let stage_ids: HashSet<&str> = stages.iter().map(|stage| stage.stage_id.as_str()).collect();
+    for stage in stages {
+        if let Some(unknown) = stage.dependencies.iter().find(|dep| !stage_ids.contains(dep.as_str())) {
+            return Err(anyhow!("Stage {} depends on unknown stage {}", stage.stage_id, unknown));
+        }
+    }
+
+    let graph: Vec<(&str, &[String])> = stages
+        .iter()
+        .map(|stage| (stage.stage_id.as_str(), stage.dependencies.as_slice()))
+        .collect();
+    match find_dependency_cycle(&graph) {
+        Some(cycle) => Err(anyhow!("Stage dependency cycle: {}", cycle.join(" -> "))),
+        None => Ok(()),
+    }
+}
+
+/// Find a dependency cycle among `(stage_id, dependencies)` pairs
+///
+/// Returns the stages along the cycle, starting and ending with the same id.
+pub fn find_dependency_cycle(stages: &[(&str, &[String])]) -> Option<Vec<String>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        Visiting,
+        Done,
+    }
+
+    fn visit<'a>(
+        id: &'a str,
+        edges: &HashMap<&'a str, &'a [String]>,
+        marks: &mut HashMap<&'a str, Mark>,
+        path: &mut Vec<&'a str>,
+    ) -> Option<Vec<String>> {
+        match marks.get(id) {
+            Some(Mark::Done) => return None,
+            Some(Mark::Visiting) => {
+                let start = path.iter().position(|visited| *visited == id).unwrap_or(0);
+                let mut cycle: Vec<String> = path[start..].iter().map(|s| s.to_string()).collect();
+                cycle.push(id.to_string());
+                return Some(cycle);
+            }
+            None => {}
+        }
+
+        marks.insert(id, Mark::Visiting);
+        path.push(id);
+        for dependency in edges.get(id).copied().unwrap_or_default() {
+            if let Some(cycle) = visit(dependency.as_str(), edges, marks, path) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        marks.insert(id, Mark::Done);
+        None
+    }
+
+    let edges: HashMap<&str, &[String]> = stages.iter().copied().collect();
+    let mut marks = HashMap::new();
+    let mut path = Vec::new();
+
+    stages.iter().find_map(|(id, _)| visit(*id, &edges, &mut marks, &mut path))
+}
+
 // Implementation stubs for supporting structures
 impl AdaptationEngine {
     pub fn new() -> Self {
@@ -1180,6 +1891,7 @@ impl AdaptationEngine {
                 adaptation_frequency: 0.2,
                 user_satisfaction: 0.9,
             },
+            failure_policy: None,
         })
     }
 
@@ -1295,6 +2007,439 @@ impl Default for AdaptiveWorkflowConfig {
             performance_tracking_enabled: true,
             max_pattern_cache_size: 1000,
             adaptation_history_retention: Duration::from_secs(86400 * 30), // 30 days
+            max_parallel_stages: 4,
+            failure_policy: FailurePolicy::FailFast,
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use std::path::Path;
+
+    /// Backends whose claude-flow CLI is replaced by a shell script run in `dir`
+    #[cfg(unix)]
+    fn script_backends(dir: &Path, script: &str) -> WorkflowBackends {
+        use std::os::unix::fs::PermissionsExt;
+
+        let program = dir.join("claude-flow.sh");
+        std::fs::write(&program, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        WorkflowBackends {
+            claude_flow: Some(ClaudeFlowService {
+                base_path: dir.to_string_lossy().to_string(),
+                program: program.to_string_lossy().to_string(),
+            }),
+            ..WorkflowBackends::default()
+        }
+    }
+
+    fn orchestrator(backends: WorkflowBackends) -> AdaptiveWorkflowOrchestrator {
+        AdaptiveWorkflowOrchestrator::new(AdaptiveWorkflowConfig::default()).with_backends(backends)
+    }
+
+    fn stage(stage_id: &str, dependencies: &[&str]) -> WorkflowStage {
+        WorkflowStage {
+            stage_id: stage_id.to_string(),
+            stage_type: StageType::Execution,
+            description: format!("Stage {}", stage_id),
+            backend: StageBackend::ClaudeFlow,
+            agents_required: Vec::new(),
+            dependencies: dependencies.iter().map(|dep| dep.to_string()).collect(),
+            timeout: Duration::from_secs(10),
+            retry_policy: RetryPolicy {
+                max_retries: 0,
+                backoff_strategy: BackoffStrategy::Fixed,
+                base_delay: Duration::from_millis(10),
+                retry_conditions: Vec::new(),
+            },
+            adaptation_hooks: Vec::new(),
+            resource_allocation: ResourceAllocation {
+                cpu_cores: 1.0,
+                memory_mb: 256,
+                network_bandwidth: 10,
+                storage_mb: 0,
+                priority: ResourcePriority::Normal,
+            },
         }
     }
-}
\ No newline at end of file
+
+    fn pattern(pattern_type: WorkflowPatternType, stages: Vec<WorkflowStage>, failure_policy: FailurePolicy) -> WorkflowPattern {
+        let allocation = stages[0].resource_allocation.clone();
+        WorkflowPattern {
+            pattern_id: "test".to_string(),
+            name: "Test".to_string(),
+            description: String::new(),
+            pattern_type,
+            complexity_range: ComplexityRange {
+                min_complexity: 0.0,
+                max_complexity: 1.0,
+                optimal_complexity: 0.5,
+            },
+            stages,
+            adaptation_rules: Vec::new(),
+            resource_requirements: ResourceProfile {
+                min_resources: allocation.clone(),
+                recommended_resources: allocation.clone(),
+                max_resources: allocation,
+                scaling_factors: ScalingFactors {
+                    cpu_scaling: 1.0,
+                    memory_scaling: 1.0,
+                    network_scaling: 1.0,
+                    storage_scaling: 1.0,
+                },
+            },
+            success_criteria: Vec::new(),
+            performance_metrics: PatternMetrics {
+                usage_count: 0,
+                success_rate: 0.0,
+                avg_execution_time: Duration::ZERO,
+                resource_efficiency: 0.0,
+                adaptation_frequency: 0.0,
+                user_satisfaction: 0.0,
+            },
+            failure_policy: Some(failure_policy),
+        }
+    }
+
+    /// Backends logging each stage's start and end to `dir/log`
+    ///
+    /// Stage `fail` exits with an error, `slow` takes a second, `busy` spins
+    /// the CPU for two seconds and `idle` sleeps for two.
+    #[cfg(unix)]
+    fn logging_backends(dir: &Path) -> WorkflowBackends {
+        script_backends(
+            dir,
+            r#"id=$(printf '%s\n' "$5" | head -1 | sed 's/.*Stage //')
+echo "start $id" >> log
+case $id in
+  fail) exit 1 ;;
+  slow) sleep 1 ;;
+  busy) end=$(($(date +%s) + 2)); while [ $(date +%s) -lt $end ]; do :; done ;;
+  idle) sleep 2 ;;
+  *) sleep 0.2 ;;
+esac
+echo "end $id" >> log
+echo $id"#,
+        )
+    }
+
+    /// Position of `entry` in the log written by `logging_backends`
+    #[cfg(unix)]
+    fn logged_at(dir: &Path, entry: &str) -> usize {
+        let log = std::fs::read_to_string(dir.join("log")).unwrap();
+        log.lines().position(|line| line == entry).unwrap_or_else(|| panic!("'{}' not logged in:\n{}", entry, log))
+    }
+
+    #[cfg(unix)]
+    fn stage_status(execution: &WorkflowExecution, stage_id: &str) -> ExecutionStatus {
+        execution.stages_completed
+            .iter()
+            .find(|stage| stage.stage_id == stage_id)
+            .map(|stage| stage.status.clone())
+            .unwrap_or_else(|| panic!("stage {} did not run", stage_id))
+    }
+
+    #[cfg(unix)]
+    async fn run(orchestrator: &AdaptiveWorkflowOrchestrator, stage: &WorkflowStage) -> StageExecution {
+        orchestrator.execute_stage(stage, "task", &HashMap::new(), &HashMap::new()).await
+    }
+
+    /// CPU readings depend on scheduling, so only check they are within what the machine could do
+    #[cfg(unix)]
+    fn assert_cpu_within_bounds(usage: &ResourceUsageRecord, elapsed: Duration) {
+        let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get()) as f64;
+        // Per-process readings can overshoot slightly between refreshes
+        let max_cores = cores * 1.5;
+        assert!((0.0..=max_cores).contains(&usage.peak_usage.cpu_cores), "{:?}", usage);
+        assert!((0.0..=max_cores * elapsed.as_secs_f64() / 3600.0).contains(&usage.cpu_hours), "{:?}", usage);
+    }
+
+    /// Whether `pid` names a process that hasn't exited
+    #[cfg(unix)]
+    fn is_running(pid: &str) -> bool {
+        std::fs::read_to_string(format!("/proc/{}/stat", pid))
+            .map(|stat| !stat.rsplit(')').next().unwrap_or_default().trim_start().starts_with('Z'))
+            .unwrap_or(false)
+    }
+
+    #[test]
+    fn test_retry_delays_follow_the_backoff_strategy() {
+        let mut policy = stage("a", &[]).retry_policy;
+        policy.base_delay = Duration::from_secs(1);
+
+        policy.backoff_strategy = BackoffStrategy::Exponential;
+        let delays: Vec<u64> = (1..=4).map(|attempt| policy.delay_for_attempt(attempt).as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8]);
+
+        policy.backoff_strategy = BackoffStrategy::Fibonacci;
+        let delays: Vec<u64> = (1..=5).map(|attempt| policy.delay_for_attempt(attempt).as_secs()).collect();
+        assert_eq!(delays, vec![1, 1, 2, 3, 5]);
+
+        policy.backoff_strategy = BackoffStrategy::Exponential;
+        assert_eq!(policy.delay_for_attempt(30), RetryPolicy::MAX_DELAY);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failed_attempts_are_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        // Fails twice, then succeeds
+        let orchestrator = orchestrator(script_backends(
+            dir.path(),
+            r#"n=$(($(cat attempts 2>/dev/null || echo 0) + 1)); echo $n > attempts
+if [ $n -lt 3 ]; then echo "attempt $n failed" >&2; exit 1; fi
+echo done"#,
+        ));
+
+        let mut stage = stage("build", &[]);
+        stage.retry_policy.max_retries = 3;
+        let execution = run(&orchestrator, &stage).await;
+        assert_eq!(execution.status, ExecutionStatus::Completed);
+        assert_eq!(execution.errors.len(), 2);
+        assert_eq!(execution.errors[0].error_type, "execution_failed");
+        assert_eq!(execution.outputs["attempts"], json!(3));
+        assert_eq!(execution.outputs["result"], json!("done\n"));
+
+        // Retries stop at max_retries, and a retry condition can rule them out
+        std::fs::remove_file(dir.path().join("attempts")).unwrap();
+        stage.retry_policy.max_retries = 1;
+        let execution = run(&orchestrator, &stage).await;
+        assert_eq!(execution.status, ExecutionStatus::Failed);
+        assert_eq!(execution.errors.len(), 2);
+
+        std::fs::remove_file(dir.path().join("attempts")).unwrap();
+        stage.retry_policy.retry_conditions = vec![RetryCondition {
+            error_type: "execution_failed".to_string(),
+            should_retry: false,
+        }];
+        let execution = run(&orchestrator, &stage).await;
+        assert_eq!(execution.errors.len(), 1);
+        assert_eq!(execution.resource_usage.efficiency_score, 0.0);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_timed_out_stage_kills_its_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let orchestrator = orchestrator(script_backends(dir.path(), "echo $$ > pid\nexec sleep 30"));
+
+        let mut stage = stage("slow", &[]);
+        stage.timeout = Duration::from_millis(500);
+        let execution = run(&orchestrator, &stage).await;
+        assert_eq!(execution.status, ExecutionStatus::Timeout);
+        assert_eq!(execution.errors.len(), 1);
+        assert_eq!(execution.errors[0].error_type, "timeout");
+
+        let pid = std::fs::read_to_string(dir.path().join("pid")).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while is_running(pid.trim()) {
+            assert!(Instant::now() < deadline, "backend {} still running after the timeout", pid.trim());
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_resource_usage_is_measured_per_stage() {
+        let dir = tempfile::tempdir().unwrap();
+        let busy = orchestrator(script_backends(
+            dir.path(),
+            "end=$(($(date +%s) + 2)); while [ $(date +%s) -lt $end ]; do :; done; echo busy",
+        ));
+        let idle_dir = tempfile::tempdir().unwrap();
+        let idle = orchestrator(script_backends(idle_dir.path(), "sleep 2; echo idle"));
+
+        let (busy_stage, idle_stage) = (stage("busy", &[]), stage("idle", &[]));
+        let started = Instant::now();
+        let (busy, idle) = tokio::join!(run(&busy, &busy_stage), run(&idle, &idle_stage));
+        let elapsed = started.elapsed();
+        assert_eq!(busy.status, ExecutionStatus::Completed);
+        assert_eq!(idle.status, ExecutionStatus::Completed);
+
+        // Each stage sees its own backend's memory while it runs
+        assert!(busy.resource_usage.memory_gb_hours > 0.0);
+        assert!(idle.resource_usage.memory_gb_hours > 0.0);
+        assert_cpu_within_bounds(&busy.resource_usage, elapsed);
+        assert_cpu_within_bounds(&idle.resource_usage, elapsed);
+    }
+
+    #[test]
+    fn test_agents_are_allocated_once() {
+        let orchestrator = orchestrator(WorkflowBackends::default());
+        let requirement = |agent_type: &str, preferred: &[&str]| AgentRequirement {
+            agent_type: agent_type.to_string(),
+            capabilities_needed: Vec::new(),
+            min_performance_score: 0.0,
+            preferred_agents: preferred.iter().map(|agent| agent.to_string()).collect(),
+            load_balance: false,
+        };
+
+        let mut stage = stage("review", &[]);
+        stage.agents_required = vec![
+            requirement("reviewer", &["alice", "bob"]),
+            requirement("coder", &[]),
+            requirement("tester", &["alice"]),
+            requirement("coder", &[]),
+        ];
+        assert_eq!(
+            orchestrator.allocate_agents_for_stage(&stage),
+            vec!["alice", "bob", "claude-flow:coder"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_scheduler_runs_stages_after_their_dependencies() {
+        let started = Instant::now();
+        let dir = tempfile::tempdir().unwrap();
+        let orchestrator = orchestrator(logging_backends(dir.path()));
+        let stages = vec![
+            stage("plan", &[]),
+            stage("busy", &["plan"]),
+            stage("idle", &["plan"]),
+            stage("merge", &["busy", "idle"]),
+        ];
+
+        let execution = orchestrator
+            .run_pattern(pattern(WorkflowPatternType::Parallel, stages, FailurePolicy::FailFast), 0.5, "task", HashMap::new())
+            .await
+            .unwrap();
+        assert_eq!(execution.status, ExecutionStatus::Completed);
+        assert_eq!(execution.stages_completed.len(), 4);
+        assert_eq!(execution.outcomes.primary_result, json!("merge\n"));
+
+        let at = |entry: &str| logged_at(dir.path(), entry);
+        assert!(at("end plan") < at("start busy") && at("end plan") < at("start idle"));
+        // Independent stages overlap
+        assert!(at("start busy") < at("end idle") && at("start idle") < at("end busy"));
+        assert!(at("end busy") < at("start merge") && at("end idle") < at("start merge"));
+
+        for stage in &execution.stages_completed {
+            assert_cpu_within_bounds(&stage.resource_usage, started.elapsed());
+        }
+        let total: f64 = execution.stages_completed.iter().map(|stage| stage.resource_usage.cpu_hours).sum();
+        assert!((execution.resource_usage.cpu_hours - total).abs() < 1e-12);
+    }
+
+    fn chained_stages() -> Vec<WorkflowStage> {
+        vec![stage("a", &[]), stage("b", &[]), stage("c", &["a"])]
+    }
+
+    #[test]
+    fn test_sequential_patterns_chain_their_stages() {
+        let chained = scheduled_stages(&pattern(WorkflowPatternType::Sequential, chained_stages(), FailurePolicy::FailFast));
+        let dependencies: Vec<&Vec<String>> = chained.iter().map(|stage| &stage.dependencies).collect();
+        assert_eq!(dependencies, vec![&vec![], &vec!["a".to_string()], &vec!["a".to_string(), "b".to_string()]]);
+
+        let unchained = scheduled_stages(&pattern(WorkflowPatternType::Parallel, chained_stages(), FailurePolicy::FailFast));
+        assert!(unchained[1].dependencies.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pipeline_stages_run_in_order() {
+        let stages = chained_stages();
+        let dir = tempfile::tempdir().unwrap();
+        let orchestrator = orchestrator(logging_backends(dir.path()));
+        let execution = orchestrator
+            .run_pattern(pattern(WorkflowPatternType::Pipeline, stages, FailurePolicy::FailFast), 0.5, "task", HashMap::new())
+            .await
+            .unwrap();
+        assert_eq!(execution.status, ExecutionStatus::Completed);
+
+        let at = |entry: &str| logged_at(dir.path(), entry);
+        assert!(at("end a") < at("start b"));
+        assert!(at("end b") < at("start c"));
+    }
+
+    #[tokio::test]
+    async fn test_dependency_cycles_are_rejected() {
+        let (a, b, c) = (vec!["c".to_string()], vec!["a".to_string()], vec!["b".to_string()]);
+        let cycle = find_dependency_cycle(&[("a", &a), ("b", &b), ("c", &c)]).unwrap();
+        assert_eq!(cycle.first(), cycle.last());
+        assert_eq!(cycle.len(), 4);
+        assert!(find_dependency_cycle(&[("a", &[]), ("b", &b)]).is_none());
+
+        assert!(validate_stage_graph(&[stage("a", &["missing"])]).is_err());
+
+        let orchestrator = orchestrator(WorkflowBackends::default());
+        let explicit = vec![stage("a", &["b"]), stage("b", &["a"])];
+        assert!(orchestrator
+            .run_pattern(pattern(WorkflowPatternType::Parallel, explicit, FailurePolicy::FailFast), 0.5, "task", HashMap::new())
+            .await
+            .is_err());
+
+        // The implicit chain counts too: b follows a, so a cannot wait for b
+        let implicit = vec![stage("a", &["b"]), stage("b", &[])];
+        assert!(orchestrator
+            .run_pattern(pattern(WorkflowPatternType::Sequential, implicit, FailurePolicy::FailFast), 0.5, "task", HashMap::new())
+            .await
+            .is_err());
+    }
+
+    #[cfg(unix)]
+    fn failing_stages() -> Vec<WorkflowStage> {
+        vec![
+            stage("fail", &[]),
+            stage("slow", &[]),
+            stage("after_fail", &["fail"]),
+            stage("after_slow", &["slow"]),
+        ]
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_fail_fast_cancels_running_stages() {
+        let dir = tempfile::tempdir().unwrap();
+        let orchestrator = orchestrator(logging_backends(dir.path()));
+
+        let execution = orchestrator
+            .run_pattern(pattern(WorkflowPatternType::Parallel, failing_stages(), FailurePolicy::FailFast), 0.5, "task", HashMap::new())
+            .await
+            .unwrap();
+        assert_eq!(execution.status, ExecutionStatus::Failed);
+        assert_eq!(stage_status(&execution, "fail"), ExecutionStatus::Failed);
+        assert_eq!(stage_status(&execution, "slow"), ExecutionStatus::Cancelled);
+
+        let mut skipped = execution.skipped_stages.clone();
+        skipped.sort();
+        assert_eq!(skipped, vec!["after_fail", "after_slow"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_continue_independent_reports_partial_success() {
+        let dir = tempfile::tempdir().unwrap();
+        let orchestrator = orchestrator(logging_backends(dir.path()));
+
+        let execution = orchestrator
+            .run_pattern(
+                pattern(WorkflowPatternType::Parallel, failing_stages(), FailurePolicy::ContinueIndependent),
+                0.5,
+                "task",
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(execution.status, ExecutionStatus::PartiallyCompleted);
+        assert_eq!(stage_status(&execution, "fail"), ExecutionStatus::Failed);
+        assert_eq!(stage_status(&execution, "slow"), ExecutionStatus::Completed);
+        assert_eq!(stage_status(&execution, "after_slow"), ExecutionStatus::Completed);
+        assert_eq!(execution.skipped_stages, vec!["after_fail"]);
+
+        // Nothing completing is still a failure
+        let execution = orchestrator
+            .run_pattern(
+                pattern(WorkflowPatternType::Parallel, vec![stage("fail", &[])], FailurePolicy::ContinueIndependent),
+                0.5,
+                "task",
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(execution.status, ExecutionStatus::Failed);
+    }
+}
@@ -62,7 +62,7 @@ pub enum AgentStatus {
     Maintenance,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum AgentCapability {
     TaskExecution,
     CodeGeneration,
@@ -132,12 +132,30 @@ pub enum MessagePriority {
 
 #[derive(Debug)]
 pub struct ConsensusManager {
-    active_proposals: Arc<RwLock<HashMap<String, ConsensusProposal>>>,
+    active_proposals: Arc<RwLock<HashMap<String, ActiveProposal>>>,
     voting_history: Arc<RwLock<VecDeque<ConsensusResult>>>,
     consensus_config: ConsensusConfiguration,
 }
 
+/// A proposal open for voting, with the weight of every agent eligible to vote on it
 #[derive(Debug, Clone)]
+struct ActiveProposal {
+    proposal: ConsensusProposal,
+    voter_weights: HashMap<String, f64>,
+}
+
+/// Votes on a proposal, weighted according to the quorum type
+#[derive(Debug, Clone, Default)]
+struct VoteTally {
+    approve: f64,
+    reject: f64,
+    /// Weight of eligible agents that have not voted yet
+    outstanding: f64,
+    /// Votes cast, abstentions included
+    votes_cast: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConsensusProposal {
     pub id: String,
     pub proposer: String,
@@ -151,7 +169,7 @@ pub struct ConsensusProposal {
     pub status: ProposalStatus,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ProposalType {
     TaskPrioritization,
     ResourceAllocation,
@@ -161,14 +179,14 @@ pub enum ProposalType {
     EmergencyResponse,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Vote {
     Approve,
     Reject,
     Abstain,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ProposalStatus {
     Active,
     Approved,
@@ -189,12 +207,28 @@ pub struct ConsensusResult {
 
 #[derive(Debug, Clone)]
 pub struct ConsensusConfiguration {
+    /// Share of eligible agents that must vote, abstentions included
     pub quorum_percentage: f64,
+    /// Voting period of proposals submitted without a deadline
     pub voting_timeout: Duration,
-    pub require_unanimous: bool,
+    pub quorum_type: QuorumType,
+    /// Abstentions count towards the quorum but not towards the decision
     pub allow_abstentions: bool,
 }
 
+/// How votes decide a proposal
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuorumType {
+    /// More approvals than rejections
+    SimpleMajority,
+    /// Approvals make up at least this share of approvals and rejections, e.g. 2/3
+    Supermajority(f64),
+    /// At least one approval and no rejections
+    Unanimity,
+    /// More approval than rejection weight, weighing agents by their track record
+    WeightedByPerformance,
+}
+
 #[derive(Debug)]
 pub struct EventBus {
     subscribers: Arc<RwLock<HashMap<EventType, Vec<mpsc::UnboundedSender<SwarmEvent>>>>>,
@@ -238,7 +272,7 @@ pub enum EventSeverity {
 pub struct SwarmConfiguration {
     pub max_agents: u32,
     pub heartbeat_interval: Duration,
-    pub consensus_timeout: Duration,
+    pub consensus: ConsensusConfiguration,
     pub message_retention: Duration,
     pub auto_scaling_enabled: bool,
     pub fault_tolerance: FaultToleranceConfig,
@@ -273,7 +307,7 @@ pub struct SwarmCoordinationMetrics {
     pub last_updated: SystemTime,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskAssignment {
     pub task_id: String,
     pub task_type: TaskType,
@@ -286,7 +320,7 @@ pub struct TaskAssignment {
     pub dependencies: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TaskType {
     CodeGeneration,
     Testing,
@@ -297,7 +331,7 @@ pub enum TaskType {
     Documentation,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum TaskPriority {
     Low = 1,
     Normal = 2,
@@ -306,7 +340,7 @@ pub enum TaskPriority {
     Emergency = 5,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskRequirements {
     pub capabilities_needed: Vec<AgentCapability>,
     pub min_agents: u32,
@@ -315,7 +349,7 @@ pub struct TaskRequirements {
     pub resource_requirements: ResourceRequirements,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceRequirements {
     pub memory_mb: u64,
     pub cpu_cores: f64,
@@ -329,12 +363,7 @@ impl AdvancedSwarmCoordinator {
         let consensus_manager = Arc::new(ConsensusManager {
             active_proposals: Arc::new(RwLock::new(HashMap::new())),
             voting_history: Arc::new(RwLock::new(VecDeque::new())),
-            consensus_config: ConsensusConfiguration {
-                quorum_percentage: 0.66,
-                voting_timeout: Duration::from_secs(30),
-                require_unanimous: false,
-                allow_abstentions: true,
-            },
+            consensus_config: config.consensus.clone(),
         });
 
         let event_bus = Arc::new(EventBus {
@@ -387,8 +416,7 @@ impl AdvancedSwarmCoordinator {
             processing_queue: VecDeque::new(),
         };
 
-        let mut agents = self.agents.write().await;
-        agents.insert(agent_id.clone(), agent);
+        self.agents.write().await.insert(agent_id.clone(), agent);
         
         // Update coordination metrics
         self.update_coordination_metrics().await;
@@ -412,8 +440,8 @@ impl AdvancedSwarmCoordinator {
 
     /// Remove an agent from the swarm
     pub async fn remove_agent(&self, agent_id: &str) -> Result<()> {
-        let mut agents = self.agents.write().await;
-        if agents.remove(agent_id).is_some() {
+        let removed = self.agents.write().await.remove(agent_id);
+        if removed.is_some() {
             self.update_coordination_metrics().await;
             
             self.emit_event(SwarmEvent {
@@ -459,12 +487,12 @@ impl AdvancedSwarmCoordinator {
     }
 
     /// Find or create communication channel between agents
-    async fn find_communication_channel(
+    async fn find_communication_channel<'a>(
         &self,
         sender: &str,
         recipient: &str,
-        channels: &HashMap<String, CommunicationChannel>,
-    ) -> Option<&CommunicationChannel> {
+        channels: &'a HashMap<String, CommunicationChannel>,
+    ) -> Option<&'a CommunicationChannel> {
         // Simple implementation - in real system would be more sophisticated
         channels.values()
             .find(|channel| {
@@ -568,16 +596,41 @@ impl AdvancedSwarmCoordinator {
     }
 
     /// Initiate consensus on a proposal
-    pub async fn initiate_consensus(&self, proposal: ConsensusProposal) -> Result<String> {
+    ///
+    /// Every agent that has not failed may vote. The proposal expires at its
+    /// deadline, or after the configured voting timeout if it has none.
+    pub async fn initiate_consensus(&self, mut proposal: ConsensusProposal) -> Result<String> {
         info!("Initiating consensus on proposal: {}", proposal.description);
 
+        let now = SystemTime::now();
+        if proposal.deadline <= now {
+            proposal.deadline = now + self.consensus_manager.consensus_config.voting_timeout;
+        }
+        proposal.status = ProposalStatus::Active;
+        proposal.votes.clear();
+
         let proposal_id = proposal.id.clone();
-        let mut proposals = self.consensus_manager.active_proposals.write().await;
-        proposals.insert(proposal_id.clone(), proposal.clone());
+        let deadline = proposal.deadline;
 
-        // Send consensus proposal messages to all agents
         let agents = self.agents.read().await;
-        for agent_id in agents.keys() {
+        let voter_weights: HashMap<String, f64> = agents.values()
+            .filter(|agent| agent.status != AgentStatus::Failed)
+            .map(|agent| (agent.id.clone(), voting_weight(agent)))
+            .collect();
+
+        {
+            let mut proposals = self.consensus_manager.active_proposals.write().await;
+            if proposals.contains_key(&proposal_id) {
+                return Err(anyhow!("Proposal {} is already open for voting", proposal_id));
+            }
+            proposals.insert(proposal_id.clone(), ActiveProposal {
+                proposal: proposal.clone(),
+                voter_weights: voter_weights.clone(),
+            });
+        }
+
+        // Send consensus proposal messages to all eligible agents
+        for agent_id in voter_weights.keys() {
             let proposal_message = SwarmMessage {
                 id: Uuid::new_v4().to_string(),
                 sender: "consensus_manager".to_string(),
@@ -592,128 +645,63 @@ impl AdvancedSwarmCoordinator {
 
             self.send_message(proposal_message).await?;
         }
+        drop(agents);
+
+        // Expire the proposal at its deadline unless the votes decide it first
+        let consensus_manager = Arc::clone(&self.consensus_manager);
+        let event_bus = Arc::clone(&self.event_bus);
+        let expiring_id = proposal_id.clone();
+        tokio::spawn(async move {
+            let remaining = deadline.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO);
+            tokio::time::sleep(remaining).await;
+
+            if let Some(result) = consensus_manager.settle(&expiring_id, &event_bus, SystemTime::now()).await {
+                debug!("Proposal {} closed at its deadline as {:?}", expiring_id, result.result);
+            }
+        });
 
         Ok(proposal_id)
     }
 
     /// Process a consensus vote
-    pub async fn process_vote(&self, proposal_id: &str, voter_id: &str, vote: Vote) -> Result<()> {
+    ///
+    /// Returns the result once the vote decides the proposal. Agents may change
+    /// their vote until then.
+    pub async fn process_vote(&self, proposal_id: &str, voter_id: &str, vote: Vote) -> Result<Option<ConsensusResult>> {
         debug!("Processing vote from {} for proposal {}: {:?}", voter_id, proposal_id, vote);
 
-        let mut proposals = self.consensus_manager.active_proposals.write().await;
-        if let Some(proposal) = proposals.get_mut(proposal_id) {
-            proposal.votes.insert(voter_id.to_string(), vote);
-
-            // Check if consensus is reached
-            if self.check_consensus_reached(proposal).await {
-                let result = self.finalize_consensus(proposal).await;
-                
-                // Move to history
-                let mut history = self.consensus_manager.voting_history.write().await;
-                history.push_back(result);
-                
-                // Limit history size
-                while history.len() > 1000 {
-                    history.pop_front();
-                }
+        let now = SystemTime::now();
+        {
+            let mut proposals = self.consensus_manager.active_proposals.write().await;
+            let active = proposals.get_mut(proposal_id)
+                .ok_or_else(|| anyhow!("Proposal {} is not open for voting", proposal_id))?;
 
-                // Remove from active proposals
-                proposals.remove(proposal_id);
+            if now >= active.proposal.deadline {
+                drop(proposals);
+                self.consensus_manager.settle(proposal_id, &self.event_bus, now).await;
+                return Err(anyhow!("Voting on proposal {} has closed", proposal_id));
+            }
+            if !active.voter_weights.contains_key(voter_id) {
+                return Err(anyhow!("Agent {} is not eligible to vote on proposal {}", voter_id, proposal_id));
+            }
+            if matches!(vote, Vote::Abstain) && !self.consensus_manager.consensus_config.allow_abstentions {
+                return Err(anyhow!("Abstentions are not allowed"));
             }
-        }
-
-        Ok(())
-    }
-
-    /// Check if consensus is reached for a proposal
-    async fn check_consensus_reached(&self, proposal: &ConsensusProposal) -> bool {
-        let agents = self.agents.read().await;
-        let total_agents = agents.len();
-        let votes_cast = proposal.votes.len();
-
-        // Check quorum
-        let quorum_met = votes_cast >= proposal.quorum_required as usize;
-        
-        if !quorum_met {
-            return false;
-        }
 
-        // Check if voting period expired
-        if SystemTime::now() > proposal.deadline {
-            return true;
+            active.proposal.votes.insert(voter_id.to_string(), vote);
         }
 
-        // Check if all agents voted
-        votes_cast >= total_agents
+        Ok(self.consensus_manager.settle(proposal_id, &self.event_bus, now).await)
     }
 
-    /// Finalize consensus result
-    async fn finalize_consensus(&self, proposal: &ConsensusProposal) -> ConsensusResult {
-        let approve_votes = proposal.votes.values().filter(|&&ref v| matches!(v, Vote::Approve)).count();
-        let reject_votes = proposal.votes.values().filter(|&&ref v| matches!(v, Vote::Reject)).count();
-        let total_votes = proposal.votes.len();
-
-        let result = if approve_votes > reject_votes && approve_votes as f64 / total_votes as f64 >= 0.51 {
-            ProposalStatus::Approved
-        } else {
-            ProposalStatus::Rejected
-        };
-
-        let agents = self.agents.read().await;
-        let participation_rate = if agents.len() > 0 {
-            total_votes as f64 / agents.len() as f64
-        } else {
-            0.0
-        };
-
-        let consensus_time = SystemTime::now()
-            .duration_since(proposal.created_at)
-            .unwrap_or(Duration::ZERO);
-
-        // Emit consensus event
-        self.emit_event(SwarmEvent {
-            id: Uuid::new_v4().to_string(),
-            event_type: if result == ProposalStatus::Approved {
-                EventType::ConsensusReached
-            } else {
-                EventType::ConsensusFailed
-            },
-            source: "consensus_manager".to_string(),
-            payload: json!({
-                "proposal_id": proposal.id,
-                "result": format!("{:?}", result),
-                "participation_rate": participation_rate,
-                "consensus_time_ms": consensus_time.as_millis()
-            }),
-            timestamp: SystemTime::now(),
-            severity: EventSeverity::Info,
-        }).await;
-
-        ConsensusResult {
-            proposal_id: proposal.id.clone(),
-            result,
-            final_votes: proposal.votes.clone(),
-            participation_rate,
-            consensus_time,
-            timestamp: SystemTime::now(),
-        }
+    /// Results of recently closed proposals, oldest first
+    pub async fn get_consensus_history(&self) -> Vec<ConsensusResult> {
+        self.consensus_manager.voting_history.read().await.iter().cloned().collect()
     }
 
     /// Emit event to all subscribers
     async fn emit_event(&self, event: SwarmEvent) {
-        let subscribers = self.event_bus.subscribers.read().await;
-        if let Some(event_subscribers) = subscribers.get(&event.event_type) {
-            for sender in event_subscribers {
-                let _ = sender.send(event.clone());
-            }
-        }
-
-        // Store in history
-        let mut history = self.event_bus.event_history.write().await;
-        history.push_back(event);
-        while history.len() > 10000 {
-            history.pop_front();
-        }
+        self.event_bus.publish(event).await;
     }
 
     /// Subscribe to events
@@ -814,6 +802,187 @@ impl AdvancedSwarmCoordinator {
     }
 }
 
+impl ConsensusManager {
+    /// Close the proposal if its outcome is decided at `now`, recording and announcing the result
+    async fn settle(&self, proposal_id: &str, event_bus: &EventBus, now: SystemTime) -> Option<ConsensusResult> {
+        let active = {
+            let mut proposals = self.active_proposals.write().await;
+            let outcome = proposals.get(proposal_id)?.outcome(&self.consensus_config, now)?;
+            let mut active = proposals.remove(proposal_id)?;
+            active.proposal.status = outcome;
+            active
+        };
+
+        let tally = active.tally(&self.consensus_config.quorum_type);
+        let result = active.into_result(now);
+
+        info!(
+            "Proposal {} closed as {:?} with {:.0}% participation",
+            result.proposal_id,
+            result.result,
+            result.participation_rate * 100.0
+        );
+
+        // Move to history
+        {
+            let mut history = self.voting_history.write().await;
+            history.push_back(result.clone());
+
+            // Limit history size
+            while history.len() > 1000 {
+                history.pop_front();
+            }
+        }
+
+        event_bus.publish(SwarmEvent {
+            id: Uuid::new_v4().to_string(),
+            event_type: if result.result == ProposalStatus::Approved {
+                EventType::ConsensusReached
+            } else {
+                EventType::ConsensusFailed
+            },
+            source: "consensus_manager".to_string(),
+            payload: json!({
+                "proposal_id": result.proposal_id,
+                "result": format!("{:?}", result.result),
+                "quorum_type": format!("{:?}", self.consensus_config.quorum_type),
+                "approve_weight": tally.approve,
+                "reject_weight": tally.reject,
+                "abstentions": result.final_votes.values().filter(|vote| matches!(vote, Vote::Abstain)).count(),
+                "participation_rate": result.participation_rate,
+                "consensus_time_ms": result.consensus_time.as_millis()
+            }),
+            timestamp: now,
+            severity: if result.result == ProposalStatus::Expired {
+                EventSeverity::Warning
+            } else {
+                EventSeverity::Info
+            },
+        }).await;
+
+        Some(result)
+    }
+}
+
+impl ActiveProposal {
+    fn tally(&self, quorum_type: &QuorumType) -> VoteTally {
+        let mut tally = VoteTally::default();
+
+        for (voter, weight) in &self.voter_weights {
+            let weight = if *quorum_type == QuorumType::WeightedByPerformance { *weight } else { 1.0 };
+            match self.proposal.votes.get(voter) {
+                Some(Vote::Approve) => tally.approve += weight,
+                Some(Vote::Reject) => tally.reject += weight,
+                Some(Vote::Abstain) => {}
+                None => {
+                    tally.outstanding += weight;
+                    continue;
+                }
+            }
+            tally.votes_cast += 1;
+        }
+
+        tally
+    }
+
+    /// Votes needed for a valid decision
+    fn required_votes(&self, config: &ConsensusConfiguration) -> usize {
+        let by_percentage = (self.voter_weights.len() as f64 * config.quorum_percentage).ceil() as usize;
+        by_percentage.max(self.proposal.quorum_required as usize)
+    }
+
+    /// The proposal's final status, or `None` while outstanding votes could still change it
+    fn outcome(&self, config: &ConsensusConfiguration, now: SystemTime) -> Option<ProposalStatus> {
+        let tally = self.tally(&config.quorum_type);
+        let quorum_met = tally.votes_cast >= self.required_votes(config);
+        let status = |approved: bool| if approved { ProposalStatus::Approved } else { ProposalStatus::Rejected };
+
+        // Voting is over: decide on the votes cast, or expire without a quorum
+        if now >= self.proposal.deadline || tally.votes_cast >= self.voter_weights.len() {
+            return Some(if quorum_met {
+                status(config.quorum_type.passes(tally.approve, tally.reject))
+            } else {
+                ProposalStatus::Expired
+            });
+        }
+
+        if !quorum_met {
+            return None;
+        }
+
+        // Settle early once no split of the outstanding votes can change the result
+        let if_all_approve = config.quorum_type.passes(tally.approve + tally.outstanding, tally.reject);
+        let if_all_reject = config.quorum_type.passes(tally.approve, tally.reject + tally.outstanding);
+        (if_all_approve == if_all_reject).then(|| status(if_all_approve))
+    }
+
+    fn into_result(self, now: SystemTime) -> ConsensusResult {
+        let eligible = self.voter_weights.len();
+        let participation_rate = if eligible > 0 {
+            self.voter_weights.keys().filter(|voter| self.proposal.votes.contains_key(*voter)).count() as f64 / eligible as f64
+        } else {
+            0.0
+        };
+
+        ConsensusResult {
+            proposal_id: self.proposal.id,
+            result: self.proposal.status,
+            final_votes: self.proposal.votes,
+            participation_rate,
+            consensus_time: now.duration_since(self.proposal.created_at).unwrap_or(Duration::ZERO),
+            timestamp: now,
+        }
+    }
+}
+
+impl QuorumType {
+    /// Whether approvals and rejections of the given weight carry a proposal
+    fn passes(&self, approve: f64, reject: f64) -> bool {
+        match self {
+            QuorumType::SimpleMajority | QuorumType::WeightedByPerformance => approve > reject,
+            QuorumType::Supermajority(share) => approve > 0.0 && approve / (approve + reject) >= *share,
+            QuorumType::Unanimity => approve > 0.0 && reject == 0.0,
+        }
+    }
+}
+
+impl EventBus {
+    /// Deliver an event to its subscribers and keep it in the history
+    async fn publish(&self, event: SwarmEvent) {
+        let subscribers = self.subscribers.read().await;
+        if let Some(event_subscribers) = subscribers.get(&event.event_type) {
+            for sender in event_subscribers {
+                let _ = sender.send(event.clone());
+            }
+        }
+        drop(subscribers);
+
+        // Store in history
+        let mut history = self.event_history.write().await;
+        history.push_back(event);
+        while history.len() > 10000 {
+            history.pop_front();
+        }
+    }
+}
+
+/// Voting weight from an agent's track record, at least 0.1 so every agent counts
+fn voting_weight(agent: &SwarmAgent) -> f64 {
+    let metrics = &agent.performance_metrics;
+    ((metrics.success_rate + metrics.coordination_efficiency) / 2.0).clamp(0.1, 1.0)
+}
+
+impl Default for ConsensusConfiguration {
+    fn default() -> Self {
+        Self {
+            quorum_percentage: 0.66,
+            voting_timeout: Duration::from_secs(30),
+            quorum_type: QuorumType::SimpleMajority,
+            allow_abstentions: true,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SwarmHealthStatus {
     pub overall_health: f64,
@@ -829,7 +998,7 @@ impl Default for SwarmConfiguration {
         Self {
             max_agents: 12,
             heartbeat_interval: Duration::from_secs(30),
-            consensus_timeout: Duration::from_secs(60),
+            consensus: ConsensusConfiguration::default(),
             message_retention: Duration::from_hours(24),
             auto_scaling_enabled: true,
             fault_tolerance: FaultToleranceConfig {
@@ -846,4 +1015,153 @@ impl Default for SwarmConfiguration {
             },
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proposal(deadline: SystemTime) -> ConsensusProposal {
+        ConsensusProposal {
+            id: Uuid::new_v4().to_string(),
+            proposer: "coordinator".to_string(),
+            proposal_type: ProposalType::TopologyChange,
+            description: "Switch to a mesh topology".to_string(),
+            payload: json!({}),
+            votes: HashMap::new(),
+            created_at: SystemTime::now(),
+            deadline,
+            quorum_required: 0,
+            status: ProposalStatus::Active,
+        }
+    }
+
+    /// An open proposal with the given voter weights and votes cast so far
+    fn active(voters: &[(&str, f64)], votes: &[(&str, Vote)]) -> ActiveProposal {
+        let mut proposal = proposal(SystemTime::now() + Duration::from_secs(60));
+        for (voter, vote) in votes {
+            proposal.votes.insert(voter.to_string(), vote.clone());
+        }
+        ActiveProposal {
+            proposal,
+            voter_weights: voters.iter().map(|(voter, weight)| (voter.to_string(), *weight)).collect(),
+        }
+    }
+
+    fn config(quorum_type: QuorumType) -> ConsensusConfiguration {
+        ConsensusConfiguration { quorum_type, ..ConsensusConfiguration::default() }
+    }
+
+    const VOTERS: [(&str, f64); 3] = [("a", 1.0), ("b", 1.0), ("c", 1.0)];
+
+    #[test]
+    fn test_simple_majority() {
+        let config = config(QuorumType::SimpleMajority);
+        let now = SystemTime::now();
+
+        // The outstanding vote cannot overturn two approvals
+        let decided = active(&VOTERS, &[("a", Vote::Approve), ("b", Vote::Approve)]);
+        assert_eq!(decided.outcome(&config, now), Some(ProposalStatus::Approved));
+
+        let split = active(&VOTERS, &[("a", Vote::Approve), ("b", Vote::Reject)]);
+        assert_eq!(split.outcome(&config, now), None);
+
+        let tied = active(&VOTERS, &[("a", Vote::Approve), ("b", Vote::Reject), ("c", Vote::Abstain)]);
+        assert_eq!(tied.outcome(&config, now), Some(ProposalStatus::Rejected));
+    }
+
+    #[test]
+    fn test_supermajority() {
+        let config = config(QuorumType::Supermajority(0.75));
+        let now = SystemTime::now();
+        let voters = [("a", 1.0), ("b", 1.0), ("c", 1.0), ("d", 1.0)];
+
+        let carried = active(
+            &voters,
+            &[("a", Vote::Approve), ("b", Vote::Approve), ("c", Vote::Approve), ("d", Vote::Reject)],
+        );
+        assert_eq!(carried.outcome(&config, now), Some(ProposalStatus::Approved));
+
+        // A simple majority is not enough
+        let short = active(
+            &voters,
+            &[("a", Vote::Approve), ("b", Vote::Approve), ("c", Vote::Reject), ("d", Vote::Abstain)],
+        );
+        assert_eq!(short.outcome(&config, now), Some(ProposalStatus::Rejected));
+    }
+
+    #[test]
+    fn test_unanimity() {
+        let config = config(QuorumType::Unanimity);
+        let now = SystemTime::now();
+
+        // A single rejection decides before everyone has voted
+        let vetoed = active(&VOTERS, &[("a", Vote::Approve), ("b", Vote::Reject)]);
+        assert_eq!(vetoed.outcome(&config, now), Some(ProposalStatus::Rejected));
+
+        let pending = active(&VOTERS, &[("a", Vote::Approve), ("b", Vote::Approve)]);
+        assert_eq!(pending.outcome(&config, now), None);
+
+        let agreed = active(&VOTERS, &[("a", Vote::Approve), ("b", Vote::Approve), ("c", Vote::Approve)]);
+        assert_eq!(agreed.outcome(&config, now), Some(ProposalStatus::Approved));
+    }
+
+    #[test]
+    fn test_weighted_by_performance() {
+        let voters = [("a", 1.0), ("b", 0.2), ("c", 0.2)];
+        let votes = [("a", Vote::Approve), ("b", Vote::Reject), ("c", Vote::Reject)];
+        let now = SystemTime::now();
+
+        let weighted = active(&voters, &votes);
+        assert_eq!(
+            weighted.outcome(&config(QuorumType::WeightedByPerformance), now),
+            Some(ProposalStatus::Approved)
+        );
+
+        // The same votes lose when every agent counts the same
+        assert_eq!(
+            weighted.outcome(&config(QuorumType::SimpleMajority), now),
+            Some(ProposalStatus::Rejected)
+        );
+    }
+
+    #[test]
+    fn test_deadline_without_quorum_expires() {
+        let config = config(QuorumType::SimpleMajority);
+        let proposal = active(&VOTERS, &[("a", Vote::Approve)]);
+        assert_eq!(proposal.outcome(&config, SystemTime::now()), None);
+
+        let closed = proposal.proposal.deadline + Duration::from_secs(1);
+        assert_eq!(proposal.outcome(&config, closed), Some(ProposalStatus::Expired));
+    }
+
+    #[tokio::test]
+    async fn test_proposal_expires_at_voting_timeout() {
+        let config = SwarmConfiguration {
+            consensus: ConsensusConfiguration {
+                voting_timeout: Duration::from_millis(50),
+                ..ConsensusConfiguration::default()
+            },
+            ..SwarmConfiguration::default()
+        };
+        let coordinator = AdvancedSwarmCoordinator::new("swarm".to_string(), SwarmTopology::Mesh, config).await;
+        let mut voters = Vec::new();
+        for _ in 0..3 {
+            voters.push(coordinator.add_agent(AgentType::Coder, vec![AgentCapability::ConsensusParticipation]).await.unwrap());
+        }
+        let mut failed = coordinator.subscribe_to_events(EventType::ConsensusFailed).await;
+
+        // A deadline in the past falls back to the configured voting timeout
+        let proposal_id = coordinator.initiate_consensus(proposal(SystemTime::UNIX_EPOCH)).await.unwrap();
+        assert!(coordinator.process_vote(&proposal_id, &voters[0], Vote::Approve).await.unwrap().is_none());
+
+        let event = tokio::time::timeout(Duration::from_secs(5), failed.recv()).await.unwrap().unwrap();
+        assert_eq!(event.payload["proposal_id"], proposal_id.as_str());
+        assert_eq!(event.payload["result"], "Expired");
+
+        let history = coordinator.get_consensus_history().await;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].result, ProposalStatus::Expired);
+        assert!(coordinator.process_vote(&proposal_id, &voters[1], Vote::Approve).await.is_err());
+    }
+}
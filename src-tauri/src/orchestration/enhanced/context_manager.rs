@@ -23,7 +23,7 @@ pub struct AdaptiveContextManager {
 }
 
 /// Managed context with adaptive features
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ManagedContext {
     pub session_id: String,
     pub context_data: ContextData,
@@ -35,7 +35,7 @@ pub struct ManagedContext {
 }
 
 /// Context data structure
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContextData {
     pub messages: VecDeque<ContextMessage>,
     pub system_prompt: String,
@@ -46,7 +46,7 @@ pub struct ContextData {
 }
 
 /// Individual context message
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContextMessage {
     pub id: String,
     pub role: MessageRole,
@@ -58,7 +58,7 @@ pub struct ContextMessage {
     pub metadata: HashMap<String, Value>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MessageRole {
     System,
     User,
@@ -68,7 +68,7 @@ pub enum MessageRole {
 }
 
 /// Context attachment (files, images, etc.)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContextAttachment {
     pub id: String,
     pub attachment_type: AttachmentType,
@@ -78,7 +78,7 @@ pub struct ContextAttachment {
     pub compression_ratio: f64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AttachmentType {
     Code,
     Documentation,
@@ -88,7 +88,7 @@ pub enum AttachmentType {
 }
 
 /// Context metadata
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContextMetadata {
     pub created_at: SystemTime,
     pub last_accessed: SystemTime,
@@ -99,7 +99,7 @@ pub struct ContextMetadata {
     pub tags: Vec<String>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ContextPriority {
     Low,
     Normal,
@@ -108,7 +108,7 @@ pub enum ContextPriority {
 }
 
 /// Retention policy for context data
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RetentionPolicy {
     pub max_age: Duration,
     pub max_size_tokens: u32,
@@ -117,7 +117,7 @@ pub struct RetentionPolicy {
 }
 
 /// Compression state tracking
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompressionState {
     pub compression_ratio: f64,
     pub compressed_tokens: u32,
@@ -127,7 +127,7 @@ pub struct CompressionState {
     pub last_compressed: SystemTime,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum CompressionMethod {
     None,
     Summarization,
@@ -137,7 +137,7 @@ pub enum CompressionMethod {
 }
 
 /// Memory usage statistics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryStats {
     pub total_memory_bytes: u64,
     pub compressed_memory_bytes: u64,
@@ -148,7 +148,7 @@ pub struct MemoryStats {
 }
 
 /// Access pattern analysis
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccessPattern {
     pub access_frequency: f64,
     pub recent_accesses: VecDeque<SystemTime>,
@@ -158,7 +158,7 @@ pub struct AccessPattern {
 }
 
 /// Optimization action record
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptimizationAction {
     pub action_type: OptimizationType,
     pub timestamp: SystemTime,
@@ -169,7 +169,7 @@ pub struct OptimizationAction {
     pub description: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum OptimizationType {
     Compression,
     Pruning,
@@ -180,7 +180,7 @@ pub enum OptimizationType {
 }
 
 /// Compressed segment
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompressedSegment {
     pub id: String,
     pub original_messages: Vec<String>,
@@ -305,6 +305,96 @@ pub struct ContextMetrics {
     pub last_updated: SystemTime,
 }
 
+impl MessageRole {
+    /// Role name used by OpenAI-compatible chat APIs
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageRole::System => "system",
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+            MessageRole::Function => "function",
+            MessageRole::Tool => "tool",
+        }
+    }
+}
+
+impl ContextMessage {
+    /// Create a message with estimated token count and default importance
+    pub fn new(role: MessageRole, content: impl Into<String>) -> Self {
+        let content = content.into();
+        let is_system = role == MessageRole::System;
+
+        Self {
+            id: Uuid::new_v4().to_string(),
+            tokens: (content.chars().count() as u32).div_ceil(4), // Rough estimate
+            role,
+            content,
+            timestamp: SystemTime::now(),
+            importance_score: if is_system { 1.0 } else { 0.5 },
+            compression_eligible: !is_system,
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Chat API representation of this message
+    pub fn to_chat_message(&self) -> Value {
+        let mut message = json!({
+            "role": self.role.as_str(),
+            "content": self.content,
+        });
+
+        // Tool results must reference the call they answer; function results carry its name
+        match self.role {
+            MessageRole::Tool => {
+                if let Some(call_id) = self.metadata.get("tool_call_id") {
+                    message["tool_call_id"] = call_id.clone();
+                }
+            }
+            MessageRole::Function => {
+                if let Some(name) = self.metadata.get("name") {
+                    message["name"] = name.clone();
+                }
+            }
+            _ => {}
+        }
+
+        message
+    }
+}
+
+impl ManagedContext {
+    /// Assemble the managed history into a chat `messages` array
+    ///
+    /// The system prompt comes first, followed by summaries of compressed
+    /// segments (which replaced the oldest messages) and the retained messages
+    /// in order.
+    pub fn to_chat_messages(&self) -> Vec<Value> {
+        let mut messages = Vec::new();
+
+        if !self.context_data.system_prompt.is_empty() {
+            messages.push(json!({
+                "role": "system",
+                "content": self.context_data.system_prompt,
+            }));
+        }
+
+        if !self.context_data.compressed_segments.is_empty() {
+            let summary = self.context_data.compressed_segments
+                .iter()
+                .map(|segment| segment.compressed_content.as_str())
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            messages.push(json!({
+                "role": "system",
+                "content": format!("Summary of earlier conversation:\n{}", summary),
+            }));
+        }
+
+        messages.extend(self.context_data.messages.iter().map(ContextMessage::to_chat_message));
+        messages
+    }
+}
+
 impl AdaptiveContextManager {
     /// Create a new adaptive context manager
     pub fn new(config: ContextManagerConfig) -> Self {
@@ -376,8 +466,8 @@ impl AdaptiveContextManager {
             optimization_history: Vec::new(),
         };
 
-        let mut contexts = self.contexts.write().await;
-        contexts.insert(session_id.clone(), context);
+        // Released before the metrics update, which reads the contexts again
+        self.contexts.write().await.insert(session_id.clone(), context);
 
         self.update_metrics().await;
         info!("Created new managed context for session: {}", session_id);
@@ -413,11 +503,18 @@ impl AdaptiveContextManager {
 
     /// Add message to context with intelligent management
     pub async fn add_message(&self, session_id: &str, message: ContextMessage) -> Result<()> {
+        self.add_messages(session_id, vec![message]).await
+    }
+
+    /// Add several messages in one step, so concurrent writers cannot interleave with them
+    pub async fn add_messages(&self, session_id: &str, messages: Vec<ContextMessage>) -> Result<()> {
         let mut contexts = self.contexts.write().await;
         
         if let Some(context) = contexts.get_mut(session_id) {
-            context.context_data.messages.push_back(message.clone());
-            context.context_data.total_tokens += message.tokens;
+            let count = messages.len();
+            let tokens: u32 = messages.iter().map(|message| message.tokens).sum();
+            context.context_data.messages.extend(messages);
+            context.context_data.total_tokens += tokens;
 
             // Check if context size exceeds limits
             if context.context_data.total_tokens > context.metadata.retention_policy.max_size_tokens {
@@ -428,7 +525,7 @@ impl AdaptiveContextManager {
                 self.compress_context_segment(context, 0.3).await?;
             }
 
-            info!("Added message to context {}: {} tokens", session_id, message.tokens);
+            info!("Added {} message(s) to context {}: {} tokens", count, session_id, tokens);
             Ok(())
         } else {
             Err(anyhow!("Context not found: {}", session_id))
@@ -503,7 +600,7 @@ impl AdaptiveContextManager {
 
         if candidates.len() >= 2 {
             let compressed_segment = self.compression_engine
-                .compress_messages(&candidates.iter().map(|(_, msg)| msg.clone()).collect())
+                .compress_messages(&candidates.iter().map(|(_, msg)| msg.clone()).collect::<Vec<_>>())
                 .await?;
             
             // Remove original messages and add compressed segment
@@ -638,11 +735,11 @@ impl AdaptiveContextManager {
         if recent_accesses > 20 {
             // High usage - increase context size for better performance
             context.access_pattern.preferred_context_size = 
-                (context.access_pattern.preferred_context_size * 1.2) as u32;
+                (context.access_pattern.preferred_context_size as f64 * 1.2) as u32;
         } else if recent_accesses < 5 {
             // Low usage - decrease context size to save memory
             context.access_pattern.preferred_context_size = 
-                (context.access_pattern.preferred_context_size * 0.8) as u32;
+                (context.access_pattern.preferred_context_size as f64 * 0.8) as u32;
         }
 
         // Clamp to reasonable bounds
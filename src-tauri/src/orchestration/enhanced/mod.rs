@@ -13,6 +13,7 @@ pub mod context_manager;
 pub mod performance_tracker;
 pub mod recovery_system;
 pub mod adaptive_workflows;
+pub mod workflow_definitions;
 
 // Re-export key enhanced orchestration types
 pub use openrouter::*;
@@ -21,6 +22,7 @@ pub use context_manager::*;
 pub use performance_tracker::*;
 pub use recovery_system::*;
 pub use adaptive_workflows::*;
+pub use workflow_definitions::*;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -28,6 +30,7 @@ use std::time::{Duration, SystemTime, Instant};
 use tokio::sync::{RwLock, mpsc};
 use uuid::Uuid;
 use anyhow::Result;
+use crate::orchestration::{ExecutionRequest, ExecutionResponse, SwarmTopology};
 
 /// Enhanced AI orchestration configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -184,6 +187,20 @@ pub struct AdvancedExecutionRequest {
     pub context_optimization: ContextOptimization,
     pub performance_requirements: PerformanceRequirements,
     pub recovery_options: RecoveryOptions,
+    #[serde(default)]
+    pub generation: GenerationOptions,
+    /// Managed conversation this request continues; its history is sent with the prompt
+    #[serde(default)]
+    pub context_id: Option<String>,
+}
+
+/// Model generation parameters
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenerationOptions {
+    /// Upper bound on completion tokens; 4000 when unset
+    pub max_tokens: Option<u32>,
+    /// Stream the completion as incremental deltas
+    pub stream: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
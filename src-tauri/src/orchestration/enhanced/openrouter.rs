@@ -12,8 +12,12 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 
+/// Completion token limit used when a request does not set one
+const DEFAULT_MAX_TOKENS: u32 = 4000;
+
 /// OpenRouter integration service
 #[derive(Debug, Clone)]
 pub struct OpenRouterService {
@@ -23,6 +27,90 @@ pub struct OpenRouterService {
     model_cache: Arc<RwLock<HashMap<String, ModelRoutingInfo>>>,
     performance_history: Arc<RwLock<HashMap<String, Vec<PerformanceEntry>>>>,
     circuit_breakers: Arc<RwLock<HashMap<String, CircuitBreaker>>>,
+    active_streams: Arc<parking_lot::RwLock<HashMap<String, CancellationToken>>>,
+    context_manager: Option<Arc<AdaptiveContextManager>>,
+}
+
+/// Incremental output of a streaming completion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamDelta {
+    pub request_id: String,
+    pub model_id: String,
+    /// Text added by this chunk
+    pub content: String,
+    /// Position of this delta within the stream
+    pub index: u32,
+    /// Completion tokens received so far (reported or estimated)
+    pub output_tokens: u64,
+    pub finish_reason: Option<String>,
+}
+
+/// Accumulated state of a streaming completion
+#[derive(Debug, Default)]
+struct StreamOutcome {
+    content: String,
+    usage: Option<TokenUsage>,
+    finish_reason: Option<String>,
+    deltas: u32,
+    cancelled: bool,
+    first_token_after: Option<Duration>,
+}
+
+/// Registration of a running stream, removed when the stream ends or its future is dropped
+struct ActiveStream {
+    streams: Arc<parking_lot::RwLock<HashMap<String, CancellationToken>>>,
+    request_id: String,
+}
+
+impl Drop for ActiveStream {
+    fn drop(&mut self) {
+        self.streams.write().remove(&self.request_id);
+    }
+}
+
+/// Incremental parser for `text/event-stream` bodies
+///
+/// Buffers raw bytes so that events and UTF-8 sequences split across network
+/// chunks are only decoded once complete.
+#[derive(Debug, Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+}
+
+impl SseParser {
+    /// Feed a chunk and return the `data` payload of every completed event
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some((end, separator_len)) = Self::find_event_end(&self.buffer) {
+            let raw: Vec<u8> = self.buffer.drain(..end + separator_len).take(end).collect();
+            let text = String::from_utf8_lossy(&raw);
+
+            let data: Vec<&str> = text
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                .collect();
+
+            // Events without data lines are keep-alive comments
+            if !data.is_empty() {
+                events.push(data.join("\n"));
+            }
+        }
+
+        events
+    }
+
+    fn find_event_end(buffer: &[u8]) -> Option<(usize, usize)> {
+        let lf = buffer.windows(2).position(|w| w == b"\n\n").map(|i| (i, 2));
+        let crlf = buffer.windows(4).position(|w| w == b"\r\n\r\n").map(|i| (i, 4));
+
+        match (lf, crlf) {
+            (Some(a), Some(b)) => Some(if a.0 <= b.0 { a } else { b }),
+            (a, b) => a.or(b),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -64,9 +152,17 @@ impl OpenRouterService {
             model_cache: Arc::new(RwLock::new(HashMap::new())),
             performance_history: Arc::new(RwLock::new(HashMap::new())),
             circuit_breakers: Arc::new(RwLock::new(HashMap::new())),
+            active_streams: Arc::new(parking_lot::RwLock::new(HashMap::new())),
+            context_manager: None,
         }
     }
 
+    /// Resolve `context_id` on requests against this context manager
+    pub fn with_context_manager(mut self, context_manager: Arc<AdaptiveContextManager>) -> Self {
+        self.context_manager = Some(context_manager);
+        self
+    }
+
     /// Initialize available models and their capabilities
     pub async fn initialize_models(&self) -> Result<()> {
         info!("Initializing OpenRouter models and capabilities");
@@ -239,11 +335,11 @@ impl OpenRouterService {
     }
 
     /// Select the optimal model based on request requirements and historical data
-    async fn select_optimal_model(
+    async fn select_optimal_model<'a>(
         &self,
-        available_models: &[&ModelRoutingInfo],
+        available_models: &[&'a ModelRoutingInfo],
         request: &AdvancedExecutionRequest,
-    ) -> Result<&ModelRoutingInfo> {
+    ) -> Result<&'a ModelRoutingInfo> {
         let mut scored_models = Vec::new();
 
         for model in available_models {
@@ -380,42 +476,233 @@ impl OpenRouterService {
             self.handle_success(model_id).await;
         }
 
-        match result {
-            Ok(response) => Ok(response),
+        let response = match result {
+            Ok(response) => response,
             Err(e) => {
                 // Attempt fallback if enabled
                 if request.recovery_options.fallback_models.len() > 0 {
-                    self.attempt_fallback(request, e).await
+                    self.attempt_fallback(request, e).await?
                 } else {
-                    Err(e)
+                    return Err(e);
                 }
             }
+        };
+
+        self.record_exchange(request, &response).await;
+        Ok(response)
+    }
+
+    /// Execute request via OpenRouter, streaming deltas to `on_delta` as they arrive
+    ///
+    /// The stream can be stopped with [`cancel_stream`](Self::cancel_stream)
+    /// using the request id; the returned response then carries the partial
+    /// output and is marked as cancelled. A stream that ends before `[DONE]`
+    /// is an error, as is starting a second stream under a running request id.
+    pub async fn execute_request_streaming<F>(
+        &self,
+        model_id: &str,
+        request: &AdvancedExecutionRequest,
+        mut on_delta: F,
+    ) -> Result<AdvancedExecutionResponse>
+    where
+        F: FnMut(StreamDelta) + Send,
+    {
+        let start_time = Instant::now();
+        let request_id = request.base_request.id.clone();
+        info!("Streaming request {} via OpenRouter model: {}", request_id, model_id);
+
+        // A second stream under the same id would take over the first one's cancel token
+        let cancel = CancellationToken::new();
+        let active_stream = {
+            let mut active_streams = self.active_streams.write();
+            if active_streams.contains_key(&request_id) {
+                return Err(anyhow!("Request {} is already streaming", request_id));
+            }
+            active_streams.insert(request_id.clone(), cancel.clone());
+            ActiveStream {
+                streams: self.active_streams.clone(),
+                request_id: request_id.clone(),
+            }
+        };
+
+        let mut outcome = StreamOutcome::default();
+        let result = self
+            .send_openrouter_stream(model_id, request, &cancel, &mut outcome, &mut on_delta)
+            .await;
+
+        drop(active_stream);
+        let total_time = start_time.elapsed();
+
+        // A user cancellation says nothing about the model's health
+        if !outcome.cancelled {
+            self.record_performance(model_id, &result, total_time).await;
+
+            if result.is_err() {
+                self.handle_failure(model_id).await;
+            } else {
+                self.handle_success(model_id).await;
+            }
         }
+
+        let response = match result {
+            Ok(response) => response,
+            // Only fall back when nothing has been shown to the user yet and they did not cancel
+            Err(e) if outcome.deltas == 0 && !outcome.cancelled && !request.recovery_options.fallback_models.is_empty() => {
+                let response = self.attempt_fallback(request, e).await?;
+                on_delta(StreamDelta {
+                    request_id: request_id.clone(),
+                    model_id: response.routing_info.selected_model.clone(),
+                    content: response.base_response.result.clone().unwrap_or_default(),
+                    index: 0,
+                    output_tokens: response.performance_data.tokens_processed.output_tokens,
+                    finish_reason: Some("stop".to_string()),
+                });
+                response
+            }
+            Err(e) => return Err(e),
+        };
+
+        self.record_exchange(request, &response).await;
+        Ok(response)
     }
 
-    /// Send request to OpenRouter API
-    async fn send_openrouter_request(
+    /// Stream a request and forward deltas to the frontend as progress events
+    pub async fn execute_request_streaming_to_app<R: tauri::Runtime>(
         &self,
+        app: &tauri::AppHandle<R>,
         model_id: &str,
         request: &AdvancedExecutionRequest,
     ) -> Result<AdvancedExecutionResponse> {
+        let max_tokens = request.generation.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+
+        let response = self
+            .execute_request_streaming(model_id, request, |delta| {
+                let progress = if max_tokens > 0 {
+                    (delta.output_tokens as f32 / max_tokens as f32).min(0.99)
+                } else {
+                    0.0
+                };
+                crate::events::emit_progress_event(app, delta.request_id, progress, delta.content);
+            })
+            .await?;
+
+        crate::events::emit_progress_event(
+            app,
+            request.base_request.id.clone(),
+            1.0,
+            String::new(),
+        );
+
+        Ok(response)
+    }
+
+    /// Cancel an in-flight streaming request, returning whether it was found
+    pub async fn cancel_stream(&self, request_id: &str) -> bool {
+        match self.active_streams.read().get(request_id) {
+            Some(token) => {
+                info!("Cancelling streaming request: {}", request_id);
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancel every in-flight streaming request, returning how many there were
+    pub fn cancel_all_streams(&self) -> usize {
+        let active_streams = self.active_streams.read();
+        for token in active_streams.values() {
+            token.cancel();
+        }
+        active_streams.len()
+    }
+
+    /// Ids of requests currently streaming
+    pub async fn active_stream_ids(&self) -> Vec<String> {
+        self.active_streams.read().keys().cloned().collect()
+    }
+
+    /// Build request headers for the OpenRouter API
+    fn request_headers(&self) -> Result<HeaderMap> {
         let mut headers = HeaderMap::new();
         if let Some(api_key) = &self.api_key {
             headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", api_key))?);
         }
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        Ok(headers)
+    }
+
+    /// Build the chat messages, prefixing the managed history when a context is given
+    async fn build_messages(&self, request: &AdvancedExecutionRequest) -> Result<Vec<Value>> {
+        let mut messages = match &request.context_id {
+            Some(context_id) => {
+                let context_manager = self.context_manager.as_ref().ok_or_else(|| {
+                    anyhow!("Request references context {} but no context manager is configured", context_id)
+                })?;
+                context_manager.get_context(context_id).await?.to_chat_messages()
+            }
+            None => Vec::new(),
+        };
+
+        messages.push(json!({
+            "role": "user",
+            "content": request.base_request.prompt
+        }));
+
+        Ok(messages)
+    }
+
+    /// Append the prompt and the assistant reply to the request's managed context
+    async fn record_exchange(&self, request: &AdvancedExecutionRequest, response: &AdvancedExecutionResponse) {
+        let (Some(context_id), Some(context_manager)) = (&request.context_id, &self.context_manager) else {
+            return;
+        };
+        // Cancelled or failed replies would leave a dangling turn in the history
+        if !response.base_response.success {
+            return;
+        }
+
+        let mut reply = ContextMessage::new(
+            MessageRole::Assistant,
+            response.base_response.result.clone().unwrap_or_default(),
+        );
+        reply.metadata.insert("model".to_string(), json!(response.routing_info.selected_model));
+        reply.metadata.insert("request_id".to_string(), json!(request.base_request.id));
 
-        let payload = json!({
+        // Added together so a concurrent request on the same context cannot land between them
+        let exchange = vec![ContextMessage::new(MessageRole::User, request.base_request.prompt.clone()), reply];
+        if let Err(e) = context_manager.add_messages(context_id, exchange).await {
+            warn!("Failed to record exchange in context {}: {}", context_id, e);
+        }
+    }
+
+    /// Build the chat completion payload
+    async fn build_chat_payload(&self, model_id: &str, request: &AdvancedExecutionRequest, stream: bool) -> Result<Value> {
+        let mut payload = json!({
             "model": model_id,
-            "messages": [{
-                "role": "user",
-                "content": request.base_request.prompt
-            }],
+            "messages": self.build_messages(request).await?,
             "temperature": request.base_request.temperature.unwrap_or(0.7),
-            "max_tokens": 4000,
-            "stream": false
+            "max_tokens": request.generation.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            "stream": stream
         });
 
+        if stream {
+            // Ask OpenRouter to append token usage to the final chunk
+            payload["usage"] = json!({ "include": true });
+        }
+
+        Ok(payload)
+    }
+
+    /// Send request to OpenRouter API
+    async fn send_openrouter_request(
+        &self,
+        model_id: &str,
+        request: &AdvancedExecutionRequest,
+    ) -> Result<AdvancedExecutionResponse> {
+        let headers = self.request_headers()?;
+        let payload = self.build_chat_payload(model_id, request, false).await?;
+
         let queue_start = Instant::now();
         let response = self.client
             .post(&format!("{}/chat/completions", self.base_url))
@@ -441,13 +728,215 @@ impl OpenRouterService {
             .to_string();
 
         // Extract token usage
-        let usage = &response_data["usage"];
-        let token_usage = TokenUsage {
-            input_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0),
-            output_tokens: usage["completion_tokens"].as_u64().unwrap_or(0),
-            total_tokens: usage["total_tokens"].as_u64().unwrap_or(0),
+        let token_usage = Self::parse_usage(&response_data["usage"]).unwrap_or(TokenUsage {
+            input_tokens: 0,
+            output_tokens: 0,
+            total_tokens: 0,
+        });
+
+        Ok(self
+            .build_execution_response(
+                model_id,
+                request,
+                content,
+                token_usage,
+                queue_time,
+                processing_time,
+                json!({}),
+            )
+            .await)
+    }
+
+    /// Send a streaming request and consume its server-sent events
+    async fn send_openrouter_stream<F>(
+        &self,
+        model_id: &str,
+        request: &AdvancedExecutionRequest,
+        cancel: &CancellationToken,
+        outcome: &mut StreamOutcome,
+        on_delta: &mut F,
+    ) -> Result<AdvancedExecutionResponse>
+    where
+        F: FnMut(StreamDelta) + Send,
+    {
+        let headers = self.request_headers()?;
+        let payload = self.build_chat_payload(model_id, request, true).await?;
+
+        let queue_start = Instant::now();
+        let mut response = tokio::select! {
+            _ = cancel.cancelled() => {
+                outcome.cancelled = true;
+                return Err(anyhow!("Streaming request cancelled before a response was received"));
+            }
+            response = self.client
+                .post(&format!("{}/chat/completions", self.base_url))
+                .headers(headers)
+                .json(&payload)
+                .send() => response?,
         };
 
+        let queue_time = queue_start.elapsed();
+        let processing_start = Instant::now();
+
+        if !response.status().is_success() {
+            return Err(anyhow!("OpenRouter request failed: {}", response.status()));
+        }
+
+        let mut parser = SseParser::default();
+        let mut finished = false;
+
+        while !finished {
+            // A cancel takes effect even when more of the body is already buffered
+            let chunk = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => {
+                    outcome.cancelled = true;
+                    break;
+                }
+                chunk = response.chunk() => chunk?,
+            };
+
+            let Some(chunk) = chunk else {
+                break;
+            };
+
+            for data in parser.push(&chunk) {
+                if data.trim() == "[DONE]" {
+                    finished = true;
+                    break;
+                }
+
+                let event: Value = match serde_json::from_str(&data) {
+                    Ok(event) => event,
+                    Err(e) => {
+                        warn!("Skipping malformed stream chunk: {}", e);
+                        continue;
+                    }
+                };
+
+                if let Some(error) = event.get("error") {
+                    return Err(anyhow!(
+                        "OpenRouter stream error: {}",
+                        error["message"].as_str().unwrap_or("unknown error")
+                    ));
+                }
+
+                if let Some(usage) = Self::parse_usage(&event["usage"]) {
+                    outcome.usage = Some(usage);
+                }
+
+                let choice = &event["choices"][0];
+                if let Some(reason) = choice["finish_reason"].as_str() {
+                    outcome.finish_reason = Some(reason.to_string());
+                }
+
+                let content = choice["delta"]["content"].as_str().unwrap_or_default();
+                if content.is_empty() {
+                    continue;
+                }
+
+                outcome.first_token_after.get_or_insert_with(|| processing_start.elapsed());
+                outcome.content.push_str(content);
+
+                on_delta(StreamDelta {
+                    request_id: request.base_request.id.clone(),
+                    model_id: model_id.to_string(),
+                    content: content.to_string(),
+                    index: outcome.deltas,
+                    output_tokens: Self::estimate_tokens(&outcome.content),
+                    finish_reason: outcome.finish_reason.clone(),
+                });
+                outcome.deltas += 1;
+            }
+        }
+
+        // A stream that ends without `[DONE]` was cut off, e.g. by a dropped connection
+        if !finished && !outcome.cancelled {
+            return Err(anyhow!(
+                "OpenRouter stream ended before completion after {} chunks",
+                outcome.deltas
+            ));
+        }
+
+        let processing_time = processing_start.elapsed();
+
+        // Usage is only reported on the final chunk; estimate it when cancelled
+        let token_usage = outcome.usage.clone().unwrap_or_else(|| {
+            let input_tokens = Self::estimate_tokens(&request.base_request.prompt);
+            let output_tokens = Self::estimate_tokens(&outcome.content);
+            TokenUsage {
+                input_tokens,
+                output_tokens,
+                total_tokens: input_tokens + output_tokens,
+            }
+        });
+
+        let mut response = self
+            .build_execution_response(
+                model_id,
+                request,
+                outcome.content.clone(),
+                token_usage,
+                queue_time,
+                processing_time,
+                json!({
+                    "streamed": true,
+                    "chunks": outcome.deltas,
+                    "cancelled": outcome.cancelled,
+                    "usage_estimated": outcome.usage.is_none(),
+                    "finish_reason": outcome.finish_reason,
+                    "time_to_first_token_ms": outcome.first_token_after.map(|d| d.as_millis() as u64),
+                }),
+            )
+            .await;
+
+        if outcome.cancelled {
+            response.base_response.success = false;
+            response.base_response.error = Some("Streaming cancelled by user".to_string());
+        }
+
+        Ok(response)
+    }
+
+    /// Parse an OpenAI-style usage object
+    fn parse_usage(usage: &Value) -> Option<TokenUsage> {
+        let input_tokens = usage["prompt_tokens"].as_u64()?;
+        let output_tokens = usage["completion_tokens"].as_u64().unwrap_or(0);
+        Some(TokenUsage {
+            input_tokens,
+            output_tokens,
+            total_tokens: usage["total_tokens"]
+                .as_u64()
+                .unwrap_or(input_tokens + output_tokens),
+        })
+    }
+
+    /// Rough token estimate (about four characters per token)
+    fn estimate_tokens(text: &str) -> u64 {
+        (text.chars().count() as u64).div_ceil(4)
+    }
+
+    /// Assemble the response shared by the streaming and non-streaming paths
+    #[allow(clippy::too_many_arguments)]
+    async fn build_execution_response(
+        &self,
+        model_id: &str,
+        request: &AdvancedExecutionRequest,
+        content: String,
+        token_usage: TokenUsage,
+        queue_time: Duration,
+        processing_time: Duration,
+        extra_metadata: Value,
+    ) -> AdvancedExecutionResponse {
+        let mut metadata = json!({
+            "model": model_id,
+            "provider": "openrouter",
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        });
+        if let (Some(target), Some(extra)) = (metadata.as_object_mut(), extra_metadata.as_object()) {
+            target.extend(extra.clone());
+        }
+
         // Build enhanced response
         let base_response = ExecutionResponse {
             id: request.base_request.id.clone(),
@@ -455,11 +944,7 @@ impl OpenRouterService {
             success: true,
             execution_time: (queue_time + processing_time).as_millis() as u64,
             error: None,
-            metadata: Some(json!({
-                "model": model_id,
-                "provider": "openrouter",
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            })),
+            metadata: Some(metadata),
             swarm_metrics: None,
             memory_operations: Vec::new(),
         };
@@ -478,7 +963,7 @@ impl OpenRouterService {
 
         let cost_breakdown = self.calculate_cost_breakdown(model_id, &token_usage).await;
 
-        Ok(AdvancedExecutionResponse {
+        AdvancedExecutionResponse {
             base_response,
             routing_info: ModelRoutingResult {
                 selected_model: model_id.to_string(),
@@ -489,7 +974,7 @@ impl OpenRouterService {
             performance_data,
             cost_breakdown,
             recovery_actions: Vec::new(),
-        })
+        }
     }
 
     /// Calculate cost breakdown for request
@@ -731,4 +1216,260 @@ impl Default for OpenRouterService {
     fn default() -> Self {
         Self::new(std::env::var("OPENROUTER_API_KEY").ok())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(prompt: &str, context_id: Option<&str>) -> AdvancedExecutionRequest {
+        serde_json::from_value(json!({
+            "base_request": { "id": "req-1", "command": "chat", "prompt": prompt, "hive_mind_commands": [] },
+            "routing_preferences": { "preferred_models": [], "avoid_models": [], "quality_preference": "Balanced" },
+            "context_optimization": { "enabled": false, "compression_ratio": 1.0, "preserve_recent": true, "adaptive_sizing": false },
+            "performance_requirements": { "priority": "Normal" },
+            "recovery_options": { "auto_retry": false, "fallback_models": [], "circuit_breaker": false, "graceful_degradation": false },
+            "context_id": context_id
+        }))
+        .unwrap()
+    }
+
+    async fn service_with_context(system_prompt: &str) -> (OpenRouterService, Arc<AdaptiveContextManager>) {
+        let context_manager = Arc::new(AdaptiveContextManager::new(ContextManagerConfig::default()));
+        let context_data = ContextData {
+            messages: Default::default(),
+            system_prompt: system_prompt.to_string(),
+            variables: HashMap::new(),
+            attachments: Vec::new(),
+            total_tokens: 0,
+            compressed_segments: Vec::new(),
+        };
+        context_manager.create_context("ctx".to_string(), context_data).await.unwrap();
+
+        let service = OpenRouterService::new(None).with_context_manager(context_manager.clone());
+        (service, context_manager)
+    }
+
+    #[test]
+    fn test_sse_parser_handles_split_events() {
+        let mut parser = SseParser::default();
+
+        // An event split mid-line and mid-character is held back until complete
+        let event = "data: {\"text\":\"héllo\"}\n\n".as_bytes();
+        let split = event.iter().position(|&b| b == 0xc3).unwrap() + 1;
+        assert!(parser.push(&event[..split]).is_empty());
+        assert_eq!(parser.push(&event[split..]), vec!["{\"text\":\"héllo\"}"]);
+
+        // Comments are skipped, multi-line data is joined and CRLF separators are accepted
+        let events = parser.push(b": keep-alive\n\ndata: a\ndata:b\r\n\r\ndata: [DONE]\n\n");
+        assert_eq!(events, vec!["a\nb", "[DONE]"]);
+        assert!(parser.buffer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_chat_payload_limits_completion_tokens() {
+        let service = OpenRouterService::new(None);
+        let mut request = request("Hi", None);
+
+        let payload = service.build_chat_payload("model-a", &request, false).await.unwrap();
+        assert_eq!(payload["max_tokens"], json!(DEFAULT_MAX_TOKENS));
+        assert!(payload.get("usage").is_none());
+
+        request.generation.max_tokens = Some(256);
+        let payload = service.build_chat_payload("model-a", &request, true).await.unwrap();
+        assert_eq!(payload["max_tokens"], json!(256));
+        assert_eq!(payload["usage"], json!({ "include": true }));
+    }
+
+    #[test]
+    fn test_parse_usage() {
+        let usage = OpenRouterService::parse_usage(&json!({
+            "prompt_tokens": 12,
+            "completion_tokens": 30,
+            "total_tokens": 42
+        }))
+        .unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens, usage.total_tokens), (12, 30, 42));
+
+        // The total is derived when missing, and usage without prompt tokens is ignored
+        let usage = OpenRouterService::parse_usage(&json!({ "prompt_tokens": 5 })).unwrap();
+        assert_eq!((usage.output_tokens, usage.total_tokens), (0, 5));
+        assert!(OpenRouterService::parse_usage(&Value::Null).is_none());
+        assert!(OpenRouterService::parse_usage(&json!({ "completion_tokens": 3 })).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_requests_carry_the_managed_history() {
+        let (service, _) = service_with_context("Be brief.").await;
+
+        let messages = service.build_messages(&request("First question", Some("ctx"))).await.unwrap();
+        assert_eq!(messages, vec![
+            json!({ "role": "system", "content": "Be brief." }),
+            json!({ "role": "user", "content": "First question" }),
+        ]);
+
+        // Without a context only the prompt is sent, and unknown contexts are an error
+        let messages = service.build_messages(&request("Hi", None)).await.unwrap();
+        assert_eq!(messages, vec![json!({ "role": "user", "content": "Hi" })]);
+        assert!(service.build_messages(&request("Hi", Some("missing"))).await.is_err());
+        assert!(OpenRouterService::new(None).build_messages(&request("Hi", Some("ctx"))).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_successful_exchanges_are_recorded() {
+        let (service, context_manager) = service_with_context("").await;
+        let first = request("What is 2 + 2?", Some("ctx"));
+        let usage = TokenUsage { input_tokens: 8, output_tokens: 1, total_tokens: 9 };
+        let response = service
+            .build_execution_response("model-a", &first, "4".to_string(), usage, Duration::ZERO, Duration::ZERO, json!({}))
+            .await;
+        service.record_exchange(&first, &response).await;
+
+        // Failed replies are not added to the history
+        let mut failed = response.clone();
+        failed.base_response.success = false;
+        service.record_exchange(&request("Ignored", Some("ctx")), &failed).await;
+
+        let context = context_manager.get_context("ctx").await.unwrap();
+        let history: Vec<_> = context.context_data.messages.iter().map(|m| (m.role.as_str(), m.content.as_str())).collect();
+        assert_eq!(history, vec![("user", "What is 2 + 2?"), ("assistant", "4")]);
+        assert_eq!(context.context_data.messages[1].metadata["model"], json!("model-a"));
+
+        let messages = service.build_messages(&request("And 3 + 3?", Some("ctx"))).await.unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[2], json!({ "role": "user", "content": "And 3 + 3?" }));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_exchanges_stay_paired() {
+        let (service, context_manager) = service_with_context("").await;
+        let mut recordings = Vec::new();
+        for i in 0..8 {
+            let service = service.clone();
+            recordings.push(tokio::spawn(async move {
+                let request = request(&format!("question {}", i), Some("ctx"));
+                let usage = TokenUsage { input_tokens: 2, output_tokens: 2, total_tokens: 4 };
+                let response = service
+                    .build_execution_response("model-a", &request, format!("answer {}", i), usage, Duration::ZERO, Duration::ZERO, json!({}))
+                    .await;
+                service.record_exchange(&request, &response).await;
+            }));
+        }
+        for recording in recordings {
+            recording.await.unwrap();
+        }
+
+        // Every question is directly followed by its own answer
+        let context = context_manager.get_context("ctx").await.unwrap();
+        let messages: Vec<_> = context.context_data.messages.iter().collect();
+        assert_eq!(messages.len(), 16);
+        for pair in messages.chunks(2) {
+            assert_eq!(pair[0].content.replace("question", "answer"), pair[1].content);
+        }
+    }
+
+    fn sse_body(events: &[Value], done: bool) -> String {
+        let mut body: String = events.iter().map(|event| format!("data: {}\n\n", event)).collect();
+        if done {
+            body.push_str("data: [DONE]\n\n");
+        }
+        body
+    }
+
+    async fn streaming_service(body: String) -> (OpenRouterService, wiremock::MockServer) {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let mut service = OpenRouterService::new(Some("test-key".to_string()));
+        service.base_url = server.uri();
+        (service, server)
+    }
+
+    #[tokio::test]
+    async fn test_streamed_deltas_are_forwarded() {
+        let body = sse_body(&[
+            json!({ "choices": [{ "delta": { "content": "Hel" } }] }),
+            json!({ "choices": [{ "delta": { "content": "lo" }, "finish_reason": "stop" }],
+                    "usage": { "prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5 } }),
+        ], true);
+        let (service, _server) = streaming_service(body).await;
+
+        let mut deltas = Vec::new();
+        let response = service
+            .execute_request_streaming("model-a", &request("Hi", None), |delta| deltas.push(delta))
+            .await
+            .unwrap();
+
+        let contents: Vec<_> = deltas.iter().map(|delta| (delta.index, delta.content.as_str())).collect();
+        assert_eq!(contents, vec![(0, "Hel"), (1, "lo")]);
+        assert_eq!(deltas[1].finish_reason.as_deref(), Some("stop"));
+        assert!(response.base_response.success);
+        assert_eq!(response.base_response.result.as_deref(), Some("Hello"));
+        assert_eq!(response.performance_data.tokens_processed.total_tokens, 5);
+        assert!(service.active_stream_ids().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_truncated_stream_is_an_error() {
+        let body = sse_body(&[json!({ "choices": [{ "delta": { "content": "Hel" } }] })], false);
+        let (service, _server) = streaming_service(body).await;
+
+        let mut deltas = 0;
+        let error = service
+            .execute_request_streaming("model-a", &request("Hi", None), |_| deltas += 1)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("ended before completion"), "{}", error);
+        assert_eq!(deltas, 1);
+        assert!(service.active_stream_ids().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stream_can_be_cancelled_midway() {
+        let body = sse_body(&[
+            json!({ "choices": [{ "delta": { "content": "Hel" } }] }),
+            json!({ "choices": [{ "delta": { "content": "lo" } }] }),
+        ], false);
+        let (service, _server) = streaming_service(body).await;
+
+        // Cancel from the first delta, before the stream would have ended
+        let canceller = service.clone();
+        let response = service
+            .execute_request_streaming("model-a", &request("Hi", None), |delta| {
+                if delta.index == 0 {
+                    canceller.active_streams.read()[&delta.request_id].cancel();
+                }
+            })
+            .await
+            .unwrap();
+
+        assert!(!response.base_response.success);
+        assert_eq!(response.base_response.error.as_deref(), Some("Streaming cancelled by user"));
+        assert!(response.base_response.result.unwrap_or_default().starts_with("Hel"));
+        assert!(service.active_stream_ids().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_stream_ids_are_rejected() {
+        let (service, _server) = streaming_service(sse_body(&[], true)).await;
+        let running = CancellationToken::new();
+        service.active_streams.write().insert("req-1".to_string(), running.clone());
+
+        let error = service
+            .execute_request_streaming("model-a", &request("Hi", None), |_| {})
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("already streaming"), "{}", error);
+
+        // The running stream keeps its token, so it can still be cancelled
+        assert!(service.cancel_stream("req-1").await);
+        assert!(running.is_cancelled());
+    }
+}
@@ -340,7 +340,8 @@ pub struct SuppressionCondition {
     pub value: Value,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ComparisonOperator {
     Equal,
     NotEqual,
@@ -619,7 +620,7 @@ impl PerformanceTracker {
                 "tokens": entry.total_tokens,
                 "cost": entry.cost_usd,
                 "success": entry.success
-            }).as_object().unwrap().clone()),
+            }).as_object().unwrap().clone().into_iter().collect()),
         });
 
         if model_metrics.usage_over_time.len() > 10000 {
@@ -1135,8 +1136,8 @@ impl TrendAnalyzer {
     pub fn new() -> Self {
         Self {
             trend_models: HashMap::new(),
-            analysis_window: Duration::from_days(7),
-            prediction_horizon: Duration::from_days(1),
+            analysis_window: Duration::from_secs(7 * 24 * 3600),
+            prediction_horizon: Duration::from_secs(24 * 3600),
         }
     }
 
@@ -0,0 +1,888 @@
+//! Declarative Workflow Definitions
+//!
+//! Loads workflow patterns authored as YAML or JSON files, validates them
+//! against the adaptive workflow types, and keeps a `PatternLibrary` in sync
+//! with the files in a project directory, reloading them when the directory
+//! reports a change.
+
+use super::*;
+use super::adaptive_workflows::{SuccessCriterion, TriggerCondition};
+use anyhow::Result;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// File extensions recognised as workflow definitions
+const DEFINITION_EXTENSIONS: &[&str] = &["yaml", "yml", "json"];
+/// Changes arriving this close together, like an editor's save, trigger one reload
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Workflow pattern as authored in a definition file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowDefinition {
+    pub pattern_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "default_pattern_type")]
+    pub pattern_type: WorkflowPatternType,
+    #[serde(default)]
+    pub complexity_range: ComplexityRangeDefinition,
+    pub stages: Vec<StageDefinition>,
+    #[serde(default)]
+    pub success_criteria: Vec<SuccessCriterionDefinition>,
+    #[serde(default)]
+    pub failure_policy: Option<FailurePolicy>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComplexityRangeDefinition {
+    pub min_complexity: f64,
+    pub max_complexity: f64,
+    pub optimal_complexity: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StageDefinition {
+    pub stage_id: String,
+    pub stage_type: StageType,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "default_backend")]
+    pub backend: StageBackend,
+    #[serde(default)]
+    pub agents_required: Vec<AgentRequirementDefinition>,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub retry_policy: RetryPolicyDefinition,
+    #[serde(default)]
+    pub adaptation_hooks: Vec<AdaptationHookDefinition>,
+    #[serde(default)]
+    pub resource_allocation: ResourceAllocationDefinition,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentRequirementDefinition {
+    pub agent_type: String,
+    #[serde(default)]
+    pub capabilities_needed: Vec<String>,
+    #[serde(default)]
+    pub min_performance_score: f64,
+    #[serde(default)]
+    pub preferred_agents: Vec<String>,
+    #[serde(default)]
+    pub load_balance: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryPolicyDefinition {
+    #[serde(default)]
+    pub max_retries: u32,
+    #[serde(default = "default_backoff_strategy")]
+    pub backoff_strategy: BackoffStrategy,
+    #[serde(default = "default_base_delay_ms")]
+    pub base_delay_ms: u64,
+    #[serde(default)]
+    pub retry_conditions: Vec<RetryConditionDefinition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryConditionDefinition {
+    pub error_type: String,
+    pub should_retry: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdaptationHookDefinition {
+    pub hook_type: HookType,
+    #[serde(default)]
+    pub trigger_conditions: Vec<TriggerConditionDefinition>,
+    pub actions: Vec<AdaptationAction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TriggerConditionDefinition {
+    pub metric: String,
+    pub operator: ComparisonOperator,
+    pub threshold: f64,
+    #[serde(default)]
+    pub duration_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceAllocationDefinition {
+    #[serde(default = "default_cpu_cores")]
+    pub cpu_cores: f64,
+    #[serde(default = "default_memory_mb")]
+    pub memory_mb: u64,
+    #[serde(default = "default_network_bandwidth")]
+    pub network_bandwidth: u64,
+    #[serde(default = "default_storage_mb")]
+    pub storage_mb: u64,
+    #[serde(default = "default_priority")]
+    pub priority: ResourcePriority,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SuccessCriterionDefinition {
+    pub metric: String,
+    pub target_value: f64,
+    #[serde(default)]
+    pub tolerance: f64,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+fn default_pattern_type() -> WorkflowPatternType {
+    WorkflowPatternType::Sequential
+}
+
+fn default_backend() -> StageBackend {
+    StageBackend::ClaudeFlow
+}
+
+fn default_timeout_secs() -> u64 {
+    300
+}
+
+fn default_backoff_strategy() -> BackoffStrategy {
+    BackoffStrategy::Exponential
+}
+
+fn default_base_delay_ms() -> u64 {
+    1000
+}
+
+fn default_cpu_cores() -> f64 {
+    1.0
+}
+
+fn default_memory_mb() -> u64 {
+    512
+}
+
+fn default_network_bandwidth() -> u64 {
+    100
+}
+
+fn default_storage_mb() -> u64 {
+    100
+}
+
+fn default_priority() -> ResourcePriority {
+    ResourcePriority::Normal
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl Default for ComplexityRangeDefinition {
+    fn default() -> Self {
+        Self {
+            min_complexity: 0.0,
+            max_complexity: 1.0,
+            optimal_complexity: 0.5,
+        }
+    }
+}
+
+impl Default for RetryPolicyDefinition {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            backoff_strategy: default_backoff_strategy(),
+            base_delay_ms: default_base_delay_ms(),
+            retry_conditions: Vec::new(),
+        }
+    }
+}
+
+impl Default for ResourceAllocationDefinition {
+    fn default() -> Self {
+        Self {
+            cpu_cores: default_cpu_cores(),
+            memory_mb: default_memory_mb(),
+            network_bandwidth: default_network_bandwidth(),
+            storage_mb: default_storage_mb(),
+            priority: default_priority(),
+        }
+    }
+}
+
+/// A definition file that could not be loaded
+#[derive(Debug, Clone)]
+pub struct WorkflowDefinitionError {
+    pub file: PathBuf,
+    /// Field path of a validation error; syntax errors carry their line and column in the message
+    pub location: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for WorkflowDefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{}: {}: {}", self.file.display(), location, self.message),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+impl std::error::Error for WorkflowDefinitionError {}
+
+impl WorkflowDefinition {
+    /// Parse and validate a definition, choosing the format from the file extension
+    pub fn from_file(path: &Path) -> std::result::Result<Self, WorkflowDefinitionError> {
+        let error = |location: Option<String>, message: String| WorkflowDefinitionError {
+            file: path.to_path_buf(),
+            location,
+            message,
+        };
+
+        let content = std::fs::read_to_string(path).map_err(|e| error(None, format!("Failed to read file: {}", e)))?;
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+
+        // Parser messages already name the field and its line and column
+        let definition: WorkflowDefinition = if extension == "json" {
+            serde_json::from_str(&content).map_err(|e| error(None, e.to_string()))?
+        } else {
+            serde_yaml::from_str(&content).map_err(|e| error(None, e.to_string()))?
+        };
+
+        definition.validate().map_err(|(location, message)| error(Some(location), message))?;
+        Ok(definition)
+    }
+
+    /// Check constraints the type system cannot express, returning the offending field path
+    pub fn validate(&self) -> std::result::Result<(), (String, String)> {
+        if self.pattern_id.trim().is_empty() {
+            return Err(("pattern_id".to_string(), "must not be empty".to_string()));
+        }
+
+        let range = &self.complexity_range;
+        if !(0.0..=1.0).contains(&range.min_complexity) || !(0.0..=1.0).contains(&range.max_complexity) {
+            return Err(("complexity_range".to_string(), "bounds must be between 0.0 and 1.0".to_string()));
+        }
+        if range.min_complexity > range.max_complexity {
+            return Err(("complexity_range".to_string(), "min_complexity exceeds max_complexity".to_string()));
+        }
+        if !(range.min_complexity..=range.max_complexity).contains(&range.optimal_complexity) {
+            return Err((
+                "complexity_range.optimal_complexity".to_string(),
+                "must lie between min_complexity and max_complexity".to_string(),
+            ));
+        }
+
+        if self.stages.is_empty() {
+            return Err(("stages".to_string(), "a workflow needs at least one stage".to_string()));
+        }
+
+        let mut stage_ids = HashSet::new();
+        for (i, stage) in self.stages.iter().enumerate() {
+            if stage.stage_id.trim().is_empty() {
+                return Err((format!("stages[{}].stage_id", i), "must not be empty".to_string()));
+            }
+            if !stage_ids.insert(stage.stage_id.as_str()) {
+                return Err((format!("stages[{}].stage_id", i), format!("duplicate stage id '{}'", stage.stage_id)));
+            }
+            if stage.timeout_secs == 0 {
+                return Err((format!("stages[{}].timeout_secs", i), "must be greater than zero".to_string()));
+            }
+            if stage.resource_allocation.cpu_cores <= 0.0 {
+                return Err((format!("stages[{}].resource_allocation.cpu_cores", i), "must be greater than zero".to_string()));
+            }
+            for (j, agent) in stage.agents_required.iter().enumerate() {
+                if !(0.0..=1.0).contains(&agent.min_performance_score) {
+                    return Err((
+                        format!("stages[{}].agents_required[{}].min_performance_score", i, j),
+                        "must be between 0.0 and 1.0".to_string(),
+                    ));
+                }
+            }
+            for (j, hook) in stage.adaptation_hooks.iter().enumerate() {
+                if hook.actions.is_empty() {
+                    return Err((
+                        format!("stages[{}].adaptation_hooks[{}].actions", i, j),
+                        "a hook needs at least one action".to_string(),
+                    ));
+                }
+            }
+        }
+
+        for (i, stage) in self.stages.iter().enumerate() {
+            for (j, dependency) in stage.dependencies.iter().enumerate() {
+                let location = format!("stages[{}].dependencies[{}]", i, j);
+                if dependency == &stage.stage_id {
+                    return Err((location, "a stage cannot depend on itself".to_string()));
+                }
+                if !stage_ids.contains(dependency.as_str()) {
+                    return Err((location, format!("unknown stage '{}'", dependency)));
+                }
+            }
+        }
+
+        // Check the graph as it will run: sequential and pipeline patterns also chain each stage to the one before it
+        let stages = scheduled_stages(&self.clone().into_pattern());
+        let graph: Vec<(&str, &[String])> = stages
+            .iter()
+            .map(|stage| (stage.stage_id.as_str(), stage.dependencies.as_slice()))
+            .collect();
+        if let Some(cycle) = find_dependency_cycle(&graph) {
+            return Err(("stages".to_string(), format!("dependency cycle: {}", cycle.join(" -> "))));
+        }
+
+        for (i, criterion) in self.success_criteria.iter().enumerate() {
+            if criterion.weight < 0.0 {
+                return Err((format!("success_criteria[{}].weight", i), "must not be negative".to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the runtime pattern from this definition
+    pub fn into_pattern(self) -> WorkflowPattern {
+        let stages: Vec<WorkflowStage> = self.stages.into_iter().map(StageDefinition::into_stage).collect();
+
+        // The pattern needs at least what its most demanding stage asks for
+        let largest = stages.iter().fold(ResourceAllocationDefinition::default().into_allocation(), |acc, stage| {
+            let stage = &stage.resource_allocation;
+            ResourceAllocation {
+                cpu_cores: acc.cpu_cores.max(stage.cpu_cores),
+                memory_mb: acc.memory_mb.max(stage.memory_mb),
+                network_bandwidth: acc.network_bandwidth.max(stage.network_bandwidth),
+                storage_mb: acc.storage_mb.max(stage.storage_mb),
+                priority: acc.priority.max(stage.priority.clone()),
+            }
+        });
+
+        WorkflowPattern {
+            pattern_id: self.pattern_id,
+            name: self.name,
+            description: self.description,
+            pattern_type: self.pattern_type,
+            complexity_range: ComplexityRange {
+                min_complexity: self.complexity_range.min_complexity,
+                max_complexity: self.complexity_range.max_complexity,
+                optimal_complexity: self.complexity_range.optimal_complexity,
+            },
+            stages,
+            adaptation_rules: Vec::new(),
+            resource_requirements: ResourceProfile {
+                min_resources: largest.clone(),
+                recommended_resources: largest.clone(),
+                max_resources: largest,
+                scaling_factors: ScalingFactors {
+                    cpu_scaling: 1.0,
+                    memory_scaling: 1.0,
+                    network_scaling: 1.0,
+                    storage_scaling: 1.0,
+                },
+            },
+            success_criteria: self.success_criteria
+                .into_iter()
+                .map(|criterion| SuccessCriterion {
+                    metric: criterion.metric,
+                    target_value: criterion.target_value,
+                    tolerance: criterion.tolerance,
+                    weight: criterion.weight,
+                })
+                .collect(),
+            performance_metrics: PatternMetrics {
+                usage_count: 0,
+                success_rate: 0.0,
+                avg_execution_time: Duration::ZERO,
+                resource_efficiency: 0.0,
+                adaptation_frequency: 0.0,
+                user_satisfaction: 0.0,
+            },
+            failure_policy: self.failure_policy,
+        }
+    }
+}
+
+impl StageDefinition {
+    fn into_stage(self) -> WorkflowStage {
+        WorkflowStage {
+            stage_id: self.stage_id,
+            stage_type: self.stage_type,
+            description: self.description,
+            backend: self.backend,
+            agents_required: self.agents_required
+                .into_iter()
+                .map(|agent| AgentRequirement {
+                    agent_type: agent.agent_type,
+                    capabilities_needed: agent.capabilities_needed,
+                    min_performance_score: agent.min_performance_score,
+                    preferred_agents: agent.preferred_agents,
+                    load_balance: agent.load_balance,
+                })
+                .collect(),
+            dependencies: self.dependencies,
+            timeout: Duration::from_secs(self.timeout_secs),
+            retry_policy: RetryPolicy {
+                max_retries: self.retry_policy.max_retries,
+                backoff_strategy: self.retry_policy.backoff_strategy,
+                base_delay: Duration::from_millis(self.retry_policy.base_delay_ms),
+                retry_conditions: self.retry_policy.retry_conditions
+                    .into_iter()
+                    .map(|condition| RetryCondition {
+                        error_type: condition.error_type,
+                        should_retry: condition.should_retry,
+                    })
+                    .collect(),
+            },
+            adaptation_hooks: self.adaptation_hooks
+                .into_iter()
+                .map(|hook| AdaptationHook {
+                    hook_type: hook.hook_type,
+                    trigger_conditions: hook.trigger_conditions
+                        .into_iter()
+                        .map(|trigger| TriggerCondition {
+                            metric: trigger.metric,
+                            operator: trigger.operator,
+                            threshold: trigger.threshold,
+                            duration: trigger.duration_secs.map(Duration::from_secs),
+                        })
+                        .collect(),
+                    actions: hook.actions,
+                })
+                .collect(),
+            resource_allocation: self.resource_allocation.into_allocation(),
+        }
+    }
+}
+
+impl ResourceAllocationDefinition {
+    fn into_allocation(self) -> ResourceAllocation {
+        ResourceAllocation {
+            cpu_cores: self.cpu_cores,
+            memory_mb: self.memory_mb,
+            network_bandwidth: self.network_bandwidth,
+            storage_mb: self.storage_mb,
+            priority: self.priority,
+        }
+    }
+}
+
+/// Outcome of a directory scan
+#[derive(Debug, Default)]
+pub struct WorkflowLoadReport {
+    pub registered: Vec<String>,
+    pub removed: Vec<String>,
+    pub errors: Vec<WorkflowDefinitionError>,
+}
+
+impl WorkflowLoadReport {
+    pub fn is_empty(&self) -> bool {
+        self.registered.is_empty() && self.removed.is_empty() && self.errors.is_empty()
+    }
+}
+
+/// Modification time and size, compared to detect edits within the timestamp resolution
+type FileStamp = (Option<SystemTime>, u64);
+
+#[derive(Debug, Clone)]
+struct LoadedDefinition {
+    stamp: FileStamp,
+    /// Pattern currently registered from this file; kept when a later edit fails validation
+    pattern_id: Option<String>,
+}
+
+/// Keeps a `PatternLibrary` in sync with the definition files in a directory
+#[derive(Debug)]
+pub struct WorkflowDefinitionLoader {
+    directory: PathBuf,
+    library: Arc<PatternLibrary>,
+    loaded: HashMap<PathBuf, LoadedDefinition>,
+}
+
+/// Handle to a background reload task; dropping it stops watching
+#[derive(Debug)]
+pub struct WorkflowWatcher {
+    cancel: CancellationToken,
+    _watcher: RecommendedWatcher,
+}
+
+impl WorkflowWatcher {
+    pub fn stop(&self) {
+        self.cancel.cancel();
+    }
+}
+
+impl Drop for WorkflowWatcher {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+impl WorkflowDefinitionLoader {
+    pub fn new(directory: impl Into<PathBuf>, library: Arc<PatternLibrary>) -> Self {
+        Self {
+            directory: directory.into(),
+            library,
+            loaded: HashMap::new(),
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Load new and changed definition files and unregister patterns whose files were removed
+    ///
+    /// Invalid files are reported without affecting the others. A file that
+    /// fails after an edit keeps its last valid pattern registered.
+    pub async fn reload(&mut self) -> Result<WorkflowLoadReport> {
+        let mut report = WorkflowLoadReport::default();
+        let files = Self::definition_files(&self.directory).await?;
+
+        // Forget files that no longer exist
+        let removed_files: Vec<PathBuf> = self.loaded
+            .keys()
+            .filter(|path| !files.contains_key(*path))
+            .cloned()
+            .collect();
+        for path in removed_files {
+            if let Some(pattern_id) = self.loaded.remove(&path).and_then(|loaded| loaded.pattern_id) {
+                self.library.unregister(&pattern_id).await;
+                report.removed.push(pattern_id);
+
+                // A file rejected as a duplicate of this pattern may now load
+                for loaded in self.loaded.values_mut().filter(|loaded| loaded.pattern_id.is_none()) {
+                    loaded.stamp = (None, 0);
+                }
+            }
+        }
+
+        let mut paths: Vec<&PathBuf> = files.keys().collect();
+        paths.sort();
+
+        for path in paths {
+            let stamp = files[path];
+            if let Some(loaded) = self.loaded.get(path) {
+                if loaded.stamp == stamp {
+                    continue;
+                }
+            }
+
+            let previous_id = self.loaded.get(path).and_then(|loaded| loaded.pattern_id.clone());
+            let parsed = {
+                let path = path.clone();
+                tokio::task::spawn_blocking(move || WorkflowDefinition::from_file(&path)).await?
+            };
+
+            let definition = match parsed {
+                Ok(definition) => definition,
+                Err(e) => {
+                    warn!("Invalid workflow definition {}", e);
+                    report.errors.push(e);
+                    self.loaded.insert(path.clone(), LoadedDefinition { stamp, pattern_id: previous_id });
+                    continue;
+                }
+            };
+
+            // Pattern ids must be unique across files
+            let owner = self.loaded
+                .iter()
+                .find(|(other, loaded)| *other != path && loaded.pattern_id.as_deref() == Some(definition.pattern_id.as_str()))
+                .map(|(other, _)| other.clone());
+            if let Some(owner) = owner {
+                let e = WorkflowDefinitionError {
+                    file: path.clone(),
+                    location: Some("pattern_id".to_string()),
+                    message: format!("pattern '{}' is already defined in {}", definition.pattern_id, owner.display()),
+                };
+                warn!("Invalid workflow definition {}", e);
+                report.errors.push(e);
+                self.loaded.insert(path.clone(), LoadedDefinition { stamp, pattern_id: previous_id });
+                continue;
+            }
+
+            let pattern_id = definition.pattern_id.clone();
+            if let Some(previous_id) = previous_id.filter(|previous| *previous != pattern_id) {
+                self.library.unregister(&previous_id).await;
+                report.removed.push(previous_id);
+            }
+
+            self.library.register(definition.into_pattern()).await;
+            report.registered.push(pattern_id.clone());
+            self.loaded.insert(path.clone(), LoadedDefinition { stamp, pattern_id: Some(pattern_id) });
+        }
+
+        if !report.is_empty() {
+            info!(
+                "Reloaded workflow definitions from {}: {} registered, {} removed, {} invalid",
+                self.directory.display(),
+                report.registered.len(),
+                report.removed.len(),
+                report.errors.len()
+            );
+        }
+
+        Ok(report)
+    }
+
+    /// Reload in the background whenever the directory reports a change
+    pub fn watch(mut self) -> Result<WorkflowWatcher> {
+        let (sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let _ = sender.send(event);
+        })?;
+        watcher.watch(&self.directory, RecursiveMode::NonRecursive)?;
+
+        let cancel = CancellationToken::new();
+        let token = cancel.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    _ = token.cancelled() => break,
+                    event = events.recv() => event,
+                };
+                match event {
+                    Some(Ok(event)) if matches!(event.kind, EventKind::Access(_)) => continue,
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => warn!("Workflow directory watcher error for {}: {}", self.directory.display(), e),
+                    None => break,
+                }
+
+                while let Ok(Some(_)) = tokio::time::timeout(RELOAD_DEBOUNCE, events.recv()).await {}
+
+                if let Err(e) = self.reload().await {
+                    warn!("Failed to scan workflow directory {}: {}", self.directory.display(), e);
+                }
+            }
+            debug!("Stopped watching workflow directory {}", self.directory.display());
+        });
+
+        Ok(WorkflowWatcher { cancel, _watcher: watcher })
+    }
+
+    /// Definition files in `directory` with their current stamps
+    async fn definition_files(directory: &Path) -> Result<HashMap<PathBuf, FileStamp>> {
+        let mut files = HashMap::new();
+        let mut entries = tokio::fs::read_dir(directory).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let is_definition = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| DEFINITION_EXTENSIONS.contains(&ext));
+            if !is_definition {
+                continue;
+            }
+
+            let metadata = entry.metadata().await?;
+            if metadata.is_file() {
+                files.insert(path, (metadata.modified().ok(), metadata.len()));
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const REVIEW_YAML: &str = r#"
+pattern_id: code_review
+name: Code Review
+pattern_type: parallel
+stages:
+  - stage_id: analyze
+    stage_type: analysis
+    timeout_secs: 60
+    resource_allocation:
+      cpu_cores: 2.0
+  - stage_id: report
+    stage_type: validation
+    backend: codex
+    dependencies: [analyze]
+    retry_policy:
+      max_retries: 2
+      base_delay_ms: 10
+failure_policy: continue_independent
+"#;
+
+    fn definition(yaml: &str) -> WorkflowDefinition {
+        serde_yaml::from_str(yaml).expect("definition should parse")
+    }
+
+    fn write(dir: &TempDir, name: &str, content: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn minimal(pattern_id: &str, stage_id: &str) -> String {
+        format!("pattern_id: {}\nname: Test\nstages:\n  - stage_id: {}\n    stage_type: execution\n", pattern_id, stage_id)
+    }
+
+    #[test]
+    fn test_yaml_definition_builds_pattern() {
+        let dir = TempDir::new().unwrap();
+        let path = write(&dir, "review.yaml", REVIEW_YAML);
+
+        let pattern = WorkflowDefinition::from_file(&path).unwrap().into_pattern();
+        assert_eq!(pattern.pattern_id, "code_review");
+        assert!(matches!(pattern.pattern_type, WorkflowPatternType::Parallel));
+        assert_eq!(pattern.failure_policy, Some(FailurePolicy::ContinueIndependent));
+        assert_eq!(pattern.stages.len(), 2);
+
+        let report = &pattern.stages[1];
+        assert!(matches!(report.backend, StageBackend::Codex));
+        assert_eq!(report.dependencies, vec!["analyze".to_string()]);
+        assert_eq!(report.timeout, Duration::from_secs(300));
+        assert_eq!(report.retry_policy.max_retries, 2);
+        assert_eq!(report.retry_policy.base_delay, Duration::from_millis(10));
+
+        // Pattern requirements follow the most demanding stage
+        assert_eq!(pattern.resource_requirements.min_resources.cpu_cores, 2.0);
+    }
+
+    #[test]
+    fn test_json_definition_builds_pattern() {
+        let dir = TempDir::new().unwrap();
+        let path = write(
+            &dir,
+            "deploy.json",
+            r#"{"pattern_id": "deploy", "name": "Deploy", "stages": [{"stage_id": "ship", "stage_type": "execution"}]}"#,
+        );
+
+        let pattern = WorkflowDefinition::from_file(&path).unwrap().into_pattern();
+        assert_eq!(pattern.pattern_id, "deploy");
+        assert!(matches!(pattern.pattern_type, WorkflowPatternType::Sequential));
+        assert!(matches!(pattern.stages[0].backend, StageBackend::ClaudeFlow));
+    }
+
+    #[test]
+    fn test_syntax_errors_report_the_line() {
+        let dir = TempDir::new().unwrap();
+        let path = write(
+            &dir,
+            "typo.yaml",
+            "pattern_id: typo\nname: Typo\nstages:\n  - stage_id: a\n    stage_type: execution\n    timeout: 10\n",
+        );
+
+        let error = WorkflowDefinition::from_file(&path).unwrap_err();
+        assert_eq!(error.file, path);
+        assert!(error.location.is_none());
+        assert!(error.message.contains("unknown field `timeout`"), "{}", error.message);
+        assert!(error.message.contains("line 6"), "{}", error.message);
+    }
+
+    #[test]
+    fn test_validation_errors_name_the_field() {
+        let unknown = definition(
+            "pattern_id: p\nname: P\nstages:\n  - stage_id: a\n    stage_type: execution\n    dependencies: [missing]\n",
+        );
+        let (location, message) = unknown.validate().unwrap_err();
+        assert_eq!(location, "stages[0].dependencies[0]");
+        assert!(message.contains("missing"));
+
+        let duplicate = definition(
+            "pattern_id: p\nname: P\nstages:\n  - stage_id: a\n    stage_type: execution\n  - stage_id: a\n    stage_type: validation\n",
+        );
+        assert_eq!(duplicate.validate().unwrap_err().0, "stages[1].stage_id");
+
+        let cycle = definition(
+            "pattern_id: p\nname: P\nstages:\n  - stage_id: a\n    stage_type: execution\n    dependencies: [b]\n  - stage_id: b\n    stage_type: validation\n    dependencies: [a]\n",
+        );
+        let (location, message) = cycle.validate().unwrap_err();
+        assert_eq!(location, "stages");
+        assert!(message.starts_with("dependency cycle"), "{}", message);
+
+        // Sequential stages also wait for the stage before them, so depending on a later one is a cycle
+        let backwards = "pattern_id: p\nname: P\nstages:\n  - stage_id: a\n    stage_type: execution\n    dependencies: [b]\n  - stage_id: b\n    stage_type: validation\n";
+        let (location, message) = definition(backwards).validate().unwrap_err();
+        assert_eq!(location, "stages");
+        assert!(message.starts_with("dependency cycle"), "{}", message);
+        let parallel = format!("pattern_type: parallel\n{}", backwards);
+        assert!(definition(&parallel).validate().is_ok());
+
+        let no_stages = definition("pattern_id: p\nname: P\nstages: []\n");
+        assert_eq!(no_stages.validate().unwrap_err().0, "stages");
+
+        let zero_timeout = definition(
+            "pattern_id: p\nname: P\nstages:\n  - stage_id: a\n    stage_type: execution\n    timeout_secs: 0\n",
+        );
+        assert_eq!(zero_timeout.validate().unwrap_err().0, "stages[0].timeout_secs");
+    }
+
+    #[tokio::test]
+    async fn test_reload_tracks_added_edited_and_removed_files() {
+        let dir = TempDir::new().unwrap();
+        let library = Arc::new(PatternLibrary::new());
+        let mut loader = WorkflowDefinitionLoader::new(dir.path(), library.clone());
+
+        let path = write(&dir, "one.yaml", &minimal("one", "a"));
+        write(&dir, "notes.txt", "not a definition");
+        let report = loader.reload().await.unwrap();
+        assert_eq!(report.registered, vec!["one".to_string()]);
+        assert!(library.get("one").await.is_some());
+
+        // Unchanged files are not reparsed
+        assert!(loader.reload().await.unwrap().is_empty());
+
+        std::fs::write(&path, minimal("one", "renamed_stage")).unwrap();
+        let report = loader.reload().await.unwrap();
+        assert_eq!(report.registered, vec!["one".to_string()]);
+        assert_eq!(library.get("one").await.unwrap().stages[0].stage_id, "renamed_stage");
+
+        // A broken edit keeps the last valid pattern
+        std::fs::write(&path, "pattern_id: one\nname: [unterminated\n").unwrap();
+        let report = loader.reload().await.unwrap();
+        assert_eq!(report.errors.len(), 1);
+        assert!(library.get("one").await.is_some());
+
+        // A second file cannot claim the same pattern id
+        write(&dir, "two.yaml", &minimal("one", "b"));
+        let report = loader.reload().await.unwrap();
+        assert_eq!(report.errors[0].location.as_deref(), Some("pattern_id"));
+
+        std::fs::remove_file(&path).unwrap();
+        let report = loader.reload().await.unwrap();
+        assert_eq!(report.removed, vec!["one".to_string()]);
+        assert_eq!(library.get("one").await.unwrap().stages[0].stage_id, "b");
+    }
+
+    #[tokio::test]
+    async fn test_watcher_reloads_on_file_events() {
+        let dir = TempDir::new().unwrap();
+        let library = Arc::new(PatternLibrary::new());
+        let watcher = WorkflowDefinitionLoader::new(dir.path(), library.clone()).watch().unwrap();
+
+        write(&dir, "watched.yaml", &minimal("watched", "a"));
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+        while library.get("watched").await.is_none() {
+            assert!(tokio::time::Instant::now() < deadline, "watcher never loaded the new definition");
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        drop(watcher);
+    }
+}
@@ -1,9 +1,17 @@
 // High-performance caching system with multiple cache levels and intelligent eviction
 // Implements advanced caching strategies for optimal performance and memory usage
 
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::{Mutex, RwLock};
 use tokio::time::{Duration, Instant};
 use tracing::{debug, info, warn};
@@ -18,10 +26,21 @@ pub struct CacheConfig {
     pub max_ttl_seconds: u64,
     pub eviction_policy: EvictionPolicy,
     pub compression_enabled: bool,
+    /// Entries smaller than this are stored uncompressed
+    #[serde(default = "default_compression_threshold")]
+    pub compression_threshold_bytes: usize,
+    /// Store the L3 tier on disk so it survives restarts
     pub persistence_enabled: bool,
+    /// Directory for the persisted L3 tier; defaults to `cache` under the app data directory
+    #[serde(default)]
+    pub persistence_dir: Option<PathBuf>,
     pub analytics_enabled: bool,
 }
 
+fn default_compression_threshold() -> usize {
+    1024
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EvictionPolicy {
     LRU,      // Least Recently Used
@@ -41,12 +60,27 @@ impl Default for CacheConfig {
             max_ttl_seconds: 86400,    // 24 hours
             eviction_policy: EvictionPolicy::LRU,
             compression_enabled: true,
+            compression_threshold_bytes: default_compression_threshold(),
             persistence_enabled: false,
+            persistence_dir: None,
             analytics_enabled: true,
         }
     }
 }
 
+impl CacheConfig {
+    /// Directory holding the persisted L3 tier
+    pub fn resolve_persistence_dir(&self) -> anyhow::Result<PathBuf> {
+        if let Some(dir) = &self.persistence_dir {
+            return Ok(dir.clone());
+        }
+
+        let dirs = directories::ProjectDirs::from("com", "autodev-ai", "neural-bridge-platform")
+            .ok_or_else(|| anyhow::anyhow!("Failed to determine the app data directory"))?;
+        Ok(dirs.data_dir().join("cache"))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheMetrics {
     pub timestamp: i64,
@@ -61,14 +95,55 @@ pub struct CacheMetrics {
     pub total_hits: u64,
     pub total_misses: u64,
     pub evictions: u64,
+    /// Original over stored size across all cached entries
     pub compression_ratio: f64,
+    /// Number of cached entries stored compressed
+    #[serde(default)]
+    pub compressed_entries: u64,
+    /// Bytes saved by compression across all cached entries
+    #[serde(default)]
+    pub compression_saved_bytes: u64,
     pub average_access_time_ms: f64,
 }
 
+/// Codec an entry's payload is stored with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompressionCodec {
+    None,
+    Gzip,
+}
+
+impl CompressionCodec {
+    pub fn compress(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            CompressionCodec::None => Ok(data.to_vec()),
+            CompressionCodec::Gzip => {
+                let mut encoder =
+                    GzEncoder::new(Vec::with_capacity(data.len() / 2), Compression::fast());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    pub fn decompress(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            CompressionCodec::None => Ok(data.to_vec()),
+            CompressionCodec::Gzip => {
+                let mut decompressed = Vec::new();
+                GzDecoder::new(data).read_to_end(&mut decompressed)?;
+                Ok(decompressed)
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CacheEntry {
     pub key: String,
+    /// Payload as stored, encoded with `codec`; empty for L3 entries held on disk
     pub data: Vec<u8>,
+    pub codec: CompressionCodec,
     pub compressed_size: usize,
     pub original_size: usize,
     pub created_at: Instant,
@@ -91,6 +166,232 @@ pub struct CacheAnalytics {
     pub cache_level_distribution: HashMap<String, u64>,
 }
 
+impl CacheAnalytics {
+    fn new(key: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            hit_count: 0,
+            miss_count: 0,
+            total_access_time_ms: 0.0,
+            average_access_time_ms: 0.0,
+            data_size_bytes: 0,
+            compression_ratio: 1.0,
+            cache_level_distribution: HashMap::new(),
+        }
+    }
+}
+
+/// Metadata stored ahead of each persisted payload
+#[derive(Debug, Serialize, Deserialize)]
+struct DiskEntryHeader {
+    key: String,
+    codec: CompressionCodec,
+    original_size: usize,
+    payload_size: usize,
+    created_at_ms: u64,
+    ttl_ms: Option<u64>,
+    access_count: u64,
+    tags: Vec<String>,
+    /// SHA-256 of the payload, hex encoded
+    checksum: String,
+}
+
+/// On-disk store backing the L3 tier
+///
+/// Each entry is one file named after the hash of its key, holding a magic
+/// number, a length-prefixed JSON header and the stored payload. Files are
+/// written to a temporary name, synced and renamed into place, so a crash
+/// leaves either the old entry or the new one.
+#[derive(Debug, Clone)]
+struct DiskTier {
+    dir: PathBuf,
+}
+
+impl DiskTier {
+    const MAGIC: &'static [u8; 8] = b"NBCACHE1";
+    const EXTENSION: &'static str = "entry";
+
+    fn open(dir: PathBuf) -> std::io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path_for(&self, key: &str) -> PathBuf {
+        let name = hex::encode(Sha256::digest(key.as_bytes()));
+        self.dir.join(format!("{}.{}", name, Self::EXTENSION))
+    }
+
+    fn write(&self, entry: &CacheEntry) -> std::io::Result<()> {
+        let created_at = SystemTime::now()
+            .checked_sub(entry.created_at.elapsed())
+            .unwrap_or_else(SystemTime::now);
+        let header = DiskEntryHeader {
+            key: entry.key.clone(),
+            codec: entry.codec,
+            original_size: entry.original_size,
+            payload_size: entry.data.len(),
+            created_at_ms: created_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            ttl_ms: entry.ttl.map(|ttl| ttl.as_millis() as u64),
+            access_count: entry.access_count,
+            tags: entry.tags.clone(),
+            checksum: hex::encode(Sha256::digest(&entry.data)),
+        };
+        let header = serde_json::to_vec(&header)?;
+
+        let path = self.path_for(&entry.key);
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(Self::MAGIC)?;
+            file.write_all(&(header.len() as u32).to_le_bytes())?;
+            file.write_all(&header)?;
+            file.write_all(&entry.data)?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp_path, &path)?;
+
+        // Persist the rename itself
+        #[cfg(unix)]
+        File::open(&self.dir)?.sync_all()?;
+
+        Ok(())
+    }
+
+    fn read_header(file: &mut File) -> std::io::Result<DiskEntryHeader> {
+        let mut magic = [0u8; 8];
+        file.read_exact(&mut magic)?;
+        if &magic != Self::MAGIC {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "not a cache entry file",
+            ));
+        }
+
+        let mut len = [0u8; 4];
+        file.read_exact(&mut len)?;
+        let mut header = vec![0u8; u32::from_le_bytes(len) as usize];
+        file.read_exact(&mut header)?;
+        Ok(serde_json::from_slice(&header)?)
+    }
+
+    /// Read a payload, verifying that it belongs to `key` and is intact
+    fn read_payload(&self, key: &str) -> std::io::Result<Vec<u8>> {
+        let mut file = File::open(self.path_for(key))?;
+        let header = Self::read_header(&mut file)?;
+        if header.key != key {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "cache entry file belongs to another key",
+            ));
+        }
+
+        let mut payload = Vec::with_capacity(header.payload_size);
+        file.read_to_end(&mut payload)?;
+        if payload.len() != header.payload_size
+            || hex::encode(Sha256::digest(&payload)) != header.checksum
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "cache entry payload is corrupt",
+            ));
+        }
+
+        Ok(payload)
+    }
+
+    async fn delete(&self, key: &str) {
+        let path = self.path_for(key);
+        let removed = tokio::task::spawn_blocking({
+            let path = path.clone();
+            move || fs::remove_file(path)
+        })
+        .await
+        .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+
+        if let Err(e) = removed {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to delete cache file {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Headers of all readable entries; unreadable files and leftovers of interrupted writes are removed
+    fn load_headers(&self) -> std::io::Result<Vec<DiskEntryHeader>> {
+        let mut headers = Vec::new();
+
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some(Self::EXTENSION) => {}
+                Some("tmp") => {
+                    let _ = fs::remove_file(&path);
+                    continue;
+                }
+                _ => continue,
+            }
+
+            match File::open(&path).and_then(|mut file| Self::read_header(&mut file)) {
+                Ok(header) if path == self.path_for(&header.key) => headers.push(header),
+                Ok(_) | Err(_) => {
+                    warn!("Removing unreadable cache file {}", path.display());
+                    let _ = fs::remove_file(&path);
+                }
+            }
+        }
+
+        Ok(headers)
+    }
+
+    fn clear(&self) -> std::io::Result<()> {
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some(Self::EXTENSION) | Some("tmp")
+            ) {
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DiskEntryHeader {
+    /// Rebuild the in-memory L3 index entry, or `None` once the TTL has passed
+    fn into_entry(self) -> Option<CacheEntry> {
+        let created_at = UNIX_EPOCH + Duration::from_millis(self.created_at_ms);
+        let age = SystemTime::now()
+            .duration_since(created_at)
+            .unwrap_or_default();
+        let ttl = self.ttl_ms.map(Duration::from_millis);
+        if ttl.is_some_and(|ttl| age > ttl) {
+            return None;
+        }
+
+        let now = Instant::now();
+        Some(CacheEntry {
+            key: self.key,
+            data: Vec::new(),
+            codec: self.codec,
+            compressed_size: self.payload_size,
+            original_size: self.original_size,
+            created_at: now.checked_sub(age).unwrap_or(now),
+            last_accessed: now,
+            access_count: self.access_count,
+            ttl,
+            compression_ratio: if self.payload_size == 0 {
+                1.0
+            } else {
+                self.original_size as f64 / self.payload_size as f64
+            },
+            tags: self.tags,
+        })
+    }
+}
+
 pub struct HighPerformanceCache {
     config: CacheConfig,
     l1_cache: Arc<RwLock<HashMap<String, CacheEntry>>>,
@@ -100,6 +401,7 @@ pub struct HighPerformanceCache {
     frequency_counter: Arc<RwLock<HashMap<String, u64>>>, // For LFU eviction
     metrics: Arc<Mutex<CacheMetrics>>,
     analytics: Arc<RwLock<HashMap<String, CacheAnalytics>>>,
+    disk_tier: Option<DiskTier>,
 }
 
 impl HighPerformanceCache {
@@ -118,6 +420,8 @@ impl HighPerformanceCache {
             total_misses: 0,
             evictions: 0,
             compression_ratio: 1.0,
+            compressed_entries: 0,
+            compression_saved_bytes: 0,
             average_access_time_ms: 0.0,
         };
 
@@ -135,9 +439,147 @@ impl HighPerformanceCache {
             frequency_counter: Arc::new(RwLock::new(HashMap::new())),
             metrics: Arc::new(Mutex::new(metrics)),
             analytics: Arc::new(RwLock::new(HashMap::new())),
+            disk_tier: None,
+        }
+    }
+
+    /// Create a cache and, when persistence is enabled, warm its L3 tier from disk
+    pub async fn open(config: CacheConfig) -> anyhow::Result<Self> {
+        let persistence_dir = if config.persistence_enabled {
+            Some(config.resolve_persistence_dir()?)
+        } else {
+            None
+        };
+
+        let mut cache = Self::new(config);
+        if let Some(dir) = persistence_dir {
+            let disk_tier = tokio::task::spawn_blocking(move || DiskTier::open(dir)).await??;
+            cache.disk_tier = Some(disk_tier);
+            let loaded = cache.warm_load().await?;
+            info!("Warm-loaded {} persisted L3 cache entries", loaded);
+        }
+
+        Ok(cache)
+    }
+
+    async fn warm_load(&self) -> anyhow::Result<usize> {
+        let Some(disk_tier) = self.disk_tier.clone() else {
+            return Ok(0);
+        };
+
+        let headers = tokio::task::spawn_blocking({
+            let disk_tier = disk_tier.clone();
+            move || disk_tier.load_headers()
+        })
+        .await??;
+
+        let mut l3_cache = self.l3_cache.write().await;
+        let mut loaded = 0;
+        for header in headers {
+            let key = header.key.clone();
+            match header.into_entry() {
+                Some(entry) => {
+                    self.ensure_l3_capacity(&mut l3_cache, entry.compressed_size)
+                        .await;
+                    l3_cache.insert(key, entry);
+                    loaded += 1;
+                }
+                None => disk_tier.delete(&key).await,
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    /// Write every live L1 and L2 entry to the disk tier, e.g. before shutdown
+    pub async fn persist(&self) -> anyhow::Result<usize> {
+        let Some(disk_tier) = self.disk_tier.clone() else {
+            return Ok(0);
+        };
+
+        let mut entries: Vec<CacheEntry> = Vec::new();
+        for tier in [&self.l1_cache, &self.l2_cache] {
+            let cache = tier.read().await;
+            entries.extend(cache.values().filter(|e| !self.is_expired(e)).cloned());
+        }
+
+        let mut l3_cache = self.l3_cache.write().await;
+        let mut persisted = 0;
+        for entry in entries {
+            let key = entry.key.clone();
+            if self.store_l3(&mut l3_cache, &disk_tier, key, entry).await {
+                persisted += 1;
+            }
+        }
+
+        info!("Persisted {} cache entries to disk", persisted);
+        Ok(persisted)
+    }
+
+    /// Insert into L3, writing the payload to disk when persistence is enabled
+    async fn insert_l3(
+        &self,
+        l3_cache: &mut HashMap<String, CacheEntry>,
+        key: String,
+        entry: CacheEntry,
+    ) {
+        self.ensure_l3_capacity(l3_cache, entry.compressed_size)
+            .await;
+
+        match self.disk_tier.clone() {
+            Some(disk_tier) => {
+                self.store_l3(l3_cache, &disk_tier, key, entry).await;
+            }
+            None => {
+                l3_cache.insert(key, entry);
+            }
         }
     }
 
+    /// Write an entry to disk and index it in L3 without its payload
+    async fn store_l3(
+        &self,
+        l3_cache: &mut HashMap<String, CacheEntry>,
+        disk_tier: &DiskTier,
+        key: String,
+        mut entry: CacheEntry,
+    ) -> bool {
+        let written = tokio::task::spawn_blocking({
+            let disk_tier = disk_tier.clone();
+            let entry = entry.clone();
+            move || disk_tier.write(&entry)
+        })
+        .await;
+
+        match written {
+            Ok(Ok(())) => {
+                entry.data = Vec::new();
+                l3_cache.insert(key, entry);
+                true
+            }
+            Ok(Err(e)) => {
+                warn!("Failed to persist cache entry {}: {}", key, e);
+                false
+            }
+            Err(e) => {
+                warn!("Failed to persist cache entry {}: {}", key, e);
+                false
+            }
+        }
+    }
+
+    /// Remove an L3 entry together with its file
+    ///
+    /// The caller holds the L3 lock until the file is gone, so a concurrent
+    /// write of the same key cannot be deleted in its place.
+    async fn remove_l3(&self, l3_cache: &mut HashMap<String, CacheEntry>, key: &str) -> bool {
+        if let Some(disk_tier) = &self.disk_tier {
+            disk_tier.delete(key).await;
+        }
+        l3_cache.remove(key).is_some()
+    }
+
+    #[tracing::instrument(skip_all)]
     pub async fn get(&self, key: &str) -> Option<Vec<u8>> {
         let start_time = Instant::now();
         let mut cache_level = "";
@@ -150,7 +592,7 @@ impl HighPerformanceCache {
                 if !self.is_expired(entry) {
                     entry.last_accessed = Instant::now();
                     entry.access_count += 1;
-                    result = Some(entry.data.clone());
+                    result = Some((entry.codec, entry.data.clone()));
                     cache_level = "L1";
                     debug!("L1 cache hit for key: {}", key);
                 } else {
@@ -162,52 +604,117 @@ impl HighPerformanceCache {
 
         // Try L2 cache if L1 miss
         if result.is_none() {
-            let mut l2_cache = self.l2_cache.write().await;
-            if let Some(entry) = l2_cache.get_mut(key) {
-                if !self.is_expired(entry) {
-                    entry.last_accessed = Instant::now();
-                    entry.access_count += 1;
-                    result = Some(entry.data.clone());
-                    cache_level = "L2";
+            let promoted = {
+                let mut l2_cache = self.l2_cache.write().await;
+                match l2_cache.get_mut(key) {
+                    Some(entry) if !self.is_expired(entry) => {
+                        entry.last_accessed = Instant::now();
+                        entry.access_count += 1;
+                        Some(entry.clone())
+                    }
+                    Some(_) => {
+                        l2_cache.remove(key);
+                        debug!("L2 cache entry expired for key: {}", key);
+                        None
+                    }
+                    None => None,
+                }
+            };
 
-                    // Promote to L1
-                    let mut l1_cache = self.l1_cache.write().await;
-                    self.ensure_l1_capacity(&mut l1_cache, entry.compressed_size)
-                        .await;
-                    l1_cache.insert(key.to_string(), entry.clone());
+            // Promote to L1 once the L2 lock is released, since making room in L1 demotes into L2
+            if let Some(entry) = promoted {
+                result = Some((entry.codec, entry.data.clone()));
+                cache_level = "L2";
 
-                    debug!("L2 cache hit for key: {}, promoted to L1", key);
-                } else {
-                    l2_cache.remove(key);
-                    debug!("L2 cache entry expired for key: {}", key);
-                }
+                let mut l1_cache = self.l1_cache.write().await;
+                self.ensure_l1_capacity(&mut l1_cache, entry.compressed_size)
+                    .await;
+                l1_cache.insert(key.to_string(), entry);
+
+                debug!("L2 cache hit for key: {}, promoted to L1", key);
             }
         }
 
         // Try L3 cache if L1 and L2 miss
         if result.is_none() {
-            let mut l3_cache = self.l3_cache.write().await;
-            if let Some(entry) = l3_cache.get_mut(key) {
-                if !self.is_expired(entry) {
-                    entry.last_accessed = Instant::now();
-                    entry.access_count += 1;
-                    result = Some(entry.data.clone());
-                    cache_level = "L3";
-
-                    // Promote to L2
-                    let mut l2_cache = self.l2_cache.write().await;
-                    self.ensure_l2_capacity(&mut l2_cache, entry.compressed_size)
-                        .await;
-                    l2_cache.insert(key.to_string(), entry.clone());
+            let hit = {
+                let mut l3_cache = self.l3_cache.write().await;
+                match l3_cache.get_mut(key) {
+                    Some(entry) if !self.is_expired(entry) => {
+                        entry.last_accessed = Instant::now();
+                        entry.access_count += 1;
+                        Some(entry.clone())
+                    }
+                    Some(_) => {
+                        self.remove_l3(&mut l3_cache, key).await;
+                        debug!("L3 cache entry expired for key: {}", key);
+                        None
+                    }
+                    None => None,
+                }
+            };
+
+            // Read the payload without holding the L3 lock, so other keys stay available meanwhile
+            let promoted = match (hit, self.disk_tier.clone()) {
+                (Some(mut entry), Some(disk_tier)) => {
+                    let owned_key = key.to_string();
+                    let payload =
+                        tokio::task::spawn_blocking(move || disk_tier.read_payload(&owned_key))
+                            .await;
 
-                    debug!("L3 cache hit for key: {}, promoted to L2", key);
-                } else {
-                    l3_cache.remove(key);
-                    debug!("L3 cache entry expired for key: {}", key);
+                    match payload {
+                        Ok(Ok(payload)) => {
+                            entry.data = payload;
+                            Some(entry)
+                        }
+                        Ok(Err(e)) => {
+                            warn!("Dropping unreadable L3 cache entry {}: {}", key, e);
+                            // Unless the entry was replaced while the file was read
+                            let mut l3_cache = self.l3_cache.write().await;
+                            if l3_cache
+                                .get(key)
+                                .is_some_and(|current| current.created_at == entry.created_at)
+                            {
+                                self.remove_l3(&mut l3_cache, key).await;
+                            }
+                            None
+                        }
+                        Err(e) => {
+                            warn!("Failed to read L3 cache entry {}: {}", key, e);
+                            None
+                        }
+                    }
                 }
+                (hit, _) => hit,
+            };
+
+            // Promote to L2 once the L3 lock is released, since making room in L2 demotes into L3
+            if let Some(entry) = promoted {
+                result = Some((entry.codec, entry.data.clone()));
+                cache_level = "L3";
+
+                let mut l2_cache = self.l2_cache.write().await;
+                self.ensure_l2_capacity(&mut l2_cache, entry.compressed_size)
+                    .await;
+                l2_cache.insert(key.to_string(), entry);
+
+                debug!("L3 cache hit for key: {}, promoted to L2", key);
             }
         }
 
+        // Decode outside the locks; an undecodable entry is dropped and reported as a miss
+        let result = match result {
+            Some((codec, data)) => match codec.decompress(&data) {
+                Ok(data) => Some(data),
+                Err(e) => {
+                    warn!("Dropping corrupt cache entry {}: {}", key, e);
+                    self.remove(key).await;
+                    None
+                }
+            },
+            None => None,
+        };
+
         let access_time = start_time.elapsed();
         self.update_access_metrics(key, cache_level, result.is_some(), access_time)
             .await;
@@ -215,6 +722,7 @@ impl HighPerformanceCache {
         result
     }
 
+    #[tracing::instrument(skip_all)]
     pub async fn set(
         &self,
         key: &str,
@@ -224,18 +732,20 @@ impl HighPerformanceCache {
     ) -> anyhow::Result<()> {
         let start_time = Instant::now();
 
-        // Compress data if enabled
-        let (compressed_data, compression_ratio) = if self.config.compression_enabled {
-            self.compress_data(&data).await?
+        let original_size = data.len();
+        let (codec, stored_data) = self.compress_data(data).await?;
+        let compression_ratio = if stored_data.is_empty() {
+            1.0
         } else {
-            (data.clone(), 1.0)
+            original_size as f64 / stored_data.len() as f64
         };
 
         let entry = CacheEntry {
             key: key.to_string(),
-            data,
-            compressed_size: compressed_data.len(),
-            original_size: compressed_data.len(),
+            codec,
+            compressed_size: stored_data.len(),
+            original_size,
+            data: stored_data,
             created_at: start_time,
             last_accessed: start_time,
             access_count: 0,
@@ -244,6 +754,22 @@ impl HighPerformanceCache {
             tags,
         };
 
+        if self.config.analytics_enabled {
+            let mut analytics = self.analytics.write().await;
+            let entry_analytics = analytics
+                .entry(key.to_string())
+                .or_insert_with(|| CacheAnalytics::new(key));
+            entry_analytics.data_size_bytes = original_size;
+            entry_analytics.compression_ratio = compression_ratio;
+        }
+
+        // Drop stale copies in lower tiers so they cannot resurface, e.g. after a restart
+        self.l2_cache.write().await.remove(key);
+        {
+            let mut l3_cache = self.l3_cache.write().await;
+            self.remove_l3(&mut l3_cache, key).await;
+        }
+
         // Store in L1 cache
         {
             let mut l1_cache = self.l1_cache.write().await;
@@ -291,7 +817,7 @@ impl HighPerformanceCache {
 
         {
             let mut l3_cache = self.l3_cache.write().await;
-            if l3_cache.remove(key).is_some() {
+            if self.remove_l3(&mut l3_cache, key).await {
                 removed = true;
             }
         }
@@ -328,6 +854,9 @@ impl HighPerformanceCache {
         {
             let mut l3_cache = self.l3_cache.write().await;
             l3_cache.clear();
+            if let Some(disk_tier) = self.disk_tier.clone() {
+                tokio::task::spawn_blocking(move || disk_tier.clear()).await??;
+            }
         }
 
         {
@@ -387,7 +916,7 @@ impl HighPerformanceCache {
                 .collect();
 
             for key in keys_to_remove {
-                l3_cache.remove(&key);
+                self.remove_l3(&mut l3_cache, &key).await;
                 removed_count += 1;
             }
         }
@@ -396,13 +925,26 @@ impl HighPerformanceCache {
         Ok(removed_count)
     }
 
-    async fn compress_data(&self, data: &[u8]) -> anyhow::Result<(Vec<u8>, f64)> {
-        // Simple compression simulation
-        let compressed_size = (data.len() as f64 * 0.6) as usize; // 40% compression
-        let compressed_data = vec![0u8; compressed_size];
-        let compression_ratio = data.len() as f64 / compressed_size as f64;
+    /// Encode a payload for storage, keeping it raw when compression is off,
+    /// the payload is below the threshold, or compressing would not shrink it
+    async fn compress_data(&self, data: Vec<u8>) -> anyhow::Result<(CompressionCodec, Vec<u8>)> {
+        if !self.config.compression_enabled || data.len() < self.config.compression_threshold_bytes
+        {
+            return Ok((CompressionCodec::None, data));
+        }
+
+        let (data, compressed) = tokio::task::spawn_blocking(move || {
+            let compressed = CompressionCodec::Gzip.compress(&data);
+            (data, compressed)
+        })
+        .await?;
+        let compressed = compressed?;
 
-        Ok((compressed_data, compression_ratio))
+        if compressed.len() < data.len() {
+            Ok((CompressionCodec::Gzip, compressed))
+        } else {
+            Ok((CompressionCodec::None, data))
+        }
     }
 
     fn is_expired(&self, entry: &CacheEntry) -> bool {
@@ -456,9 +998,7 @@ impl HighPerformanceCache {
                     // Move to L3 if not expired
                     if !self.is_expired(&entry) {
                         let mut l3_cache = self.l3_cache.write().await;
-                        self.ensure_l3_capacity(&mut l3_cache, entry.compressed_size)
-                            .await;
-                        l3_cache.insert(key, entry);
+                        self.insert_l3(&mut l3_cache, key, entry).await;
                     }
                 }
             }
@@ -478,7 +1018,7 @@ impl HighPerformanceCache {
                 .select_eviction_candidates(l3_cache, required_size)
                 .await;
             for key in to_remove {
-                l3_cache.remove(&key);
+                self.remove_l3(l3_cache, &key).await;
             }
         }
     }
@@ -617,16 +1157,7 @@ impl HighPerformanceCache {
             let mut analytics = self.analytics.write().await;
             let entry = analytics
                 .entry(key.to_string())
-                .or_insert_with(|| CacheAnalytics {
-                    key: key.to_string(),
-                    hit_count: 0,
-                    miss_count: 0,
-                    total_access_time_ms: 0.0,
-                    average_access_time_ms: 0.0,
-                    data_size_bytes: 0,
-                    compression_ratio: 1.0,
-                    cache_level_distribution: HashMap::new(),
-                });
+                .or_insert_with(|| CacheAnalytics::new(key));
 
             if hit {
                 entry.hit_count += 1;
@@ -675,9 +1206,18 @@ impl HighPerformanceCache {
             .map(|e| e.compressed_size)
             .sum();
 
-        if total_compressed > 0 {
-            metrics.compression_ratio = total_original as f64 / total_compressed as f64;
-        }
+        metrics.compression_ratio = if total_compressed > 0 {
+            total_original as f64 / total_compressed as f64
+        } else {
+            1.0
+        };
+        metrics.compressed_entries = l1_cache
+            .values()
+            .chain(l2_cache.values())
+            .chain(l3_cache.values())
+            .filter(|e| e.codec != CompressionCodec::None)
+            .count() as u64;
+        metrics.compression_saved_bytes = total_original.saturating_sub(total_compressed) as u64;
 
         metrics.clone()
     }
@@ -730,7 +1270,7 @@ impl HighPerformanceCache {
                 .collect();
 
             for key in keys_to_remove {
-                l3_cache.remove(&key);
+                self.remove_l3(&mut l3_cache, &key).await;
                 removed_count += 1;
             }
         }
@@ -746,13 +1286,29 @@ lazy_static::lazy_static! {
 }
 
 pub async fn initialize_cache(config: CacheConfig) -> anyhow::Result<()> {
-    let cache = HighPerformanceCache::new(config);
     let mut global_cache = GLOBAL_CACHE.write().await;
+    // Keep what the replaced cache holds in memory, so a reconfigured cache can warm-load it
+    if let Some(previous) = global_cache.take() {
+        if let Err(e) = previous.persist().await {
+            warn!("Failed to persist the replaced cache: {}", e);
+        }
+    }
+
+    let cache = HighPerformanceCache::open(config).await?;
     *global_cache = Some(cache);
     info!("Global high-performance cache initialized");
     Ok(())
 }
 
+/// Write the global cache's L1 and L2 entries to disk, e.g. when the app exits
+pub async fn persist_cache() -> anyhow::Result<usize> {
+    let cache = GLOBAL_CACHE.read().await;
+    match cache.as_ref() {
+        Some(cache) => cache.persist().await,
+        None => Ok(0),
+    }
+}
+
 pub async fn cache_get(key: &str) -> Option<Vec<u8>> {
     let cache = GLOBAL_CACHE.read().await;
     if let Some(cache) = cache.as_ref() {
@@ -811,6 +1367,108 @@ mod tests {
         assert!(metrics.total_requests > 0);
     }
 
+    #[test]
+    async fn test_compressed_entries_round_trip() {
+        let cache = HighPerformanceCache::new(CacheConfig::default());
+
+        let large = "model response ".repeat(1000).into_bytes();
+        let small = b"short".to_vec();
+        cache
+            .set("large", large.clone(), None, vec![])
+            .await
+            .unwrap();
+        cache
+            .set("small", small.clone(), None, vec![])
+            .await
+            .unwrap();
+
+        {
+            let l1_cache = cache.l1_cache.read().await;
+            assert_eq!(l1_cache["large"].codec, CompressionCodec::Gzip);
+            assert!(l1_cache["large"].compressed_size < large.len());
+            assert_eq!(l1_cache["small"].codec, CompressionCodec::None);
+        }
+
+        assert_eq!(cache.get("large").await.unwrap(), large);
+        assert_eq!(cache.get("small").await.unwrap(), small);
+
+        let metrics = cache.get_metrics().await;
+        assert_eq!(metrics.compressed_entries, 1);
+        assert!(metrics.compression_ratio > 1.0);
+        assert!(metrics.compression_saved_bytes > 0);
+    }
+
+    #[test]
+    async fn test_persisted_entries_survive_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = CacheConfig {
+            persistence_enabled: true,
+            persistence_dir: Some(dir.path().to_path_buf()),
+            ..CacheConfig::default()
+        };
+
+        let data = "persisted payload ".repeat(200).into_bytes();
+        {
+            let cache = HighPerformanceCache::open(config.clone()).await.unwrap();
+            cache
+                .set(
+                    "persisted",
+                    data.clone(),
+                    Some(Duration::from_secs(3600)),
+                    vec!["session".to_string()],
+                )
+                .await
+                .unwrap();
+            assert_eq!(cache.persist().await.unwrap(), 1);
+        }
+
+        let cache = HighPerformanceCache::open(config.clone()).await.unwrap();
+        {
+            let l3_cache = cache.l3_cache.read().await;
+            let entry = &l3_cache["persisted"];
+            assert_eq!(entry.tags, vec!["session".to_string()]);
+            assert_eq!(entry.ttl, Some(Duration::from_secs(3600)));
+            assert!(entry.data.is_empty());
+        }
+        assert_eq!(cache.get("persisted").await.unwrap(), data);
+
+        assert_eq!(
+            cache.clear_by_tags(&["session".to_string()]).await.unwrap(),
+            2
+        );
+        let reopened = HighPerformanceCache::open(config).await.unwrap();
+        assert!(reopened.l3_cache.read().await.is_empty());
+    }
+
+    #[test]
+    async fn test_corrupt_persisted_entries_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = CacheConfig {
+            persistence_enabled: true,
+            persistence_dir: Some(dir.path().to_path_buf()),
+            ..CacheConfig::default()
+        };
+
+        {
+            let cache = HighPerformanceCache::open(config.clone()).await.unwrap();
+            cache
+                .set("damaged", b"payload".to_vec(), None, vec![])
+                .await
+                .unwrap();
+            assert_eq!(cache.persist().await.unwrap(), 1);
+        }
+
+        let cache = HighPerformanceCache::open(config).await.unwrap();
+        let path = cache.disk_tier.as_ref().unwrap().path_for("damaged");
+        let mut bytes = std::fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+
+        assert!(cache.get("damaged").await.is_none());
+        assert!(cache.l3_cache.read().await.is_empty());
+        assert!(!path.exists());
+    }
+
     #[test]
     async fn test_cache_eviction() {
         let mut config = CacheConfig::default();
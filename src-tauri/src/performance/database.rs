@@ -1,10 +1,16 @@
 // Database performance optimization with connection pooling, query analysis, and caching
 // Implements advanced database optimization strategies for high-performance applications
 
+use once_cell::sync::Lazy;
+use parking_lot::Mutex as SyncMutex;
+use regex::Regex;
+use rusqlite::types::ValueRef;
+use rusqlite::{params_from_iter, StatementStatus};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::{Mutex, RwLock, Semaphore};
 use tokio::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
@@ -18,9 +24,15 @@ pub struct DatabaseConfig {
     pub query_timeout_seconds: u64,
     pub query_cache_enabled: bool,
     pub query_cache_size_mb: u64,
+    /// Cached reads expire after this long, so writes made outside the optimizer show up
+    #[serde(default = "default_query_cache_ttl")]
+    pub query_cache_ttl_seconds: u64,
     pub query_analysis_enabled: bool,
     pub slow_query_threshold_ms: u64,
     pub prepare_statements: bool,
+    /// SQLite file to optimize; defaults to the application database
+    #[serde(default)]
+    pub database_path: Option<PathBuf>,
 }
 
 impl Default for DatabaseConfig {
@@ -33,13 +45,38 @@ impl Default for DatabaseConfig {
             query_timeout_seconds: 60,
             query_cache_enabled: true,
             query_cache_size_mb: 256,
+            query_cache_ttl_seconds: default_query_cache_ttl(),
             query_analysis_enabled: true,
             slow_query_threshold_ms: 1000,
             prepare_statements: true,
+            database_path: None,
         }
     }
 }
 
+fn default_query_cache_ttl() -> u64 {
+    30
+}
+
+static NUMERIC_LITERAL: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b\d+\b").unwrap());
+static STRING_LITERAL: Lazy<Regex> = Lazy::new(|| Regex::new(r"'[^']*'").unwrap());
+static READ_TABLE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\b(?:from|join)\s+["`\[]?([A-Za-z_][\w.]*)"#).unwrap());
+static WRITTEN_TABLE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)^\s*(?:insert(?:\s+or\s+\w+)?\s+into|replace\s+into|update(?:\s+or\s+\w+)?|delete\s+from)\s+["`\[]?([A-Za-z_][\w.]*)"#,
+    )
+    .unwrap()
+});
+static PLAN_SCAN: Lazy<Regex> = Lazy::new(|| Regex::new(r"^SCAN (?:TABLE )?(\w+)").unwrap());
+static WHERE_KEYWORD: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bwhere\b").unwrap());
+static WHERE_CLAUSE_END: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\b(?:group\s+by|order\s+by|limit|having)\b").unwrap());
+static FILTERED_COLUMN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(?:(\w+)\.)?(\w+)\s*(?:=|<=|>=|<>|!=|<|>|\bin\b|\blike\b|\bbetween\b|\bis\b)")
+        .unwrap()
+});
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseMetrics {
     pub timestamp: i64,
@@ -69,6 +106,12 @@ pub struct QueryAnalysis {
     pub table_scans: u64,
     pub rows_examined: u64,
     pub rows_returned: u64,
+    /// `EXPLAIN QUERY PLAN` details, one per plan step
+    #[serde(default)]
+    pub query_plan: Vec<String>,
+    /// `CREATE INDEX` statements that would avoid the plan's table scans
+    #[serde(default)]
+    pub suggested_indexes: Vec<String>,
     pub optimization_suggestions: Vec<String>,
 }
 
@@ -80,20 +123,48 @@ struct Connection {
     is_active: bool,
     query_count: u64,
     total_query_time: Duration,
+    /// Opened before the database file was replaced; closed instead of pooled on release
+    retired: bool,
+    handle: Arc<SyncMutex<rusqlite::Connection>>,
 }
 
 #[derive(Debug, Clone)]
 struct CachedQuery {
     query_hash: String,
     result: String, // JSON serialized result
+    /// Tables read by the query; writes to any of them invalidate the entry
+    tables: HashSet<String>,
     cached_at: Instant,
     hit_count: u64,
     size_bytes: usize,
 }
 
+/// What running a statement produced
+#[derive(Debug)]
+struct QueryOutcome {
+    result: serde_json::Value,
+    /// The statement only reads and returns rows, so its result may be cached
+    readonly: bool,
+    rows_returned: u64,
+    rows_examined: u64,
+    plan: Option<QueryPlan>,
+}
+
+/// Summary of an `EXPLAIN QUERY PLAN`
+#[derive(Debug, Clone, Default)]
+struct QueryPlan {
+    details: Vec<String>,
+    uses_index: bool,
+    /// Tables read without an index
+    scanned_tables: Vec<String>,
+}
+
 pub struct DatabaseOptimizer {
     config: DatabaseConfig,
+    database_path: PathBuf,
     connection_pool: Arc<RwLock<Vec<Connection>>>,
+    /// One permit per connection that may still be handed out
+    pool_slots: Arc<Semaphore>,
     query_cache: Arc<RwLock<HashMap<String, CachedQuery>>>,
     query_analytics: Arc<RwLock<HashMap<String, QueryAnalysis>>>,
     metrics: Arc<Mutex<DatabaseMetrics>>,
@@ -102,25 +173,21 @@ pub struct DatabaseOptimizer {
 }
 
 impl DatabaseOptimizer {
-    pub fn new(config: DatabaseConfig) -> Self {
+    pub async fn new(config: DatabaseConfig) -> anyhow::Result<Self> {
+        let database_path = Self::resolve_database_path(&config)?;
+        let busy_timeout = Duration::from_secs(config.connection_timeout_seconds);
+
         let mut connection_pool = Vec::new();
 
         // Initialize minimum connections
-        for _ in 0..config.min_connections {
-            connection_pool.push(Connection {
-                id: Uuid::new_v4(),
-                created_at: Instant::now(),
-                last_used: Instant::now(),
-                is_active: false,
-                query_count: 0,
-                total_query_time: Duration::ZERO,
-            });
+        for _ in 0..config.min_connections.min(config.max_connections) {
+            connection_pool.push(Self::open_connection(database_path.clone(), busy_timeout).await?);
         }
 
         let metrics = DatabaseMetrics {
             timestamp: chrono::Utc::now().timestamp(),
             active_connections: 0,
-            total_connections: config.min_connections,
+            total_connections: connection_pool.len() as u32,
             queries_per_second: 0.0,
             average_query_time_ms: 0.0,
             slow_queries_count: 0,
@@ -132,128 +199,246 @@ impl DatabaseOptimizer {
         };
 
         info!(
-            "Database optimizer initialized with {} connections",
-            config.min_connections
+            "Database optimizer initialized with {} connections to {}",
+            connection_pool.len(),
+            database_path.display()
         );
 
-        Self {
+        Ok(Self {
+            pool_slots: Arc::new(Semaphore::new(config.max_connections.max(1) as usize)),
             config,
+            database_path,
             connection_pool: Arc::new(RwLock::new(connection_pool)),
             query_cache: Arc::new(RwLock::new(HashMap::new())),
             query_analytics: Arc::new(RwLock::new(HashMap::new())),
             metrics: Arc::new(Mutex::new(metrics)),
             slow_queries: Arc::new(RwLock::new(VecDeque::new())),
             execution_history: Arc::new(RwLock::new(VecDeque::new())),
+        })
+    }
+
+    /// The configured file, else the application database, else its default location
+    fn resolve_database_path(config: &DatabaseConfig) -> anyhow::Result<PathBuf> {
+        if let Some(path) = &config.database_path {
+            return Ok(path.clone());
+        }
+
+        let app_path = crate::database::global_database()
+            .ok()
+            .and_then(|database| database.config().sqlite_path().ok().flatten());
+        match app_path {
+            Some(path) => Ok(path),
+            None => crate::database::DatabaseConfig::default()
+                .sqlite_path()?
+                .ok_or_else(|| anyhow::anyhow!("Application database has no file path")),
         }
     }
 
+    async fn open_connection(path: PathBuf, busy_timeout: Duration) -> anyhow::Result<Connection> {
+        let handle = tokio::task::spawn_blocking(move || {
+            crate::database::open_connection(Some(path), busy_timeout)
+        })
+        .await??;
+
+        Ok(Connection {
+            id: Uuid::new_v4(),
+            created_at: Instant::now(),
+            last_used: Instant::now(),
+            is_active: false,
+            query_count: 0,
+            total_query_time: Duration::ZERO,
+            retired: false,
+            handle: Arc::new(SyncMutex::new(handle)),
+        })
+    }
+
+    /// Check out a connection, waiting up to the connection timeout while the pool is exhausted
     pub async fn acquire_connection(&self) -> anyhow::Result<Uuid> {
-        let mut pool = self.connection_pool.write().await;
+        let timeout = Duration::from_secs(self.config.connection_timeout_seconds);
+        match tokio::time::timeout(timeout, self.pool_slots.acquire()).await {
+            Ok(permit) => permit?.forget(),
+            Err(_) => return Err(anyhow::anyhow!("Database connection pool is full")),
+        }
 
-        // Find an available connection
-        for connection in pool.iter_mut() {
-            if !connection.is_active {
-                connection.is_active = true;
-                connection.last_used = Instant::now();
+        {
+            let mut pool = self.connection_pool.write().await;
 
-                debug!("Acquired existing connection: {}", connection.id);
-                return Ok(connection.id);
+            // Find an available connection
+            if self.config.connection_pool_enabled {
+                if let Some(connection) = pool.iter_mut().find(|conn| !conn.is_active) {
+                    connection.is_active = true;
+                    connection.last_used = Instant::now();
+
+                    debug!("Acquired existing connection: {}", connection.id);
+                    return Ok(connection.id);
+                }
             }
         }
 
-        // No available connections, create new one if under limit
-        if pool.len() < self.config.max_connections as usize {
-            let new_connection = Connection {
-                id: Uuid::new_v4(),
-                created_at: Instant::now(),
-                last_used: Instant::now(),
-                is_active: true,
-                query_count: 0,
-                total_query_time: Duration::ZERO,
-            };
-
-            let connection_id = new_connection.id;
-            pool.push(new_connection);
+        // No available connections; the permit guarantees we are under the limit
+        let mut new_connection = match Self::open_connection(
+            self.database_path.clone(),
+            Duration::from_secs(self.config.connection_timeout_seconds),
+        )
+        .await
+        {
+            Ok(connection) => connection,
+            Err(e) => {
+                self.pool_slots.add_permits(1);
+                return Err(e);
+            }
+        };
+        new_connection.is_active = true;
 
-            info!("Created new database connection: {}", connection_id);
-            return Ok(connection_id);
-        }
+        let connection_id = new_connection.id;
+        self.connection_pool.write().await.push(new_connection);
 
-        // Pool is full
-        Err(anyhow::anyhow!("Database connection pool is full"))
+        info!("Created new database connection: {}", connection_id);
+        Ok(connection_id)
     }
 
     pub async fn release_connection(&self, connection_id: Uuid) -> anyhow::Result<()> {
         let mut pool = self.connection_pool.write().await;
 
-        for connection in pool.iter_mut() {
-            if connection.id == connection_id {
-                connection.is_active = false;
-                connection.last_used = Instant::now();
+        let index = pool
+            .iter()
+            .position(|conn| conn.id == connection_id)
+            .ok_or_else(|| anyhow::anyhow!("Connection not found"))?;
+        if !pool[index].is_active {
+            return Ok(());
+        }
 
-                debug!("Released connection: {}", connection_id);
-                return Ok(());
-            }
+        // Without pooling every connection is closed after use
+        if self.config.connection_pool_enabled && !pool[index].retired {
+            pool[index].is_active = false;
+            pool[index].last_used = Instant::now();
+        } else {
+            pool.remove(index);
         }
+        self.pool_slots.add_permits(1);
 
-        Err(anyhow::anyhow!("Connection not found"))
+        debug!("Released connection: {}", connection_id);
+        Ok(())
     }
 
+    /// Run a statement with positional `?` parameters
+    ///
+    /// Parameters are bound as text; SQLite's column affinity converts them
+    /// for numeric columns. Results of read-only statements are cached until
+    /// one of their tables is written through the optimizer or the cache TTL
+    /// passes.
     pub async fn execute_query(
         &self,
         connection_id: Uuid,
         query: &str,
         parameters: &[&str],
+    ) -> anyhow::Result<String> {
+        self.execute(connection_id, query, parameters, false).await
+    }
+
+    /// Run a statement that reads rows, refusing anything that could modify the database
+    pub async fn execute_read_query(
+        &self,
+        connection_id: Uuid,
+        query: &str,
+        parameters: &[&str],
+    ) -> anyhow::Result<String> {
+        self.execute(connection_id, query, parameters, true).await
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn execute(
+        &self,
+        connection_id: Uuid,
+        query: &str,
+        parameters: &[&str],
+        read_only: bool,
     ) -> anyhow::Result<String> {
         let start_time = Instant::now();
         let query_hash = self.hash_query(query, parameters);
 
-        // Check cache first
+        // Check cache first; only reads are cached
         if self.config.query_cache_enabled {
-            let cache = self.query_cache.read().await;
-            if let Some(cached) = cache.get(&query_hash) {
-                // Update cache hit stats
-                let mut cache_write = self.query_cache.write().await;
-                if let Some(cached_mut) = cache_write.get_mut(&query_hash) {
-                    cached_mut.hit_count += 1;
+            let ttl = Duration::from_secs(self.config.query_cache_ttl_seconds);
+            let mut cache = self.query_cache.write().await;
+            match cache.get_mut(&query_hash) {
+                Some(cached) if cached.cached_at.elapsed() <= ttl => {
+                    cached.hit_count += 1;
+
+                    debug!("Query cache hit for hash: {}", query_hash);
+                    return Ok(cached.result.clone());
                 }
-
-                debug!("Query cache hit for hash: {}", query_hash);
-                return Ok(cached.result.clone());
+                Some(_) => {
+                    cache.remove(&query_hash);
+                }
+                None => {}
             }
         }
 
-        // Update connection stats
-        let mut pool = self.connection_pool.write().await;
-        let connection = pool
-            .iter_mut()
-            .find(|conn| conn.id == connection_id)
-            .ok_or_else(|| anyhow::anyhow!("Connection not found"))?;
+        let handle = {
+            let pool = self.connection_pool.read().await;
+            let connection = pool
+                .iter()
+                .find(|conn| conn.id == connection_id && conn.is_active)
+                .ok_or_else(|| anyhow::anyhow!("Connection not found"))?;
+            connection.handle.clone()
+        };
+
+        // Only the first execution of a statement needs its plan
+        let explain = self.config.query_analysis_enabled
+            && !self.query_analytics.read().await.contains_key(&query_hash);
 
-        connection.query_count += 1;
+        let outcome = match self
+            .run_statement(handle, query, parameters, explain, read_only)
+            .await
+        {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                let mut metrics = self.metrics.lock().await;
+                metrics.failed_queries += 1;
+                if e.to_string().contains("database is locked") {
+                    metrics.deadlocks += 1;
+                }
+                error!("Query failed: {} - {}", e, query);
+                return Err(e);
+            }
+        };
+        let execution_time = start_time.elapsed();
 
-        // Simulate query execution
-        let execution_time = self.simulate_query_execution(query).await?;
-        connection.total_query_time += execution_time;
+        // Update connection stats
+        {
+            let mut pool = self.connection_pool.write().await;
+            if let Some(connection) = pool.iter_mut().find(|conn| conn.id == connection_id) {
+                connection.query_count += 1;
+                connection.total_query_time += execution_time;
+            }
+        }
 
-        // Generate mock result
-        let result = serde_json::json!({
-            "status": "success",
-            "rows_affected": 1,
-            "execution_time_ms": execution_time.as_millis(),
-            "data": [{"id": 1, "value": "test"}]
-        })
-        .to_string();
+        let mut result = outcome.result;
+        result["execution_time_ms"] = serde_json::json!(execution_time.as_secs_f64() * 1000.0);
+        let result = result.to_string();
 
         // Update query analytics
         if self.config.query_analysis_enabled {
-            self.update_query_analytics(query, &query_hash, execution_time)
-                .await;
+            self.update_query_analytics(
+                query,
+                &query_hash,
+                execution_time,
+                &outcome.plan,
+                outcome.rows_examined,
+                outcome.rows_returned,
+            )
+            .await;
         }
 
-        // Cache the result
+        // Cache reads, and drop cached reads of any table this statement changed
         if self.config.query_cache_enabled {
-            self.cache_query_result(&query_hash, &result).await;
+            if outcome.readonly {
+                self.cache_query_result(&query_hash, &result, Self::read_tables(query))
+                    .await;
+            } else {
+                self.invalidate_cache_for_write(query).await;
+            }
         }
 
         // Record execution history
@@ -264,6 +449,7 @@ impl DatabaseOptimizer {
         if history.len() > 1000 {
             history.drain(..500);
         }
+        drop(history);
 
         // Check for slow queries
         if execution_time.as_millis() > self.config.slow_query_threshold_ms as u128 {
@@ -274,38 +460,62 @@ impl DatabaseOptimizer {
         Ok(result)
     }
 
-    async fn simulate_query_execution(&self, query: &str) -> anyhow::Result<Duration> {
-        // Simulate different query types with different execution times
-        let base_time = if query.to_lowercase().contains("select") {
-            Duration::from_millis(50)
-        } else if query.to_lowercase().contains("insert") {
-            Duration::from_millis(30)
-        } else if query.to_lowercase().contains("update") {
-            Duration::from_millis(75)
-        } else if query.to_lowercase().contains("delete") {
-            Duration::from_millis(40)
-        } else {
-            Duration::from_millis(100)
-        };
-
-        // Add some randomness
-        let variation = Duration::from_millis(rand::random::<u64>() % 50);
-        let execution_time = base_time + variation;
+    /// Execute on the blocking pool, interrupting the statement if it exceeds the query timeout
+    async fn run_statement(
+        &self,
+        handle: Arc<SyncMutex<rusqlite::Connection>>,
+        query: &str,
+        parameters: &[&str],
+        explain: bool,
+        read_only: bool,
+    ) -> anyhow::Result<QueryOutcome> {
+        let query = query.to_string();
+        let parameters: Vec<String> = parameters.iter().map(|p| p.to_string()).collect();
+        let prepare_cached = self.config.prepare_statements;
+        let interrupt = handle.lock().get_interrupt_handle();
+
+        let mut task = tokio::task::spawn_blocking(move || {
+            let conn = handle.lock();
+            // The plan is only analysis; statements SQLite cannot explain still run
+            let plan = if explain {
+                explain_query_plan(&conn, &query, &parameters)
+                    .map_err(|e| debug!("No query plan for statement: {}", e))
+                    .ok()
+            } else {
+                None
+            };
+            run_on_connection(&conn, &query, &parameters, prepare_cached, plan, read_only)
+        });
 
-        // Simulate actual work
-        tokio::time::sleep(Duration::from_millis(1)).await;
+        let timeout = Duration::from_secs(self.config.query_timeout_seconds);
+        let outcome = match tokio::time::timeout(timeout, &mut task).await {
+            Ok(joined) => joined?.map_err(|e| match e {
+                StatementError::NotReadOnly => {
+                    anyhow::anyhow!("Only statements that read rows are allowed")
+                }
+                StatementError::Sqlite(e) => e.into(),
+            })?,
+            Err(_) => {
+                interrupt.interrupt();
+                let _ = task.await;
+                return Err(anyhow::anyhow!(
+                    "Query exceeded the {} second timeout",
+                    self.config.query_timeout_seconds
+                ));
+            }
+        };
 
-        Ok(execution_time)
+        Ok(outcome)
     }
 
     fn hash_query(&self, query: &str, parameters: &[&str]) -> String {
         use sha2::{Digest, Sha256};
 
         let mut hasher = Sha256::new();
-        hasher.update(query.as_bytes());
-
-        for param in parameters {
-            hasher.update(param.as_bytes());
+        // Length prefixes keep ("ab", "c") and ("a", "bc") apart
+        for part in std::iter::once(query).chain(parameters.iter().copied()) {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
         }
 
         hex::encode(hasher.finalize())
@@ -316,6 +526,9 @@ impl DatabaseOptimizer {
         query: &str,
         query_hash: &str,
         execution_time: Duration,
+        plan: &Option<QueryPlan>,
+        rows_examined: u64,
+        rows_returned: u64,
     ) {
         let mut analytics = self.query_analytics.write().await;
 
@@ -330,14 +543,22 @@ impl DatabaseOptimizer {
                 min_execution_time_ms: f64::MAX,
                 max_execution_time_ms: 0.0,
                 last_executed: chrono::Utc::now().timestamp(),
-                uses_index: self.analyze_index_usage(query),
+                uses_index: false,
                 table_scans: 0,
-                rows_examined: rand::random::<u64>() % 1000 + 100,
-                rows_returned: rand::random::<u64>() % 100 + 1,
+                rows_examined: 0,
+                rows_returned: 0,
+                query_plan: Vec::new(),
+                suggested_indexes: Vec::new(),
                 optimization_suggestions: Vec::new(),
             });
 
-        let exec_time_ms = execution_time.as_millis() as f64;
+        if let Some(plan) = plan {
+            analysis.uses_index = plan.uses_index;
+            analysis.query_plan = plan.details.clone();
+            analysis.suggested_indexes = suggest_indexes(query, &plan.scanned_tables);
+        }
+
+        let exec_time_ms = execution_time.as_secs_f64() * 1000.0;
 
         analysis.execution_count += 1;
         analysis.total_execution_time_ms += exec_time_ms;
@@ -346,6 +567,11 @@ impl DatabaseOptimizer {
         analysis.min_execution_time_ms = analysis.min_execution_time_ms.min(exec_time_ms);
         analysis.max_execution_time_ms = analysis.max_execution_time_ms.max(exec_time_ms);
         analysis.last_executed = chrono::Utc::now().timestamp();
+        analysis.rows_examined += rows_examined;
+        analysis.rows_returned += rows_returned;
+        analysis.table_scans += plan
+            .as_ref()
+            .map_or(0, |plan| plan.scanned_tables.len() as u64);
 
         // Generate optimization suggestions
         self.generate_optimization_suggestions(analysis);
@@ -356,41 +582,36 @@ impl DatabaseOptimizer {
         let mut normalized = query.to_lowercase();
 
         // Replace numeric literals
-        normalized = regex::Regex::new(r"\b\d+\b")
-            .unwrap()
-            .replace_all(&normalized, "?")
-            .to_string();
+        normalized = NUMERIC_LITERAL.replace_all(&normalized, "?").to_string();
 
         // Replace string literals
-        normalized = regex::Regex::new(r"'[^']*'")
-            .unwrap()
-            .replace_all(&normalized, "?")
-            .to_string();
+        normalized = STRING_LITERAL.replace_all(&normalized, "?").to_string();
 
         normalized
     }
 
-    fn analyze_index_usage(&self, query: &str) -> bool {
-        // Simple heuristic - assume queries with WHERE clauses use indexes
-        query.to_lowercase().contains("where") || query.to_lowercase().contains("join")
-    }
-
     fn generate_optimization_suggestions(&self, analysis: &mut QueryAnalysis) {
         analysis.optimization_suggestions.clear();
 
-        if analysis.average_execution_time_ms > 500.0 {
+        for index in &analysis.suggested_indexes {
+            analysis
+                .optimization_suggestions
+                .push(format!("Add an index to avoid a table scan: {}", index));
+        }
+
+        if analysis.average_execution_time_ms > 500.0 && analysis.suggested_indexes.is_empty() {
             analysis
                 .optimization_suggestions
                 .push("Consider adding appropriate indexes".to_string());
         }
 
-        if !analysis.uses_index && analysis.execution_count > 10 {
+        if !analysis.uses_index && analysis.table_scans > 0 && analysis.execution_count > 10 {
             analysis
                 .optimization_suggestions
                 .push("Query may benefit from indexing".to_string());
         }
 
-        if analysis.rows_examined > analysis.rows_returned * 10 {
+        if analysis.rows_returned > 0 && analysis.rows_examined > analysis.rows_returned * 10 {
             analysis
                 .optimization_suggestions
                 .push("High rows examined to returned ratio - optimize WHERE clause".to_string());
@@ -403,7 +624,38 @@ impl DatabaseOptimizer {
         }
     }
 
-    async fn cache_query_result(&self, query_hash: &str, result: &str) {
+    /// Lowercase table names following FROM or JOIN
+    fn read_tables(query: &str) -> HashSet<String> {
+        READ_TABLE
+            .captures_iter(query)
+            .map(|captures| captures[1].to_lowercase())
+            .collect()
+    }
+
+    /// Table a write statement modifies, or `None` when it cannot be determined
+    fn written_table(query: &str) -> Option<String> {
+        WRITTEN_TABLE
+            .captures(query)
+            .map(|captures| captures[1].to_lowercase())
+    }
+
+    async fn invalidate_cache_for_write(&self, query: &str) {
+        let mut cache = self.query_cache.write().await;
+        let before = cache.len();
+
+        match Self::written_table(query) {
+            Some(table) => cache.retain(|_, cached| !cached.tables.contains(&table)),
+            // DDL, transactions and anything unparsed may affect any table
+            None => cache.clear(),
+        }
+
+        let invalidated = before - cache.len();
+        if invalidated > 0 {
+            debug!("Invalidated {} cached queries after write", invalidated);
+        }
+    }
+
+    async fn cache_query_result(&self, query_hash: &str, result: &str, tables: HashSet<String>) {
         let mut cache = self.query_cache.write().await;
 
         // Check cache size limit
@@ -428,6 +680,7 @@ impl DatabaseOptimizer {
             CachedQuery {
                 query_hash: query_hash.to_string(),
                 result: result.to_string(),
+                tables,
                 cached_at: Instant::now(),
                 hit_count: 0,
                 size_bytes: result.len(),
@@ -479,7 +732,8 @@ impl DatabaseOptimizer {
         // Calculate average query time
         if !history.is_empty() {
             let total_time: Duration = history.iter().map(|(_, duration, _)| *duration).sum();
-            metrics.average_query_time_ms = total_time.as_millis() as f64 / history.len() as f64;
+            metrics.average_query_time_ms =
+                total_time.as_secs_f64() * 1000.0 / history.len() as f64;
         }
 
         // Update slow queries count
@@ -496,6 +750,16 @@ impl DatabaseOptimizer {
             }
         }
 
+        // Share of analyzed statements whose plan uses an index or scans nothing
+        let analytics = self.query_analytics.read().await;
+        if !analytics.is_empty() {
+            let efficient = analytics
+                .values()
+                .filter(|analysis| analysis.uses_index || analysis.table_scans == 0)
+                .count();
+            metrics.index_usage_efficiency = efficient as f64 / analytics.len() as f64 * 100.0;
+        }
+
         metrics.timestamp = chrono::Utc::now().timestamp();
         metrics.clone()
     }
@@ -627,6 +891,23 @@ impl DatabaseOptimizer {
         Ok(cleaned_count)
     }
 
+    /// Close every connection and drop cached results, e.g. after the database file was
+    /// replaced; connections in use are closed when released
+    pub async fn reset_connections(&self) -> usize {
+        let mut pool = self.connection_pool.write().await;
+        let initial_count = pool.len();
+        pool.retain(|conn| conn.is_active);
+        for conn in pool.iter_mut() {
+            conn.retired = true;
+        }
+        let closed_count = initial_count - pool.len();
+        drop(pool);
+
+        self.query_cache.write().await.clear();
+        info!("Closed {} idle database connections", closed_count);
+        closed_count
+    }
+
     pub async fn clear_query_cache(&self) -> anyhow::Result<usize> {
         let mut cache = self.query_cache.write().await;
         let cleared_count = cache.len();
@@ -637,6 +918,169 @@ impl DatabaseOptimizer {
     }
 }
 
+/// Run `EXPLAIN QUERY PLAN` for a statement
+fn explain_query_plan(
+    conn: &rusqlite::Connection,
+    query: &str,
+    parameters: &[String],
+) -> rusqlite::Result<QueryPlan> {
+    let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", query))?;
+    let details = stmt
+        .query_map(params_from_iter(parameters.iter()), |row| {
+            row.get::<_, String>(3)
+        })?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+
+    let mut plan = QueryPlan::default();
+    for detail in &details {
+        if detail.contains(" USING ") && detail.contains("INDEX")
+            || detail.contains("INTEGER PRIMARY KEY")
+        {
+            plan.uses_index = true;
+        } else if let Some(captures) = PLAN_SCAN.captures(detail) {
+            plan.scanned_tables.push(captures[1].to_lowercase());
+        }
+    }
+    plan.details = details;
+
+    Ok(plan)
+}
+
+#[derive(Debug)]
+enum StatementError {
+    /// A read-only execution was asked to run a statement that does not just read rows
+    NotReadOnly,
+    Sqlite(rusqlite::Error),
+}
+
+impl From<rusqlite::Error> for StatementError {
+    fn from(e: rusqlite::Error) -> Self {
+        StatementError::Sqlite(e)
+    }
+}
+
+/// Execute a statement and render its result as JSON
+fn run_on_connection(
+    conn: &rusqlite::Connection,
+    query: &str,
+    parameters: &[String],
+    prepare_cached: bool,
+    plan: Option<QueryPlan>,
+    read_only: bool,
+) -> Result<QueryOutcome, StatementError> {
+    if prepare_cached {
+        let mut stmt = conn.prepare_cached(query)?;
+        collect_statement(conn, &mut stmt, parameters, plan, read_only)
+    } else {
+        let mut stmt = conn.prepare(query)?;
+        collect_statement(conn, &mut stmt, parameters, plan, read_only)
+    }
+}
+
+fn collect_statement(
+    conn: &rusqlite::Connection,
+    stmt: &mut rusqlite::Statement<'_>,
+    parameters: &[String],
+    plan: Option<QueryPlan>,
+    read_only: bool,
+) -> Result<QueryOutcome, StatementError> {
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    // SQLite also reports BEGIN, ATTACH and the like as read-only; those return no columns
+    let readonly = stmt.readonly() && !columns.is_empty();
+    if read_only && !readonly {
+        return Err(StatementError::NotReadOnly);
+    }
+
+    let result = if columns.is_empty() {
+        let rows_affected = stmt.execute(params_from_iter(parameters.iter()))?;
+        serde_json::json!({
+            "status": "success",
+            "rows_affected": rows_affected,
+            "last_insert_rowid": conn.last_insert_rowid(),
+            "data": [],
+        })
+    } else {
+        let mut data = Vec::new();
+        let mut rows = stmt.query(params_from_iter(parameters.iter()))?;
+        while let Some(row) = rows.next()? {
+            let mut object = serde_json::Map::new();
+            for (index, column) in columns.iter().enumerate() {
+                object.insert(column.clone(), json_value(row.get_ref(index)?));
+            }
+            data.push(serde_json::Value::Object(object));
+        }
+        drop(rows);
+
+        serde_json::json!({
+            "status": "success",
+            "rows_affected": 0,
+            "columns": columns,
+            "data": data,
+        })
+    };
+
+    let rows_returned = result["data"].as_array().map_or(0, |data| data.len()) as u64;
+    let fullscan_steps = stmt.get_status(StatementStatus::FullscanStep).max(0) as u64;
+
+    Ok(QueryOutcome {
+        result,
+        readonly,
+        rows_returned,
+        rows_examined: fullscan_steps.max(rows_returned),
+        plan,
+    })
+}
+
+fn json_value(value: ValueRef<'_>) -> serde_json::Value {
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => serde_json::json!(i),
+        ValueRef::Real(f) => serde_json::json!(f),
+        ValueRef::Text(t) => serde_json::Value::String(String::from_utf8_lossy(t).into_owned()),
+        ValueRef::Blob(b) => serde_json::Value::String(hex::encode(b)),
+    }
+}
+
+/// Indexes on the columns a query filters scanned tables by
+fn suggest_indexes(query: &str, scanned_tables: &[String]) -> Vec<String> {
+    let Some(where_keyword) = WHERE_KEYWORD.find(query) else {
+        return Vec::new();
+    };
+    let clause = &query[where_keyword.end()..];
+    let clause_end = WHERE_CLAUSE_END
+        .find(clause)
+        .map_or(clause.len(), |m| m.start());
+
+    let mut suggestions = Vec::new();
+    for table in scanned_tables {
+        let mut columns: Vec<String> = Vec::new();
+        for captures in FILTERED_COLUMN.captures_iter(&clause[..clause_end]) {
+            let qualifier = captures.get(1).map(|m| m.as_str().to_lowercase());
+            // Unqualified columns are only attributable when one table is scanned
+            let belongs = match &qualifier {
+                Some(qualifier) => qualifier == table,
+                None => scanned_tables.len() == 1,
+            };
+            let column = captures[2].to_lowercase();
+            if belongs && !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+
+        if !columns.is_empty() {
+            suggestions.push(format!(
+                "CREATE INDEX idx_{}_{} ON {}({})",
+                table,
+                columns.join("_"),
+                table,
+                columns.join(", ")
+            ));
+        }
+    }
+
+    suggestions
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseOptimizationReport {
     pub timestamp: i64,
@@ -680,7 +1124,7 @@ lazy_static::lazy_static! {
 }
 
 pub async fn initialize_database_optimizer(config: DatabaseConfig) -> anyhow::Result<()> {
-    let optimizer = DatabaseOptimizer::new(config);
+    let optimizer = DatabaseOptimizer::new(config).await?;
     let mut global_optimizer = DATABASE_OPTIMIZER.write().await;
     *global_optimizer = Some(optimizer);
     info!("Global database optimizer initialized");
@@ -701,6 +1145,31 @@ pub async fn execute_optimized_query(query: &str, parameters: &[&str]) -> anyhow
     }
 }
 
+/// Run a read-only statement on a pooled connection; anything that could write is refused
+pub async fn execute_optimized_read_query(
+    query: &str,
+    parameters: &[&str],
+) -> anyhow::Result<String> {
+    let optimizer = DATABASE_OPTIMIZER.read().await;
+    if let Some(optimizer) = optimizer.as_ref() {
+        let connection_id = optimizer.acquire_connection().await?;
+        let result = optimizer
+            .execute_read_query(connection_id, query, parameters)
+            .await;
+        optimizer.release_connection(connection_id).await?;
+        result
+    } else {
+        Err(anyhow::anyhow!("Database optimizer not initialized"))
+    }
+}
+
+/// Reconnect the global optimizer, whose connections still point at a database file that was replaced
+pub async fn reset_database_connections() {
+    if let Some(optimizer) = DATABASE_OPTIMIZER.read().await.as_ref() {
+        optimizer.reset_connections().await;
+    }
+}
+
 pub async fn get_database_metrics() -> Option<DatabaseMetrics> {
     let optimizer = DATABASE_OPTIMIZER.read().await;
     if let Some(optimizer) = optimizer.as_ref() {
@@ -715,48 +1184,358 @@ mod tests {
     use super::*;
     use tokio::test;
 
+    async fn test_optimizer(temp_dir: &tempfile::TempDir) -> DatabaseOptimizer {
+        let config = DatabaseConfig {
+            min_connections: 1,
+            max_connections: 2,
+            connection_timeout_seconds: 1,
+            database_path: Some(temp_dir.path().join("optimizer.db")),
+            ..Default::default()
+        };
+        DatabaseOptimizer::new(config).await.unwrap()
+    }
+
     #[test]
     async fn test_database_optimizer() {
-        let config = DatabaseConfig::default();
-        let optimizer = DatabaseOptimizer::new(config);
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let optimizer = test_optimizer(&temp_dir).await;
 
         let connection_id = optimizer.acquire_connection().await.unwrap();
         assert!(!connection_id.is_nil());
 
-        let result = optimizer
-            .execute_query(connection_id, "SELECT * FROM test", &[])
+        optimizer
+            .execute_query(
+                connection_id,
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, age INTEGER)",
+                &[],
+            )
             .await
             .unwrap();
-        assert!(!result.is_empty());
+        let insert: serde_json::Value = serde_json::from_str(
+            &optimizer
+                .execute_query(
+                    connection_id,
+                    "INSERT INTO users (name, age) VALUES (?, ?)",
+                    &["alice", "42"],
+                )
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(insert["rows_affected"], 1);
+
+        let result: serde_json::Value = serde_json::from_str(
+            &optimizer
+                .execute_query(connection_id, "SELECT name, age FROM users", &[])
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result["data"][0]["name"], "alice");
+        assert_eq!(result["data"][0]["age"], 42);
+
+        assert!(optimizer
+            .execute_query(connection_id, "SELECT * FROM missing", &[])
+            .await
+            .is_err());
 
         optimizer.release_connection(connection_id).await.unwrap();
 
         let metrics = optimizer.get_metrics().await;
         assert!(metrics.total_connections > 0);
+        assert_eq!(metrics.failed_queries, 1);
     }
 
     #[test]
     async fn test_query_caching() {
-        let config = DatabaseConfig::default();
-        let optimizer = DatabaseOptimizer::new(config);
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let optimizer = test_optimizer(&temp_dir).await;
 
         let connection_id = optimizer.acquire_connection().await.unwrap();
+        optimizer
+            .execute_query(
+                connection_id,
+                "CREATE TABLE users (id INTEGER, name TEXT)",
+                &[],
+            )
+            .await
+            .unwrap();
+        optimizer
+            .execute_query(connection_id, "INSERT INTO users VALUES (1, 'alice')", &[])
+            .await
+            .unwrap();
 
         // Execute same query twice
-        let query = "SELECT * FROM users WHERE id = 1";
-        let _result1 = optimizer
-            .execute_query(connection_id, query, &[])
+        let query = "SELECT name FROM users WHERE id = ?";
+        let first = optimizer
+            .execute_query(connection_id, query, &["1"])
+            .await
+            .unwrap();
+        let second = optimizer
+            .execute_query(connection_id, query, &["1"])
+            .await
+            .unwrap();
+        assert_eq!(first, second);
+        assert!(optimizer.get_metrics().await.cache_hit_rate > 0.0);
+
+        // Writing the table invalidates the cached read
+        optimizer
+            .execute_query(
+                connection_id,
+                "UPDATE users SET name = ? WHERE id = ?",
+                &["bob", "1"],
+            )
+            .await
+            .unwrap();
+        let after_write: serde_json::Value = serde_json::from_str(
+            &optimizer
+                .execute_query(connection_id, query, &["1"])
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(after_write["data"][0]["name"], "bob");
+
+        optimizer.release_connection(connection_id).await.unwrap();
+    }
+
+    #[test]
+    async fn test_index_suggestions_from_query_plan() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let optimizer = test_optimizer(&temp_dir).await;
+
+        let connection_id = optimizer.acquire_connection().await.unwrap();
+        optimizer
+            .execute_query(
+                connection_id,
+                "CREATE TABLE events (id INTEGER, kind TEXT)",
+                &[],
+            )
+            .await
+            .unwrap();
+
+        let query = "SELECT id FROM events WHERE kind = ?";
+        optimizer
+            .execute_query(connection_id, query, &["login"])
+            .await
+            .unwrap();
+        let scan = optimizer.get_query_analytics().await;
+        let scan = scan
+            .iter()
+            .find(|analysis| analysis.query_template.contains("from events"))
+            .unwrap();
+        assert!(!scan.uses_index);
+        assert_eq!(scan.table_scans, 1);
+        assert_eq!(
+            scan.suggested_indexes,
+            vec!["CREATE INDEX idx_events_kind ON events(kind)".to_string()]
+        );
+
+        optimizer
+            .execute_query(connection_id, &scan.suggested_indexes[0], &[])
             .await
             .unwrap();
-        let _result2 = optimizer
+        optimizer
+            .execute_query(connection_id, query, &["logout"])
+            .await
+            .unwrap();
+        let analytics = optimizer.get_query_analytics().await;
+        assert!(analytics
+            .iter()
+            .any(|analysis| analysis.uses_index && analysis.suggested_indexes.is_empty()));
+
+        optimizer.release_connection(connection_id).await.unwrap();
+    }
+
+    #[test]
+    async fn test_cached_reads_expire() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = DatabaseConfig {
+            min_connections: 1,
+            max_connections: 2,
+            query_cache_ttl_seconds: 0,
+            database_path: Some(temp_dir.path().join("optimizer.db")),
+            ..Default::default()
+        };
+        let optimizer = DatabaseOptimizer::new(config).await.unwrap();
+
+        let connection_id = optimizer.acquire_connection().await.unwrap();
+        optimizer
+            .execute_query(connection_id, "CREATE TABLE users (name TEXT)", &[])
+            .await
+            .unwrap();
+        let query = "SELECT count(*) AS total FROM users";
+        optimizer
             .execute_query(connection_id, query, &[])
             .await
             .unwrap();
 
+        // A write the optimizer never sees, as from another connection
+        let outside = rusqlite::Connection::open(temp_dir.path().join("optimizer.db")).unwrap();
+        outside
+            .execute("INSERT INTO users VALUES ('alice')", [])
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let result: serde_json::Value = serde_json::from_str(
+            &optimizer
+                .execute_query(connection_id, query, &[])
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result["data"][0]["total"], 1);
+
+        optimizer.release_connection(connection_id).await.unwrap();
+    }
+
+    #[test]
+    async fn test_read_query_refuses_writes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let optimizer = test_optimizer(&temp_dir).await;
+
+        let connection_id = optimizer.acquire_connection().await.unwrap();
+        optimizer
+            .execute_query(connection_id, "CREATE TABLE users (name TEXT)", &[])
+            .await
+            .unwrap();
+
+        for statement in [
+            "INSERT INTO users VALUES ('alice')",
+            "DROP TABLE users",
+            "BEGIN IMMEDIATE",
+            "PRAGMA query_only = 0",
+        ] {
+            assert!(
+                optimizer
+                    .execute_read_query(connection_id, statement, &[])
+                    .await
+                    .is_err(),
+                "{} was allowed",
+                statement
+            );
+        }
+
+        let result: serde_json::Value = serde_json::from_str(
+            &optimizer
+                .execute_read_query(connection_id, "SELECT count(*) AS total FROM users", &[])
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result["data"][0]["total"], 0);
+
         optimizer.release_connection(connection_id).await.unwrap();
+    }
 
-        let metrics = optimizer.get_metrics().await;
-        // Second query should hit cache
-        assert!(metrics.cache_hit_rate > 0.0 || metrics.queries_per_second > 0.0);
+    #[test]
+    async fn test_index_suggestions_ignore_keyword_case_and_spacing() {
+        let suggestions = suggest_indexes(
+            "SELECT id FROM events\n\tWhere kind = ? ORDER BY id",
+            &["events".to_string()],
+        );
+        assert_eq!(
+            suggestions,
+            vec!["CREATE INDEX idx_events_kind ON events(kind)".to_string()]
+        );
+        assert!(
+            suggest_indexes("SELECT id FROM events_where_view", &["events".to_string()]).is_empty()
+        );
+    }
+
+    #[test]
+    async fn test_pool_exhaustion_times_out() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let optimizer = test_optimizer(&temp_dir).await;
+
+        let first = optimizer.acquire_connection().await.unwrap();
+        let second = optimizer.acquire_connection().await.unwrap();
+        assert_ne!(first, second);
+        assert!(optimizer.acquire_connection().await.is_err());
+
+        optimizer.release_connection(first).await.unwrap();
+        let third = optimizer.acquire_connection().await.unwrap();
+        assert_eq!(third, first);
+    }
+
+    #[test]
+    async fn test_cache_keys_keep_parameters_apart() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let optimizer = test_optimizer(&temp_dir).await;
+        let query = "SELECT ? AS first, ? AS second";
+        assert_ne!(
+            optimizer.hash_query(query, &["ab", "c"]),
+            optimizer.hash_query(query, &["a", "bc"])
+        );
+        assert_ne!(
+            optimizer.hash_query("SELECT ?", &["1"]),
+            optimizer.hash_query("SELECT ?1", &[])
+        );
+
+        let connection_id = optimizer.acquire_connection().await.unwrap();
+        optimizer
+            .execute_query(connection_id, query, &["ab", "c"])
+            .await
+            .unwrap();
+        let result: serde_json::Value = serde_json::from_str(
+            &optimizer
+                .execute_query(connection_id, query, &["a", "bc"])
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result["data"][0]["first"], "a");
+        assert_eq!(result["data"][0]["second"], "bc");
+        optimizer.release_connection(connection_id).await.unwrap();
+    }
+
+    #[test]
+    async fn test_reset_connections_follows_a_replaced_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let optimizer = test_optimizer(&temp_dir).await;
+        let query = "SELECT name FROM users";
+
+        let connection_id = optimizer.acquire_connection().await.unwrap();
+        optimizer
+            .execute_query(connection_id, "CREATE TABLE users (name TEXT)", &[])
+            .await
+            .unwrap();
+        optimizer
+            .execute_query(connection_id, "INSERT INTO users VALUES ('old')", &[])
+            .await
+            .unwrap();
+        optimizer
+            .execute_query(connection_id, query, &[])
+            .await
+            .unwrap();
+        optimizer.release_connection(connection_id).await.unwrap();
+        let held = optimizer.acquire_connection().await.unwrap();
+
+        // Swap in another database file, as a backup restore does
+        let replacement = temp_dir.path().join("replacement.db");
+        {
+            let conn = rusqlite::Connection::open(&replacement).unwrap();
+            conn.execute_batch("CREATE TABLE users (name TEXT); INSERT INTO users VALUES ('new');")
+                .unwrap();
+        }
+        assert_eq!(optimizer.reset_connections().await, 0);
+        for sidecar in ["optimizer.db-wal", "optimizer.db-shm"] {
+            let _ = std::fs::remove_file(temp_dir.path().join(sidecar));
+        }
+        std::fs::rename(&replacement, temp_dir.path().join("optimizer.db")).unwrap();
+
+        // The connection in use at the reset is closed, not pooled, once released
+        optimizer.release_connection(held).await.unwrap();
+        let connection_id = optimizer.acquire_connection().await.unwrap();
+        assert_ne!(connection_id, held);
+        let result: serde_json::Value = serde_json::from_str(
+            &optimizer
+                .execute_query(connection_id, query, &[])
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result["data"][0]["name"], "new");
+        optimizer.release_connection(connection_id).await.unwrap();
     }
 }
@@ -0,0 +1,240 @@
+// Flame graph rendering from collapsed ("folded") stack samples
+// Produces self-contained SVG so flame graphs can be shown without external tooling
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+#[derive(Debug, Clone)]
+pub struct FlameGraphOptions {
+    pub title: String,
+    pub width: f64,
+    pub frame_height: f64,
+    /// Frames narrower than this many pixels are omitted
+    pub min_frame_width: f64,
+}
+
+impl Default for FlameGraphOptions {
+    fn default() -> Self {
+        Self {
+            title: "Flame Graph".to_string(),
+            width: 1200.0,
+            frame_height: 16.0,
+            min_frame_width: 0.1,
+        }
+    }
+}
+
+const PAD_X: f64 = 10.0;
+const PAD_TOP: f64 = 34.0;
+const PAD_BOTTOM: f64 = 10.0;
+const FONT_SIZE: f64 = 12.0;
+const CHAR_WIDTH: f64 = 7.0;
+
+#[derive(Debug, Default)]
+struct Frame {
+    value: u64,
+    children: BTreeMap<String, Frame>,
+}
+
+/// Parse `frame;frame;frame count` lines as written by flamegraph tooling
+pub fn parse_folded(input: &str) -> HashMap<String, u64> {
+    let mut stacks = HashMap::new();
+
+    for line in input.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((stack, count)) = line.rsplit_once(' ') {
+            if let Ok(count) = count.parse::<u64>() {
+                *stacks.entry(stack.trim_end().to_string()).or_insert(0) += count;
+            }
+        }
+    }
+
+    stacks
+}
+
+/// Render folded stacks as an SVG flame graph, root at the bottom
+pub fn render_svg(stacks: &HashMap<String, u64>, options: &FlameGraphOptions) -> String {
+    let mut root = Frame::default();
+    for (stack, count) in stacks {
+        root.value += count;
+        let mut frame = &mut root;
+        for name in stack.split(';').filter(|name| !name.is_empty()) {
+            frame = frame.children.entry(name.to_string()).or_default();
+            frame.value += count;
+        }
+    }
+
+    let depth = max_depth(&root);
+    let height = PAD_TOP + (depth + 1) as f64 * options.frame_height + PAD_BOTTOM;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="Verdana, sans-serif" font-size="{f}">"#,
+        w = options.width,
+        h = height,
+        f = FONT_SIZE
+    );
+    let _ = writeln!(
+        svg,
+        r##"<rect x="0" y="0" width="100%" height="100%" fill="#f8f8f8"/>"##
+    );
+    let _ = writeln!(
+        svg,
+        r#"<text x="{}" y="24" text-anchor="middle" font-size="17">{}</text>"#,
+        options.width / 2.0,
+        escape_xml(&options.title)
+    );
+
+    if root.value == 0 {
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="middle">No samples</text>"#,
+            options.width / 2.0,
+            height - PAD_BOTTOM - 4.0
+        );
+    } else {
+        let scale = (options.width - 2.0 * PAD_X) / root.value as f64;
+        let layout = Layout {
+            options,
+            scale,
+            total: root.value,
+            height,
+        };
+        layout.draw(&mut svg, "all", &root, 0, 0);
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+struct Layout<'a> {
+    options: &'a FlameGraphOptions,
+    /// Pixels per sample
+    scale: f64,
+    total: u64,
+    height: f64,
+}
+
+impl Layout<'_> {
+    fn draw(&self, svg: &mut String, name: &str, frame: &Frame, offset: u64, depth: usize) {
+        let width = frame.value as f64 * self.scale;
+        if width < self.options.min_frame_width {
+            return;
+        }
+
+        let x = PAD_X + offset as f64 * self.scale;
+        let y = self.height - PAD_BOTTOM - (depth + 1) as f64 * self.options.frame_height;
+        let percentage = frame.value as f64 / self.total as f64 * 100.0;
+
+        let _ = write!(
+            svg,
+            r#"<g><title>{} ({} samples, {:.2}%)</title><rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="{}" rx="2" ry="2"/>"#,
+            escape_xml(name),
+            frame.value,
+            percentage,
+            x,
+            y,
+            width,
+            self.options.frame_height - 1.0,
+            frame_color(name)
+        );
+        if let Some(label) = fit_label(name, width) {
+            let _ = write!(
+                svg,
+                r#"<text x="{:.2}" y="{:.2}">{}</text>"#,
+                x + 3.0,
+                y + self.options.frame_height - 4.5,
+                escape_xml(&label)
+            );
+        }
+        svg.push_str("</g>\n");
+
+        let mut child_offset = offset;
+        for (child_name, child) in &frame.children {
+            self.draw(svg, child_name, child, child_offset, depth + 1);
+            child_offset += child.value;
+        }
+    }
+}
+
+fn max_depth(frame: &Frame) -> usize {
+    frame
+        .children
+        .values()
+        .map(|child| max_depth(child) + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+/// The frame name, shortened with `..` to fit, or `None` if no useful text fits
+fn fit_label(name: &str, width: f64) -> Option<String> {
+    let fits = ((width - 6.0) / CHAR_WIDTH) as usize;
+    let length = name.chars().count();
+
+    if length <= fits {
+        Some(name.to_string())
+    } else if fits >= 3 {
+        Some(format!(
+            "{}..",
+            name.chars().take(fits - 2).collect::<String>()
+        ))
+    } else {
+        None
+    }
+}
+
+/// Warm palette keyed by a hash of the name, so a frame keeps its color across renders
+fn frame_color(name: &str) -> String {
+    // FNV-1a
+    let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+
+    format!(
+        "rgb({},{},{})",
+        205 + hash % 50,
+        (hash >> 8) % 230,
+        (hash >> 16) % 55
+    )
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_render_folded_stacks() {
+        let stacks = parse_folded(
+            "main;handle_request;query 30\n\
+             main;handle_request 10\n\
+             main;render<T> 60\n\
+             malformed line\n",
+        );
+        assert_eq!(stacks.len(), 3);
+        assert_eq!(stacks["main;render<T>"], 60);
+
+        let svg = render_svg(&stacks, &FlameGraphOptions::default());
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains("<title>all (100 samples, 100.00%)</title>"));
+        assert!(svg.contains("<title>handle_request (40 samples, 40.00%)</title>"));
+        assert!(svg.contains("<title>render&lt;T&gt; (60 samples, 60.00%)</title>"));
+    }
+
+    #[test]
+    fn test_render_without_samples() {
+        let svg = render_svg(&HashMap::new(), &FlameGraphOptions::default());
+        assert!(svg.contains("No samples"));
+    }
+}
@@ -4,6 +4,7 @@
 pub mod cache;
 pub mod concurrency;
 pub mod database;
+pub mod flame_graph;
 pub mod memory;
 pub mod metrics;
 pub mod monitoring;
@@ -1,19 +1,34 @@
 // Network optimization for request handling, compression, and bandwidth management
 // Implements advanced networking strategies for high-performance applications
 
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use reqwest::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, RETRY_AFTER};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::{Read, Write};
 use std::sync::Arc;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::{Mutex, RwLock, Semaphore};
 use tokio::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
+/// Idle pooled connections are closed after this long
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkConfig {
     pub compression_enabled: bool,
     pub compression_threshold_bytes: usize,
+    /// Hosts, optionally as `host:port`, known to accept gzip request bodies; other
+    /// hosts only get one when the request asks for it, since many servers reject them
+    #[serde(default)]
+    pub gzip_request_hosts: Vec<String>,
     pub max_concurrent_requests: usize,
+    /// Requests to one host beyond this wait for a free connection
+    #[serde(default = "default_max_connections_per_host")]
+    pub max_connections_per_host: usize,
     pub request_timeout_seconds: u64,
     pub keep_alive_enabled: bool,
     pub connection_pooling: bool,
@@ -34,7 +49,9 @@ impl Default for NetworkConfig {
         Self {
             compression_enabled: true,
             compression_threshold_bytes: 1024,
+            gzip_request_hosts: Vec::new(),
             max_concurrent_requests: 200,
+            max_connections_per_host: default_max_connections_per_host(),
             request_timeout_seconds: 30,
             keep_alive_enabled: true,
             connection_pooling: true,
@@ -49,9 +66,14 @@ impl Default for NetworkConfig {
     }
 }
 
+fn default_max_connections_per_host() -> usize {
+    16
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkMetrics {
     pub timestamp: i64,
+    /// Requests currently in flight
     pub active_connections: u32,
     pub total_requests: u64,
     pub successful_requests: u64,
@@ -61,7 +83,6 @@ pub struct NetworkMetrics {
     pub compression_ratio: f64,
     pub bytes_sent: u64,
     pub bytes_received: u64,
-    pub connection_pool_efficiency: f64,
     pub retry_rate_percent: f64,
 }
 
@@ -72,6 +93,11 @@ pub struct RequestMetrics {
     pub method: String,
     pub started_at: Instant,
     pub completed_at: Option<Instant>,
+    pub status_code: Option<u16>,
+    /// Request body before and after compression
+    pub request_size_bytes: usize,
+    pub bytes_sent: u64,
+    /// Response body after decoding, and as received on the wire
     pub response_size_bytes: Option<usize>,
     pub compressed_size_bytes: Option<usize>,
     pub retry_count: u32,
@@ -79,24 +105,23 @@ pub struct RequestMetrics {
     pub error_message: Option<String>,
 }
 
+/// Per-request settings
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    /// Gzip the body even if the host is not in `gzip_request_hosts`
+    pub gzip_body: bool,
+}
+
+/// Sends requests through one pooled `reqwest` client, which owns the connections
 pub struct NetworkOptimizer {
     config: NetworkConfig,
-    active_requests: Arc<RwLock<HashMap<Uuid, RequestMetrics>>>,
+    client: reqwest::Client,
+    active_requests: Arc<parking_lot::RwLock<HashMap<Uuid, RequestMetrics>>>,
     request_history: Arc<RwLock<Vec<RequestMetrics>>>,
     metrics: Arc<Mutex<NetworkMetrics>>,
-    connection_pool: Arc<RwLock<HashMap<String, Vec<Connection>>>>,
+    host_limits: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
     bandwidth_tracker: Arc<Mutex<BandwidthTracker>>,
-}
-
-#[derive(Debug, Clone)]
-struct Connection {
-    id: Uuid,
-    host: String,
-    port: u16,
-    created_at: Instant,
-    last_used: Instant,
-    is_active: bool,
-    request_count: u64,
+    traffic_totals: Arc<Mutex<TrafficTotals>>,
 }
 
 #[derive(Debug)]
@@ -105,8 +130,35 @@ struct BandwidthTracker {
     current_bytes_per_second: f64,
 }
 
+/// Running totals behind the ratio metrics
+#[derive(Debug, Default)]
+struct TrafficTotals {
+    payload_bytes: u64,
+    wire_bytes: u64,
+    retried_requests: u64,
+}
+
+/// Slot in `active_requests`, released when the request finishes, fails or is dropped
+struct ActiveRequest {
+    requests: Arc<parking_lot::RwLock<HashMap<Uuid, RequestMetrics>>>,
+    id: Uuid,
+}
+
+impl Drop for ActiveRequest {
+    fn drop(&mut self) {
+        self.requests.write().remove(&self.id);
+    }
+}
+
+/// Request body as it goes on the wire
+#[derive(Debug)]
+struct EncodedBody {
+    bytes: Vec<u8>,
+    gzipped: bool,
+}
+
 impl NetworkOptimizer {
-    pub fn new(config: NetworkConfig) -> Self {
+    pub fn new(config: NetworkConfig) -> anyhow::Result<Self> {
         let metrics = NetworkMetrics {
             timestamp: chrono::Utc::now().timestamp(),
             active_connections: 0,
@@ -118,26 +170,43 @@ impl NetworkOptimizer {
             compression_ratio: 1.0,
             bytes_sent: 0,
             bytes_received: 0,
-            connection_pool_efficiency: 100.0,
             retry_rate_percent: 0.0,
         };
 
+        // Responses are decoded here rather than by reqwest so wire sizes can be measured
+        let reuse_connections = config.connection_pooling && config.keep_alive_enabled;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.request_timeout_seconds))
+            .pool_max_idle_per_host(if reuse_connections {
+                config.max_connections_per_host
+            } else {
+                0
+            })
+            .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+            .tcp_keepalive(config.keep_alive_enabled.then(|| Duration::from_secs(60)))
+            .no_gzip()
+            .no_brotli()
+            .no_deflate()
+            .build()?;
+
         info!(
             "Network optimizer initialized with compression: {}, pooling: {}",
             config.compression_enabled, config.connection_pooling
         );
 
-        Self {
+        Ok(Self {
             config,
-            active_requests: Arc::new(RwLock::new(HashMap::new())),
+            client,
+            active_requests: Arc::new(parking_lot::RwLock::new(HashMap::new())),
             request_history: Arc::new(RwLock::new(Vec::new())),
             metrics: Arc::new(Mutex::new(metrics)),
-            connection_pool: Arc::new(RwLock::new(HashMap::new())),
+            host_limits: Arc::new(Mutex::new(HashMap::new())),
             bandwidth_tracker: Arc::new(Mutex::new(BandwidthTracker {
                 bytes_per_second_history: Vec::new(),
                 current_bytes_per_second: 0.0,
             })),
-        }
+            traffic_totals: Arc::new(Mutex::new(TrafficTotals::default())),
+        })
     }
 
     pub async fn execute_request(
@@ -146,17 +215,48 @@ impl NetworkOptimizer {
         url: &str,
         body: Option<&[u8]>,
         headers: Option<&HashMap<String, String>>,
+    ) -> anyhow::Result<NetworkResponse> {
+        self.execute_request_with_options(method, url, body, headers, &RequestOptions::default())
+            .await
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn execute_request_with_options(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&[u8]>,
+        headers: Option<&HashMap<String, String>>,
+        options: &RequestOptions,
     ) -> anyhow::Result<NetworkResponse> {
         let request_id = Uuid::new_v4();
         let start_time = Instant::now();
-
-        // Check concurrent request limit
-        {
-            let active_requests = self.active_requests.read().await;
-            if active_requests.len() >= self.config.max_concurrent_requests {
-                return Err(anyhow::anyhow!("Maximum concurrent requests exceeded"));
-            }
-        }
+        let method = reqwest::Method::from_bytes(method.to_uppercase().as_bytes())?;
+
+        // Reject what cannot be sent before the request takes a slot
+        let host = Self::extract_host(url)?;
+
+        // Compress the body where the host or request opted in, unless the caller already encoded it
+        let caller_encoded = headers.is_some_and(|headers| {
+            headers
+                .keys()
+                .any(|name| name.eq_ignore_ascii_case(CONTENT_ENCODING.as_str()))
+        });
+        let host_name = host
+            .rsplit_once(':')
+            .map_or(host.as_str(), |(name, _)| name);
+        let gzip_body = options.gzip_body
+            || self.config.gzip_request_hosts.iter().any(|gzip_host| {
+                gzip_host.eq_ignore_ascii_case(&host) || gzip_host.eq_ignore_ascii_case(host_name)
+            });
+        let encoded_body = match body {
+            Some(body_data) if gzip_body && !caller_encoded => Some(self.compress_data(body_data)?),
+            Some(body_data) => Some(EncodedBody {
+                bytes: body_data.to_vec(),
+                gzipped: false,
+            }),
+            None => None,
+        };
 
         // Create request metrics
         let mut request_metrics = RequestMetrics {
@@ -165,6 +265,9 @@ impl NetworkOptimizer {
             method: method.to_string(),
             started_at: start_time,
             completed_at: None,
+            status_code: None,
+            request_size_bytes: body.map_or(0, |b| b.len()),
+            bytes_sent: 0,
             response_size_bytes: None,
             compressed_size_bytes: None,
             retry_count: 0,
@@ -172,78 +275,96 @@ impl NetworkOptimizer {
             error_message: None,
         };
 
-        // Add to active requests
-        {
-            let mut active_requests = self.active_requests.write().await;
+        // Check the concurrent request limit and take a slot; the guard frees it on every exit
+        let active_request = {
+            let mut active_requests = self.active_requests.write();
+            if active_requests.len() >= self.config.max_concurrent_requests {
+                return Err(anyhow::anyhow!("Maximum concurrent requests exceeded"));
+            }
             active_requests.insert(request_id, request_metrics.clone());
-        }
-
-        // Get or create connection
-        let connection = self.get_connection(url).await?;
-
-        // Compress body if applicable
-        let (final_body, compression_ratio) = if let Some(body_data) = body {
-            self.compress_data(body_data).await?
-        } else {
-            (None, 1.0)
+            ActiveRequest {
+                requests: self.active_requests.clone(),
+                id: request_id,
+            }
         };
 
+        // Wait while the host is at its connection limit
+        let _host_permit = self.host_semaphore(&host).await.acquire_owned().await?;
+
         // Execute request with retry logic
+        let max_retries = self.config.retry_configuration.max_retries;
         let mut response = None;
         let mut last_error = None;
 
-        for attempt in 0..=self.config.retry_configuration.max_retries {
+        for attempt in 0..=max_retries {
             request_metrics.retry_count = attempt;
 
-            match self
-                .execute_single_request(
-                    &connection,
-                    method,
-                    url,
-                    final_body.as_deref(),
-                    headers,
-                    compression_ratio,
-                )
+            let delay = match self
+                .execute_single_request(&method, url, encoded_body.as_ref(), headers)
                 .await
             {
+                Ok(resp)
+                    if attempt < max_retries
+                        && Self::is_retryable_status(&method, resp.status_code) =>
+                {
+                    let delay = Self::retry_after(&resp.headers)
+                        .map(|delay| {
+                            delay.min(Duration::from_millis(
+                                self.config.retry_configuration.max_delay_ms,
+                            ))
+                        })
+                        .unwrap_or_else(|| self.calculate_retry_delay(attempt));
+                    last_error = Some(anyhow::anyhow!("HTTP {}", resp.status_code));
+                    delay
+                }
                 Ok(resp) => {
                     response = Some(resp);
-                    request_metrics.success = true;
                     break;
                 }
                 Err(e) => {
+                    let retryable = Self::is_retryable_error(&method, &e);
                     last_error = Some(e);
-                    if attempt < self.config.retry_configuration.max_retries {
-                        let delay = self.calculate_retry_delay(attempt);
-                        debug!(
-                            "Request failed, retrying in {:?} (attempt {})",
-                            delay,
-                            attempt + 1
-                        );
-                        tokio::time::sleep(delay).await;
+                    if !retryable || attempt == max_retries {
+                        break;
                     }
+                    self.calculate_retry_delay(attempt)
                 }
-            }
+            };
+
+            debug!(
+                "Request to {} failed, retrying in {:?} (attempt {})",
+                url,
+                delay,
+                attempt + 1
+            );
+            tokio::time::sleep(delay).await;
         }
 
         // Complete request metrics
         request_metrics.completed_at = Some(Instant::now());
 
-        if let Some(error) = &last_error {
-            request_metrics.error_message = Some(error.to_string());
+        match &response {
+            Some(resp) => {
+                request_metrics.status_code = Some(resp.status_code);
+                request_metrics.bytes_sent = resp.bytes_sent;
+                request_metrics.response_size_bytes = Some(resp.body.len());
+                request_metrics.compressed_size_bytes = Some(resp.bytes_received as usize);
+                request_metrics.success = resp.status_code < 400;
+                if !request_metrics.success {
+                    request_metrics.error_message = Some(format!("HTTP {}", resp.status_code));
+                }
+            }
+            None => {
+                request_metrics.error_message = last_error.as_ref().map(|e| e.to_string());
+            }
         }
 
         // Update metrics
         self.update_request_metrics(&request_metrics).await;
 
-        // Release connection
-        self.release_connection(connection).await?;
-
         // Remove from active requests and add to history
+        drop(active_request);
         {
-            let mut active_requests = self.active_requests.write().await;
-            active_requests.remove(&request_id);
-
             let mut history = self.request_history.write().await;
             history.push(request_metrics);
 
@@ -262,190 +383,176 @@ impl NetworkOptimizer {
         }
     }
 
-    async fn get_connection(&self, url: &str) -> anyhow::Result<Connection> {
-        if !self.config.connection_pooling {
-            return Ok(self.create_new_connection(url)?);
-        }
-
-        let host = self.extract_host(url)?;
-        let port = self.extract_port(url)?;
-        let pool_key = format!("{}:{}", host, port);
-
-        let mut pool = self.connection_pool.write().await;
-        let host_pool = pool.entry(pool_key.clone()).or_insert_with(Vec::new);
-
-        // Look for available connection
-        for connection in host_pool.iter_mut() {
-            if !connection.is_active {
-                connection.is_active = true;
-                connection.last_used = Instant::now();
-                debug!("Reusing connection {} for {}", connection.id, pool_key);
-                return Ok(connection.clone());
-            }
-        }
-
-        // Create new connection
-        let new_connection = Connection {
-            id: Uuid::new_v4(),
-            host: host.to_string(),
-            port,
-            created_at: Instant::now(),
-            last_used: Instant::now(),
-            is_active: true,
-            request_count: 0,
-        };
-
-        host_pool.push(new_connection.clone());
-        info!(
-            "Created new connection {} for {}",
-            new_connection.id, pool_key
-        );
-        Ok(new_connection)
+    /// Statuses worth retrying; only idempotent methods are retried after the server saw them
+    fn is_retryable_status(method: &reqwest::Method, status_code: u16) -> bool {
+        method.is_idempotent() && matches!(status_code, 408 | 429 | 500 | 502 | 503 | 504)
     }
 
-    async fn release_connection(&self, mut connection: Connection) -> anyhow::Result<()> {
-        if !self.config.connection_pooling {
-            return Ok(());
+    /// Timeouts and dropped connections are retried for idempotent methods; other
+    /// methods are only retried when the connection was never established
+    fn is_retryable_error(method: &reqwest::Method, error: &anyhow::Error) -> bool {
+        match error.downcast_ref::<reqwest::Error>() {
+            Some(e) if e.is_connect() => true,
+            Some(e) => method.is_idempotent() && (e.is_timeout() || e.is_request()),
+            None => false,
         }
-
-        connection.is_active = false;
-        connection.last_used = Instant::now();
-        connection.request_count += 1;
-
-        let pool_key = format!("{}:{}", connection.host, connection.port);
-        let mut pool = self.connection_pool.write().await;
-
-        let connection_id = connection.id.clone();
-        if let Some(host_pool) = pool.get_mut(&pool_key) {
-            for conn in host_pool.iter_mut() {
-                if conn.id == connection.id {
-                    *conn = connection;
-                    break;
-                }
-            }
-        }
-
-        debug!("Released connection {}", connection_id);
-        Ok(())
     }
 
-    fn create_new_connection(&self, url: &str) -> anyhow::Result<Connection> {
-        let host = self.extract_host(url)?;
-        let port = self.extract_port(url)?;
-
-        Ok(Connection {
-            id: Uuid::new_v4(),
-            host: host.to_string(),
-            port,
-            created_at: Instant::now(),
-            last_used: Instant::now(),
-            is_active: true,
-            request_count: 0,
-        })
+    /// Delay requested by a `Retry-After: <seconds>` header
+    fn retry_after(headers: &HashMap<String, String>) -> Option<Duration> {
+        headers
+            .get(RETRY_AFTER.as_str())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
     }
 
-    fn extract_host(&self, url: &str) -> anyhow::Result<String> {
-        let parsed_url = url::Url::parse(url)?;
-        parsed_url
-            .host_str()
-            .map(|s| s.to_string())
-            .ok_or_else(|| anyhow::anyhow!("Invalid URL: no host"))
+    /// Connection limiter for a host and port, created on first use
+    async fn host_semaphore(&self, host: &str) -> Arc<Semaphore> {
+        let mut host_limits = self.host_limits.lock().await;
+        host_limits
+            .entry(host.to_string())
+            .or_insert_with(|| {
+                Arc::new(Semaphore::new(self.config.max_connections_per_host.max(1)))
+            })
+            .clone()
     }
 
-    fn extract_port(&self, url: &str) -> anyhow::Result<u16> {
+    /// `host:port` of a URL, with the scheme's default port
+    fn extract_host(url: &str) -> anyhow::Result<String> {
         let parsed_url = url::Url::parse(url)?;
-        Ok(parsed_url
-            .port()
-            .unwrap_or_else(|| match parsed_url.scheme() {
-                "https" => 443,
-                "http" => 80,
-                _ => 80,
-            }))
+        let host = parsed_url
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("Invalid URL: no host"))?;
+        let port = parsed_url.port_or_known_default().unwrap_or(80);
+        Ok(format!("{}:{}", host, port))
     }
 
-    async fn compress_data(&self, data: &[u8]) -> anyhow::Result<(Option<Vec<u8>>, f64)> {
+    /// Gzip a request body when it is large enough and compression actually helps
+    fn compress_data(&self, data: &[u8]) -> anyhow::Result<EncodedBody> {
         if !self.config.compression_enabled || data.len() < self.config.compression_threshold_bytes
         {
-            return Ok((Some(data.to_vec()), 1.0));
+            return Ok(EncodedBody {
+                bytes: data.to_vec(),
+                gzipped: false,
+            });
         }
 
-        // Simple compression simulation
-        let compressed_size = (data.len() as f64 * 0.7) as usize; // 30% compression
-        let compressed_data = vec![0u8; compressed_size];
-        let compression_ratio = data.len() as f64 / compressed_size as f64;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
 
         debug!(
             "Compressed {} bytes to {} bytes (ratio: {:.2})",
             data.len(),
-            compressed_size,
-            compression_ratio
+            compressed.len(),
+            data.len() as f64 / compressed.len().max(1) as f64
         );
 
-        Ok((Some(compressed_data), compression_ratio))
+        if compressed.len() >= data.len() {
+            return Ok(EncodedBody {
+                bytes: data.to_vec(),
+                gzipped: false,
+            });
+        }
+
+        Ok(EncodedBody {
+            bytes: compressed,
+            gzipped: true,
+        })
+    }
+
+    /// Decode a response body according to its `Content-Encoding`
+    fn decode_body(content_encoding: Option<&str>, body: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        match content_encoding.map(|encoding| encoding.trim().to_ascii_lowercase()) {
+            None => return Ok(body.to_vec()),
+            Some(encoding) if encoding.is_empty() || encoding == "identity" => {
+                return Ok(body.to_vec())
+            }
+            Some(encoding) if encoding == "gzip" || encoding == "x-gzip" => {
+                GzDecoder::new(body).read_to_end(&mut decoded)?;
+            }
+            Some(encoding) if encoding == "deflate" => {
+                ZlibDecoder::new(body).read_to_end(&mut decoded)?;
+            }
+            Some(encoding) => {
+                return Err(anyhow::anyhow!(
+                    "Unsupported response encoding: {}",
+                    encoding
+                ))
+            }
+        }
+        Ok(decoded)
     }
 
     async fn execute_single_request(
         &self,
-        connection: &Connection,
-        method: &str,
+        method: &reqwest::Method,
         url: &str,
-        body: Option<&[u8]>,
-        _headers: Option<&HashMap<String, String>>,
-        compression_ratio: f64,
+        body: Option<&EncodedBody>,
+        headers: Option<&HashMap<String, String>>,
     ) -> anyhow::Result<NetworkResponse> {
-        // Simulate network request execution
-        let execution_time = self.simulate_network_request(method, url).await?;
+        let bytes_sent = body.map_or(0, |b| b.bytes.len());
 
         // Check bandwidth limit
         if let Some(limit_mbps) = self.config.bandwidth_limit_mbps {
-            self.enforce_bandwidth_limit(limit_mbps, body.map_or(0, |b| b.len()))
-                .await?;
+            self.enforce_bandwidth_limit(limit_mbps, bytes_sent).await?;
         }
 
-        // Simulate response
-        let response_body = b"{'status': 'success', 'data': 'mock response'}";
-        let compressed_response = if compression_ratio > 1.0 {
-            (response_body.len() as f64 / compression_ratio) as usize
-        } else {
-            response_body.len()
-        };
+        let mut request = self
+            .client
+            .request(method.clone(), url)
+            .header(ACCEPT_ENCODING, "gzip, deflate");
+        if let Some(headers) = headers {
+            for (name, value) in headers {
+                request = request.header(name.as_str(), value.as_str());
+            }
+        }
+        if let Some(body) = body {
+            if body.gzipped {
+                request = request.header(CONTENT_ENCODING, "gzip");
+            }
+            request = request.body(body.bytes.clone());
+        }
+
+        let started_at = Instant::now();
+        let response = request.send().await?;
+        let status_code = response.status().as_u16();
+        let mut response_headers: HashMap<String, String> = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|value| (name.as_str().to_string(), value.to_string()))
+            })
+            .collect();
+        let wire_body = response.bytes().await?;
+        let response_time = started_at.elapsed();
+
+        // The decoded body no longer matches the encoding headers
+        let content_encoding = response_headers.remove(CONTENT_ENCODING.as_str());
+        let response_body = Self::decode_body(content_encoding.as_deref(), &wire_body)?;
+        if content_encoding.is_some() {
+            response_headers.remove(CONTENT_LENGTH.as_str());
+        }
 
         // Update bandwidth tracking
-        self.update_bandwidth_tracking(body.map_or(0, |b| b.len()) + response_body.len())
+        self.update_bandwidth_tracking(bytes_sent + wire_body.len())
             .await;
 
         Ok(NetworkResponse {
-            status_code: 200,
-            headers: HashMap::new(),
-            body: response_body.to_vec(),
-            response_time: execution_time,
-            compression_ratio,
-            connection_id: connection.id,
-            bytes_sent: body.map_or(0, |b| b.len()) as u64,
-            bytes_received: compressed_response as u64,
+            status_code,
+            headers: response_headers,
+            compression_ratio: response_body.len().max(1) as f64 / wire_body.len().max(1) as f64,
+            body: response_body,
+            response_time,
+            bytes_sent: bytes_sent as u64,
+            bytes_received: wire_body.len() as u64,
         })
     }
 
-    async fn simulate_network_request(&self, method: &str, _url: &str) -> anyhow::Result<Duration> {
-        // Simulate different request types
-        let base_time = match method {
-            "GET" => Duration::from_millis(100),
-            "POST" => Duration::from_millis(150),
-            "PUT" => Duration::from_millis(200),
-            "DELETE" => Duration::from_millis(120),
-            _ => Duration::from_millis(100),
-        };
-
-        // Add network latency simulation
-        let latency = Duration::from_millis(rand::random::<u64>() % 50 + 10);
-        let total_time = base_time + latency;
-
-        // Simulate actual network delay
-        tokio::time::sleep(Duration::from_millis(1)).await;
-
-        Ok(total_time)
-    }
-
     fn calculate_retry_delay(&self, attempt: u32) -> Duration {
         let base_delay = self.config.retry_configuration.initial_delay_ms as f64;
         let multiplier = self.config.retry_configuration.backoff_multiplier;
@@ -509,7 +616,8 @@ impl NetworkOptimizer {
         if let Some(completed_at) = request_metrics.completed_at {
             let response_time = completed_at
                 .duration_since(request_metrics.started_at)
-                .as_millis() as f64;
+                .as_secs_f64()
+                * 1000.0;
 
             // Simple moving average
             let total_requests = metrics.total_requests as f64;
@@ -523,40 +631,34 @@ impl NetworkOptimizer {
         metrics.bandwidth_utilization_mbps =
             (tracker.current_bytes_per_second * 8.0) / (1024.0 * 1024.0);
 
-        // Update retry rate
+        // Update traffic, compression and retry totals
+        let mut totals = self.traffic_totals.lock().await;
+        let bytes_received = request_metrics.compressed_size_bytes.unwrap_or(0) as u64;
+        metrics.bytes_sent += request_metrics.bytes_sent;
+        metrics.bytes_received += bytes_received;
+
+        if request_metrics.status_code.is_some() {
+            totals.payload_bytes += (request_metrics.request_size_bytes
+                + request_metrics.response_size_bytes.unwrap_or(0))
+                as u64;
+            totals.wire_bytes += request_metrics.bytes_sent + bytes_received;
+        }
+        if totals.wire_bytes > 0 {
+            metrics.compression_ratio = totals.payload_bytes as f64 / totals.wire_bytes as f64;
+        }
+
         if request_metrics.retry_count > 0 {
-            let retry_requests =
-                (metrics.total_requests as f64 * metrics.retry_rate_percent / 100.0) + 1.0;
-            metrics.retry_rate_percent = (retry_requests / metrics.total_requests as f64) * 100.0;
+            totals.retried_requests += 1;
         }
+        metrics.retry_rate_percent =
+            (totals.retried_requests as f64 / metrics.total_requests as f64) * 100.0;
 
         metrics.timestamp = chrono::Utc::now().timestamp();
     }
 
     pub async fn get_metrics(&self) -> NetworkMetrics {
         let mut metrics = self.metrics.lock().await;
-
-        // Update active connections
-        let pool = self.connection_pool.read().await;
-        let active_connections: u32 = pool
-            .values()
-            .flat_map(|connections| connections.iter())
-            .filter(|conn| conn.is_active)
-            .count() as u32;
-
-        metrics.active_connections = active_connections;
-
-        // Calculate connection pool efficiency
-        let total_connections: u32 = pool
-            .values()
-            .flat_map(|connections| connections.iter())
-            .count() as u32;
-
-        if total_connections > 0 {
-            metrics.connection_pool_efficiency =
-                (active_connections as f64 / total_connections as f64) * 100.0;
-        }
-
+        metrics.active_connections = self.active_requests.read().len() as u32;
         metrics.clone()
     }
 
@@ -618,7 +720,9 @@ impl NetworkOptimizer {
                 5.0
             },
             reliability_improvement: if failure_rate > 3.0 { 15.0 } else { 5.0 },
-            connection_efficiency_improvement: if metrics.connection_pool_efficiency < 70.0 {
+            connection_efficiency_improvement: if !self.config.connection_pooling
+                || !self.config.keep_alive_enabled
+            {
                 30.0
             } else {
                 10.0
@@ -627,33 +731,6 @@ impl NetworkOptimizer {
 
         Ok(report)
     }
-
-    pub async fn cleanup_connections(&self) -> anyhow::Result<usize> {
-        let mut pool = self.connection_pool.write().await;
-        let mut cleaned_count = 0;
-
-        for (host, connections) in pool.iter_mut() {
-            let initial_count = connections.len();
-            let cutoff = Instant::now() - Duration::from_secs(300); // 5 minutes
-
-            connections.retain(|conn| {
-                if !conn.is_active && conn.last_used < cutoff {
-                    debug!("Cleaning up inactive connection {} for {}", conn.id, host);
-                    false
-                } else {
-                    true
-                }
-            });
-
-            cleaned_count += initial_count - connections.len();
-        }
-
-        // Remove empty host pools
-        pool.retain(|_, connections| !connections.is_empty());
-
-        info!("Cleaned up {} inactive network connections", cleaned_count);
-        Ok(cleaned_count)
-    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -662,8 +739,8 @@ pub struct NetworkResponse {
     pub headers: HashMap<String, String>,
     pub body: Vec<u8>,
     pub response_time: Duration,
+    /// Decoded response size over the bytes received on the wire
     pub compression_ratio: f64,
-    pub connection_id: Uuid,
     pub bytes_sent: u64,
     pub bytes_received: u64,
 }
@@ -701,7 +778,7 @@ lazy_static::lazy_static! {
 }
 
 pub async fn initialize_network_optimizer(config: NetworkConfig) -> anyhow::Result<()> {
-    let optimizer = NetworkOptimizer::new(config);
+    let optimizer = NetworkOptimizer::new(config)?;
     let mut global_optimizer = NETWORK_OPTIMIZER.write().await;
     *global_optimizer = Some(optimizer);
     info!("Global network optimizer initialized");
@@ -735,35 +812,255 @@ pub async fn get_network_metrics() -> Option<NetworkMetrics> {
 mod tests {
     use super::*;
     use tokio::test;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn fast_retry_config() -> NetworkConfig {
+        NetworkConfig {
+            retry_configuration: RetryConfig {
+                max_retries: 2,
+                initial_delay_ms: 10,
+                backoff_multiplier: 2.0,
+                max_delay_ms: 50,
+            },
+            ..NetworkConfig::default()
+        }
+    }
 
     #[test]
     async fn test_network_optimizer() {
-        let config = NetworkConfig::default();
-        let optimizer = NetworkOptimizer::new(config);
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/test"))
+            .and(header("content-encoding", "gzip"))
+            .respond_with(ResponseTemplate::new(201).set_body_string("created"))
+            .mount(&server)
+            .await;
 
+        let optimizer = NetworkOptimizer::new(NetworkConfig::default()).unwrap();
+        let payload = "neural bridge ".repeat(200);
         let response = optimizer
-            .execute_request("GET", "https://api.example.com/test", None, None)
+            .execute_request_with_options(
+                "POST",
+                &format!("{}/test", server.uri()),
+                Some(payload.as_bytes()),
+                None,
+                &RequestOptions { gzip_body: true },
+            )
             .await
             .unwrap();
 
+        assert_eq!(response.status_code, 201);
+        assert_eq!(response.body, b"created");
+        assert!(response.bytes_sent < payload.len() as u64);
+
+        let metrics = optimizer.get_metrics().await;
+        assert_eq!(metrics.successful_requests, 1);
+        assert_eq!(metrics.bytes_sent, response.bytes_sent);
+        assert!(metrics.compression_ratio > 1.0);
+        assert!(metrics.average_response_time_ms > 0.0);
+    }
+
+    #[test]
+    async fn test_request_gzip_is_opt_in() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("content-encoding", "gzip"))
+            .respond_with(ResponseTemplate::new(201))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        let payload = "neural bridge ".repeat(200);
+
+        // Plain by default
+        let optimizer = NetworkOptimizer::new(NetworkConfig::default()).unwrap();
+        let response = optimizer
+            .execute_request("POST", &server.uri(), Some(payload.as_bytes()), None)
+            .await
+            .unwrap();
         assert_eq!(response.status_code, 200);
-        assert!(!response.body.is_empty());
+        assert_eq!(response.bytes_sent, payload.len() as u64);
+
+        // Gzipped for hosts that opted in
+        let host = url::Url::parse(&server.uri()).unwrap();
+        let optimizer = NetworkOptimizer::new(NetworkConfig {
+            gzip_request_hosts: vec![format!(
+                "{}:{}",
+                host.host_str().unwrap(),
+                host.port().unwrap()
+            )],
+            ..NetworkConfig::default()
+        })
+        .unwrap();
+        let response = optimizer
+            .execute_request("POST", &server.uri(), Some(payload.as_bytes()), None)
+            .await
+            .unwrap();
+        assert_eq!(response.status_code, 201);
+    }
+
+    #[test]
+    async fn test_gzip_response_is_decoded() {
+        let server = MockServer::start().await;
+        let body = "x".repeat(4096);
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body.as_bytes()).unwrap();
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-encoding", "gzip")
+                    .set_body_bytes(encoder.finish().unwrap()),
+            )
+            .mount(&server)
+            .await;
+
+        let optimizer = NetworkOptimizer::new(NetworkConfig::default()).unwrap();
+        let response = optimizer
+            .execute_request("GET", &server.uri(), None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(response.body, body.as_bytes());
+        assert!(response.bytes_received < body.len() as u64);
+        assert!(response.compression_ratio > 10.0);
+        assert!(!response.headers.contains_key("content-encoding"));
+    }
+
+    #[test]
+    async fn test_retries_server_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .mount(&server)
+            .await;
+
+        let optimizer = NetworkOptimizer::new(fast_retry_config()).unwrap();
+        let response = optimizer
+            .execute_request("GET", &server.uri(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(response.status_code, 200);
+
+        // POST is not idempotent, so a server error is returned rather than retried
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let response = optimizer
+            .execute_request("POST", &server.uri(), Some(b"{}"), None)
+            .await
+            .unwrap();
+        assert_eq!(response.status_code, 500);
+
+        let metrics = optimizer.get_metrics().await;
+        assert_eq!(metrics.total_requests, 2);
+        assert_eq!(metrics.failed_requests, 1);
+        assert_eq!(metrics.retry_rate_percent, 50.0);
     }
 
     #[test]
     async fn test_connection_pooling() {
-        let config = NetworkConfig::default();
-        let optimizer = NetworkOptimizer::new(config);
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(100)))
+            .mount(&server)
+            .await;
+
+        let optimizer = Arc::new(
+            NetworkOptimizer::new(NetworkConfig {
+                max_connections_per_host: 1,
+                ..NetworkConfig::default()
+            })
+            .unwrap(),
+        );
+
+        // One connection per host serializes concurrent requests
+        let started = Instant::now();
+        let requests: Vec<_> = (0..3)
+            .map(|_| {
+                let optimizer = optimizer.clone();
+                let url = server.uri();
+                tokio::spawn(
+                    async move { optimizer.execute_request("GET", &url, None, None).await },
+                )
+            })
+            .collect();
+        for request in requests {
+            request.await.unwrap().unwrap();
+        }
+        assert!(started.elapsed() >= Duration::from_millis(300));
+
+        let metrics = optimizer.get_metrics().await;
+        assert_eq!(metrics.total_requests, 3);
+        assert_eq!(metrics.active_connections, 0);
+    }
+
+    #[test]
+    async fn test_failed_and_dropped_requests_release_their_slot() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/slow"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/ok"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .mount(&server)
+            .await;
+
+        let optimizer = Arc::new(
+            NetworkOptimizer::new(NetworkConfig {
+                max_concurrent_requests: 2,
+                ..fast_retry_config()
+            })
+            .unwrap(),
+        );
+
+        for url in [
+            "not a url",
+            "file:///etc/hosts",
+            "http://",
+            "data:text/plain,hi",
+        ] {
+            assert!(optimizer
+                .execute_request("GET", url, None, None)
+                .await
+                .is_err());
+        }
+
+        // A caller that gives up mid-request frees its slot as well
+        let slow_url = format!("{}/slow", server.uri());
+        let slow = {
+            let optimizer = optimizer.clone();
+            tokio::spawn(async move {
+                optimizer
+                    .execute_request("GET", &slow_url, None, None)
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(optimizer.get_metrics().await.active_connections, 1);
+        slow.abort();
+        let _ = slow.await;
+        assert_eq!(optimizer.get_metrics().await.active_connections, 0);
 
-        // Execute multiple requests to the same host
         for _ in 0..3 {
-            let _response = optimizer
-                .execute_request("GET", "https://api.example.com/test", None, None)
+            let response = optimizer
+                .execute_request("GET", &format!("{}/ok", server.uri()), None, None)
                 .await
                 .unwrap();
+            assert_eq!(response.body, b"ok");
         }
-
-        let metrics = optimizer.get_metrics().await;
-        assert!(metrics.total_requests >= 3);
+        assert_eq!(optimizer.get_metrics().await.active_connections, 0);
     }
 }
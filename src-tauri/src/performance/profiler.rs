@@ -1,12 +1,18 @@
 // Advanced performance profiler for real-time bottleneck detection and optimization suggestions
 // Implements comprehensive profiling capabilities for Rust applications
 
+use super::flame_graph::{self, FlameGraphOptions};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::thread::ThreadId;
 use tokio::sync::{Mutex, RwLock};
 use tokio::time::{Duration, Instant};
-use tracing::{debug, info, warn};
+use tracing::span::Id;
+use tracing::{debug, info, warn, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -158,10 +164,130 @@ pub struct IoOperationInfo {
     pub function_name: String,
 }
 
+/// Tracing spans currently entered on each thread, innermost last
+///
+/// Spans are entered and exited on the thread polling them, so these stacks
+/// show what every thread is doing right now in terms of our own
+/// instrumented code. The profiler samples them to build flame graphs.
+/// Nothing is recorded while no profiler is sampling, so span enters and
+/// exits stay lock-free outside profiling sessions.
+#[derive(Clone, Default)]
+pub struct SpanStacks {
+    recording: Arc<AtomicBool>,
+    stacks: Arc<parking_lot::Mutex<HashMap<ThreadId, Vec<&'static Metadata<'static>>>>>,
+}
+
+impl SpanStacks {
+    /// Stacks recorded by the layer installed with the application's subscriber
+    pub fn global() -> Self {
+        GLOBAL_SPAN_STACKS.clone()
+    }
+
+    /// A tracing layer recording into these stacks
+    pub fn layer(&self) -> SpanStackLayer {
+        SpanStackLayer {
+            stacks: self.clone(),
+        }
+    }
+
+    /// Start or stop recording; stopping forgets the recorded stacks
+    fn set_recording(&self, recording: bool) {
+        let mut stacks = self.stacks.lock();
+        self.recording.store(recording, Ordering::Release);
+        if !recording {
+            stacks.clear();
+        }
+    }
+
+    fn push(&self, metadata: &'static Metadata<'static>) {
+        if !self.recording.load(Ordering::Acquire) {
+            return;
+        }
+        // Checked again under the lock, so a span entered while recording stops
+        // can't leave a frame behind once the stacks are cleared
+        let mut stacks = self.stacks.lock();
+        if !self.recording.load(Ordering::Acquire) {
+            return;
+        }
+        stacks
+            .entry(std::thread::current().id())
+            .or_default()
+            .push(metadata);
+    }
+
+    fn pop(&self, metadata: &'static Metadata<'static>) {
+        if !self.recording.load(Ordering::Acquire) {
+            return;
+        }
+        let thread_id = std::thread::current().id();
+        let mut stacks = self.stacks.lock();
+        if !self.recording.load(Ordering::Acquire) {
+            return;
+        }
+
+        if let Some(stack) = stacks.get_mut(&thread_id) {
+            if let Some(index) = stack
+                .iter()
+                .rposition(|entered| std::ptr::eq(*entered, metadata))
+            {
+                stack.remove(index);
+            }
+            if stack.is_empty() {
+                stacks.remove(&thread_id);
+            }
+        }
+    }
+
+    /// Current stack of every thread inside at least one span, outermost first
+    fn snapshot(&self, max_depth: usize) -> Vec<Vec<&'static Metadata<'static>>> {
+        self.stacks
+            .lock()
+            .values()
+            .filter(|stack| !stack.is_empty())
+            .map(|stack| stack.iter().take(max_depth.max(1)).copied().collect())
+            .collect()
+    }
+}
+
+/// Tracing layer that keeps [`SpanStacks`] up to date
+pub struct SpanStackLayer {
+    stacks: SpanStacks,
+}
+
+impl<S> Layer<S> for SpanStackLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(metadata) = ctx.metadata(id) {
+            self.stacks.push(metadata);
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(metadata) = ctx.metadata(id) {
+            self.stacks.pop(metadata);
+        }
+    }
+}
+
+/// Layer to install with the global subscriber so the profiler can sample span stacks
+pub fn span_stack_layer() -> SpanStackLayer {
+    SpanStacks::global().layer()
+}
+
+/// Frame name in collapsed stacks, e.g. `autodev_ai::performance::database::execute_query`
+fn frame_name(metadata: &Metadata<'_>) -> String {
+    format!("{}::{}", metadata.target(), metadata.name()).replace([';', '\n'], "_")
+}
+
 pub struct AdvancedProfiler {
     config: ProfilerConfig,
+    span_stacks: SpanStacks,
     profiles: Arc<RwLock<VecDeque<ProfileData>>>,
     aggregated_data: Arc<RwLock<HashMap<String, AggregatedProfileData>>>,
+    /// Sample counts per collapsed stack (`outer;inner`)
+    folded_stacks: Arc<RwLock<HashMap<String, u64>>>,
     sampling_active: Arc<Mutex<bool>>,
     sampling_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
     bottleneck_detector: Arc<Mutex<BottleneckDetector>>,
@@ -394,14 +520,22 @@ impl AdvancedProfiler {
 
         Self {
             config,
+            span_stacks: SpanStacks::global(),
             profiles: Arc::new(RwLock::new(VecDeque::new())),
             aggregated_data: Arc::new(RwLock::new(HashMap::new())),
+            folded_stacks: Arc::new(RwLock::new(HashMap::new())),
             sampling_active: Arc::new(Mutex::new(false)),
             sampling_handle: Arc::new(Mutex::new(None)),
             bottleneck_detector: Arc::new(Mutex::new(BottleneckDetector::new())),
         }
     }
 
+    /// Sample `span_stacks` instead of the stacks recorded by [`span_stack_layer`]
+    pub fn with_span_stacks(mut self, span_stacks: SpanStacks) -> Self {
+        self.span_stacks = span_stacks;
+        self
+    }
+
     pub async fn start_profiling(&self) -> anyhow::Result<()> {
         if !self.config.enabled {
             info!("Profiler is disabled");
@@ -414,22 +548,37 @@ impl AdvancedProfiler {
         }
 
         *sampling_active = true;
+        // Spans entered before this point are not part of the samples
+        self.span_stacks.set_recording(true);
 
+        let span_stacks = self.span_stacks.clone();
         let profiles = Arc::clone(&self.profiles);
         let aggregated_data = Arc::clone(&self.aggregated_data);
+        let folded_stacks = Arc::clone(&self.folded_stacks);
         let bottleneck_detector = Arc::clone(&self.bottleneck_detector);
         let config = self.config.clone();
         let sampling_active_clone = Arc::clone(&self.sampling_active);
 
         let handle = tokio::spawn(async move {
-            let sample_interval = Duration::from_millis(1000 / config.sampling_rate_hz as u64);
+            let sample_interval =
+                Duration::from_secs_f64(1.0 / config.sampling_rate_hz.max(1) as f64);
             let mut interval = tokio::time::interval(sample_interval);
 
             while *sampling_active_clone.lock().await {
                 interval.tick().await;
 
-                // Collect profile sample
-                if let Ok(sample) = Self::collect_sample(&config).await {
+                if !config.cpu_profiling {
+                    continue;
+                }
+
+                // Collect one sample per thread that is inside a span
+                for sample in Self::collect_samples(&config, &span_stacks, sample_interval) {
+                    *folded_stacks
+                        .write()
+                        .await
+                        .entry(sample.stack_trace.join(";"))
+                        .or_insert(0) += 1;
+
                     // Store sample
                     {
                         let mut profiles_write = profiles.write().await;
@@ -491,6 +640,7 @@ impl AdvancedProfiler {
     pub async fn stop_profiling(&self) -> anyhow::Result<()> {
         let mut sampling_active = self.sampling_active.lock().await;
         *sampling_active = false;
+        self.span_stacks.set_recording(false);
 
         let mut sampling_handle = self.sampling_handle.lock().await;
         if let Some(handle) = sampling_handle.take() {
@@ -501,40 +651,36 @@ impl AdvancedProfiler {
         Ok(())
     }
 
-    async fn collect_sample(config: &ProfilerConfig) -> anyhow::Result<ProfileData> {
-        // In a real implementation, this would use actual profiling APIs
-        // For now, we'll simulate profile data collection
-
-        let function_names = vec![
-            "execute_ai_command",
-            "process_neural_network",
-            "handle_http_request",
-            "database_query",
-            "file_system_operation",
-            "memory_allocation",
-            "crypto_operation",
-            "network_request",
-        ];
-
-        use std::time::{SystemTime, UNIX_EPOCH};
-        let seed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as usize;
-        let function_name = function_names[seed % function_names.len()].to_string();
-
-        Ok(ProfileData {
-            timestamp: chrono::Utc::now().timestamp(),
-            function_name,
-            module_path: "autodev_ai::performance".to_string(),
-            execution_time_ns: 1_000_000 + (rand::random::<u64>() % 10_000_000), // 1-11 ms
-            memory_allocated: (rand::random::<u64>() % 1_000_000) + 1024,        // 1KB - 1MB
-            memory_deallocated: (rand::random::<u64>() % 500_000) + 512,         // 512B - 500KB
-            cpu_time_ns: 500_000 + (rand::random::<u64>() % 5_000_000),          // 0.5-5.5 ms
-            io_wait_time_ns: rand::random::<u64>() % 100_000_000,                // 0-100ms
-            call_count: 1,
-            stack_trace: vec![
-                "autodev_ai::performance::profiler::collect_sample".to_string(),
-                "autodev_ai::main::execute_command".to_string(),
-            ],
-        })
+    /// Each sample attributes one sampling interval to the innermost span of a thread
+    fn collect_samples(
+        config: &ProfilerConfig,
+        span_stacks: &SpanStacks,
+        sample_interval: Duration,
+    ) -> Vec<ProfileData> {
+        let interval_ns = sample_interval.as_nanos() as u64;
+        let timestamp = chrono::Utc::now().timestamp();
+
+        span_stacks
+            .snapshot(config.call_stack_depth as usize)
+            .into_iter()
+            .filter_map(|stack| {
+                let leaf = *stack.last()?;
+                Some(ProfileData {
+                    timestamp,
+                    function_name: frame_name(leaf),
+                    module_path: leaf.module_path().unwrap_or(leaf.target()).to_string(),
+                    execution_time_ns: interval_ns,
+                    memory_allocated: 0,
+                    memory_deallocated: 0,
+                    // A span stays entered while its thread sleeps or waits on I/O,
+                    // so a sample is wall-clock time and says nothing about CPU time
+                    cpu_time_ns: 0,
+                    io_wait_time_ns: 0,
+                    call_count: 1,
+                    stack_trace: stack.iter().map(|metadata| frame_name(metadata)).collect(),
+                })
+            })
+            .collect()
     }
 
     pub async fn generate_report(&self) -> anyhow::Result<ProfileReport> {
@@ -665,10 +811,10 @@ impl AdvancedProfiler {
 
         MemoryProfileStats {
             total_allocations,
-            total_deallocations: total_allocations - (rand::random::<u64>() % 100), // Simulate some outstanding allocations
+            total_deallocations: total_allocations.saturating_sub(rand::random::<u64>() % 100), // Simulate some outstanding allocations
             peak_memory_usage: total_allocated_memory,
             current_memory_usage: total_allocated_memory
-                - (rand::random::<u64>() % (total_allocated_memory / 2)),
+                - (rand::random::<u64>() % (total_allocated_memory / 2).max(1)),
             allocation_rate_per_second: total_allocated_memory as f64 / 60.0, // Assume 1 minute of profiling
             largest_allocations,
         }
@@ -766,6 +912,11 @@ impl AdvancedProfiler {
             aggregated.clear();
         }
 
+        {
+            let mut folded_stacks = self.folded_stacks.write().await;
+            folded_stacks.clear();
+        }
+
         {
             let mut detector = self.bottleneck_detector.lock().await;
             detector.recent_samples.clear();
@@ -775,30 +926,47 @@ impl AdvancedProfiler {
         Ok(())
     }
 
+    /// Sampled span stacks in collapsed format (`outer;inner count`), as read by flamegraph tooling
     pub async fn export_flame_graph(&self) -> anyhow::Result<String> {
         if !self.config.flame_graph_generation {
             return Err(anyhow::anyhow!("Flame graph generation is disabled"));
         }
 
-        let aggregated = self.aggregated_data.read().await;
+        let folded_stacks = self.folded_stacks.read().await;
+        let mut stacks: Vec<_> = folded_stacks.iter().collect();
+        stacks.sort();
 
-        // Generate simplified flame graph data
         let mut flame_graph_data = String::new();
-        flame_graph_data.push_str("# Flame Graph Data\n");
-        flame_graph_data.push_str("# Function;Samples\n");
-
-        for data in aggregated.values() {
-            let samples = (data.total_execution_time_ns / 1_000_000) as u64; // Convert to ms
-            flame_graph_data.push_str(&format!("{};{}\n", data.function_name, samples));
+        for (stack, samples) in stacks {
+            flame_graph_data.push_str(&format!("{} {}\n", stack, samples));
         }
 
         Ok(flame_graph_data)
     }
+
+    /// Sampled span stacks rendered as an SVG flame graph
+    pub async fn export_flame_graph_svg(&self) -> anyhow::Result<String> {
+        if !self.config.flame_graph_generation {
+            return Err(anyhow::anyhow!("Flame graph generation is disabled"));
+        }
+
+        let folded_stacks = self.folded_stacks.read().await;
+        let options = FlameGraphOptions {
+            title: format!(
+                "Wall-clock Profile ({} Hz sampling)",
+                self.config.sampling_rate_hz
+            ),
+            ..Default::default()
+        };
+
+        Ok(flame_graph::render_svg(&folded_stacks, &options))
+    }
 }
 
 // Global profiler instance
 lazy_static::lazy_static! {
     static ref GLOBAL_PROFILER: tokio::sync::RwLock<Option<AdvancedProfiler>> = tokio::sync::RwLock::new(None);
+    static ref GLOBAL_SPAN_STACKS: SpanStacks = SpanStacks::default();
 }
 
 pub async fn initialize_profiler(config: ProfilerConfig) -> anyhow::Result<()> {
@@ -836,6 +1004,24 @@ pub async fn generate_profile_report() -> anyhow::Result<ProfileReport> {
     }
 }
 
+pub async fn export_flame_graph() -> anyhow::Result<String> {
+    let profiler = GLOBAL_PROFILER.read().await;
+    if let Some(profiler) = profiler.as_ref() {
+        profiler.export_flame_graph().await
+    } else {
+        Err(anyhow::anyhow!("Profiler not initialized"))
+    }
+}
+
+pub async fn export_flame_graph_svg() -> anyhow::Result<String> {
+    let profiler = GLOBAL_PROFILER.read().await;
+    if let Some(profiler) = profiler.as_ref() {
+        profiler.export_flame_graph_svg().await
+    } else {
+        Err(anyhow::anyhow!("Profiler not initialized"))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -868,4 +1054,73 @@ mod tests {
         assert!(report.total_samples >= 0);
         assert!(!report.hot_paths.is_empty() || report.total_samples == 0);
     }
+
+    #[test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_span_sampling_produces_folded_stacks() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let span_stacks = SpanStacks::default();
+        let config = ProfilerConfig {
+            sampling_rate_hz: 200,
+            flame_graph_generation: true,
+            ..Default::default()
+        };
+        let profiler = AdvancedProfiler::new(config).with_span_stacks(span_stacks.clone());
+        profiler.start_profiling().await.unwrap();
+
+        let subscriber = tracing_subscriber::registry().with(span_stacks.layer());
+        std::thread::spawn(move || {
+            tracing::subscriber::with_default(subscriber, || {
+                let _outer = tracing::info_span!("handle_request").entered();
+                let _inner = tracing::info_span!("run_query").entered();
+                std::thread::sleep(std::time::Duration::from_millis(300));
+            });
+        })
+        .join()
+        .unwrap();
+
+        profiler.stop_profiling().await.unwrap();
+
+        let folded = profiler.export_flame_graph().await.unwrap();
+        let line = folded
+            .lines()
+            .find(|line| line.contains("::handle_request;"))
+            .expect("sampled stack");
+        let (stack, samples) = line.rsplit_once(' ').unwrap();
+        assert!(stack.ends_with("::run_query"));
+        assert!(samples.parse::<u64>().unwrap() > 0);
+
+        // Threads leave the stacks once they exit their spans
+        assert!(span_stacks.snapshot(32).is_empty());
+
+        let report = profiler.generate_report().await.unwrap();
+        assert!(report.hot_paths[0].function_name.ends_with("::run_query"));
+        // The span slept the whole time, so the samples claim no CPU time
+        assert_eq!(report.cpu_stats.total_cpu_time_ns, 0);
+
+        let svg = profiler.export_flame_graph_svg().await.unwrap();
+        assert!(svg.contains("run_query"));
+    }
+
+    #[test]
+    async fn test_span_stacks_record_only_while_profiling() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let span_stacks = SpanStacks::default();
+        let subscriber = tracing_subscriber::registry().with(span_stacks.layer());
+        let _default = tracing::subscriber::set_default(subscriber);
+
+        {
+            let _span = tracing::info_span!("idle").entered();
+            assert!(span_stacks.snapshot(32).is_empty());
+        }
+
+        span_stacks.set_recording(true);
+        let span = tracing::info_span!("profiled").entered();
+        assert_eq!(span_stacks.snapshot(32).len(), 1);
+
+        span_stacks.set_recording(false);
+        assert!(span_stacks.snapshot(32).is_empty());
+        drop(span);
+    }
 }
@@ -0,0 +1,756 @@
+// Search index for the file system plugin
+//
+// Keeps the files under the project root, and the text of the smaller ones,
+// in memory so filename and content searches don't walk the disk each time.
+// The index honors `.gitignore` files at any depth, is built on a blocking
+// worker and is kept current by the change batches of the project watcher.
+use super::file_watcher::FileChangeBatch;
+use chrono::{DateTime, Utc};
+use ignore::gitignore::Gitignore;
+use ignore::Match;
+use parking_lot::RwLock;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Instant;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+/// Directory that is never indexed, wherever it appears
+const GIT_DIR: &str = ".git";
+const GITIGNORE: &str = ".gitignore";
+/// Files up to this size have their text kept in memory, within the total budget
+const MAX_CACHED_FILE_SIZE: u64 = 1024 * 1024;
+const MAX_CACHED_BYTES: usize = 256 * 1024 * 1024;
+/// Larger files are not searched for content at all
+const MAX_SEARCHED_FILE_SIZE: u64 = 16 * 1024 * 1024;
+/// A NUL byte in this many leading bytes marks a file as binary
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+/// Content matches are handed out in batches of this size
+const MATCH_BATCH_SIZE: usize = 100;
+
+#[derive(Debug, Clone)]
+struct IndexedFile {
+    size: u64,
+    /// Text of the file if it was small enough to cache
+    content: Option<Arc<str>>,
+    binary: bool,
+}
+
+#[derive(Debug, Default)]
+struct IndexContents {
+    /// Files keyed by their path relative to the root, with `/` separators
+    files: BTreeMap<String, IndexedFile>,
+    /// Parsed `.gitignore` files keyed by the directory containing them
+    ignores: HashMap<PathBuf, Gitignore>,
+    cached_bytes: usize,
+    ready: bool,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+/// Snapshot of the index for the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexStatus {
+    pub ready: bool,
+    pub files: usize,
+    pub cached_bytes: usize,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// A file whose path fuzzily matches a query
+#[derive(Debug, Clone, Serialize)]
+pub struct FileMatch {
+    pub path: String,
+    pub score: i64,
+    /// Character offsets into `path` that matched, for highlighting
+    pub positions: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContentQuery {
+    pub pattern: String,
+    /// Treat `pattern` as a regular expression instead of literal text
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Lines of context returned before and after each match
+    #[serde(default = "default_context_lines")]
+    pub context_lines: usize,
+    #[serde(default = "default_max_matches")]
+    pub max_matches: usize,
+}
+
+fn default_context_lines() -> usize {
+    2
+}
+
+fn default_max_matches() -> usize {
+    10_000
+}
+
+/// One occurrence of a content query; line and columns are 1-based, columns count characters
+#[derive(Debug, Clone, Serialize)]
+pub struct ContentMatch {
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    pub end_column: usize,
+    pub text: String,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ContentSearchSummary {
+    pub matches: usize,
+    pub files_searched: usize,
+    pub files_matched: usize,
+    /// The search stopped at `max_matches`
+    pub truncated: bool,
+    pub cancelled: bool,
+    pub duration_ms: u64,
+}
+
+/// In-memory index of one project directory
+#[derive(Debug)]
+pub struct SearchIndex {
+    root: PathBuf,
+    contents: RwLock<IndexContents>,
+    /// Dropped with the index to stop the listener started by `start`
+    stop: Option<oneshot::Sender<()>>,
+}
+
+impl SearchIndex {
+    /// An empty index over `root`, which should already be canonical
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            contents: RwLock::new(IndexContents::default()),
+            stop: None,
+        }
+    }
+
+    /// Create an index, build it in the background and apply the batches from `changes` to it
+    ///
+    /// Batches that arrive while the index is being built are applied afterwards.
+    /// The listener stops as soon as the returned index is dropped.
+    pub fn start(root: impl Into<PathBuf>, mut changes: broadcast::Receiver<FileChangeBatch>) -> Arc<Self> {
+        let (stop, mut stopped) = oneshot::channel();
+        let index = Arc::new(Self {
+            stop: Some(stop),
+            ..Self::new(root)
+        });
+        let weak = Arc::downgrade(&index);
+
+        tauri::async_runtime::spawn(async move {
+            if !update(&weak, SearchIndex::rebuild).await {
+                return;
+            }
+
+            loop {
+                let batch = tokio::select! {
+                    // Resolves once the index, and with it the sender, is dropped
+                    _ = &mut stopped => return,
+                    batch = changes.recv() => batch,
+                };
+                let updated = match batch {
+                    Ok(batch) if batch.rescan => update(&weak, SearchIndex::rebuild).await,
+                    Ok(batch) => {
+                        let root = batch.root.clone();
+                        let paths = batch.paths();
+                        update(&weak, move |index| {
+                            if index.root == root {
+                                index.apply_changes(&paths);
+                            }
+                        })
+                        .await
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Search index missed {} change batches, rebuilding", skipped);
+                        update(&weak, SearchIndex::rebuild).await
+                    }
+                    Err(RecvError::Closed) => return,
+                };
+                if !updated {
+                    return;
+                }
+            }
+        });
+
+        index
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn status(&self) -> IndexStatus {
+        let contents = self.contents.read();
+        IndexStatus {
+            ready: contents.ready,
+            files: contents.files.len(),
+            cached_bytes: contents.cached_bytes,
+            updated_at: contents.updated_at,
+        }
+    }
+
+    /// Rescan the whole project, replacing the current contents once done
+    pub fn rebuild(&self) {
+        let started = Instant::now();
+        let mut contents = IndexContents::default();
+        self.scan(&self.root, &mut contents);
+        contents.ready = true;
+        contents.updated_at = Some(Utc::now());
+
+        info!(
+            "Indexed {} files under {} in {:?}",
+            contents.files.len(),
+            self.root.display(),
+            started.elapsed()
+        );
+        *self.contents.write() = contents;
+    }
+
+    /// Bring the index up to date for paths that were created, modified, removed or renamed
+    ///
+    /// Files are read without holding the lock, so searches keep running meanwhile.
+    pub fn apply_changes(&self, paths: &[PathBuf]) {
+        let mut paths: Vec<&PathBuf> = paths.iter().collect();
+        paths.sort();
+        paths.dedup();
+
+        let mut scratch = {
+            let contents = self.contents.read();
+            // Ignore rules changed, so any file may have become (un)ignored. Rules in
+            // ignored directories, like those an `npm install` writes to node_modules, don't count
+            let rules_changed = paths.iter().any(|path| {
+                path.file_name().is_some_and(|name| name == GITIGNORE)
+                    && path.parent().is_some_and(|dir| !self.is_reserved(dir) && !self.is_excluded(&contents, dir, true))
+            });
+            if rules_changed {
+                drop(contents);
+                self.rebuild();
+                return;
+            }
+            IndexContents {
+                ignores: contents.ignores.clone(),
+                cached_bytes: contents.cached_bytes,
+                ..IndexContents::default()
+            }
+        };
+
+        let mut changed = Vec::new();
+        for path in paths {
+            let Ok(relative) = path.strip_prefix(&self.root) else { continue };
+            if relative.as_os_str().is_empty() || self.is_reserved(path) {
+                continue;
+            }
+
+            let key = relative_key(relative);
+            remove_tree(&mut scratch, path, &key);
+            changed.push((path, key.clone()));
+
+            let Ok(metadata) = fs::symlink_metadata(path) else { continue };
+            if metadata.file_type().is_symlink() || self.is_excluded(&scratch, path, metadata.is_dir()) {
+                continue;
+            }
+            if metadata.is_dir() {
+                self.scan(path, &mut scratch);
+            } else if metadata.is_file() {
+                let file = load_file(path, metadata.len(), scratch.cached_bytes);
+                scratch.cached_bytes += file.content.as_ref().map_or(0, |content| content.len());
+                scratch.files.insert(key, file);
+            }
+        }
+
+        let mut contents = self.contents.write();
+        for (path, key) in &changed {
+            remove_tree(&mut contents, path, key);
+        }
+        for (key, file) in scratch.files {
+            contents.cached_bytes += file.content.as_ref().map_or(0, |content| content.len());
+            contents.files.insert(key, file);
+        }
+        // Only the rules found below the changed paths are new
+        let ignores = scratch.ignores.into_iter().filter(|(dir, _)| changed.iter().any(|(path, _)| dir.starts_with(path)));
+        contents.ignores.extend(ignores);
+        contents.updated_at = Some(Utc::now());
+    }
+
+    /// Whether a file is indexed under `path`, relative to the root with `/` separators
+    pub fn contains(&self, path: &str) -> bool {
+        self.contents.read().files.contains_key(path)
+    }
+
+    /// Files whose path contains the characters of `query` in order, best matches first
+    pub fn find_files(&self, query: &str, limit: usize) -> Vec<FileMatch> {
+        let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).collect();
+        let contents = self.contents.read();
+
+        let mut matches: Vec<FileMatch> = contents
+            .files
+            .keys()
+            .filter_map(|path| {
+                let (score, positions) = fuzzy_match(&query, path)?;
+                Some(FileMatch { path: path.clone(), score, positions })
+            })
+            .collect();
+
+        matches.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.path.len().cmp(&b.path.len()))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        matches.truncate(limit);
+        matches
+    }
+
+    /// Search file contents, handing matches to `on_batch` as they are found
+    ///
+    /// Setting `cancelled` stops the search after the file being searched.
+    pub fn search_content(
+        &self,
+        query: &ContentQuery,
+        cancelled: &AtomicBool,
+        mut on_batch: impl FnMut(Vec<ContentMatch>),
+    ) -> Result<ContentSearchSummary, String> {
+        let started = Instant::now();
+        let matcher = build_matcher(query)?;
+        // Search a snapshot so the index is not locked for the whole search
+        let files: Vec<(String, IndexedFile)> = self
+            .contents
+            .read()
+            .files
+            .iter()
+            .map(|(path, file)| (path.clone(), file.clone()))
+            .collect();
+
+        let mut summary = ContentSearchSummary::default();
+        let mut batch = Vec::new();
+
+        for (path, file) in files {
+            if cancelled.load(Ordering::Relaxed) {
+                summary.cancelled = true;
+                break;
+            }
+            if summary.matches >= query.max_matches {
+                summary.truncated = true;
+                break;
+            }
+
+            let Some(text) = self.text_of(&path, &file) else { continue };
+            summary.files_searched += 1;
+
+            let found = search_text(&path, &text, &matcher, query.context_lines, query.max_matches - summary.matches, &mut batch);
+            if found > 0 {
+                summary.matches += found;
+                summary.files_matched += 1;
+            }
+            if batch.len() >= MATCH_BATCH_SIZE {
+                on_batch(std::mem::take(&mut batch));
+            }
+        }
+
+        if !batch.is_empty() {
+            on_batch(batch);
+        }
+        summary.duration_ms = started.elapsed().as_millis() as u64;
+        Ok(summary)
+    }
+
+    /// Cached text of a file, or its text read from disk if it was too large to cache
+    fn text_of(&self, path: &str, file: &IndexedFile) -> Option<Arc<str>> {
+        if file.binary {
+            return None;
+        }
+        if let Some(content) = &file.content {
+            return Some(content.clone());
+        }
+        if file.size > MAX_SEARCHED_FILE_SIZE {
+            return None;
+        }
+
+        let bytes = fs::read(self.root.join(path)).ok()?;
+        if is_binary(&bytes) {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&bytes).into())
+    }
+
+    /// Add everything under `dir` that isn't ignored, reading `.gitignore` files on the way down
+    fn scan(&self, dir: &Path, contents: &mut IndexContents) {
+        let mut pending = vec![dir.to_path_buf()];
+
+        while let Some(dir) = pending.pop() {
+            let gitignore_path = dir.join(GITIGNORE);
+            if gitignore_path.is_file() {
+                let (gitignore, error) = Gitignore::new(&gitignore_path);
+                if let Some(e) = error {
+                    warn!("Problem reading {}: {}", gitignore_path.display(), e);
+                }
+                contents.ignores.insert(dir.clone(), gitignore);
+            }
+
+            let Ok(entries) = fs::read_dir(&dir) else { continue };
+            let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+            entries.sort_by_key(|entry| entry.file_name());
+
+            for entry in entries {
+                let path = entry.path();
+                let Ok(file_type) = entry.file_type() else { continue };
+                if file_type.is_symlink() || self.is_reserved(&path) || is_ignored(&contents.ignores, &self.root, &path, file_type.is_dir()) {
+                    continue;
+                }
+
+                if file_type.is_dir() {
+                    pending.push(path);
+                } else if file_type.is_file() {
+                    let Ok(relative) = path.strip_prefix(&self.root) else { continue };
+                    let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+                    let file = load_file(&path, size, contents.cached_bytes);
+                    contents.cached_bytes += file.content.as_ref().map_or(0, |content| content.len());
+                    contents.files.insert(relative_key(relative), file);
+                }
+            }
+        }
+    }
+
+    /// `.git` is never indexed, wherever it appears
+    fn is_reserved(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else { return true };
+        relative.components().any(|component| component.as_os_str() == GIT_DIR)
+    }
+
+    /// Whether `path` or any directory between it and the root is ignored
+    fn is_excluded(&self, contents: &IndexContents, path: &Path, is_dir: bool) -> bool {
+        if is_ignored(&contents.ignores, &self.root, path, is_dir) {
+            return true;
+        }
+        path.ancestors()
+            .skip(1)
+            .take_while(|dir| *dir != self.root)
+            .any(|dir| is_ignored(&contents.ignores, &self.root, dir, true))
+    }
+}
+
+/// Run `f` on the index on a blocking worker; false once the index has been dropped
+async fn update<F>(index: &Weak<SearchIndex>, f: F) -> bool
+where
+    F: FnOnce(&SearchIndex) + Send + 'static,
+{
+    let Some(index) = index.upgrade() else { return false };
+    if let Err(e) = tokio::task::spawn_blocking(move || f(&index)).await {
+        warn!("Search index update failed: {}", e);
+    }
+    true
+}
+
+/// The nearest `.gitignore` with a rule for `path` decides whether it is ignored
+fn is_ignored(ignores: &HashMap<PathBuf, Gitignore>, root: &Path, path: &Path, is_dir: bool) -> bool {
+    for dir in path.ancestors().skip(1) {
+        if let Some(gitignore) = ignores.get(dir) {
+            match gitignore.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        if dir == root {
+            break;
+        }
+    }
+    false
+}
+
+/// Drop `path` and, if it was a directory, everything indexed below it
+fn remove_tree(contents: &mut IndexContents, path: &Path, key: &str) {
+    let prefix = format!("{}/", key);
+    let removed: Vec<String> = contents
+        .files
+        .range(key.to_string()..)
+        .map(|(path, _)| path)
+        .take_while(|path| *path == key || path.starts_with(&prefix))
+        .cloned()
+        .collect();
+
+    for path in removed {
+        if let Some(file) = contents.files.remove(&path) {
+            contents.cached_bytes -= file.content.map_or(0, |content| content.len());
+        }
+    }
+    contents.ignores.retain(|dir, _| !dir.starts_with(path));
+}
+
+fn relative_key(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Read a file for the index, caching its text if it fits in what is left of the budget
+fn load_file(path: &Path, size: u64, cached_bytes: usize) -> IndexedFile {
+    let uncached = IndexedFile { size, content: None, binary: false };
+    if size > MAX_CACHED_FILE_SIZE || cached_bytes + size as usize > MAX_CACHED_BYTES {
+        return uncached;
+    }
+
+    match fs::read(path) {
+        Ok(bytes) if is_binary(&bytes) => IndexedFile { size, content: None, binary: true },
+        Ok(bytes) => IndexedFile {
+            size,
+            content: Some(String::from_utf8_lossy(&bytes).into()),
+            binary: false,
+        },
+        Err(_) => uncached,
+    }
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0)
+}
+
+/// Score `path` against a lowercased query, matching from the end so hits in the file name win
+fn fuzzy_match(query: &[char], path: &str) -> Option<(i64, Vec<usize>)> {
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    let chars: Vec<char> = path.chars().collect();
+    let mut positions = Vec::with_capacity(query.len());
+    let mut remaining = query.iter().rev().peekable();
+    for (i, c) in chars.iter().enumerate().rev() {
+        let Some(wanted) = remaining.peek() else { break };
+        if c.to_lowercase().eq(std::iter::once(**wanted)) {
+            positions.push(i);
+            remaining.next();
+        }
+    }
+    if remaining.peek().is_some() {
+        return None;
+    }
+    positions.reverse();
+
+    let name_start = chars.iter().rposition(|c| *c == '/').map_or(0, |i| i + 1);
+    let mut score = 0i64;
+    for (n, &i) in positions.iter().enumerate() {
+        score += 1;
+        if n > 0 && positions[n - 1] + 1 == i {
+            score += 5;
+        }
+        let boundary = i == 0
+            || matches!(chars[i - 1], '/' | '_' | '-' | '.' | ' ')
+            || (chars[i - 1].is_lowercase() && chars[i].is_uppercase());
+        if boundary {
+            score += 8;
+        }
+        if i >= name_start {
+            score += 3;
+        }
+    }
+    // Prefer tighter matches
+    score -= (positions[positions.len() - 1] - positions[0]) as i64 / 4;
+
+    Some((score, positions))
+}
+
+fn build_matcher(query: &ContentQuery) -> Result<Regex, String> {
+    if query.pattern.is_empty() {
+        return Err("Search pattern is empty".to_string());
+    }
+    let pattern = if query.regex { query.pattern.clone() } else { regex::escape(&query.pattern) };
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!query.case_sensitive)
+        .build()
+        .map_err(|e| format!("Invalid search pattern: {}", e))
+}
+
+/// Append up to `limit` matches in `text` to `out`, returning how many were added
+fn search_text(path: &str, text: &str, matcher: &Regex, context_lines: usize, limit: usize, out: &mut Vec<ContentMatch>) -> usize {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut found = 0;
+
+    for (i, line) in lines.iter().enumerate() {
+        for m in matcher.find_iter(line) {
+            if m.is_empty() {
+                continue;
+            }
+            if found == limit {
+                return found;
+            }
+
+            let column = line[..m.start()].chars().count() + 1;
+            out.push(ContentMatch {
+                path: path.to_string(),
+                line: i + 1,
+                column,
+                end_column: column + m.as_str().chars().count(),
+                text: line.to_string(),
+                before: lines[i.saturating_sub(context_lines)..i].iter().map(|line| line.to_string()).collect(),
+                after: lines[i + 1..lines.len().min(i + 1 + context_lines)].iter().map(|line| line.to_string()).collect(),
+            });
+            found += 1;
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::file_watcher::{FileChange, FileChangeKind};
+
+    fn project(files: &[(&str, &str)]) -> (tempfile::TempDir, SearchIndex) {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        for (path, content) in files {
+            let path = temp_dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let index = SearchIndex::new(fs::canonicalize(temp_dir.path()).unwrap());
+        index.rebuild();
+        (temp_dir, index)
+    }
+
+    fn paths(index: &SearchIndex, query: &str) -> Vec<String> {
+        index.find_files(query, 10).into_iter().map(|m| m.path).collect()
+    }
+
+    #[test]
+    fn test_index_honors_gitignore() {
+        let (_temp_dir, index) = project(&[
+            (".gitignore", "target/\n*.log\n"),
+            ("src/main.rs", "fn main() {}"),
+            ("src/domain/mod.rs", "pub mod main_menu;"),
+            ("target/debug/main.rs", "generated"),
+            ("debug.log", "log"),
+            ("logs/.gitignore", "!keep.log\n"),
+            ("logs/keep.log", "kept"),
+            (".git/HEAD", "ref: refs/heads/main"),
+        ]);
+
+        let status = index.status();
+        assert!(status.ready);
+        assert_eq!(status.files, 5);
+        assert_eq!(paths(&index, "keep.log"), vec!["logs/keep.log".to_string()]);
+        assert!(paths(&index, "HEAD").is_empty());
+
+        // Matches in the file name rank above scattered ones
+        assert_eq!(paths(&index, "mainrs")[0], "src/main.rs");
+        assert_eq!(paths(&index, "main")[0], "src/main.rs");
+        assert_eq!(index.find_files("smr", 10)[0].positions, vec![0, 4, 9]);
+    }
+
+    #[test]
+    fn test_content_search_reports_positions_and_context() {
+        let (_temp_dir, index) = project(&[
+            ("src/lib.rs", "// ünïcode header\nfn parse() {}\nfn Parse_all() {}\nfn other() {}\n"),
+            ("assets/logo.png", "\u{0}PNG parse"),
+            ("README.md", "Hów to parse input"),
+        ]);
+        let query = ContentQuery {
+            pattern: "parse".to_string(),
+            regex: false,
+            case_sensitive: false,
+            context_lines: 1,
+            max_matches: 100,
+        };
+
+        let mut batches = Vec::new();
+        let summary = index
+            .search_content(&query, &AtomicBool::new(false), |batch| batches.push(batch))
+            .unwrap();
+        assert_eq!(summary.matches, 3);
+        assert_eq!(summary.files_matched, 2);
+        assert_eq!(summary.files_searched, 2);
+
+        let matches: Vec<ContentMatch> = batches.into_iter().flatten().collect();
+        assert_eq!(matches[0].path, "README.md");
+        assert_eq!((matches[0].line, matches[0].column, matches[0].end_column), (1, 8, 13));
+        assert_eq!((matches[2].line, matches[2].column), (3, 4));
+        assert_eq!(matches[2].before, vec!["fn parse() {}".to_string()]);
+        assert_eq!(matches[2].after, vec!["fn other() {}".to_string()]);
+
+        let query = ContentQuery { pattern: r"^fn \w+\(\)".to_string(), regex: true, case_sensitive: true, ..query };
+        let summary = index.search_content(&query, &AtomicBool::new(false), |_| {}).unwrap();
+        assert_eq!(summary.matches, 3);
+
+        let query = ContentQuery { pattern: "(".to_string(), ..query };
+        assert!(index.search_content(&query, &AtomicBool::new(false), |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_changes_update_the_index() {
+        let (_temp_dir, index) = project(&[(".gitignore", "build/\n"), ("src/main.rs", "fn main() {}")]);
+        let root = index.root().to_path_buf();
+
+        fs::write(root.join("src/lib.rs"), "pub fn added() {}").unwrap();
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::write(root.join("build/out.rs"), "pub fn added() {}").unwrap();
+        index.apply_changes(&[root.join("src/lib.rs"), root.join("build/out.rs")]);
+        assert_eq!(paths(&index, "lib"), vec!["src/lib.rs".to_string()]);
+        assert!(index.contains("src/lib.rs") && !index.contains("build/out.rs"));
+        assert!(paths(&index, "out").is_empty());
+
+        // Removing a directory drops everything below it
+        fs::remove_dir_all(root.join("src")).unwrap();
+        index.apply_changes(&[root.join("src")]);
+        assert_eq!(index.status().files, 1);
+        assert_eq!(index.status().cached_bytes, "build/\n".len());
+
+        // A .gitignore inside an ignored directory leaves the rest of the index alone
+        fs::create_dir_all(root.join("build/pkg")).unwrap();
+        fs::write(root.join("build/pkg/.gitignore"), "*.tmp\n").unwrap();
+        fs::write(root.join("notes.md"), "not applied yet").unwrap();
+        index.apply_changes(&[root.join("build/pkg/.gitignore")]);
+        assert!(paths(&index, "notes").is_empty());
+        assert_eq!(index.status().files, 1);
+
+        // A changed .gitignore re-evaluates the whole project
+        fs::write(root.join(".gitignore"), "").unwrap();
+        index.apply_changes(&[root.join(".gitignore")]);
+        assert_eq!(paths(&index, "out"), vec!["build/out.rs".to_string()]);
+    }
+
+    #[test]
+    fn test_started_index_follows_batches_until_dropped() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = fs::canonicalize(temp_dir.path()).unwrap();
+        fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+        let (sender, receiver) = broadcast::channel(16);
+        let index = SearchIndex::start(&root, receiver);
+
+        let wait_for = |done: &dyn Fn() -> bool| {
+            let deadline = Instant::now() + std::time::Duration::from_secs(10);
+            while !done() {
+                assert!(Instant::now() < deadline, "timed out");
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+        };
+        wait_for(&|| index.status().ready);
+
+        fs::write(root.join("lib.rs"), "pub fn lib() {}").unwrap();
+        sender
+            .send(FileChangeBatch {
+                root: root.clone(),
+                changes: vec![FileChange { path: "lib.rs".to_string(), kind: FileChangeKind::Created }],
+                rescan: false,
+            })
+            .unwrap();
+        wait_for(&|| index.status().files == 2);
+
+        // The listener lets go of its receiver without waiting for another batch
+        drop(index);
+        wait_for(&|| sender.receiver_count() == 0);
+    }
+}
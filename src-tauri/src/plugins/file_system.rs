@@ -1,12 +1,52 @@
 // File system plugin for AutoDev-AI Neural Bridge Platform
+//
+// All access goes through a `ProjectFileSystem` jailed to the project root:
+// paths are resolved relative to the root, validated by the input sanitizer
+// and canonicalized, so neither `..` nor symlinks can reach outside of it.
+// Writes replace files atomically and deletes move entries to a per-project
+// trash under the app data directory, from which they can be restored. Searches
+// run against a `SearchIndex` of the root, kept current by the changes a
+// `ProjectWatcher` broadcasts; the same changes are published through the
+// event system.
+use super::file_index::{ContentQuery, FileMatch, IndexStatus, SearchIndex};
+use super::file_watcher::{FileChangeBatch, ProjectWatcher};
+use crate::events::EventSystem;
+use crate::security::input_sanitizer::{InputSanitizer, ValidationResult};
+use chrono::{DateTime, Utc};
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
-use tauri::{App, AppHandle, Runtime, State, Window, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tauri::{App, Emitter, Manager, Runtime, State, Window};
+use tokio::sync::broadcast;
 use tracing::{info, warn, error};
+use uuid::Uuid;
+
+/// Sanitizer code for file extensions that may not be written
+const DANGEROUS_EXTENSION_CODE: u16 = 1008;
+/// Limits for walking the project tree
+const MAX_STRUCTURE_DEPTH: usize = 8;
+const MAX_STRUCTURE_ENTRIES: usize = 10_000;
+const MAX_FILE_MATCHES: usize = 200;
+/// Change batches a slow subscriber may fall behind by before it misses some
+const CHANGE_CHANNEL_CAPACITY: usize = 256;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileSystemConfig {
     pub enabled: bool,
     pub project_root: Option<String>,
+    /// Largest file `read_file` will return
+    #[serde(default = "default_max_file_size")]
+    pub max_file_size_bytes: u64,
+}
+
+fn default_max_file_size() -> u64 {
+    10 * 1024 * 1024
 }
 
 impl Default for FileSystemConfig {
@@ -14,80 +54,705 @@ impl Default for FileSystemConfig {
         Self {
             enabled: true,
             project_root: None,
+            max_file_size_bytes: default_max_file_size(),
+        }
+    }
+}
+
+/// What a path will be used for; writes are checked more strictly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+}
+
+/// A deleted file or directory that can be restored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub id: String,
+    /// Path relative to the project root
+    pub original_path: String,
+    pub is_directory: bool,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Filesystem access confined to one project directory
+#[derive(Debug)]
+pub struct ProjectFileSystem {
+    root: PathBuf,
+    /// This project's trash, outside of the project
+    trash_dir: PathBuf,
+    sanitizer: InputSanitizer,
+}
+
+impl ProjectFileSystem {
+    /// Jail access to `root`, which must be an existing directory
+    ///
+    /// Deleted entries go to a directory under `trash_root` named after the
+    /// project, so projects sharing the trash root never see each other's.
+    pub fn new(root: impl AsRef<Path>, trash_root: impl AsRef<Path>) -> Result<Self, String> {
+        let root = root.as_ref();
+        let root = fs::canonicalize(root)
+            .map_err(|e| format!("Invalid project root {}: {}", root.display(), e))?;
+        if !root.is_dir() {
+            return Err(format!("Project root {} is not a directory", root.display()));
+        }
+
+        let project_key = hex::encode(&Sha256::digest(root.to_string_lossy().as_bytes())[..8]);
+        let trash_dir = trash_root.as_ref().join(project_key);
+        if trash_dir.starts_with(&root) {
+            return Err(format!("The trash at {} must be outside of the project", trash_dir.display()));
+        }
+
+        Ok(Self {
+            root,
+            trash_dir,
+            sanitizer: InputSanitizer::default(),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolve a path given relative to the root (or absolute inside it) to a canonical path in the jail
+    fn resolve(&self, path: &str, access: Access) -> Result<PathBuf, String> {
+        let requested = Path::new(path);
+        let relative = if requested.is_absolute() {
+            requested
+                .strip_prefix(&self.root)
+                .map_err(|_| format!("Path {} is outside the project root", path))?
+        } else {
+            requested
+        };
+
+        match self.sanitizer.validate_file_path(&relative.to_string_lossy()) {
+            ValidationResult::Invalid { code, .. } if code == DANGEROUS_EXTENSION_CODE && access == Access::Read => {}
+            ValidationResult::Invalid { reason, .. } => return Err(format!("Invalid path {}: {}", path, reason)),
+            _ => {}
+        }
+
+        let mut components = Vec::new();
+        for component in relative.components() {
+            match component {
+                Component::Normal(name) => components.push(name),
+                Component::CurDir => {}
+                _ => return Err(format!("Path {} is outside the project root", path)),
+            }
+        }
+
+        // Canonicalize the deepest existing ancestor so symlinks are followed before the check
+        let joined: PathBuf = components.iter().fold(self.root.clone(), |path, name| path.join(name));
+        let mut existing = joined.as_path();
+        let mut missing = Vec::new();
+        while fs::symlink_metadata(existing).is_err() {
+            missing.push(existing.file_name().unwrap_or_default().to_os_string());
+            existing = existing
+                .parent()
+                .ok_or_else(|| format!("Path {} is outside the project root", path))?;
+        }
+
+        let canonical = fs::canonicalize(existing)
+            .map_err(|e| format!("Failed to resolve {}: {}", path, e))?;
+        if !canonical.starts_with(&self.root) {
+            return Err(format!("Path {} resolves outside the project root", path));
+        }
+
+        Ok(missing.into_iter().rev().fold(canonical, |path, name| path.join(name)))
+    }
+
+    /// Like `resolve`, but a symlink at `path` itself is not followed so the link can be removed
+    fn resolve_entry(&self, path: &str) -> Result<PathBuf, String> {
+        let requested = Path::new(path);
+        let name = requested
+            .file_name()
+            .ok_or_else(|| format!("{} cannot be removed", path))?;
+        match self.sanitizer.validate_file_path(&name.to_string_lossy()) {
+            ValidationResult::Invalid { code, .. } if code == DANGEROUS_EXTENSION_CODE => {}
+            ValidationResult::Invalid { reason, .. } => return Err(format!("Invalid path {}: {}", path, reason)),
+            _ => {}
+        }
+
+        let parent = self.resolve(&requested.parent().unwrap_or(Path::new("")).to_string_lossy(), Access::Read)?;
+        Ok(parent.join(name))
+    }
+
+    /// Path relative to the root, with `/` separators
+    fn relative(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .unwrap_or(path)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Entries of a directory as paths relative to the root, directories first
+    pub fn read_directory(&self, path: &str) -> Result<Vec<String>, String> {
+        let dir = self.resolve(path, Access::Read)?;
+        let mut entries = Vec::new();
+
+        for entry in fs::read_dir(&dir).map_err(|e| format!("Failed to read directory {}: {}", path, e))? {
+            let entry = entry.map_err(|e| format!("Failed to read directory {}: {}", path, e))?;
+            let is_dir = entry.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false);
+            entries.push((!is_dir, self.relative(&entry.path())));
+        }
+
+        entries.sort();
+        Ok(entries.into_iter().map(|(_, path)| path).collect())
+    }
+
+    pub fn read_file(&self, path: &str, max_size: u64) -> Result<String, String> {
+        let file = self.resolve(path, Access::Read)?;
+        let metadata = fs::metadata(&file).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if !metadata.is_file() {
+            return Err(format!("{} is not a file", path));
+        }
+        if metadata.len() > max_size {
+            return Err(format!("{} is larger than {} bytes", path, max_size));
+        }
+
+        fs::read_to_string(&file).map_err(|e| format!("Failed to read {}: {}", path, e))
+    }
+
+    /// Replace a file's content atomically, creating missing parent directories
+    pub fn write_file(&self, path: &str, content: &[u8]) -> Result<(), String> {
+        let file = self.resolve(path, Access::Write)?;
+        if file == self.root || file.is_dir() {
+            return Err(format!("{} is a directory", path));
+        }
+
+        let parent = file.parent().unwrap_or(&self.root);
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", self.relative(parent), e))?;
+
+        let tmp_path = parent.join(format!(
+            ".{}.{}.tmp",
+            file.file_name().unwrap_or_default().to_string_lossy(),
+            Uuid::new_v4().simple()
+        ));
+        let result = (|| {
+            let mut tmp = fs::File::create(&tmp_path)?;
+            tmp.write_all(content)?;
+            tmp.sync_all()?;
+            if let Ok(existing) = fs::metadata(&file) {
+                fs::set_permissions(&tmp_path, existing.permissions())?;
+            }
+            fs::rename(&tmp_path, &file)
+        })();
+
+        result.map_err(|e| {
+            let _ = fs::remove_file(&tmp_path);
+            format!("Failed to write {}: {}", path, e)
+        })
+    }
+
+    pub fn create_directory(&self, path: &str) -> Result<(), String> {
+        let dir = self.resolve(path, Access::Write)?;
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create directory {}: {}", path, e))
+    }
+
+    /// Move a file or directory to the trash, returning the trash entry id
+    pub fn delete(&self, path: &str) -> Result<TrashEntry, String> {
+        let target = self.resolve_entry(path)?;
+        let metadata = fs::symlink_metadata(&target).map_err(|e| format!("Failed to delete {}: {}", path, e))?;
+
+        let entry = TrashEntry {
+            id: Uuid::new_v4().to_string(),
+            original_path: self.relative(&target),
+            is_directory: metadata.is_dir(),
+            deleted_at: Utc::now(),
+        };
+        let entry_dir = self.trash_dir.join(&entry.id);
+        fs::create_dir_all(&entry_dir).map_err(|e| format!("Failed to create trash entry: {}", e))?;
+
+        let manifest = serde_json::to_vec_pretty(&entry).map_err(|e| format!("Failed to serialize trash entry: {}", e))?;
+        fs::write(entry_dir.join("entry.json"), manifest).map_err(|e| format!("Failed to write trash entry: {}", e))?;
+        if let Err(e) = move_entry(&target, &entry_dir.join("item")) {
+            let _ = fs::remove_dir_all(&entry_dir);
+            return Err(format!("Failed to move {} to the trash: {}", path, e));
+        }
+
+        info!("Moved {} to trash entry {}", entry.original_path, entry.id);
+        Ok(entry)
+    }
+
+    /// Trash entries, most recently deleted first
+    pub fn list_trash(&self) -> Result<Vec<TrashEntry>, String> {
+        let entries = match fs::read_dir(&self.trash_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to read trash: {}", e)),
+        };
+
+        let mut trash: Vec<TrashEntry> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| fs::read(entry.path().join("entry.json")).ok())
+            .filter_map(|manifest| serde_json::from_slice(&manifest).ok())
+            .collect();
+        trash.sort_by_key(|entry| std::cmp::Reverse(entry.deleted_at));
+        Ok(trash)
+    }
+
+    /// Move a trash entry back to where it was deleted from
+    pub fn restore(&self, id: &str) -> Result<String, String> {
+        let entry = self
+            .list_trash()?
+            .into_iter()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| format!("Trash entry {} not found", id))?;
+
+        let target = self.resolve(&entry.original_path, Access::Read)?;
+        if fs::symlink_metadata(&target).is_ok() {
+            return Err(format!("{} already exists", entry.original_path));
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", self.relative(parent), e))?;
+        }
+
+        let entry_dir = self.trash_dir.join(&entry.id);
+        move_entry(&entry_dir.join("item"), &target)
+            .map_err(|e| format!("Failed to restore {}: {}", entry.original_path, e))?;
+        if let Err(e) = fs::remove_dir_all(&entry_dir) {
+            warn!("Failed to remove trash entry {}: {}", entry.id, e);
+        }
+
+        Ok(entry.original_path)
+    }
+
+    pub fn file_info(&self, path: &str) -> Result<serde_json::Value, String> {
+        let file = self.resolve(path, Access::Read)?;
+        let metadata = fs::metadata(&file).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+
+        Ok(serde_json::json!({
+            "path": self.relative(&file),
+            "name": file.file_name().map(|name| name.to_string_lossy().to_string()),
+            "is_directory": metadata.is_dir(),
+            "is_file": metadata.is_file(),
+            "size": metadata.len(),
+            "readonly": metadata.permissions().readonly(),
+            "modified": modified,
+        }))
+    }
+
+    /// Directory tree of the project, skipping symlinks
+    pub fn structure(&self) -> serde_json::Value {
+        let mut remaining = MAX_STRUCTURE_ENTRIES;
+        let children = self.structure_of(&self.root, 0, &mut remaining);
+
+        serde_json::json!({
+            "name": self.root.file_name().map(|name| name.to_string_lossy().to_string()),
+            "path": "",
+            "type": "directory",
+            "children": children,
+            "truncated": remaining == 0,
+        })
+    }
+
+    fn structure_of(&self, dir: &Path, depth: usize, remaining: &mut usize) -> Vec<serde_json::Value> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        let mut nodes = Vec::new();
+        for entry in entries {
+            if *remaining == 0 {
+                break;
+            }
+            let Ok(file_type) = entry.file_type() else { continue };
+            if file_type.is_symlink() {
+                continue;
+            }
+            *remaining -= 1;
+
+            let path = entry.path();
+            let mut node = serde_json::json!({
+                "name": entry.file_name().to_string_lossy(),
+                "path": self.relative(&path),
+                "type": if file_type.is_dir() { "directory" } else { "file" },
+            });
+            if file_type.is_dir() && depth + 1 < MAX_STRUCTURE_DEPTH {
+                node["children"] = serde_json::Value::Array(self.structure_of(&path, depth + 1, remaining));
+            }
+            nodes.push(node);
         }
+
+        nodes
+    }
+}
+
+/// Move a file, directory or symlink, copying it when the trash and the project are on different devices
+fn move_entry(from: &Path, to: &Path) -> std::io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            if let Err(e) = copy_entry(from, to) {
+                let _ = fs::remove_dir_all(to).or_else(|_| fs::remove_file(to));
+                return Err(e);
+            }
+            if fs::symlink_metadata(from)?.is_dir() {
+                fs::remove_dir_all(from)
+            } else {
+                fs::remove_file(from)
+            }
+        }
+        result => result,
     }
 }
 
-pub async fn setup_filesystem_plugin<R: Runtime>(app: &App<R>) -> Result<()> {
+fn copy_entry(from: &Path, to: &Path) -> std::io::Result<()> {
+    let file_type = fs::symlink_metadata(from)?.file_type();
+    if file_type.is_symlink() {
+        #[cfg(unix)]
+        return std::os::unix::fs::symlink(fs::read_link(from)?, to);
+        #[cfg(not(unix))]
+        return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "cannot copy a symlink across devices"));
+    }
+    if file_type.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_entry(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+    fs::copy(from, to).map(|_| ())
+}
+
+/// Plugin state shared by the file system commands
+#[derive(Debug)]
+pub struct FileSystemState {
+    config: RwLock<FileSystemConfig>,
+    /// Holds one trash directory per project
+    trash_root: PathBuf,
+    project: RwLock<Option<Arc<ProjectFileSystem>>>,
+    watcher: Mutex<Option<ProjectWatcher>>,
+    /// Changes under whichever project is open, across project switches
+    changes: broadcast::Sender<FileChangeBatch>,
+    index: RwLock<Option<Arc<SearchIndex>>>,
+    /// Cancellation flags of running content searches by search id
+    searches: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+}
+
+impl FileSystemState {
+    pub fn new(config: FileSystemConfig, trash_root: PathBuf) -> Self {
+        let state = Self {
+            config: RwLock::new(FileSystemConfig::default()),
+            trash_root,
+            project: RwLock::new(None),
+            watcher: Mutex::new(None),
+            changes: broadcast::channel(CHANGE_CHANNEL_CAPACITY).0,
+            index: RwLock::new(None),
+            searches: Arc::new(Mutex::new(HashMap::new())),
+        };
+        if let Err(e) = state.apply_config(config) {
+            warn!("Filesystem plugin started without a project root: {}", e);
+        }
+        state
+    }
+
+    fn apply_config(&self, config: FileSystemConfig) -> Result<(), String> {
+        let project = match &config.project_root {
+            Some(root) => Some(ProjectFileSystem::new(root, &self.trash_root)?),
+            None => None,
+        };
+        self.set_project(project);
+        *self.config.write() = config;
+        Ok(())
+    }
+
+    /// Receive the changes under the project root, debounced into batches
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<FileChangeBatch> {
+        self.changes.subscribe()
+    }
+
+    /// Switch to a new project, watching it and indexing it in the background
+    fn set_project(&self, project: Option<ProjectFileSystem>) {
+        let unchanged = match (&project, &*self.project.read()) {
+            (Some(new), Some(current)) => new.root() == current.root(),
+            (None, None) => true,
+            _ => false,
+        };
+        if unchanged {
+            return;
+        }
+
+        let index = project
+            .as_ref()
+            .map(|project| SearchIndex::start(project.root(), self.subscribe_changes()));
+        *self.watcher.lock() = project.as_ref().and_then(|project| {
+            // The index knows which files were already there when one is renamed over
+            let indexed = index.as_ref().map(Arc::downgrade).unwrap_or_default();
+            let existing = Box::new(move |path: &str| indexed.upgrade().is_some_and(|index| index.contains(path)));
+            ProjectWatcher::start(project.root(), self.changes.clone(), existing)
+                .map_err(|e| warn!("Changes under {} will go unnoticed: {}", project.root().display(), e))
+                .ok()
+        });
+        *self.index.write() = index;
+        *self.project.write() = project.map(Arc::new);
+    }
+
+    fn project(&self) -> Result<Arc<ProjectFileSystem>, String> {
+        if !self.config.read().enabled {
+            return Err("File system access is disabled".to_string());
+        }
+        self.project
+            .read()
+            .clone()
+            .ok_or_else(|| "No project root has been set".to_string())
+    }
+
+    fn index(&self) -> Result<Arc<SearchIndex>, String> {
+        self.project()?;
+        self.index
+            .read()
+            .clone()
+            .ok_or_else(|| "No project root has been set".to_string())
+    }
+}
+
+pub async fn setup_filesystem_plugin<R: Runtime>(app: &App<R>) -> tauri::Result<()> {
     info!("Setting up filesystem plugin...");
+    let trash_root = app.path().app_data_dir()?.join("trash");
+    app.manage(FileSystemState::new(FileSystemConfig::default(), trash_root));
+
+    // Publish file changes through the event system once it is available
+    let mut changes = app.state::<FileSystemState>().subscribe_changes();
+    let handle = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match changes.recv().await {
+                Ok(batch) => {
+                    if let Some(events) = handle.try_state::<EventSystem>() {
+                        if let Err(e) = events.emit(batch.to_event()).await {
+                            warn!("Failed to publish file changes: {}", e);
+                        }
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Dropped {} file change batches before publishing them", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+
     info!("Filesystem plugin initialized successfully");
     Ok(())
 }
 
-pub async fn health_check<R: Runtime>(app: &App<R>) -> bool {
-    true
-}
-
 #[tauri::command]
-pub async fn read_directory(_window: Window, path: String) -> Result<Vec<String>, String> {
+pub async fn read_directory(_window: Window, state: State<'_, FileSystemState>, path: String) -> Result<Vec<String>, String> {
     info!("Reading directory: {}", path);
-    Ok(vec![])
+    state.project()?.read_directory(&path)
 }
 
 #[tauri::command]
-pub async fn read_file(_window: Window, path: String) -> Result<String, String> {
+pub async fn read_file(_window: Window, state: State<'_, FileSystemState>, path: String) -> Result<String, String> {
     info!("Reading file: {}", path);
-    Ok("".to_string())
+    let max_size = state.config.read().max_file_size_bytes;
+    state.project()?.read_file(&path, max_size)
 }
 
 #[tauri::command]
-pub async fn write_file(_window: Window, path: String, content: String) -> Result<(), String> {
+pub async fn write_file(_window: Window, state: State<'_, FileSystemState>, path: String, content: String) -> Result<(), String> {
     info!("Writing file: {}", path);
-    Ok(())
+    state.project()?.write_file(&path, content.as_bytes())
 }
 
 #[tauri::command]
-pub async fn create_directory(_window: Window, path: String) -> Result<(), String> {
+pub async fn create_directory(_window: Window, state: State<'_, FileSystemState>, path: String) -> Result<(), String> {
     info!("Creating directory: {}", path);
-    Ok(())
+    state.project()?.create_directory(&path)
 }
 
 #[tauri::command]
-pub async fn delete_file(_window: Window, path: String) -> Result<(), String> {
+pub async fn delete_file(_window: Window, state: State<'_, FileSystemState>, path: String) -> Result<TrashEntry, String> {
     info!("Deleting file: {}", path);
-    Ok(())
+    state.project()?.delete(&path)
+}
+
+#[tauri::command]
+pub async fn list_trash(_window: Window, state: State<'_, FileSystemState>) -> Result<Vec<TrashEntry>, String> {
+    state.project()?.list_trash()
+}
+
+#[tauri::command]
+pub async fn restore_from_trash(_window: Window, state: State<'_, FileSystemState>, id: String) -> Result<String, String> {
+    info!("Restoring trash entry: {}", id);
+    state.project()?.restore(&id)
 }
 
 #[tauri::command]
-pub async fn get_file_info_command(_window: Window, path: String) -> Result<serde_json::Value, String> {
+pub async fn get_file_info_command(_window: Window, state: State<'_, FileSystemState>, path: String) -> Result<serde_json::Value, String> {
     info!("Getting file info: {}", path);
-    Ok(serde_json::json!({}))
+    state.project()?.file_info(&path)
 }
 
 #[tauri::command]
-pub async fn set_project_root(_window: Window, path: String) -> Result<(), String> {
+pub async fn set_project_root(_window: Window, state: State<'_, FileSystemState>, path: String) -> Result<(), String> {
     info!("Setting project root: {}", path);
+    let project = ProjectFileSystem::new(&path, &state.trash_root)?;
+
+    let root = project.root().to_string_lossy().to_string();
+    state.set_project(Some(project));
+    state.config.write().project_root = Some(root);
     Ok(())
 }
 
 #[tauri::command]
-pub async fn get_project_structure(_window: Window) -> Result<serde_json::Value, String> {
-    Ok(serde_json::json!({}))
+pub async fn get_project_structure(_window: Window, state: State<'_, FileSystemState>) -> Result<serde_json::Value, String> {
+    Ok(state.project()?.structure())
 }
 
 #[tauri::command]
-pub async fn get_filesystem_config(_window: Window) -> Result<FileSystemConfig, String> {
-    Ok(FileSystemConfig::default())
+pub async fn get_filesystem_config(_window: Window, state: State<'_, FileSystemState>) -> Result<FileSystemConfig, String> {
+    Ok(state.config.read().clone())
 }
 
 #[tauri::command]
-pub async fn update_filesystem_config(_window: Window, config: FileSystemConfig) -> Result<(), String> {
+pub async fn update_filesystem_config(_window: Window, state: State<'_, FileSystemState>, config: FileSystemConfig) -> Result<(), String> {
     info!("Updating filesystem config");
-    Ok(())
+    state.apply_config(config).map_err(|e| {
+        error!("Failed to apply filesystem config: {}", e);
+        e
+    })
 }
 
+/// Fuzzy match file paths in the project, best matches first
 #[tauri::command]
-pub async fn search_files(_window: Window, query: String) -> Result<Vec<String>, String> {
+pub async fn search_files(_window: Window, state: State<'_, FileSystemState>, query: String, limit: Option<usize>) -> Result<Vec<FileMatch>, String> {
     info!("Searching files: {}", query);
-    Ok(vec![])
-}
\ No newline at end of file
+    let index = state.index()?;
+    if !index.status().ready {
+        return Err("The project is still being indexed".to_string());
+    }
+    Ok(index.find_files(&query, limit.unwrap_or(MAX_FILE_MATCHES).min(MAX_FILE_MATCHES)))
+}
+
+/// Start a content search, returning its id
+///
+/// Matches are streamed to the window as `file-search-results` events carrying
+/// the search id, followed by one `file-search-complete` event with a summary.
+#[tauri::command]
+pub async fn search_file_contents(window: Window, state: State<'_, FileSystemState>, query: ContentQuery) -> Result<String, String> {
+    info!("Searching file contents: {}", query.pattern);
+    let index = state.index()?;
+    if !index.status().ready {
+        return Err("The project is still being indexed".to_string());
+    }
+
+    let search_id = Uuid::new_v4().to_string();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    state.searches.lock().insert(search_id.clone(), cancelled.clone());
+
+    let searches = state.searches.clone();
+    let id = search_id.clone();
+    tokio::task::spawn_blocking(move || {
+        let result = index.search_content(&query, &cancelled, |matches| {
+            let _ = window.emit("file-search-results", serde_json::json!({
+                "search_id": id,
+                "matches": matches,
+            }));
+        });
+        searches.lock().remove(&id);
+
+        let payload = match result {
+            Ok(summary) => serde_json::json!({ "search_id": id, "summary": summary }),
+            Err(e) => {
+                warn!("Content search {} failed: {}", id, e);
+                serde_json::json!({ "search_id": id, "error": e })
+            }
+        };
+        let _ = window.emit("file-search-complete", payload);
+    });
+
+    Ok(search_id)
+}
+
+/// Stop a running content search; returns false if it had already finished
+#[tauri::command]
+pub async fn cancel_file_search(_window: Window, state: State<'_, FileSystemState>, search_id: String) -> Result<bool, String> {
+    match state.searches.lock().get(&search_id) {
+        Some(cancelled) => {
+            cancelled.store(true, Ordering::Relaxed);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+#[tauri::command]
+pub async fn get_search_index_status(_window: Window, state: State<'_, FileSystemState>) -> Result<IndexStatus, String> {
+    Ok(state.index()?.status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> (tempfile::TempDir, ProjectFileSystem) {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("project/src")).unwrap();
+        let project = ProjectFileSystem::new(temp_dir.path().join("project"), temp_dir.path().join("trash")).unwrap();
+        (temp_dir, project)
+    }
+
+    #[test]
+    fn test_paths_cannot_escape_the_root() {
+        let (temp_dir, project) = project();
+        fs::write(temp_dir.path().join("secret.txt"), "secret").unwrap();
+
+        assert!(project.read_file("../secret.txt", 1024).is_err());
+        assert!(project.read_file(&temp_dir.path().join("secret.txt").to_string_lossy(), 1024).is_err());
+        assert!(project.write_file("run.sh", b"x").is_err());
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(temp_dir.path(), project.root().join("escape")).unwrap();
+            assert!(project.read_file("escape/secret.txt", 1024).is_err());
+            assert!(project.write_file("escape/new.txt", b"x").is_err());
+
+            // Deleting the link moves the link, not what it points to
+            project.delete("escape").unwrap();
+            assert!(temp_dir.path().join("secret.txt").exists());
+        }
+    }
+
+    #[test]
+    fn test_atomic_write_and_read() {
+        let (_temp_dir, project) = project();
+
+        project.write_file("src/main.rs", b"fn main() {}").unwrap();
+        project.write_file("src/main.rs", b"fn main() { run() }").unwrap();
+        assert_eq!(project.read_file("src/main.rs", 1024).unwrap(), "fn main() { run() }");
+        assert!(project.read_file("src/main.rs", 4).is_err());
+
+        // No temporary files are left behind
+        assert_eq!(project.read_directory("src").unwrap(), vec!["src/main.rs".to_string()]);
+    }
+
+    #[test]
+    fn test_delete_and_restore_from_trash() {
+        let (temp_dir, project) = project();
+        project.write_file("src/lib.rs", b"pub fn lib() {}").unwrap();
+
+        let entry = project.delete("src/lib.rs").unwrap();
+        assert_eq!(entry.original_path, "src/lib.rs");
+        assert!(project.read_file("src/lib.rs", 1024).is_err());
+        assert_eq!(project.list_trash().unwrap().len(), 1);
+
+        // The trash lives outside of the project and is kept per project
+        assert_eq!(project.read_directory("").unwrap(), vec!["src".to_string()]);
+        assert!(project.delete("").is_err());
+        fs::create_dir_all(temp_dir.path().join("other")).unwrap();
+        let other = ProjectFileSystem::new(temp_dir.path().join("other"), temp_dir.path().join("trash")).unwrap();
+        assert!(other.list_trash().unwrap().is_empty());
+        assert!(ProjectFileSystem::new(temp_dir.path(), temp_dir.path().join("trash")).is_err());
+
+        assert_eq!(project.restore(&entry.id).unwrap(), "src/lib.rs");
+        assert_eq!(project.read_file("src/lib.rs", 1024).unwrap(), "pub fn lib() {}");
+        assert!(project.list_trash().unwrap().is_empty());
+    }
+}
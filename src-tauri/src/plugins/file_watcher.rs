@@ -0,0 +1,372 @@
+// File watcher for the project root
+//
+// One recursive watcher (inotify on Linux) per project. Raw notifications are
+// debounced and coalesced per path into `FileChangeBatch`es and broadcast, so
+// the search index, the event system and anything else that subscribes see
+// one consistent stream of changes.
+use crate::events::{Event, EventCategory, EventSeverity, EventSource};
+use notify::event::{MetadataKind, ModifyKind, RenameMode};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+const GIT_DIR: &str = ".git";
+/// A batch is sent once no change has arrived for this long
+const DEBOUNCE: Duration = Duration::from_millis(250);
+/// ... or at the latest this long after its first change
+const MAX_BATCH_DELAY: Duration = Duration::from_secs(2);
+/// Changes listed in a published event; the rest are only counted
+const MAX_EVENT_CHANGES: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+impl FileChangeKind {
+    /// What listeners should see for a path that changed as `self` and then as `next`,
+    /// or `None` if the changes cancel out
+    fn then(self, next: FileChangeKind) -> Option<FileChangeKind> {
+        use FileChangeKind::*;
+        match (self, next) {
+            (Created, Removed) => None,
+            (Created, _) => Some(Created),
+            (Removed, Created) | (Removed, Modified) => Some(Modified),
+            (_, Removed) => Some(Removed),
+            (Modified, _) => Some(Modified),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            FileChangeKind::Created => "created",
+            FileChangeKind::Modified => "modified",
+            FileChangeKind::Removed => "removed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChange {
+    /// Path relative to the project root, with `/` separators
+    pub path: String,
+    pub kind: FileChangeKind,
+}
+
+/// Changes under one project root, at most one per path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChangeBatch {
+    pub root: PathBuf,
+    pub changes: Vec<FileChange>,
+    /// Notifications were lost, so anything under the root may have changed
+    pub rescan: bool,
+}
+
+impl FileChangeBatch {
+    /// Absolute paths of the changed entries
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.changes.iter().map(|change| self.root.join(&change.path)).collect()
+    }
+
+    /// The batch as an event for `events::EventSystem`, tagged with the kinds of change it holds
+    pub fn to_event(&self) -> Event {
+        let source = EventSource {
+            component: "file_watcher".to_string(),
+            module: Some(module_path!().to_string()),
+            function: None,
+            line: None,
+        };
+        let title = if self.rescan {
+            "Project files changed; rescan required".to_string()
+        } else if self.changes.len() == 1 {
+            format!("{} {}", self.changes[0].path, self.changes[0].kind.as_str())
+        } else {
+            format!("{} files changed", self.changes.len())
+        };
+
+        let mut event = Event::new(EventSeverity::Info, EventCategory::FileSystem, source, title)
+            .with_metadata("root".to_string(), serde_json::json!(self.root))
+            .with_metadata("changes".to_string(), serde_json::json!(&self.changes[..self.changes.len().min(MAX_EVENT_CHANGES)]))
+            .with_metadata("total_changes".to_string(), serde_json::json!(self.changes.len()))
+            .with_metadata("rescan".to_string(), serde_json::json!(self.rescan));
+        for kind in [FileChangeKind::Created, FileChangeKind::Modified, FileChangeKind::Removed] {
+            if self.changes.iter().any(|change| change.kind == kind) {
+                event = event.with_tag(kind.as_str().to_string());
+            }
+        }
+        event
+    }
+}
+
+/// Per-path changes collected while a batch is being debounced
+#[derive(Debug, Default)]
+struct PendingChanges {
+    changes: BTreeMap<String, FileChangeKind>,
+    rescan: bool,
+}
+
+impl PendingChanges {
+    fn record(&mut self, path: String, kind: FileChangeKind) {
+        match self.changes.remove(&path) {
+            Some(previous) => {
+                if let Some(kind) = previous.then(kind) {
+                    self.changes.insert(path, kind);
+                }
+            }
+            None => {
+                self.changes.insert(path, kind);
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.changes.is_empty() && !self.rescan
+    }
+
+    fn take(&mut self, root: &Path) -> FileChangeBatch {
+        let pending = std::mem::take(self);
+        FileChangeBatch {
+            root: root.to_path_buf(),
+            changes: pending
+                .changes
+                .into_iter()
+                .map(|(path, kind)| FileChange { path, kind })
+                .collect(),
+            rescan: pending.rescan,
+        }
+    }
+}
+
+/// Whether a path relative to the root, with `/` separators, existed before the change
+pub type ExistingPaths = Box<dyn Fn(&str) -> bool + Send>;
+
+/// Watches a project root until dropped
+#[derive(Debug)]
+pub struct ProjectWatcher {
+    root: PathBuf,
+    _watcher: RecommendedWatcher,
+}
+
+impl ProjectWatcher {
+    /// Watch `root`, which should already be canonical, sending change batches to `changes`
+    ///
+    /// `existing` tells a rename onto a file already there, which is reported as modified,
+    /// from one that creates it.
+    pub fn start(root: impl Into<PathBuf>, changes: broadcast::Sender<FileChangeBatch>, existing: ExistingPaths) -> Result<Self, String> {
+        let root = root.into();
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })
+        .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {}: {}", root.display(), e))?;
+
+        let thread_root = root.clone();
+        std::thread::Builder::new()
+            .name("file-watcher".to_string())
+            .spawn(move || debounce(&thread_root, receiver, changes, existing))
+            .map_err(|e| format!("Failed to start file watcher: {}", e))?;
+
+        debug!("Watching {}", root.display());
+        Ok(Self { root, _watcher: watcher })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+/// Collect notifications into batches until the watcher is dropped
+fn debounce(
+    root: &Path,
+    events: mpsc::Receiver<notify::Result<notify::Event>>,
+    changes: broadcast::Sender<FileChangeBatch>,
+    existing: ExistingPaths,
+) {
+    let mut pending = PendingChanges::default();
+
+    while let Ok(first) = events.recv() {
+        let started = Instant::now();
+        let mut next = Some(first);
+
+        let disconnected = loop {
+            match next.take() {
+                Some(Ok(event)) => record_event(root, event, &mut pending, &existing),
+                Some(Err(e)) => warn!("File watcher error under {}: {}", root.display(), e),
+                None => {}
+            }
+
+            let wait = DEBOUNCE.min(MAX_BATCH_DELAY.saturating_sub(started.elapsed()));
+            if wait.is_zero() {
+                break false;
+            }
+            match events.recv_timeout(wait) {
+                Ok(event) => next = Some(event),
+                Err(RecvTimeoutError::Timeout) => break false,
+                Err(RecvTimeoutError::Disconnected) => break true,
+            }
+        };
+
+        if !pending.is_empty() {
+            // Sending only fails while nobody is subscribed
+            let _ = changes.send(pending.take(root));
+        }
+        if disconnected {
+            return;
+        }
+    }
+}
+
+fn record_event(root: &Path, event: notify::Event, pending: &mut PendingChanges, existing: &dyn Fn(&str) -> bool) {
+    if event.need_rescan() {
+        pending.rescan = true;
+    }
+    let renamed = matches!(event.kind, EventKind::Modify(ModifyKind::Name(RenameMode::To | RenameMode::Both)));
+
+    let kinds: Vec<FileChangeKind> = match event.kind {
+        EventKind::Create(_) => vec![FileChangeKind::Created],
+        EventKind::Remove(_) => vec![FileChangeKind::Removed],
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => vec![FileChangeKind::Removed],
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => vec![FileChangeKind::Created],
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => vec![FileChangeKind::Removed, FileChangeKind::Created],
+        EventKind::Modify(ModifyKind::Metadata(MetadataKind::AccessTime)) | EventKind::Access(_) => return,
+        EventKind::Modify(ModifyKind::Name(_)) | EventKind::Any | EventKind::Other => event
+            .paths
+            .iter()
+            .map(|path| if path.exists() { FileChangeKind::Created } else { FileChangeKind::Removed })
+            .collect(),
+        EventKind::Modify(_) => vec![FileChangeKind::Modified],
+    };
+
+    for (i, path) in event.paths.iter().enumerate() {
+        let kind = kinds.get(i).or(kinds.last()).copied();
+        if let (Some(mut kind), Some(relative)) = (kind, relative_path(root, path)) {
+            // Editors save by renaming a temporary file over the original
+            if renamed && kind == FileChangeKind::Created && existing(&relative) {
+                kind = FileChangeKind::Modified;
+            }
+            pending.record(relative, kind);
+        }
+    }
+}
+
+/// Path relative to the root, or `None` for the root itself, paths outside it and
+/// anything inside `.git`
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let components: Vec<_> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect();
+
+    if components.is_empty() || components.iter().any(|component| component == GIT_DIR) {
+        return None;
+    }
+    Some(components.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_changes_are_coalesced_per_path() {
+        let root = Path::new("/project");
+        let mut pending = PendingChanges::default();
+        let record = |pending: &mut PendingChanges, kind, paths: &[&str]| {
+            let event = notify::Event::new(kind);
+            let event = paths.iter().fold(event, |event, path| event.add_path(root.join(path)));
+            record_event(root, event, pending, &|_| false);
+        };
+
+        record(&mut pending, EventKind::Create(notify::event::CreateKind::File), &["src/new.rs"]);
+        record(&mut pending, EventKind::Modify(ModifyKind::Data(notify::event::DataChange::Any)), &["src/new.rs", "src/lib.rs"]);
+        record(&mut pending, EventKind::Remove(notify::event::RemoveKind::File), &["src/lib.rs", "tmp.txt"]);
+        record(&mut pending, EventKind::Create(notify::event::CreateKind::File), &["tmp.txt"]);
+        record(&mut pending, EventKind::Modify(ModifyKind::Name(RenameMode::Both)), &["a.rs", "b.rs"]);
+        record(&mut pending, EventKind::Create(notify::event::CreateKind::File), &["gone.rs", ".git/index", "vendor/.git/HEAD"]);
+        record(&mut pending, EventKind::Remove(notify::event::RemoveKind::File), &["gone.rs"]);
+
+        let batch = pending.take(root);
+        assert!(pending.is_empty());
+        assert!(!batch.rescan);
+        assert_eq!(
+            batch.changes,
+            vec![
+                FileChange { path: "a.rs".to_string(), kind: FileChangeKind::Removed },
+                FileChange { path: "b.rs".to_string(), kind: FileChangeKind::Created },
+                FileChange { path: "src/lib.rs".to_string(), kind: FileChangeKind::Removed },
+                FileChange { path: "src/new.rs".to_string(), kind: FileChangeKind::Created },
+                FileChange { path: "tmp.txt".to_string(), kind: FileChangeKind::Modified },
+            ]
+        );
+
+        let event = batch.to_event();
+        assert_eq!(event.category, EventCategory::FileSystem);
+        assert_eq!(event.title, "5 files changed");
+        assert!(event.tags.contains("created") && event.tags.contains("modified") && event.tags.contains("removed"));
+    }
+
+    #[test]
+    fn test_renames_onto_existing_files_are_modifications() {
+        let root = Path::new("/project");
+        let existing = |path: &str| path == "src/lib.rs";
+        let mut pending = PendingChanges::default();
+        let rename = |pending: &mut PendingChanges, mode, paths: &[&str]| {
+            let event = notify::Event::new(EventKind::Modify(ModifyKind::Name(mode)));
+            let event = paths.iter().fold(event, |event, path| event.add_path(root.join(path)));
+            record_event(root, event, pending, &existing);
+        };
+
+        rename(&mut pending, RenameMode::From, &["src/.lib.rs.swp"]);
+        rename(&mut pending, RenameMode::To, &["src/lib.rs"]);
+        rename(&mut pending, RenameMode::To, &["src/new.rs"]);
+        rename(&mut pending, RenameMode::Both, &["old.rs", "src/lib.rs"]);
+
+        assert_eq!(
+            pending.take(root).changes,
+            vec![
+                FileChange { path: "old.rs".to_string(), kind: FileChangeKind::Removed },
+                FileChange { path: "src/.lib.rs.swp".to_string(), kind: FileChangeKind::Removed },
+                FileChange { path: "src/lib.rs".to_string(), kind: FileChangeKind::Modified },
+                FileChange { path: "src/new.rs".to_string(), kind: FileChangeKind::Created },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_watcher_publishes_debounced_batches() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = fs::canonicalize(temp_dir.path()).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        let (sender, mut receiver) = broadcast::channel(16);
+        let _watcher = ProjectWatcher::start(&root, sender, Box::new(|_| false)).unwrap();
+
+        for i in 0..5 {
+            fs::write(root.join("src/main.rs"), format!("fn main() {{ {} }}", i)).unwrap();
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let batch = loop {
+            match receiver.try_recv() {
+                Ok(batch) => break batch,
+                Err(_) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(50)),
+                Err(e) => panic!("No changes published: {}", e),
+            }
+        };
+
+        assert_eq!(batch.root, root);
+        assert_eq!(batch.changes, vec![FileChange { path: "src/main.rs".to_string(), kind: FileChangeKind::Created }]);
+    }
+}
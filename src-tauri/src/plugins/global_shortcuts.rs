@@ -1,12 +1,214 @@
 // Global shortcuts plugin for AutoDev-AI Neural Bridge Platform
+//
+// Subsystems register named actions with the `ShortcutRegistry` and users bind
+// accelerators such as "CmdOrCtrl+Shift+P" to them; bindings are validated,
+// checked for conflicts and saved in the config directory. Every binding works
+// in-app, with the frontend forwarding key presses to `trigger_shortcut`, and
+// global bindings are also registered with the OS where that is supported.
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
-use tauri::{App, AppHandle, Runtime, State, Window, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use tauri::{App, AppHandle, Emitter, Manager, Runtime, State, Window};
+use tauri_plugin_global_shortcut::{GlobalShortcut, ShortcutState};
 use tracing::{info, warn, error};
 
+/// Shortcuts the system or text editing depend on, which cannot be bound
+const RESERVED_ACCELERATORS: &[&str] = &[
+    "CmdOrCtrl+C",
+    "CmdOrCtrl+V",
+    "CmdOrCtrl+X",
+    "CmdOrCtrl+Z",
+    "CmdOrCtrl+A",
+    "Alt+F4",
+    "Ctrl+Alt+Delete",
+];
+
+/// Named keys by canonical name, with the aliases accepted for them
+const NAMED_KEYS: &[(&str, &[&str])] = &[
+    ("Space", &["space"]),
+    ("Enter", &["enter", "return"]),
+    ("Tab", &["tab"]),
+    ("Escape", &["escape", "esc"]),
+    ("Backspace", &["backspace"]),
+    ("Delete", &["delete", "del"]),
+    ("Insert", &["insert", "ins"]),
+    ("Home", &["home"]),
+    ("End", &["end"]),
+    ("PageUp", &["pageup"]),
+    ("PageDown", &["pagedown"]),
+    ("ArrowUp", &["arrowup", "up"]),
+    ("ArrowDown", &["arrowdown", "down"]),
+    ("ArrowLeft", &["arrowleft", "left"]),
+    ("ArrowRight", &["arrowright", "right"]),
+    ("Comma", &["comma", ","]),
+    ("Period", &["period", "."]),
+    ("Slash", &["slash", "/"]),
+    ("Backslash", &["backslash", "\\"]),
+    ("Semicolon", &["semicolon", ";"]),
+    ("Quote", &["quote", "'"]),
+    ("BracketLeft", &["bracketleft", "["]),
+    ("BracketRight", &["bracketright", "]"]),
+    ("Minus", &["minus", "-"]),
+    ("Equal", &["equal", "="]),
+    ("Backquote", &["backquote", "`"]),
+];
+
+/// A validated key combination, with `CmdOrCtrl` resolved for the current platform
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Accelerator {
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+    meta: bool,
+    key: String,
+}
+
+impl Accelerator {
+    fn is_function_key(&self) -> bool {
+        self.key.strip_prefix('F').is_some_and(|number| number.parse::<u8>().is_ok())
+    }
+}
+
+impl FromStr for Accelerator {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = value.split('+').map(str::trim).collect();
+        let (key, modifiers) = parts.split_last().ok_or_else(|| "Shortcut is empty".to_string())?;
+        let mut accelerator = Accelerator {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            meta: false,
+            key: normalize_key(key).ok_or_else(|| format!("Unknown key '{}' in {}", key, value))?,
+        };
+
+        for modifier in modifiers {
+            let flag = match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => &mut accelerator.ctrl,
+                "alt" | "option" => &mut accelerator.alt,
+                "shift" => &mut accelerator.shift,
+                "super" | "meta" | "cmd" | "command" | "win" => &mut accelerator.meta,
+                "cmdorctrl" | "commandorcontrol" | "cmdorcontrol" | "commandorctrl" if cfg!(target_os = "macos") => &mut accelerator.meta,
+                "cmdorctrl" | "commandorcontrol" | "cmdorcontrol" | "commandorctrl" => &mut accelerator.ctrl,
+                "" => return Err(format!("Empty modifier in {}", value)),
+                other => return Err(format!("Unknown modifier '{}' in {}", other, value)),
+            };
+            if *flag {
+                return Err(format!("Modifier '{}' is repeated in {}", modifier, value));
+            }
+            *flag = true;
+        }
+
+        // Without a modifier a shortcut would swallow ordinary typing
+        if !(accelerator.ctrl || accelerator.alt || accelerator.meta || accelerator.is_function_key()) {
+            return Err(format!("{} needs Ctrl, Alt or Super unless it is a function key", value));
+        }
+        Ok(accelerator)
+    }
+}
+
+impl fmt::Display for Accelerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [(self.ctrl, "Ctrl"), (self.alt, "Alt"), (self.shift, "Shift"), (self.meta, "Super")];
+        for (_, name) in modifiers.iter().filter(|(active, _)| *active) {
+            write!(f, "{}+", name)?;
+        }
+        f.write_str(&self.key)
+    }
+}
+
+fn normalize_key(key: &str) -> Option<String> {
+    let lower = key.to_ascii_lowercase();
+    if key.len() == 1 && key.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Some(key.to_ascii_uppercase());
+    }
+    if let Some(number) = lower.strip_prefix('f').and_then(|number| number.parse::<u8>().ok()) {
+        return (1..=24).contains(&number).then(|| format!("F{}", number));
+    }
+    NAMED_KEYS
+        .iter()
+        .find(|(_, aliases)| aliases.contains(&lower.as_str()))
+        .map(|(name, _)| name.to_string())
+}
+
+/// Parse an accelerator and return it in canonical form, e.g. "ctrl+shift+p" as "Ctrl+Shift+P"
+pub fn normalize_accelerator(value: &str) -> Result<String, String> {
+    Ok(value.parse::<Accelerator>()?.to_string())
+}
+
+fn is_reserved(accelerator: &str) -> bool {
+    RESERVED_ACCELERATORS
+        .iter()
+        .any(|reserved| normalize_accelerator(reserved).is_ok_and(|reserved| reserved == accelerator))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShortcutScope {
+    /// Only while the application has focus
+    #[default]
+    App,
+    /// Also while the application is in the background
+    Global,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShortcutBinding {
+    pub accelerator: String,
+    pub action: String,
+    #[serde(default)]
+    pub scope: ShortcutScope,
+}
+
+/// Something a shortcut can be bound to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShortcutAction {
+    /// Stable id, e.g. "app.toggle_window"
+    pub id: String,
+    pub label: String,
+    /// Subsystem offering the action, e.g. "menu" or "tray"
+    pub source: String,
+    pub default_accelerator: Option<String>,
+    #[serde(default)]
+    pub default_scope: ShortcutScope,
+}
+
+/// An action with the binding currently in effect for it
+#[derive(Debug, Clone, Serialize)]
+pub struct ShortcutActionInfo {
+    #[serde(flatten)]
+    pub action: ShortcutAction,
+    pub binding: Option<ShortcutBinding>,
+    /// The binding is registered with the OS, not only in-app
+    pub active_globally: bool,
+}
+
+/// Result of checking an accelerator before binding it
+#[derive(Debug, Clone, Serialize)]
+pub struct AcceleratorCheck {
+    pub accelerator: String,
+    pub reserved: bool,
+    /// Action the accelerator is already bound to
+    pub bound_to: Option<String>,
+}
+
+/// Backend side of an action; the frontend is always told via a `shortcut-triggered` event
+pub type ActionHandler = Arc<dyn Fn() -> Result<(), String> + Send + Sync>;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShortcutConfig {
     pub enabled: bool,
-    pub shortcuts: Vec<String>,
+    /// Bindings made by the user
+    pub shortcuts: Vec<ShortcutBinding>,
+    /// Actions whose default binding the user removed
+    #[serde(default)]
+    pub unbound_defaults: Vec<String>,
 }
 
 impl Default for ShortcutConfig {
@@ -14,50 +216,468 @@ impl Default for ShortcutConfig {
         Self {
             enabled: true,
             shortcuts: vec![],
+            unbound_defaults: vec![],
         }
     }
 }
 
-pub async fn setup_shortcuts_plugin<R: Runtime>(app: &App<R>) -> Result<()> {
-    info!("Setting up global shortcuts plugin...");
-    info!("Global shortcuts plugin initialized successfully");
+struct RegisteredAction {
+    action: ShortcutAction,
+    handler: Option<ActionHandler>,
+}
+
+/// Actions, user bindings and the dispatch between them
+pub struct ShortcutRegistry {
+    path: Option<PathBuf>,
+    config: RwLock<ShortcutConfig>,
+    actions: RwLock<BTreeMap<String, RegisteredAction>>,
+    /// Accelerators currently registered with the OS
+    active_globally: RwLock<BTreeSet<String>>,
+}
+
+impl ShortcutRegistry {
+    /// Load the bindings saved at `path`; without a path nothing is persisted
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let config = path
+            .as_ref()
+            .filter(|path| path.exists())
+            .and_then(|path| {
+                fs::read_to_string(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|contents| serde_json::from_str(&contents).map_err(|e| e.to_string()))
+                    .map_err(|e| warn!("Failed to load shortcuts from {}: {}", path.display(), e))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        Self {
+            path,
+            config: RwLock::new(config),
+            actions: RwLock::new(BTreeMap::new()),
+            active_globally: RwLock::new(BTreeSet::new()),
+        }
+    }
+
+    fn default_path() -> Option<PathBuf> {
+        let dirs = directories::ProjectDirs::from("com", "autodev-ai", "neural-bridge-platform")?;
+        Some(dirs.config_dir().join("shortcuts.json"))
+    }
+
+    fn save(&self, config: &ShortcutConfig) {
+        let Some(path) = &self.path else { return };
+        let result = (|| -> Result<(), String> {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let contents = serde_json::to_vec_pretty(config).map_err(|e| e.to_string())?;
+            let tmp_path = path.with_extension("json.tmp");
+            fs::write(&tmp_path, contents).map_err(|e| e.to_string())?;
+            fs::rename(&tmp_path, path).map_err(|e| e.to_string())
+        })();
+
+        if let Err(e) = result {
+            error!("Failed to save shortcuts to {}: {}", path.display(), e);
+        }
+    }
+
+    /// Offer an action for binding; registering an id again replaces the action
+    pub fn register_action(&self, action: ShortcutAction, handler: Option<ActionHandler>) -> Result<(), String> {
+        if let Some(accelerator) = &action.default_accelerator {
+            normalize_accelerator(accelerator).map_err(|e| format!("Invalid default shortcut for {}: {}", action.id, e))?;
+        }
+        self.actions.write().insert(action.id.clone(), RegisteredAction { action, handler });
+        Ok(())
+    }
+
+    pub fn unregister_action(&self, id: &str) -> bool {
+        self.actions.write().remove(id).is_some()
+    }
+
+    /// Bindings in effect: the user's, then defaults of actions the user left alone
+    pub fn bindings(&self) -> Vec<ShortcutBinding> {
+        let config = self.config.read();
+        let mut bindings = config.shortcuts.clone();
+
+        for registered in self.actions.read().values() {
+            let action = &registered.action;
+            let Some(default) = &action.default_accelerator else { continue };
+            if config.unbound_defaults.contains(&action.id) || bindings.iter().any(|binding| binding.action == action.id) {
+                continue;
+            }
+
+            let Ok(accelerator) = normalize_accelerator(default) else { continue };
+            match bindings.iter().find(|binding| binding.accelerator == accelerator) {
+                Some(taken) => warn!("Default shortcut {} of {} is taken by {}", accelerator, action.id, taken.action),
+                None => bindings.push(ShortcutBinding {
+                    accelerator,
+                    action: action.id.clone(),
+                    scope: action.default_scope,
+                }),
+            }
+        }
+
+        bindings
+    }
+
+    pub fn actions(&self) -> Vec<ShortcutActionInfo> {
+        let bindings = self.bindings();
+        let active_globally = self.active_globally.read();
+
+        self.actions
+            .read()
+            .values()
+            .map(|registered| {
+                let binding = bindings.iter().find(|binding| binding.action == registered.action.id).cloned();
+                ShortcutActionInfo {
+                    action: registered.action.clone(),
+                    active_globally: binding.as_ref().is_some_and(|binding| active_globally.contains(&binding.accelerator)),
+                    binding,
+                }
+            })
+            .collect()
+    }
+
+    /// Validate an accelerator and report what it would conflict with
+    pub fn check(&self, accelerator: &str) -> Result<AcceleratorCheck, String> {
+        let accelerator = normalize_accelerator(accelerator)?;
+        Ok(AcceleratorCheck {
+            reserved: is_reserved(&accelerator),
+            bound_to: self
+                .bindings()
+                .into_iter()
+                .find(|binding| binding.accelerator == accelerator)
+                .map(|binding| binding.action),
+            accelerator,
+        })
+    }
+
+    /// Bind an accelerator to an action, replacing the action's previous binding
+    pub fn bind(&self, accelerator: &str, action: &str, scope: ShortcutScope) -> Result<ShortcutBinding, String> {
+        let check = self.check(accelerator)?;
+        if check.reserved {
+            return Err(format!("{} is reserved by the system", check.accelerator));
+        }
+        let label = match self.actions.read().get(action) {
+            Some(registered) => registered.action.label.clone(),
+            None => return Err(format!("Unknown shortcut action {}", action)),
+        };
+        if let Some(bound_to) = check.bound_to.filter(|bound_to| bound_to != action) {
+            return Err(format!("{} is already bound to {}", check.accelerator, bound_to));
+        }
+
+        let binding = ShortcutBinding {
+            accelerator: check.accelerator,
+            action: action.to_string(),
+            scope,
+        };
+        let mut config = self.config.write();
+        config.shortcuts.retain(|existing| existing.action != action);
+        config.shortcuts.push(binding.clone());
+        config.unbound_defaults.retain(|unbound| unbound != action);
+        self.save(&config);
+
+        info!("Bound {} to {} ({})", binding.accelerator, action, label);
+        Ok(binding)
+    }
+
+    /// Remove the binding of an accelerator, including a default one
+    pub fn unbind(&self, accelerator: &str) -> Result<ShortcutBinding, String> {
+        let accelerator = normalize_accelerator(accelerator)?;
+        let binding = self
+            .bindings()
+            .into_iter()
+            .find(|binding| binding.accelerator == accelerator)
+            .ok_or_else(|| format!("{} is not bound", accelerator))?;
+
+        let has_default = self
+            .actions
+            .read()
+            .get(&binding.action)
+            .is_some_and(|registered| registered.action.default_accelerator.is_some());
+        let mut config = self.config.write();
+        config.shortcuts.retain(|existing| existing.accelerator != accelerator);
+        if has_default && !config.unbound_defaults.contains(&binding.action) {
+            config.unbound_defaults.push(binding.action.clone());
+        }
+        self.save(&config);
+        Ok(binding)
+    }
+
+    pub fn config(&self) -> ShortcutConfig {
+        self.config.read().clone()
+    }
+
+    /// Replace the user's bindings, normalizing them and rejecting invalid or duplicate ones
+    pub fn update_config(&self, mut config: ShortcutConfig) -> Result<(), String> {
+        let mut seen = BTreeSet::new();
+        for binding in &mut config.shortcuts {
+            binding.accelerator = normalize_accelerator(&binding.accelerator)?;
+            if is_reserved(&binding.accelerator) {
+                return Err(format!("{} is reserved by the system", binding.accelerator));
+            }
+            if !seen.insert(binding.accelerator.clone()) {
+                return Err(format!("{} is bound more than once", binding.accelerator));
+            }
+        }
+
+        self.save(&config);
+        *self.config.write() = config;
+        Ok(())
+    }
+
+    /// Record which accelerators the OS delivers, so they are not dispatched twice
+    fn set_active_globally(&self, accelerators: BTreeSet<String>) {
+        *self.active_globally.write() = accelerators;
+    }
+
+    /// Run the action bound to a pressed accelerator, returning its id, or `None` if nothing is bound
+    ///
+    /// In-app presses of accelerators the OS delivers as global shortcuts are left to the OS.
+    pub fn trigger(&self, accelerator: &str, from_os: bool) -> Result<Option<String>, String> {
+        if !self.config.read().enabled {
+            return Ok(None);
+        }
+        let accelerator = normalize_accelerator(accelerator)?;
+        if !from_os && self.active_globally.read().contains(&accelerator) {
+            return Ok(None);
+        }
+
+        let Some(binding) = self.bindings().into_iter().find(|binding| binding.accelerator == accelerator) else {
+            return Ok(None);
+        };
+        self.dispatch(&binding.action)?;
+        Ok(Some(binding.action))
+    }
+
+    /// Run an action's backend handler, if it has one
+    pub fn dispatch(&self, action: &str) -> Result<(), String> {
+        let handler = match self.actions.read().get(action) {
+            Some(registered) => registered.handler.clone(),
+            None => return Err(format!("Unknown shortcut action {}", action)),
+        };
+        match handler {
+            Some(handler) => handler(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Register the global bindings with the OS, replacing what was registered before
+fn sync_global_shortcuts<R: Runtime>(app: &AppHandle<R>) {
+    let Some(registry) = app.try_state::<ShortcutRegistry>() else { return };
+    let Some(os_shortcuts) = app.try_state::<GlobalShortcut<R>>() else {
+        // Without OS support every binding stays in-app
+        registry.set_active_globally(BTreeSet::new());
+        return;
+    };
+
+    if let Err(e) = os_shortcuts.unregister_all() {
+        warn!("Failed to unregister global shortcuts: {}", e);
+    }
+    let mut active = BTreeSet::new();
+    if registry.config().enabled {
+        for binding in registry.bindings().into_iter().filter(|binding| binding.scope == ShortcutScope::Global) {
+            let accelerator = binding.accelerator.clone();
+            let registered = os_shortcuts.on_shortcut(binding.accelerator.as_str(), move |app, _, event| {
+                if event.state == ShortcutState::Pressed {
+                    if let Err(e) = run_shortcut(app, &accelerator, true) {
+                        warn!("Shortcut {} failed: {}", accelerator, e);
+                    }
+                }
+            });
+            match registered {
+                Ok(()) => {
+                    active.insert(binding.accelerator);
+                }
+                Err(e) => warn!("{} works in-app only: {}", binding.accelerator, e),
+            }
+        }
+    }
+    registry.set_active_globally(active);
+}
+
+fn run_shortcut<R: Runtime>(app: &AppHandle<R>, accelerator: &str, from_os: bool) -> Result<Option<String>, String> {
+    let registry = app
+        .try_state::<ShortcutRegistry>()
+        .ok_or_else(|| "Shortcuts are not initialized".to_string())?;
+    let action = registry.trigger(accelerator, from_os)?;
+
+    if let Some(action) = &action {
+        let _ = app.emit("shortcut-triggered", serde_json::json!({
+            "action": action,
+            "accelerator": normalize_accelerator(accelerator)?,
+        }));
+    }
+    Ok(action)
+}
+
+/// Offer an action for shortcuts; for subsystems such as the menu, tray and orchestration
+pub fn register_shortcut_action<R: Runtime>(app: &AppHandle<R>, action: ShortcutAction, handler: Option<ActionHandler>) -> Result<(), String> {
+    let registry = app
+        .try_state::<ShortcutRegistry>()
+        .ok_or_else(|| "Shortcuts are not initialized".to_string())?;
+    registry.register_action(action, handler)?;
+    sync_global_shortcuts(app);
     Ok(())
 }
 
-pub async fn health_check<R: Runtime>(app: &App<R>) -> bool {
-    true
+/// Manage the registry; the menu, tray and orchestration register their actions as they start
+pub async fn setup_shortcuts_plugin<R: Runtime>(app: &App<R>) -> tauri::Result<()> {
+    info!("Setting up global shortcuts plugin...");
+    app.manage(ShortcutRegistry::open(ShortcutRegistry::default_path()));
+
+    // Not every platform supports global shortcuts (e.g. Wayland); bindings then work in-app only
+    if let Err(e) = app.handle().plugin(tauri_plugin_global_shortcut::Builder::new().build()) {
+        warn!("Global shortcuts are unavailable, shortcuts will only work in-app: {}", e);
+    }
+
+    info!("Global shortcuts plugin initialized successfully");
+    Ok(())
 }
 
 #[tauri::command]
-pub async fn get_shortcut_config(_window: Window) -> Result<ShortcutConfig, String> {
-    Ok(ShortcutConfig::default())
+pub async fn get_shortcut_config(_window: Window, registry: State<'_, ShortcutRegistry>) -> Result<ShortcutConfig, String> {
+    Ok(registry.config())
 }
 
 #[tauri::command]
-pub async fn update_shortcut_config(_window: Window, config: ShortcutConfig) -> Result<(), String> {
+pub async fn update_shortcut_config(window: Window, registry: State<'_, ShortcutRegistry>, config: ShortcutConfig) -> Result<(), String> {
     info!("Updating shortcut config");
+    registry.update_config(config)?;
+    sync_global_shortcuts(window.app_handle());
     Ok(())
 }
 
 #[tauri::command]
-pub async fn get_available_shortcut_actions(_window: Window) -> Result<Vec<String>, String> {
-    Ok(vec![])
+pub async fn get_available_shortcut_actions(_window: Window, registry: State<'_, ShortcutRegistry>) -> Result<Vec<ShortcutActionInfo>, String> {
+    Ok(registry.actions())
 }
 
 #[tauri::command]
-pub async fn register_custom_shortcut(_window: Window, shortcut: String, action: String) -> Result<(), String> {
+pub async fn register_custom_shortcut(
+    window: Window,
+    registry: State<'_, ShortcutRegistry>,
+    shortcut: String,
+    action: String,
+    scope: Option<ShortcutScope>,
+) -> Result<ShortcutBinding, String> {
     info!("Registering shortcut: {} -> {}", shortcut, action);
-    Ok(())
+    let binding = registry.bind(&shortcut, &action, scope.unwrap_or_default())?;
+    sync_global_shortcuts(window.app_handle());
+    Ok(binding)
 }
 
 #[tauri::command]
-pub async fn unregister_shortcut(_window: Window, shortcut: String) -> Result<(), String> {
+pub async fn unregister_shortcut(window: Window, registry: State<'_, ShortcutRegistry>, shortcut: String) -> Result<ShortcutBinding, String> {
     info!("Unregistering shortcut: {}", shortcut);
-    Ok(())
+    let binding = registry.unbind(&shortcut)?;
+    sync_global_shortcuts(window.app_handle());
+    Ok(binding)
 }
 
+/// Validate a shortcut and report conflicts without binding it
 #[tauri::command]
-pub async fn test_shortcut(_window: Window, shortcut: String) -> Result<(), String> {
+pub async fn test_shortcut(_window: Window, registry: State<'_, ShortcutRegistry>, shortcut: String) -> Result<AcceleratorCheck, String> {
     info!("Testing shortcut: {}", shortcut);
-    Ok(())
-}
\ No newline at end of file
+    registry.check(&shortcut)
+}
+
+/// Dispatch a key combination pressed in the app; returns the action it ran, if any
+#[tauri::command]
+pub async fn trigger_shortcut(window: Window, shortcut: String) -> Result<Option<String>, String> {
+    run_shortcut(window.app_handle(), &shortcut, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn action(id: &str, default_accelerator: Option<&str>) -> ShortcutAction {
+        ShortcutAction {
+            id: id.to_string(),
+            label: id.to_string(),
+            source: "test".to_string(),
+            default_accelerator: default_accelerator.map(str::to_string),
+            default_scope: ShortcutScope::App,
+        }
+    }
+
+    #[test]
+    fn test_accelerators_are_parsed_and_normalized() {
+        assert_eq!(normalize_accelerator("shift+ctrl+p").unwrap(), "Ctrl+Shift+P");
+        assert_eq!(normalize_accelerator("Alt + Up").unwrap(), "Alt+ArrowUp");
+        assert_eq!(normalize_accelerator("Super+,").unwrap(), "Super+Comma");
+        assert_eq!(normalize_accelerator("f5").unwrap(), "F5");
+        #[cfg(not(target_os = "macos"))]
+        assert_eq!(normalize_accelerator("CmdOrCtrl+K").unwrap(), "Ctrl+K");
+
+        assert!(normalize_accelerator("").is_err());
+        assert!(normalize_accelerator("Ctrl+").is_err());
+        assert!(normalize_accelerator("Hyper+K").is_err());
+        assert!(normalize_accelerator("Ctrl+Ctrl+K").is_err());
+        assert!(normalize_accelerator("Ctrl+F25").is_err());
+        // Plain and Shift-only letters are typing
+        assert!(normalize_accelerator("K").is_err());
+        assert!(normalize_accelerator("Shift+K").is_err());
+    }
+
+    #[test]
+    fn test_bindings_detect_conflicts_and_persist() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("shortcuts.json");
+        let registry = ShortcutRegistry::open(Some(path.clone()));
+        registry.register_action(action("palette", Some("Ctrl+Shift+P")), None).unwrap();
+        registry.register_action(action("search", Some("Ctrl+Shift+P")), None).unwrap();
+        registry.register_action(action("build", None), None).unwrap();
+
+        // The first default wins a clash between defaults
+        assert_eq!(registry.bindings().len(), 1);
+        assert_eq!(registry.check("ctrl+shift+p").unwrap().bound_to.as_deref(), Some("palette"));
+
+        assert!(registry.bind("Ctrl+Shift+P", "build", ShortcutScope::App).unwrap_err().contains("palette"));
+        assert!(registry.bind("Ctrl+C", "build", ShortcutScope::App).unwrap_err().contains("reserved"));
+        assert!(registry.bind("Ctrl+B", "deploy", ShortcutScope::App).is_err());
+        registry.bind("Ctrl+B", "build", ShortcutScope::Global).unwrap();
+        registry.bind("Ctrl+Alt+B", "build", ShortcutScope::Global).unwrap();
+
+        // Removing the palette default frees it for the other action
+        registry.unbind("Ctrl+Shift+P").unwrap();
+        assert_eq!(registry.check("Ctrl+Shift+P").unwrap().bound_to.as_deref(), Some("search"));
+
+        let reopened = ShortcutRegistry::open(Some(path));
+        reopened.register_action(action("palette", Some("Ctrl+Shift+P")), None).unwrap();
+        reopened.register_action(action("build", None), None).unwrap();
+        let bindings = reopened.bindings();
+        assert_eq!(bindings, vec![ShortcutBinding {
+            accelerator: "Ctrl+Alt+B".to_string(),
+            action: "build".to_string(),
+            scope: ShortcutScope::Global,
+        }]);
+    }
+
+    #[test]
+    fn test_bound_actions_are_dispatched() {
+        let registry = ShortcutRegistry::open(None);
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        registry.register_action(action("build", Some("Ctrl+B")), Some(Arc::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }))).unwrap();
+
+        assert_eq!(registry.trigger("ctrl+b", false).unwrap().as_deref(), Some("build"));
+        assert_eq!(registry.trigger("Ctrl+N", false).unwrap(), None);
+        assert!(registry.trigger("Ctrl+", false).is_err());
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        // Once the OS delivers the shortcut, in-app presses are not dispatched again
+        registry.set_active_globally(BTreeSet::from(["Ctrl+B".to_string()]));
+        assert_eq!(registry.trigger("Ctrl+B", false).unwrap(), None);
+        assert_eq!(registry.trigger("Ctrl+B", true).unwrap().as_deref(), Some("build"));
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+        registry.update_config(ShortcutConfig { enabled: false, ..ShortcutConfig::default() }).unwrap();
+        assert_eq!(registry.trigger("Ctrl+B", true).unwrap(), None);
+    }
+}
@@ -1,7 +1,7 @@
 // Logging plugin for AutoDev-AI Neural Bridge Platform
 use serde::{Deserialize, Serialize};
-use tauri::{App, AppHandle, Runtime, State, Window, Result};
-use tracing::{info, warn, error};
+use tauri::{App, Runtime, Window};
+use tracing::info;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoggingConfig {
@@ -20,7 +20,7 @@ impl Default for LoggingConfig {
     }
 }
 
-pub async fn setup_logging_plugin<R: Runtime>(app: &App<R>) -> Result<()> {
+pub async fn setup_logging_plugin<R: Runtime>(app: &App<R>) -> tauri::Result<()> {
     info!("Setting up logging plugin...");
     info!("Logging plugin initialized successfully");
     Ok(())
@@ -1,7 +1,7 @@
 // Menu plugin for AutoDev-AI Neural Bridge Platform
 use serde::{Deserialize, Serialize};
-use tauri::{App, AppHandle, Runtime, State, Window, Result};
-use tracing::{info, warn, error};
+use tauri::{App, Runtime, Window};
+use tracing::info;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MenuConfig {
@@ -26,7 +26,7 @@ impl Default for MenuConfig {
     }
 }
 
-pub async fn setup_menu_plugin<R: Runtime>(app: &App<R>) -> Result<()> {
+pub async fn setup_menu_plugin<R: Runtime>(app: &App<R>) -> tauri::Result<()> {
     info!("Setting up menu plugin...");
     
     // Menu setup would go here
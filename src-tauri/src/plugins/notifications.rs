@@ -1,14 +1,49 @@
 // Notifications plugin for AutoDev-AI Neural Bridge Platform
+//
+// A `NotificationCenter` keeps notifications with their read/dismissed state in
+// a JSON file in the app data directory. Repeats of a recent notification are
+// grouped into it, desktop alerts respect the do-not-disturb windows of the
+// config, and actions are dispatched to handlers registered by other modules.
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, NaiveTime, Utc, Weekday};
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
-use tauri::{App, AppHandle, Runtime, State, Window, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tauri::{App, AppHandle, Emitter, Manager, Runtime, State, Window};
+use tauri_plugin_notification::NotificationExt;
 use tracing::{info, warn, error};
+use uuid::Uuid;
+
+/// Built-in actions every notification supports
+const OPEN_ACTION: &str = "open";
+const DISMISS_ACTION: &str = "dismiss";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NotificationConfig {
     pub enabled: bool,
     pub sound_enabled: bool,
     pub desktop_notifications: bool,
+    /// Lowest severity shown as a desktop notification: "all", "warning" or "error"
     pub priority_filter: String,
+    /// Periods in which no desktop notifications are shown
+    #[serde(default)]
+    pub do_not_disturb: Vec<DoNotDisturbWindow>,
+    /// A notification repeated within this many seconds is grouped with the earlier one
+    #[serde(default = "default_group_window_secs")]
+    pub group_window_secs: u64,
+    #[serde(default = "default_max_stored")]
+    pub max_stored: usize,
+}
+
+fn default_group_window_secs() -> u64 {
+    300
+}
+
+fn default_max_stored() -> usize {
+    500
 }
 
 impl Default for NotificationConfig {
@@ -18,91 +53,661 @@ impl Default for NotificationConfig {
             sound_enabled: true,
             desktop_notifications: true,
             priority_filter: "all".to_string(),
+            do_not_disturb: Vec::new(),
+            group_window_secs: default_group_window_secs(),
+            max_stored: default_max_stored(),
+        }
+    }
+}
+
+impl NotificationConfig {
+    fn validate(&self) -> Result<(), String> {
+        min_desktop_severity(&self.priority_filter)?;
+        for window in &self.do_not_disturb {
+            parse_time(&window.start)?;
+            parse_time(&window.end)?;
+        }
+        Ok(())
+    }
+
+    /// Whether a notification should also be shown on the desktop at local time `at`
+    fn should_alert(&self, severity: NotificationSeverity, at: NaiveDateTime) -> bool {
+        self.enabled
+            && self.desktop_notifications
+            && min_desktop_severity(&self.priority_filter).is_ok_and(|min| severity >= min)
+            && !self.do_not_disturb.iter().any(|window| window.contains(at))
+    }
+}
+
+fn min_desktop_severity(priority_filter: &str) -> Result<NotificationSeverity, String> {
+    match priority_filter {
+        "all" => Ok(NotificationSeverity::Info),
+        "warning" => Ok(NotificationSeverity::Warning),
+        "error" => Ok(NotificationSeverity::Error),
+        other => Err(format!("Unknown priority filter: {}", other)),
+    }
+}
+
+/// Local time window such as 22:00-07:00; windows ending before they start run past midnight
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoNotDisturbWindow {
+    /// "HH:MM"
+    pub start: String,
+    pub end: String,
+    /// Days the window starts on; every day if empty
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+}
+
+impl DoNotDisturbWindow {
+    fn contains(&self, at: NaiveDateTime) -> bool {
+        let (Ok(start), Ok(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+        let time = at.time();
+        let day = at.weekday();
+
+        if start <= end {
+            self.starts_on(day) && time >= start && time < end
+        } else {
+            // After midnight the window that started the day before applies
+            (self.starts_on(day) && time >= start) || (self.starts_on(day.pred()) && time < end)
+        }
+    }
+
+    fn starts_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| format!("Invalid time {}, expected HH:MM", value))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationSeverity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationAction {
+    pub id: String,
+    pub label: String,
+}
+
+/// A notification as raised by a part of the application
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewNotification {
+    pub title: String,
+    pub body: String,
+    pub severity: NotificationSeverity,
+    /// Component that raised it, e.g. "docker" or "updater"
+    pub source: String,
+    #[serde(default)]
+    pub actions: Vec<NotificationAction>,
+    /// Route in the UI to open for the notification
+    #[serde(default)]
+    pub deep_link: Option<String>,
+    /// Notifications with the same key are grouped; defaults to source and title
+    #[serde(default)]
+    pub group_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: String,
+    pub title: String,
+    pub body: String,
+    pub severity: NotificationSeverity,
+    pub source: String,
+    pub actions: Vec<NotificationAction>,
+    pub deep_link: Option<String>,
+    pub group_key: String,
+    /// How many times it was raised while grouped
+    pub count: u32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub read: bool,
+    pub dismissed: bool,
+}
+
+/// Handler for a notification action, registered under the action id
+pub type ActionHandler = Arc<dyn Fn(&Notification) -> Result<(), String> + Send + Sync>;
+
+/// What is written to disk
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredNotifications {
+    config: NotificationConfig,
+    /// Most recently updated first
+    notifications: Vec<Notification>,
+}
+
+/// The store serialized under its lock, numbered in the order of the changes
+struct Snapshot {
+    version: u64,
+    contents: Vec<u8>,
+}
+
+/// Notification store shared by the notification commands
+pub struct NotificationCenter {
+    path: Option<PathBuf>,
+    store: RwLock<StoredNotifications>,
+    /// Version of the last snapshot taken; only bumped under the store's write lock
+    version: AtomicU64,
+    /// Version of the snapshot on disk, locked while a snapshot is written
+    saved_version: Mutex<u64>,
+    handlers: RwLock<HashMap<String, ActionHandler>>,
+}
+
+impl NotificationCenter {
+    /// Load the notifications saved at `path`, or start empty; without a path nothing is persisted
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let store = path
+            .as_ref()
+            .filter(|path| path.exists())
+            .and_then(|path| {
+                fs::read(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|contents| serde_json::from_slice(&contents).map_err(|e| e.to_string()))
+                    .map_err(|e| warn!("Failed to load notifications from {}: {}", path.display(), e))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        Self {
+            path,
+            store: RwLock::new(store),
+            version: AtomicU64::new(0),
+            saved_version: Mutex::new(0),
+            handlers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn default_path() -> Option<PathBuf> {
+        let dirs = directories::ProjectDirs::from("com", "autodev-ai", "neural-bridge-platform")?;
+        Some(dirs.data_dir().join("notifications.json"))
+    }
+
+    /// Serialize the store for `save`; call with the write lock held
+    fn snapshot(&self, store: &StoredNotifications) -> Option<Snapshot> {
+        let path = self.path.as_ref()?;
+        match serde_json::to_vec_pretty(store) {
+            Ok(contents) => Some(Snapshot {
+                version: self.version.fetch_add(1, Ordering::Relaxed) + 1,
+                contents,
+            }),
+            Err(e) => {
+                error!("Failed to save notifications to {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Write a snapshot to disk once the store lock is released, replacing the previous
+    /// file atomically; a snapshot older than the one on disk is skipped
+    fn save(&self, snapshot: Option<Snapshot>) {
+        let (Some(path), Some(snapshot)) = (&self.path, snapshot) else { return };
+        let mut saved_version = self.saved_version.lock();
+        if snapshot.version <= *saved_version {
+            return;
+        }
+
+        let result = (|| -> Result<(), String> {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let tmp_path = path.with_extension("json.tmp");
+            fs::write(&tmp_path, snapshot.contents).map_err(|e| e.to_string())?;
+            fs::rename(&tmp_path, path).map_err(|e| e.to_string())
+        })();
+
+        match result {
+            Ok(()) => *saved_version = snapshot.version,
+            Err(e) => error!("Failed to save notifications to {}: {}", path.display(), e),
+        }
+    }
+
+    /// Add a notification, or group it with a recent one with the same key
+    ///
+    /// Returns the stored notification and whether it should be shown on the desktop,
+    /// or `None` if notifications are disabled. Repeats grouped into a notification
+    /// are only shown if they raise its severity.
+    pub fn notify(&self, new: NewNotification) -> Option<(Notification, bool)> {
+        self.notify_at(new, Utc::now())
+    }
+
+    fn notify_at(&self, new: NewNotification, now: DateTime<Utc>) -> Option<(Notification, bool)> {
+        let mut store = self.store.write();
+        if !store.config.enabled {
+            return None;
+        }
+
+        let group_key = new
+            .group_key
+            .clone()
+            .unwrap_or_else(|| format!("{}:{}", new.source, new.title));
+        let group_window = chrono::Duration::seconds(store.config.group_window_secs as i64);
+        let grouped = store.notifications.iter().position(|notification| {
+            notification.group_key == group_key && !notification.dismissed && now - notification.updated_at <= group_window
+        });
+
+        let mut escalated = true;
+        let notification = match grouped {
+            Some(index) => {
+                let mut notification = store.notifications.remove(index);
+                escalated = new.severity > notification.severity;
+                notification.count += 1;
+                notification.title = new.title;
+                notification.body = new.body;
+                notification.severity = notification.severity.max(new.severity);
+                notification.actions = new.actions;
+                notification.deep_link = new.deep_link;
+                notification.updated_at = now;
+                notification.read = false;
+                notification
+            }
+            None => Notification {
+                id: Uuid::new_v4().to_string(),
+                title: new.title,
+                body: new.body,
+                severity: new.severity,
+                source: new.source,
+                actions: new.actions,
+                deep_link: new.deep_link,
+                group_key,
+                count: 1,
+                created_at: now,
+                updated_at: now,
+                read: false,
+                dismissed: false,
+            },
+        };
+        store.notifications.insert(0, notification.clone());
+
+        // Forget dismissed notifications first, then the oldest
+        while store.notifications.len() > store.config.max_stored.max(1) {
+            let index = store
+                .notifications
+                .iter()
+                .rposition(|notification| notification.dismissed)
+                .unwrap_or(store.notifications.len() - 1);
+            store.notifications.remove(index);
+        }
+
+        let alert = escalated && store.config.should_alert(notification.severity, now.with_timezone(&Local).naive_local());
+        let snapshot = self.snapshot(&store);
+        drop(store);
+        self.save(snapshot);
+        Some((notification, alert))
+    }
+
+    /// Notifications, most recently updated first
+    pub fn list(&self, include_dismissed: bool) -> Vec<Notification> {
+        self.store
+            .read()
+            .notifications
+            .iter()
+            .filter(|notification| include_dismissed || !notification.dismissed)
+            .cloned()
+            .collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Notification> {
+        self.store.read().notifications.iter().find(|notification| notification.id == id).cloned()
+    }
+
+    pub fn unread_count(&self) -> usize {
+        self.store
+            .read()
+            .notifications
+            .iter()
+            .filter(|notification| !notification.read && !notification.dismissed)
+            .count()
+    }
+
+    fn update(&self, id: &str, change: impl FnOnce(&mut Notification)) -> Result<(), String> {
+        let mut store = self.store.write();
+        let notification = store
+            .notifications
+            .iter_mut()
+            .find(|notification| notification.id == id)
+            .ok_or_else(|| format!("Notification {} not found", id))?;
+        change(notification);
+        let snapshot = self.snapshot(&store);
+        drop(store);
+        self.save(snapshot);
+        Ok(())
+    }
+
+    pub fn mark_read(&self, id: &str) -> Result<(), String> {
+        self.update(id, |notification| notification.read = true)
+    }
+
+    pub fn mark_all_read(&self) {
+        let mut store = self.store.write();
+        store.notifications.iter_mut().for_each(|notification| notification.read = true);
+        let snapshot = self.snapshot(&store);
+        drop(store);
+        self.save(snapshot);
+    }
+
+    /// Hide a notification; it stays stored, marked dismissed, until trimmed or cleared
+    pub fn dismiss(&self, id: &str) -> Result<(), String> {
+        self.update(id, |notification| {
+            notification.read = true;
+            notification.dismissed = true;
+        })
+    }
+
+    /// Remove every notification, returning how many there were
+    pub fn clear(&self) -> usize {
+        let mut store = self.store.write();
+        let count = store.notifications.len();
+        store.notifications.clear();
+        let snapshot = self.snapshot(&store);
+        drop(store);
+        self.save(snapshot);
+        count
+    }
+
+    pub fn config(&self) -> NotificationConfig {
+        self.store.read().config.clone()
+    }
+
+    pub fn update_config(&self, config: NotificationConfig) -> Result<(), String> {
+        config.validate()?;
+        let mut store = self.store.write();
+        store.config = config;
+        let snapshot = self.snapshot(&store);
+        drop(store);
+        self.save(snapshot);
+        Ok(())
+    }
+
+    /// Run `handler` when `action` is chosen on a notification that offers it
+    pub fn register_action_handler(&self, action: impl Into<String>, handler: ActionHandler) {
+        self.handlers.write().insert(action.into(), handler);
+    }
+
+    /// Dispatch an action of a notification to its handler and mark the notification read
+    pub fn handle_action(&self, id: &str, action: &str) -> Result<(), String> {
+        let notification = self.get(id).ok_or_else(|| format!("Notification {} not found", id))?;
+        if action == DISMISS_ACTION {
+            return self.dismiss(id);
+        }
+
+        let offered = notification.actions.iter().any(|offered| offered.id == action)
+            || (action == OPEN_ACTION && notification.deep_link.is_some());
+        if !offered {
+            return Err(format!("Notification {} has no action {}", id, action));
         }
+
+        let handler = self
+            .handlers
+            .read()
+            .get(action)
+            .cloned()
+            .ok_or_else(|| format!("No handler registered for action {}", action))?;
+        handler(&notification)?;
+        self.mark_read(id)
+    }
+}
+
+/// Store a notification, tell the frontend about it and show it on the desktop if allowed
+pub fn push_notification<R: Runtime>(app: &AppHandle<R>, new: NewNotification) -> Option<Notification> {
+    let center = app.try_state::<NotificationCenter>()?;
+    let (notification, alert) = center.notify(new)?;
+
+    if let Err(e) = app.emit("notification", &notification) {
+        warn!("Failed to emit notification: {}", e);
     }
+    if alert {
+        let mut builder = app
+            .notification()
+            .builder()
+            .title(&notification.title)
+            .body(&notification.body);
+        if center.config().sound_enabled {
+            builder = builder.sound("default");
+        }
+        if let Err(e) = builder.show() {
+            warn!("Failed to show desktop notification: {}", e);
+        }
+    }
+
+    Some(notification)
 }
 
-pub async fn setup_notifications_plugin<R: Runtime>(app: &App<R>) -> Result<()> {
+pub async fn setup_notifications_plugin<R: Runtime>(app: &App<R>) -> tauri::Result<()> {
     info!("Setting up notifications plugin...");
-    
-    // Notifications setup would go here
-    // For now, just log that it's initialized
-    
+
+    let center = NotificationCenter::open(NotificationCenter::default_path());
+
+    // Opening a notification navigates the UI to its deep link
+    let handle = app.handle().clone();
+    center.register_action_handler(OPEN_ACTION, Arc::new(move |notification: &Notification| {
+        let deep_link = notification.deep_link.as_deref().ok_or("Notification has no deep link")?;
+        handle
+            .emit("notification-open", serde_json::json!({ "id": notification.id, "deep_link": deep_link }))
+            .map_err(|e| format!("Failed to open {}: {}", deep_link, e))
+    }));
+
+    app.manage(center);
     info!("Notifications plugin initialized successfully");
     Ok(())
 }
 
-pub async fn health_check<R: Runtime>(app: &App<R>) -> bool {
-    // Check if notifications are functional
-    true
-}
-
 #[tauri::command]
-pub async fn get_notifications(_window: Window) -> Result<Vec<String>, String> {
-    Ok(vec![])
+pub async fn get_notifications(_window: Window, center: State<'_, NotificationCenter>, include_dismissed: Option<bool>) -> Result<Vec<Notification>, String> {
+    Ok(center.list(include_dismissed.unwrap_or(false)))
 }
 
 #[tauri::command]
-pub async fn get_unread_count(_window: Window) -> Result<u32, String> {
-    Ok(0)
+pub async fn get_unread_count(_window: Window, center: State<'_, NotificationCenter>) -> Result<u32, String> {
+    Ok(center.unread_count() as u32)
 }
 
 #[tauri::command]
 pub async fn mark_notification_read(
     _window: Window,
+    center: State<'_, NotificationCenter>,
     id: String,
 ) -> Result<(), String> {
     info!("Marking notification as read: {}", id);
-    Ok(())
+    center.mark_read(&id)
 }
 
 #[tauri::command]
-pub async fn mark_all_notifications_read(_window: Window) -> Result<(), String> {
+pub async fn mark_all_notifications_read(_window: Window, center: State<'_, NotificationCenter>) -> Result<(), String> {
     info!("Marking all notifications as read");
+    center.mark_all_read();
     Ok(())
 }
 
 #[tauri::command]
 pub async fn dismiss_notification(
     _window: Window,
+    center: State<'_, NotificationCenter>,
     id: String,
 ) -> Result<(), String> {
     info!("Dismissing notification: {}", id);
-    Ok(())
+    center.dismiss(&id)
 }
 
 #[tauri::command]
-pub async fn clear_all_notifications(_window: Window) -> Result<(), String> {
+pub async fn clear_all_notifications(_window: Window, center: State<'_, NotificationCenter>) -> Result<(), String> {
     info!("Clearing all notifications");
+    center.clear();
     Ok(())
 }
 
 #[tauri::command]
-pub async fn get_notification_config(_window: Window) -> Result<NotificationConfig, String> {
-    Ok(NotificationConfig::default())
+pub async fn get_notification_config(_window: Window, center: State<'_, NotificationCenter>) -> Result<NotificationConfig, String> {
+    Ok(center.config())
 }
 
 #[tauri::command]
 pub async fn update_notification_config(
     _window: Window,
+    center: State<'_, NotificationCenter>,
     config: NotificationConfig,
 ) -> Result<(), String> {
     info!("Updating notification config");
-    Ok(())
+    center.update_config(config)
 }
 
 #[tauri::command]
-pub async fn send_test_notification(_window: Window) -> Result<(), String> {
+pub async fn send_test_notification(window: Window) -> Result<(), String> {
     info!("Sending test notification");
-    Ok(())
+    push_notification(window.app_handle(), NewNotification {
+        title: "Test notification".to_string(),
+        body: "Notifications are working".to_string(),
+        severity: NotificationSeverity::Info,
+        source: "notifications".to_string(),
+        actions: Vec::new(),
+        deep_link: None,
+        group_key: None,
+    })
+    .map(|_| ())
+    .ok_or_else(|| "Notifications are disabled".to_string())
 }
 
 #[tauri::command]
 pub async fn handle_notification_action(
     _window: Window,
+    center: State<'_, NotificationCenter>,
     id: String,
     action: String,
 ) -> Result<(), String> {
     info!("Handling notification action: {} for {}", action, id);
-    Ok(())
-}
\ No newline at end of file
+    center.handle_action(&id, &action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn build_failed(body: &str) -> NewNotification {
+        NewNotification {
+            title: "Build failed".to_string(),
+            body: body.to_string(),
+            severity: NotificationSeverity::Error,
+            source: "orchestration".to_string(),
+            actions: vec![NotificationAction { id: "retry".to_string(), label: "Retry".to_string() }],
+            deep_link: Some("/tasks/42".to_string()),
+            group_key: None,
+        }
+    }
+
+    #[test]
+    fn test_notifications_are_grouped_and_persisted() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("notifications.json");
+        let center = NotificationCenter::open(Some(path.clone()));
+        let start = Utc::now();
+
+        let (first, alert) = center.notify_at(build_failed("step 1"), start).unwrap();
+        assert!(alert);
+        let (repeat, alert) = center.notify_at(build_failed("step 2"), start + chrono::Duration::seconds(60)).unwrap();
+        assert!(!alert);
+        assert_eq!(repeat.id, first.id);
+        assert_eq!(repeat.count, 2);
+        assert_eq!(repeat.body, "step 2");
+
+        // Outside the grouping window a new notification is raised
+        let (later, _) = center.notify_at(build_failed("step 3"), start + chrono::Duration::seconds(1000)).unwrap();
+        assert_ne!(later.id, first.id);
+        assert_eq!(center.unread_count(), 2);
+
+        center.mark_read(&first.id).unwrap();
+        center.dismiss(&later.id).unwrap();
+        assert!(center.mark_read("missing").is_err());
+
+        let reopened = NotificationCenter::open(Some(path));
+        assert_eq!(reopened.unread_count(), 0);
+        assert_eq!(reopened.list(false).len(), 1);
+        assert!(reopened.list(true)[0].dismissed);
+    }
+
+    #[test]
+    fn test_grouped_repeats_alert_only_when_severity_rises() {
+        let center = NotificationCenter::open(None);
+        let start = Utc::now();
+        let warning = |body: &str| NewNotification {
+            severity: NotificationSeverity::Warning,
+            ..build_failed(body)
+        };
+
+        let (_, alert) = center.notify_at(warning("flaky"), start).unwrap();
+        assert!(alert);
+        let (_, alert) = center.notify_at(warning("flaky again"), start + chrono::Duration::seconds(1)).unwrap();
+        assert!(!alert);
+        let (escalated, alert) = center.notify_at(build_failed("broken"), start + chrono::Duration::seconds(2)).unwrap();
+        assert!(alert);
+        assert_eq!((escalated.count, escalated.severity), (3, NotificationSeverity::Error));
+        let (_, alert) = center.notify_at(warning("flaky"), start + chrono::Duration::seconds(3)).unwrap();
+        assert!(!alert);
+    }
+
+    #[test]
+    fn test_do_not_disturb_windows() {
+        let night = DoNotDisturbWindow {
+            start: "22:00".to_string(),
+            end: "07:00".to_string(),
+            days: vec![Weekday::Fri],
+        };
+        // 2026-10-16 is a Friday
+        let at = |day: u32, time: &str| {
+            NaiveDateTime::parse_from_str(&format!("2026-10-{} {}", day, time), "%Y-%m-%d %H:%M").unwrap()
+        };
+        assert!(night.contains(at(16, "23:30")));
+        assert!(night.contains(at(17, "06:59")));
+        assert!(!night.contains(at(17, "07:00")));
+        assert!(!night.contains(at(15, "23:30")));
+
+        let config = NotificationConfig {
+            priority_filter: "warning".to_string(),
+            do_not_disturb: vec![night],
+            ..NotificationConfig::default()
+        };
+        assert!(config.validate().is_ok());
+        assert!(!config.should_alert(NotificationSeverity::Error, at(16, "23:30")));
+        assert!(config.should_alert(NotificationSeverity::Error, at(16, "12:00")));
+        assert!(!config.should_alert(NotificationSeverity::Info, at(16, "12:00")));
+
+        let invalid = NotificationConfig {
+            do_not_disturb: vec![DoNotDisturbWindow { start: "25:00".to_string(), end: "07:00".to_string(), days: vec![] }],
+            ..NotificationConfig::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_actions_are_dispatched_to_handlers() {
+        let center = NotificationCenter::open(None);
+        let (notification, _) = center.notify(build_failed("step 1")).unwrap();
+
+        assert!(center.handle_action(&notification.id, "retry").is_err());
+
+        let retries = Arc::new(AtomicUsize::new(0));
+        let counter = retries.clone();
+        center.register_action_handler("retry", Arc::new(move |_: &Notification| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }));
+        center.handle_action(&notification.id, "retry").unwrap();
+        assert_eq!(retries.load(Ordering::SeqCst), 1);
+        assert_eq!(center.unread_count(), 0);
+
+        // Only actions the notification offers can be triggered
+        assert!(center.handle_action(&notification.id, "deploy").is_err());
+
+        center.handle_action(&notification.id, "dismiss").unwrap();
+        assert!(center.list(false).is_empty());
+    }
+}
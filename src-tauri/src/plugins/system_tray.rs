@@ -1,7 +1,7 @@
 // System tray plugin for AutoDev-AI Neural Bridge Platform
 use serde::{Deserialize, Serialize};
-use tauri::{App, AppHandle, Runtime, State, Window, Result};
-use tracing::{info, warn, error};
+use tauri::{App, Runtime, Window};
+use tracing::info;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemTrayConfig {
@@ -22,7 +22,7 @@ impl Default for SystemTrayConfig {
     }
 }
 
-pub async fn setup_system_tray_plugin<R: Runtime>(app: &App<R>) -> Result<()> {
+pub async fn setup_system_tray_plugin<R: Runtime>(app: &App<R>) -> tauri::Result<()> {
     info!("Setting up system tray plugin...");
     
     // System tray setup would go here
@@ -1,7 +1,7 @@
 // Updater plugin for AutoDev-AI Neural Bridge Platform
 use serde::{Deserialize, Serialize};
-use tauri::{App, AppHandle, Runtime, State, Window, Result};
-use tracing::{info, warn, error};
+use tauri::{App, Runtime, Window};
+use tracing::info;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdaterConfig {
@@ -25,7 +25,7 @@ impl Default for UpdaterConfig {
     }
 }
 
-pub async fn setup_updater_plugin<R: Runtime>(app: &App<R>) -> Result<()> {
+pub async fn setup_updater_plugin<R: Runtime>(app: &App<R>) -> tauri::Result<()> {
     info!("Setting up updater plugin...");
     
     // Updater setup would go here
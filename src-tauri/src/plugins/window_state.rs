@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::fs;
-use tauri::{App, AppHandle, Manager, Runtime, Window, Result};
+use tauri::{App, AppHandle, Manager, Runtime, WebviewWindow};
 use tracing::{info, warn, error};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,7 +72,7 @@ impl MultiWindowState {
         }
     }
 
-    pub fn get_config_path() -> Result<PathBuf> {
+    pub fn get_config_path() -> tauri::Result<PathBuf> {
         let dirs = directories::ProjectDirs::from("com", "autodev-ai", "neural-bridge-platform")
             .ok_or_else(|| tauri::Error::Io(std::io::Error::new(
                 std::io::ErrorKind::Other,
@@ -83,7 +83,7 @@ impl MultiWindowState {
         Ok(config_dir.join("multi_window_state.json"))
     }
     
-    pub fn load() -> Result<Self> {
+    pub fn load() -> tauri::Result<Self> {
         let config_path = Self::get_config_path()?;
         
         if !config_path.exists() {
@@ -100,7 +100,7 @@ impl MultiWindowState {
         Ok(state)
     }
 
-    pub fn save(&self) -> Result<()> {
+    pub fn save(&self) -> tauri::Result<()> {
         let config_path = Self::get_config_path()?;
         
         if let Some(parent) = config_path.parent() {
@@ -115,7 +115,7 @@ impl MultiWindowState {
     }
 }
 
-pub async fn setup_window_state_plugin<R: Runtime>(app: &App<R>) -> Result<()> {
+pub async fn setup_window_state_plugin<R: Runtime>(app: &App<R>) -> tauri::Result<()> {
     info!("Setting up window state plugin...");
     
     let mut multi_state = match MultiWindowState::load() {
@@ -126,7 +126,7 @@ pub async fn setup_window_state_plugin<R: Runtime>(app: &App<R>) -> Result<()> {
         }
     };
 
-    for window in app.windows().values() {
+    for window in app.webview_windows().values() {
         let label = window.label();
         
         if let Some(saved_state) = multi_state.windows.get(label) {
@@ -140,26 +140,28 @@ pub async fn setup_window_state_plugin<R: Runtime>(app: &App<R>) -> Result<()> {
 
     let app_handle = app.handle();
     
-    for window in app.windows().values() {
+    for window in app.webview_windows().values() {
         let window_clone = window.clone();
         let app_handle_clone = app_handle.clone();
         
         window.on_window_event(move |event| {
-            let label = window_clone.label();
+            let label = window_clone.label().to_string();
             
             match event {
                 tauri::WindowEvent::Moved(_) | tauri::WindowEvent::Resized(_) => {
                     let app_handle = app_handle_clone.clone();
+                    let label = label.clone();
                     tauri::async_runtime::spawn(async move {
-                        if let Err(e) = save_window_state_to_storage(&app_handle, label).await {
+                        if let Err(e) = save_window_state_to_storage(&app_handle, &label).await {
                             error!("Failed to save window state for '{}': {}", label, e);
                         }
                     });
                 }
                 tauri::WindowEvent::CloseRequested { .. } => {
                     let app_handle = app_handle_clone.clone();
+                    let label = label.clone();
                     tauri::async_runtime::spawn(async move {
-                        if let Err(e) = save_window_state_to_storage(&app_handle, label).await {
+                        if let Err(e) = save_window_state_to_storage(&app_handle, &label).await {
                             error!("Failed to save window state on close for '{}': {}", label, e);
                         }
                     });
@@ -167,7 +169,7 @@ pub async fn setup_window_state_plugin<R: Runtime>(app: &App<R>) -> Result<()> {
                 tauri::WindowEvent::Focused(focused) => {
                     if *focused {
                         let app_handle = app_handle_clone.clone();
-                        let label = label.to_string();
+                        let label = label.clone();
                         tauri::async_runtime::spawn(async move {
                             if let Err(e) = update_last_active_window(&app_handle, &label).await {
                                 error!("Failed to update last active window: {}", e);
@@ -253,9 +255,9 @@ pub async fn reset_window_state<R: Runtime>(
 }
 
 async fn restore_window_state<R: Runtime>(
-    window: &Window<R>,
+    window: &WebviewWindow<R>,
     state: &WindowState,
-) -> Result<()> {
+) -> tauri::Result<()> {
     // Restore window properties
     Ok(())
 }
@@ -263,10 +265,10 @@ async fn restore_window_state<R: Runtime>(
 async fn save_window_state_to_storage<R: Runtime>(
     app_handle: &AppHandle<R>,
     window_label: &str,
-) -> Result<()> {
+) -> tauri::Result<()> {
     let mut multi_state = MultiWindowState::load().unwrap_or_default();
     
-    if let Some(window) = app_handle.get_window(window_label) {
+    if let Some(window) = app_handle.get_webview_window(window_label) {
         let state = get_current_window_state(&window).await?;
         multi_state.windows.insert(window_label.to_string(), state);
         multi_state.updated_at = chrono::Utc::now();
@@ -276,14 +278,14 @@ async fn save_window_state_to_storage<R: Runtime>(
     Ok(())
 }
 
-async fn get_current_window_state<R: Runtime>(window: &Window<R>) -> Result<WindowState> {
+async fn get_current_window_state<R: Runtime>(window: &WebviewWindow<R>) -> tauri::Result<WindowState> {
     Ok(WindowState::default())
 }
 
 async fn update_last_active_window<R: Runtime>(
     app_handle: &AppHandle<R>,
     window_label: &str,
-) -> Result<()> {
+) -> tauri::Result<()> {
     let mut multi_state = MultiWindowState::load().unwrap_or_default();
     multi_state.last_active = Some(window_label.to_string());
     multi_state.updated_at = chrono::Utc::now();
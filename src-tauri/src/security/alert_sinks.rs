@@ -0,0 +1,611 @@
+//! Security Alert Sinks
+//!
+//! Delivers high-severity security events to external sinks: HTTP webhooks
+//! with HMAC-signed JSON bodies, Unix domain sockets and syslog-format files.
+//! Failed deliveries are retried with exponential backoff and, once retries
+//! are exhausted, appended to a dead-letter file so no alert is lost silently.
+
+use super::audit_logger::{SecurityEvent, SecurityEventType, SecuritySeverity};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tracing::error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Header carrying the hex HMAC-SHA256 of `"{timestamp}.{body}"`
+pub const SIGNATURE_HEADER: &str = "X-NeuralBridge-Signature";
+/// Header carrying the Unix timestamp the signature was computed at
+pub const TIMESTAMP_HEADER: &str = "X-NeuralBridge-Timestamp";
+
+/// Destination an alert is delivered to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertSinkKind {
+    /// POST the alert as JSON, signed with `secret`
+    Webhook {
+        url: String,
+        secret: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+    /// Write the alert as one JSON line to a listening Unix domain socket
+    UnixSocket { path: PathBuf },
+    /// Append the alert as an RFC 5424 line to a file
+    SyslogFile {
+        path: PathBuf,
+        #[serde(default = "default_syslog_app_name")]
+        app_name: String,
+    },
+}
+
+fn default_syslog_app_name() -> String {
+    "neural-bridge".to_string()
+}
+
+fn default_min_severity() -> SecuritySeverity {
+    SecuritySeverity::Error
+}
+
+/// A configured alert sink
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertSinkConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: AlertSinkKind,
+    /// Lowest severity this sink receives
+    #[serde(default = "default_min_severity")]
+    pub min_severity: SecuritySeverity,
+}
+
+impl AlertSinkConfig {
+    /// Whether this sink should receive the event
+    pub fn accepts(&self, event: &SecurityEvent) -> bool {
+        event.severity >= self.min_severity
+    }
+}
+
+/// Retry behaviour shared by all sinks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub request_timeout_ms: u64,
+}
+
+impl Default for AlertRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 10_000,
+            request_timeout_ms: 5_000,
+        }
+    }
+}
+
+impl AlertRetryPolicy {
+    /// Delay before retrying after the given (1-based) failed attempt
+    pub fn backoff_for_attempt(&self, attempt: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        Duration::from_millis(
+            self.initial_backoff_ms
+                .saturating_mul(factor)
+                .min(self.max_backoff_ms),
+        )
+    }
+}
+
+/// Body sent to webhooks and sockets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertPayload {
+    pub sink: String,
+    pub sent_at: DateTime<Utc>,
+    pub event: SecurityEvent,
+}
+
+/// Record appended to the dead-letter file for an undeliverable alert
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterRecord {
+    pub sink: String,
+    pub attempts: u32,
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+    pub event: SecurityEvent,
+}
+
+/// Outcome of delivering one alert to one sink
+#[derive(Debug, Clone)]
+pub struct AlertDeliveryReport {
+    pub sink: String,
+    pub attempts: u32,
+    pub error: Option<String>,
+}
+
+/// Error from a single delivery attempt
+#[derive(Debug)]
+struct DeliveryError {
+    message: String,
+    retryable: bool,
+}
+
+impl DeliveryError {
+    fn retryable(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            retryable: true,
+        }
+    }
+
+    fn permanent(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            retryable: false,
+        }
+    }
+}
+
+/// Delivers alerts to every configured sink
+#[derive(Debug)]
+pub struct AlertDispatcher {
+    sinks: Vec<AlertSinkConfig>,
+    retry: AlertRetryPolicy,
+    dead_letter_path: PathBuf,
+    client: reqwest::Client,
+}
+
+impl AlertDispatcher {
+    pub fn new(
+        sinks: Vec<AlertSinkConfig>,
+        retry: AlertRetryPolicy,
+        dead_letter_path: PathBuf,
+    ) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(retry.request_timeout_ms))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            sinks,
+            retry,
+            dead_letter_path,
+            client,
+        }
+    }
+
+    pub fn has_sinks(&self) -> bool {
+        !self.sinks.is_empty()
+    }
+
+    /// Whether any sink's threshold admits the event
+    pub fn accepts(&self, event: &SecurityEvent) -> bool {
+        self.sinks.iter().any(|sink| sink.accepts(event))
+    }
+
+    /// Deliver an event to every sink whose threshold it meets
+    ///
+    /// Alerts that still fail after all retries are written to the dead-letter file.
+    pub async fn dispatch(&self, event: &SecurityEvent) -> Vec<AlertDeliveryReport> {
+        let deliveries = self
+            .sinks
+            .iter()
+            .filter(|sink| sink.accepts(event))
+            .map(|sink| self.deliver_with_retry(sink, event));
+
+        let reports = futures::future::join_all(deliveries).await;
+
+        for report in &reports {
+            if let Some(error) = &report.error {
+                self.dead_letter(report, error, event).await;
+            }
+        }
+
+        reports
+    }
+
+    async fn deliver_with_retry(
+        &self,
+        sink: &AlertSinkConfig,
+        event: &SecurityEvent,
+    ) -> AlertDeliveryReport {
+        let max_attempts = self.retry.max_attempts.max(1);
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+            match self.deliver(sink, event).await {
+                Ok(()) => {
+                    return AlertDeliveryReport {
+                        sink: sink.name.clone(),
+                        attempts,
+                        error: None,
+                    }
+                }
+                Err(e) if e.retryable && attempts < max_attempts => {
+                    tokio::time::sleep(self.retry.backoff_for_attempt(attempts)).await;
+                }
+                Err(e) => {
+                    return AlertDeliveryReport {
+                        sink: sink.name.clone(),
+                        attempts,
+                        error: Some(e.message),
+                    }
+                }
+            }
+        }
+    }
+
+    async fn deliver(
+        &self,
+        sink: &AlertSinkConfig,
+        event: &SecurityEvent,
+    ) -> Result<(), DeliveryError> {
+        match &sink.kind {
+            AlertSinkKind::Webhook {
+                url,
+                secret,
+                headers,
+            } => {
+                self.deliver_webhook(&sink.name, url, secret, headers, event)
+                    .await
+            }
+            AlertSinkKind::UnixSocket { path } => {
+                self.deliver_unix_socket(&sink.name, path, event).await
+            }
+            AlertSinkKind::SyslogFile { path, app_name } => {
+                let line = format_syslog_line(app_name, event);
+                append_line(path, &line)
+                    .await
+                    .map_err(|e| DeliveryError::retryable(format!("syslog file: {}", e)))
+            }
+        }
+    }
+
+    async fn deliver_webhook(
+        &self,
+        sink_name: &str,
+        url: &str,
+        secret: &str,
+        headers: &HashMap<String, String>,
+        event: &SecurityEvent,
+    ) -> Result<(), DeliveryError> {
+        let body = serde_json::to_vec(&AlertPayload {
+            sink: sink_name.to_string(),
+            sent_at: Utc::now(),
+            event: event.clone(),
+        })
+        .map_err(|e| DeliveryError::permanent(format!("serialize alert: {}", e)))?;
+
+        let timestamp = Utc::now().timestamp();
+        let signature = sign_payload(secret, timestamp, &body);
+
+        let mut request = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, format!("sha256={}", signature));
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| DeliveryError::retryable(format!("webhook request: {}", e)))?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else if status.is_server_error()
+            || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            || status == reqwest::StatusCode::REQUEST_TIMEOUT
+        {
+            Err(DeliveryError::retryable(format!(
+                "webhook returned {}",
+                status
+            )))
+        } else {
+            Err(DeliveryError::permanent(format!(
+                "webhook returned {}",
+                status
+            )))
+        }
+    }
+
+    #[cfg(unix)]
+    async fn deliver_unix_socket(
+        &self,
+        sink_name: &str,
+        path: &std::path::Path,
+        event: &SecurityEvent,
+    ) -> Result<(), DeliveryError> {
+        let mut line = serde_json::to_vec(&AlertPayload {
+            sink: sink_name.to_string(),
+            sent_at: Utc::now(),
+            event: event.clone(),
+        })
+        .map_err(|e| DeliveryError::permanent(format!("serialize alert: {}", e)))?;
+        line.push(b'\n');
+
+        let timeout = Duration::from_millis(self.retry.request_timeout_ms);
+        let send = async {
+            let mut stream = tokio::net::UnixStream::connect(path).await?;
+            stream.write_all(&line).await?;
+            stream.shutdown().await
+        };
+
+        match tokio::time::timeout(timeout, send).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(DeliveryError::retryable(format!("unix socket: {}", e))),
+            Err(_) => Err(DeliveryError::retryable("unix socket: timed out")),
+        }
+    }
+
+    #[cfg(not(unix))]
+    async fn deliver_unix_socket(
+        &self,
+        _sink_name: &str,
+        _path: &std::path::Path,
+        _event: &SecurityEvent,
+    ) -> Result<(), DeliveryError> {
+        Err(DeliveryError::permanent(
+            "unix socket sinks are not supported on this platform",
+        ))
+    }
+
+    async fn dead_letter(&self, report: &AlertDeliveryReport, error: &str, event: &SecurityEvent) {
+        let record = DeadLetterRecord {
+            sink: report.sink.clone(),
+            attempts: report.attempts,
+            error: error.to_string(),
+            failed_at: Utc::now(),
+            event: event.clone(),
+        };
+
+        let line = match serde_json::to_string(&record) {
+            Ok(line) => line,
+            Err(e) => {
+                error!("Failed to serialize dead-letter alert: {}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = append_line(&self.dead_letter_path, &line).await {
+            error!(
+                "Failed to write dead-letter alert to {}: {}",
+                self.dead_letter_path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Hex HMAC-SHA256 over `"{timestamp}.{body}"`, as sent in [`SIGNATURE_HEADER`]
+pub fn sign_payload(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Syslog severity for a security severity
+fn syslog_severity(severity: SecuritySeverity) -> u8 {
+    match severity {
+        SecuritySeverity::Info => 6,
+        SecuritySeverity::Warning => 4,
+        SecuritySeverity::Error => 3,
+        SecuritySeverity::Critical => 2,
+        SecuritySeverity::Emergency => 0,
+    }
+}
+
+/// MSGID field: the event type's variant name, which never contains spaces
+fn syslog_msg_id(event_type: &SecurityEventType) -> String {
+    match event_type {
+        SecurityEventType::Custom(_) => "Custom".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Format an event as an RFC 5424 line using the security/authorization facility
+pub fn format_syslog_line(app_name: &str, event: &SecurityEvent) -> String {
+    const AUTH_FACILITY: u8 = 4;
+
+    let priority = AUTH_FACILITY * 8 + syslog_severity(event.severity);
+    let hostname = sysinfo::System::host_name().unwrap_or_else(|| "-".to_string());
+    let details = serde_json::to_string(&event.details).unwrap_or_else(|_| "{}".to_string());
+
+    format!(
+        "<{}>1 {} {} {} {} {} - {} (risk score {}, outcome {:?}, id {}) {}",
+        priority,
+        event
+            .timestamp
+            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        hostname,
+        app_name,
+        std::process::id(),
+        syslog_msg_id(&event.event_type),
+        event.event_type,
+        event.risk_score,
+        event.outcome,
+        event.id,
+        details
+    )
+}
+
+async fn append_line(path: &std::path::Path, line: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    file.write_all(b"\n").await?;
+    file.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::audit_logger::SecurityOutcome;
+    use tempfile::TempDir;
+
+    fn test_event(severity: SecuritySeverity) -> SecurityEvent {
+        SecurityEvent {
+            id: "alert-1".to_string(),
+            timestamp: Utc::now(),
+            event_type: SecurityEventType::AttackDetected,
+            severity,
+            session_id: Some("session-1".to_string()),
+            user_id: None,
+            window_label: None,
+            source_ip: None,
+            command: None,
+            details: HashMap::new(),
+            outcome: SecurityOutcome::Blocked,
+            risk_score: 90,
+        }
+    }
+
+    fn fast_retry() -> AlertRetryPolicy {
+        AlertRetryPolicy {
+            max_attempts: 2,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 1,
+            request_timeout_ms: 500,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_syslog_sink_respects_threshold() {
+        let temp_dir = TempDir::new().unwrap();
+        let syslog_path = temp_dir.path().join("alerts.log");
+        let dispatcher = AlertDispatcher::new(
+            vec![AlertSinkConfig {
+                name: "syslog".to_string(),
+                kind: AlertSinkKind::SyslogFile {
+                    path: syslog_path.clone(),
+                    app_name: "test".to_string(),
+                },
+                min_severity: SecuritySeverity::Critical,
+            }],
+            fast_retry(),
+            temp_dir.path().join("dead_letter.jsonl"),
+        );
+
+        assert!(dispatcher
+            .dispatch(&test_event(SecuritySeverity::Error))
+            .await
+            .is_empty());
+
+        let reports = dispatcher
+            .dispatch(&test_event(SecuritySeverity::Critical))
+            .await;
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].error.is_none());
+
+        let content = tokio::fs::read_to_string(&syslog_path).await.unwrap();
+        assert_eq!(content.lines().count(), 1);
+        assert!(content.starts_with("<34>1 "));
+        assert!(content.contains(" test "));
+        assert!(content.contains("AttackDetected"));
+    }
+
+    #[tokio::test]
+    async fn test_webhook_body_is_signed() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/alerts"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let dispatcher = AlertDispatcher::new(
+            vec![AlertSinkConfig {
+                name: "webhook".to_string(),
+                kind: AlertSinkKind::Webhook {
+                    url: format!("{}/alerts", server.uri()),
+                    secret: "shared-secret".to_string(),
+                    headers: HashMap::new(),
+                },
+                min_severity: SecuritySeverity::Warning,
+            }],
+            fast_retry(),
+            temp_dir.path().join("dead_letter.jsonl"),
+        );
+
+        let reports = dispatcher
+            .dispatch(&test_event(SecuritySeverity::Error))
+            .await;
+        assert!(reports[0].error.is_none());
+
+        let requests = server.received_requests().await.unwrap();
+        let request = &requests[0];
+        let timestamp: i64 = request.headers[TIMESTAMP_HEADER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let expected = format!(
+            "sha256={}",
+            sign_payload("shared-secret", timestamp, &request.body)
+        );
+        assert_eq!(
+            request.headers[SIGNATURE_HEADER].to_str().unwrap(),
+            expected
+        );
+
+        let payload: AlertPayload = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(payload.event.id, "alert-1");
+    }
+
+    #[tokio::test]
+    async fn test_undeliverable_alert_is_dead_lettered() {
+        let temp_dir = TempDir::new().unwrap();
+        let dead_letter_path = temp_dir.path().join("dead_letter.jsonl");
+        let dispatcher = AlertDispatcher::new(
+            vec![AlertSinkConfig {
+                name: "socket".to_string(),
+                kind: AlertSinkKind::UnixSocket {
+                    path: temp_dir.path().join("missing.sock"),
+                },
+                min_severity: SecuritySeverity::Warning,
+            }],
+            fast_retry(),
+            dead_letter_path.clone(),
+        );
+
+        let reports = dispatcher
+            .dispatch(&test_event(SecuritySeverity::Error))
+            .await;
+        assert!(reports[0].error.is_some());
+
+        let content = tokio::fs::read_to_string(&dead_letter_path).await.unwrap();
+        let record: DeadLetterRecord = serde_json::from_str(content.trim()).unwrap();
+        assert_eq!(record.sink, "socket");
+        assert_eq!(record.event.id, "alert-1");
+        if cfg!(unix) {
+            assert_eq!(record.attempts, 2);
+        }
+    }
+}
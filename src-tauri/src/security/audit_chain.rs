@@ -0,0 +1,702 @@
+//! Audit Log Hash Chain
+//!
+//! Record format and verification for the tamper-evident audit log. Every
+//! persisted record carries a sequence number, the hash of the record before
+//! it and its own hash, so editing, removing or reordering a line breaks the
+//! chain. Periodic checkpoints sign the chain head with a key kept outside the
+//! log directory, which stops an attacker from simply recomputing every hash
+//! after an edit. The head itself is also recorded, signed, next to the key,
+//! so records removed from the end of the log show up as well.
+
+use super::audit_logger::SecurityEvent;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, BufReader};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// `prev_hash` of the first record in a chain
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Longest tail read when recovering the chain head from an existing log
+const TAIL_READ_BYTES: u64 = 256 * 1024;
+
+/// Signed statement of the chain head at a point in time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditCheckpoint {
+    pub timestamp: DateTime<Utc>,
+    /// Sequence number and hash of the last event covered
+    pub covered_seq: u64,
+    pub covered_hash: String,
+    /// Hex HMAC-SHA256 over the fields above
+    pub signature: String,
+}
+
+/// Payload of a persisted record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEntry {
+    Event(SecurityEvent),
+    Checkpoint(AuditCheckpoint),
+}
+
+/// One line of the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub seq: u64,
+    pub prev_hash: String,
+    pub hash: String,
+    #[serde(flatten)]
+    pub entry: AuditEntry,
+}
+
+impl AuditRecord {
+    /// Hash of a record's contents, independent of map ordering
+    pub fn compute_hash(seq: u64, prev_hash: &str, entry: &AuditEntry) -> String {
+        let value = serde_json::to_value(entry).unwrap_or(serde_json::Value::Null);
+
+        let mut hasher = Sha256::new();
+        hasher.update(seq.to_string().as_bytes());
+        hasher.update(b"|");
+        hasher.update(prev_hash.as_bytes());
+        hasher.update(b"|");
+        hasher.update(canonical_json(&value).as_bytes());
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Position of the chain head
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainHead {
+    pub seq: u64,
+    pub hash: String,
+}
+
+/// Chain head as last written, stored outside the log directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedHead {
+    #[serde(flatten)]
+    pub head: ChainHead,
+    /// Hex HMAC-SHA256 over the head
+    pub signature: String,
+}
+
+impl RecordedHead {
+    pub fn sign(key: &[u8], head: ChainHead) -> Self {
+        let signature = hex::encode(head_mac(key, &head).finalize().into_bytes());
+        Self { head, signature }
+    }
+
+    pub fn verify(&self, key: &[u8]) -> bool {
+        hex::decode(&self.signature)
+            .is_ok_and(|signature| head_mac(key, &self.head).verify_slice(&signature).is_ok())
+    }
+}
+
+fn head_mac(key: &[u8], head: &ChainHead) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(b"head|");
+    mac.update(head.seq.to_string().as_bytes());
+    mac.update(b"|");
+    mac.update(head.hash.as_bytes());
+    mac
+}
+
+/// Appends records to the chain and decides when a checkpoint is due
+#[derive(Debug)]
+pub struct AuditChain {
+    /// Head of the chain, `None` before the first record
+    head: Option<ChainHead>,
+    events_since_checkpoint: u64,
+    checkpoint_interval: u64,
+    key: Vec<u8>,
+}
+
+impl AuditChain {
+    pub fn new(head: Option<ChainHead>, checkpoint_interval: u64, key: Vec<u8>) -> Self {
+        Self {
+            head,
+            events_since_checkpoint: 0,
+            checkpoint_interval,
+            key,
+        }
+    }
+
+    pub fn head(&self) -> Option<&ChainHead> {
+        self.head.as_ref()
+    }
+
+    /// The current head, signed for storage outside the log
+    pub fn recorded_head(&self) -> Option<RecordedHead> {
+        self.head
+            .clone()
+            .map(|head| RecordedHead::sign(&self.key, head))
+    }
+
+    /// Link the next entry onto the chain
+    pub fn append(&mut self, entry: AuditEntry) -> AuditRecord {
+        let (seq, prev_hash) = match &self.head {
+            Some(head) => (head.seq + 1, head.hash.clone()),
+            None => (0, GENESIS_HASH.to_string()),
+        };
+        let hash = AuditRecord::compute_hash(seq, &prev_hash, &entry);
+
+        match &entry {
+            AuditEntry::Event(_) => self.events_since_checkpoint += 1,
+            AuditEntry::Checkpoint(_) => self.events_since_checkpoint = 0,
+        }
+        self.head = Some(ChainHead {
+            seq,
+            hash: hash.clone(),
+        });
+
+        AuditRecord {
+            seq,
+            prev_hash,
+            hash,
+            entry,
+        }
+    }
+
+    /// Whether enough events were written since the last checkpoint
+    pub fn checkpoint_due(&self) -> bool {
+        self.checkpoint_interval > 0 && self.events_since_checkpoint >= self.checkpoint_interval
+    }
+
+    /// Sign the current head, or `None` if there is nothing to cover
+    pub fn checkpoint(&self) -> Option<AuditEntry> {
+        let head = self.head.as_ref()?;
+        let timestamp = Utc::now();
+        let signature = sign_checkpoint(&self.key, timestamp, head.seq, &head.hash);
+
+        Some(AuditEntry::Checkpoint(AuditCheckpoint {
+            timestamp,
+            covered_seq: head.seq,
+            covered_hash: head.hash.clone(),
+            signature,
+        }))
+    }
+}
+
+/// Hex HMAC-SHA256 signature of a checkpoint
+pub fn sign_checkpoint(key: &[u8], timestamp: DateTime<Utc>, seq: u64, hash: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_rfc3339().as_bytes());
+    mac.update(b"|");
+    mac.update(seq.to_string().as_bytes());
+    mac.update(b"|");
+    mac.update(hash.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn verify_checkpoint_signature(key: &[u8], checkpoint: &AuditCheckpoint) -> bool {
+    let Ok(signature) = hex::decode(&checkpoint.signature) else {
+        return false;
+    };
+
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(checkpoint.timestamp.to_rfc3339().as_bytes());
+    mac.update(b"|");
+    mac.update(checkpoint.covered_seq.to_string().as_bytes());
+    mac.update(b"|");
+    mac.update(checkpoint.covered_hash.as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// JSON with object keys sorted, so hashes do not depend on map iteration order
+fn canonical_json(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let fields: Vec<String> = keys
+                .into_iter()
+                .map(|key| {
+                    format!(
+                        "{}:{}",
+                        serde_json::Value::String(key.clone()),
+                        canonical_json(&map[key])
+                    )
+                })
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        serde_json::Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+/// Load the checkpoint signing key, creating it on first use
+pub async fn load_or_create_key(path: &Path) -> std::io::Result<Vec<u8>> {
+    match tokio::fs::read(path).await {
+        Ok(key) if !key.is_empty() => return Ok(key),
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let key: [u8; 32] = rand::random();
+    tokio::fs::write(path, key).await?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tokio::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).await?;
+    }
+
+    Ok(key.to_vec())
+}
+
+/// Recover the chain head from the last complete record of an existing log
+pub async fn read_chain_head(path: &Path) -> std::io::Result<Option<ChainHead>> {
+    let mut file = match tokio::fs::File::open(path).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let len = file.metadata().await?.len();
+    file.seek(std::io::SeekFrom::Start(
+        len.saturating_sub(TAIL_READ_BYTES),
+    ))
+    .await?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).await?;
+
+    // A write torn by a crash leaves a partial last line; the record before it is the head
+    let tail = String::from_utf8_lossy(&tail);
+    let head = tail
+        .lines()
+        .rev()
+        .filter(|line| !line.trim().is_empty())
+        .find_map(|line| serde_json::from_str::<AuditRecord>(line).ok())
+        .map(|record| ChainHead {
+            seq: record.seq,
+            hash: record.hash,
+        });
+
+    Ok(head)
+}
+
+/// Cut off a final line left incomplete by an interrupted write
+///
+/// Returns the number of bytes removed. Records are written whole, ending in a
+/// newline, so anything after the last newline never completed.
+pub async fn truncate_torn_record(path: &Path) -> std::io::Result<u64> {
+    let mut file = match tokio::fs::OpenOptions::new().read(true).write(true).open(path).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+
+    let len = file.metadata().await?.len();
+    let tail_start = len.saturating_sub(TAIL_READ_BYTES);
+    file.seek(std::io::SeekFrom::Start(tail_start)).await?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).await?;
+
+    let complete_len = match tail.iter().rposition(|byte| *byte == b'\n') {
+        Some(newline) => tail_start + newline as u64 + 1,
+        None if tail_start == 0 => 0,
+        // A single line longer than the tail is not a record this logger wrote
+        None => return Ok(0),
+    };
+    if complete_len == len {
+        return Ok(0);
+    }
+
+    file.set_len(complete_len).await?;
+    file.sync_all().await?;
+    Ok(len - complete_len)
+}
+
+/// Read the recorded chain head, `None` if none was written yet
+pub async fn load_recorded_head(path: &Path) -> std::io::Result<Option<RecordedHead>> {
+    match tokio::fs::read(path).await {
+        Ok(content) => serde_json::from_slice(&content)
+            .map(Some)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Replace the recorded chain head, so a crash leaves either the old or the new one
+pub async fn store_recorded_head(path: &Path, head: &RecordedHead) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let temp_path = path.with_extension("tmp");
+    let content = serde_json::to_vec(head)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    tokio::fs::write(&temp_path, content).await?;
+    tokio::fs::rename(&temp_path, path).await
+}
+
+/// First point at which the chain does not hold
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrokenLink {
+    pub file: PathBuf,
+    /// 1-based line number in `file`
+    pub line: u64,
+    pub seq: Option<u64>,
+    pub reason: String,
+}
+
+/// Result of walking the audit log files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditVerificationReport {
+    pub valid: bool,
+    pub files_checked: u64,
+    pub records_checked: u64,
+    pub checkpoints_verified: u64,
+    pub last_seq: Option<u64>,
+    pub last_hash: Option<String>,
+    /// Events after the last verified checkpoint, protected by the hash chain and recorded head only
+    pub unsigned_tail: u64,
+    pub first_broken_link: Option<BrokenLink>,
+}
+
+/// Walk log files, oldest first, as one chain from genesis and report the first broken link
+///
+/// Each file continues where the one before it ended. When `recorded_head`
+/// is given, the chain must also reach it, so records cut from the end of the
+/// newest file are detected even when no checkpoint covers them.
+pub async fn verify_chain(
+    files: &[PathBuf],
+    key: &[u8],
+    recorded_head: Option<&RecordedHead>,
+) -> std::io::Result<AuditVerificationReport> {
+    let mut report = AuditVerificationReport {
+        valid: true,
+        files_checked: 0,
+        records_checked: 0,
+        checkpoints_verified: 0,
+        last_seq: None,
+        last_hash: None,
+        unsigned_tail: 0,
+        first_broken_link: None,
+    };
+
+    if let Some(recorded) = recorded_head.filter(|recorded| !recorded.verify(key)) {
+        report.first_broken_link = Some(BrokenLink {
+            file: files.last().cloned().unwrap_or_default(),
+            line: 0,
+            seq: Some(recorded.head.seq),
+            reason: "recorded chain head signature is invalid".to_string(),
+        });
+    }
+
+    let mut line_number = 0u64;
+    for path in files {
+        if report.first_broken_link.is_some() {
+            break;
+        }
+        report.files_checked += 1;
+        line_number = 0;
+
+        let file = tokio::fs::File::open(path).await?;
+        let mut lines = BufReader::new(file).lines();
+        while let Some(line) = lines.next_line().await? {
+            line_number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if let Err((seq, reason)) = verify_record(&line, key, recorded_head, &mut report) {
+                report.first_broken_link = Some(BrokenLink {
+                    file: path.clone(),
+                    line: line_number,
+                    seq,
+                    reason,
+                });
+                break;
+            }
+        }
+    }
+
+    if let (None, Some(recorded)) = (&report.first_broken_link, recorded_head) {
+        if report.last_seq.map_or(true, |last_seq| last_seq < recorded.head.seq) {
+            report.first_broken_link = Some(BrokenLink {
+                file: files.last().cloned().unwrap_or_default(),
+                line: line_number + 1,
+                seq: Some(recorded.head.seq),
+                reason: format!(
+                    "log ends before the recorded chain head at sequence {}",
+                    recorded.head.seq
+                ),
+            });
+        }
+    }
+
+    report.valid = report.first_broken_link.is_none();
+    Ok(report)
+}
+
+/// Check one line against the chain so far, returning the offending sequence number and reason
+fn verify_record(
+    line: &str,
+    key: &[u8],
+    recorded_head: Option<&RecordedHead>,
+    report: &mut AuditVerificationReport,
+) -> Result<(), (Option<u64>, String)> {
+    let record: AuditRecord = serde_json::from_str(line)
+        .map_err(|e| (None, format!("record is not part of the chain: {}", e)))?;
+
+    match (&report.last_seq, &report.last_hash) {
+        (Some(last_seq), Some(last_hash)) => {
+            if record.seq != last_seq + 1 {
+                return Err((
+                    Some(record.seq),
+                    format!("expected sequence {}, found {}", last_seq + 1, record.seq),
+                ));
+            }
+            if &record.prev_hash != last_hash {
+                return Err((
+                    Some(record.seq),
+                    "previous hash does not match the preceding record".to_string(),
+                ));
+            }
+        }
+        // Anything but genesis at the start means earlier records were removed
+        _ if record.seq != 0 || record.prev_hash != GENESIS_HASH => {
+            return Err((
+                Some(record.seq),
+                "first record of the chain does not start from genesis".to_string(),
+            ));
+        }
+        _ => {}
+    }
+
+    if AuditRecord::compute_hash(record.seq, &record.prev_hash, &record.entry) != record.hash {
+        return Err((
+            Some(record.seq),
+            "record contents do not match its hash".to_string(),
+        ));
+    }
+
+    if let Some(recorded) = recorded_head.filter(|recorded| recorded.head.seq == record.seq) {
+        if recorded.head.hash != record.hash {
+            return Err((
+                Some(record.seq),
+                "record does not match the recorded chain head".to_string(),
+            ));
+        }
+    }
+
+    match &record.entry {
+        AuditEntry::Event(_) => report.unsigned_tail += 1,
+        AuditEntry::Checkpoint(checkpoint) => {
+            let covers_previous = report.last_seq == Some(checkpoint.covered_seq)
+                && report.last_hash.as_deref() == Some(checkpoint.covered_hash.as_str());
+            if !covers_previous || !verify_checkpoint_signature(key, checkpoint) {
+                return Err((
+                    Some(record.seq),
+                    "checkpoint signature is invalid".to_string(),
+                ));
+            }
+            report.checkpoints_verified += 1;
+            report.unsigned_tail = 0;
+        }
+    }
+
+    report.records_checked += 1;
+    report.last_seq = Some(record.seq);
+    report.last_hash = Some(record.hash);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::audit_logger::{SecurityEventType, SecurityOutcome, SecuritySeverity};
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn test_event(id: &str) -> SecurityEvent {
+        SecurityEvent {
+            id: id.to_string(),
+            timestamp: Utc::now(),
+            event_type: SecurityEventType::CommandExecuted,
+            severity: SecuritySeverity::Info,
+            session_id: None,
+            user_id: None,
+            window_label: None,
+            source_ip: None,
+            command: Some("list_files".to_string()),
+            details: HashMap::from([
+                ("a".to_string(), serde_json::json!(1)),
+                ("b".to_string(), serde_json::json!({"y": 2, "x": 1})),
+            ]),
+            outcome: SecurityOutcome::Success,
+            risk_score: 5,
+        }
+    }
+
+    /// Write a chain of `events` with a checkpoint every two, returning its lines and head
+    async fn write_chain(path: &Path, key: &[u8], events: usize) -> (Vec<String>, RecordedHead) {
+        let mut chain = AuditChain::new(None, 2, key.to_vec());
+        let mut lines = Vec::new();
+        for i in 0..events {
+            let record = chain.append(AuditEntry::Event(test_event(&format!("event-{}", i))));
+            lines.push(serde_json::to_string(&record).unwrap());
+            if chain.checkpoint_due() {
+                let record = chain.append(chain.checkpoint().unwrap());
+                lines.push(serde_json::to_string(&record).unwrap());
+            }
+        }
+        write_lines(path, &lines).await;
+        (lines, chain.recorded_head().unwrap())
+    }
+
+    async fn write_lines(path: &Path, lines: &[String]) {
+        tokio::fs::write(path, lines.join("\n") + "\n")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_intact_chain_verifies() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("audit.log");
+        let (_, recorded) = write_chain(&path, b"key", 5).await;
+
+        let report = verify_chain(&[path.clone()], b"key", Some(&recorded))
+            .await
+            .unwrap();
+        assert!(report.valid, "{:?}", report.first_broken_link);
+        assert_eq!(report.records_checked, 7);
+        assert_eq!(report.checkpoints_verified, 2);
+        assert_eq!(report.unsigned_tail, 1);
+
+        let head = read_chain_head(&path).await.unwrap().unwrap();
+        assert_eq!(Some(head.seq), report.last_seq);
+        assert_eq!(Some(head.hash), report.last_hash);
+    }
+
+    #[tokio::test]
+    async fn test_edited_record_is_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("audit.log");
+        let (mut lines, _) = write_chain(&path, b"key", 5).await;
+
+        lines[3] = lines[3].replace("list_files", "delete_files");
+        write_lines(&path, &lines).await;
+
+        let report = verify_chain(&[path], b"key", None).await.unwrap();
+        assert!(!report.valid);
+        let broken = report.first_broken_link.unwrap();
+        assert_eq!(broken.line, 4);
+        assert_eq!(broken.reason, "record contents do not match its hash");
+    }
+
+    #[tokio::test]
+    async fn test_rehashed_chain_fails_checkpoint() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("audit.log");
+        write_chain(&path, b"key", 2).await;
+
+        let report = verify_chain(&[path], b"other-key", None).await.unwrap();
+        let broken = report.first_broken_link.unwrap();
+        assert_eq!(broken.line, 3);
+        assert_eq!(broken.reason, "checkpoint signature is invalid");
+    }
+
+    #[tokio::test]
+    async fn test_removed_records_are_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("audit.log");
+        let (lines, recorded) = write_chain(&path, b"key", 5).await;
+
+        // Removing the start leaves a chain that no longer begins at genesis
+        write_lines(&path, &lines[2..]).await;
+        let report = verify_chain(&[path.clone()], b"key", None).await.unwrap();
+        let broken = report.first_broken_link.unwrap();
+        assert_eq!(broken.line, 1);
+        assert_eq!(broken.reason, "first record of the chain does not start from genesis");
+
+        // Removing unsigned records from the end only shows against the recorded head
+        write_lines(&path, &lines[..lines.len() - 1]).await;
+        assert!(verify_chain(&[path.clone()], b"key", None).await.unwrap().valid);
+        let report = verify_chain(&[path.clone()], b"key", Some(&recorded))
+            .await
+            .unwrap();
+        let broken = report.first_broken_link.unwrap();
+        assert_eq!(broken.line, 7);
+        assert_eq!(broken.seq, Some(6));
+
+        // The recorded head cannot be rewritten to match without the key
+        let forged = RecordedHead {
+            head: ChainHead {
+                seq: 6,
+                hash: report.last_hash.unwrap(),
+            },
+            signature: recorded.signature.clone(),
+        };
+        let report = verify_chain(&[path], b"key", Some(&forged)).await.unwrap();
+        assert_eq!(
+            report.first_broken_link.unwrap().reason,
+            "recorded chain head signature is invalid"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rotated_files_are_verified_as_one_chain() {
+        let temp_dir = TempDir::new().unwrap();
+        let current = temp_dir.path().join("audit.log");
+        let rotated = temp_dir.path().join("audit.20240101_000000.log");
+        let (lines, recorded) = write_chain(&current, b"key", 5).await;
+        write_lines(&rotated, &lines[..4]).await;
+        write_lines(&current, &lines[4..]).await;
+
+        let files = vec![rotated.clone(), current.clone()];
+        let report = verify_chain(&files, b"key", Some(&recorded)).await.unwrap();
+        assert!(report.valid, "{:?}", report.first_broken_link);
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(report.records_checked, 7);
+
+        // Dropping the rotated file cuts the chain off from genesis
+        let report = verify_chain(&[current.clone()], b"key", Some(&recorded))
+            .await
+            .unwrap();
+        assert_eq!(report.first_broken_link.unwrap().file, current);
+
+        // Files out of order do not link up
+        let report = verify_chain(&[current.clone(), rotated], b"key", None)
+            .await
+            .unwrap();
+        assert!(!report.valid);
+    }
+
+    #[tokio::test]
+    async fn test_torn_record_is_cut_off() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("audit.log");
+        let (lines, recorded) = write_chain(&path, b"key", 3).await;
+
+        let torn = format!("{}\n{}", lines.join("\n"), r#"{"seq":5,"prev_hash":"ab"#);
+        tokio::fs::write(&path, &torn).await.unwrap();
+
+        let head = read_chain_head(&path).await.unwrap().unwrap();
+        assert_eq!(head, recorded.head);
+
+        let removed = truncate_torn_record(&path).await.unwrap();
+        assert_eq!(removed, r#"{"seq":5,"prev_hash":"ab"#.len() as u64);
+        assert_eq!(truncate_torn_record(&path).await.unwrap(), 0);
+        assert!(verify_chain(&[path], b"key", Some(&recorded))
+            .await
+            .unwrap()
+            .valid);
+    }
+}
@@ -3,14 +3,19 @@
 //! Comprehensive logging system for security events, violations, and system activities.
 //! Provides tamper-evident logging with structured event tracking.
 
+use super::alert_sinks::{AlertDispatcher, AlertRetryPolicy, AlertSinkConfig};
+use super::audit_chain::{self, AuditChain, AuditEntry, AuditVerificationReport, ChainHead};
+use super::audit_query::{rotated_log_files, AuditQuery, AuditQueryResult, AuditQueryRunner};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::fs::{File, OpenOptions};
 use tokio::io::{AsyncWriteExt, BufWriter};
 use tokio::sync::RwLock;
+use tracing::{error, warn};
 use uuid::Uuid;
 
 /// Security event severity levels
@@ -138,7 +143,19 @@ pub struct AuditConfig {
     pub compress_old_logs: bool,
     pub retention_days: u32,
     pub enable_real_time_alerts: bool,
+    /// Lowest severity printed as a console alert when no sinks are configured
     pub alert_severity_threshold: SecuritySeverity,
+    /// Destinations for real-time alerts, each with its own severity threshold
+    pub alert_sinks: Vec<AlertSinkConfig>,
+    pub alert_retry: AlertRetryPolicy,
+    /// Alerts no sink could accept after all retries are appended here
+    pub alert_dead_letter_path: PathBuf,
+    /// Key used to sign chain checkpoints; created on first use, and never inside the log directory
+    pub integrity_key_path: PathBuf,
+    /// Signed chain head as last written, kept with the key
+    pub integrity_head_path: PathBuf,
+    /// Events between signed checkpoints, 0 to disable checkpoints
+    pub checkpoint_interval: u64,
 }
 
 impl Default for AuditConfig {
@@ -152,10 +169,25 @@ impl Default for AuditConfig {
             retention_days: 90,
             enable_real_time_alerts: true,
             alert_severity_threshold: SecuritySeverity::Warning,
+            alert_sinks: Vec::new(),
+            alert_retry: AlertRetryPolicy::default(),
+            alert_dead_letter_path: PathBuf::from("./logs/security_alerts_dead_letter.jsonl"),
+            integrity_key_path: default_integrity_dir().join("audit.key"),
+            integrity_head_path: default_integrity_dir().join("audit_head.json"),
+            checkpoint_interval: 100,
         }
     }
 }
 
+/// App-private directory for the integrity key and recorded chain head
+///
+/// Kept apart from the logs, so whoever can rewrite the logs cannot also re-sign them.
+fn default_integrity_dir() -> PathBuf {
+    directories::ProjectDirs::from("com", "autodev-ai", "neural-bridge-platform")
+        .map(|dirs| dirs.data_local_dir().join("audit"))
+        .unwrap_or_else(|| PathBuf::from("./audit_integrity"))
+}
+
 /// Security audit statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditStats {
@@ -174,9 +206,11 @@ pub struct AuditStats {
 pub struct SecurityAuditLogger {
     config: AuditConfig,
     log_writer: RwLock<Option<BufWriter<File>>>,
+    chain: RwLock<Option<AuditChain>>,
     stats: RwLock<AuditStats>,
     event_buffer: RwLock<Vec<SecurityEvent>>,
     buffer_size: usize,
+    alert_dispatcher: Arc<AlertDispatcher>,
 }
 
 impl SecurityAuditLogger {
@@ -187,17 +221,25 @@ impl SecurityAuditLogger {
 
     /// Create a new security audit logger with custom configuration
     pub async fn with_config(config: AuditConfig) -> Self {
+        let alert_dispatcher = Arc::new(AlertDispatcher::new(
+            config.alert_sinks.clone(),
+            config.alert_retry.clone(),
+            config.alert_dead_letter_path.clone(),
+        ));
+
         let mut logger = Self {
             config,
             log_writer: RwLock::new(None),
+            chain: RwLock::new(None),
             stats: RwLock::new(AuditStats::default()),
             event_buffer: RwLock::new(Vec::new()),
             buffer_size: 100,
+            alert_dispatcher,
         };
 
         // Initialize log file
         if let Err(e) = logger.initialize_log_file().await {
-            eprintln!("Failed to initialize audit log file: {}", e);
+            error!("Failed to initialize audit log file: {}", e);
         }
 
         logger
@@ -209,6 +251,16 @@ impl SecurityAuditLogger {
         if let Some(parent) = self.config.log_file_path.parent() {
             tokio::fs::create_dir_all(parent).await?;
         }
+        self.check_integrity_paths().await?;
+
+        let torn = audit_chain::truncate_torn_record(&self.config.log_file_path).await?;
+        if torn > 0 {
+            warn!(
+                "Removed {} bytes of an incomplete audit record from {}",
+                torn,
+                self.config.log_file_path.display()
+            );
+        }
 
         // Open log file in append mode
         let file = OpenOptions::new()
@@ -217,12 +269,21 @@ impl SecurityAuditLogger {
             .open(&self.config.log_file_path)
             .await?;
 
+        // Resume the hash chain from the existing log; after rotation it simply continues
+        {
+            let mut chain = self.chain.write().await;
+            if chain.is_none() {
+                let key = audit_chain::load_or_create_key(&self.config.integrity_key_path).await?;
+                let head = self.resume_head(&key).await?;
+                *chain = Some(AuditChain::new(head, self.config.checkpoint_interval, key));
+            }
+        }
+
         let writer = BufWriter::new(file);
-        let mut log_writer = self.log_writer.write().await;
-        *log_writer = Some(writer);
+        *self.log_writer.write().await = Some(writer);
 
         // Log initialization event
-        self.log_event_internal(SecurityEvent {
+        let event = SecurityEvent {
             id: Uuid::new_v4().to_string(),
             timestamp: Utc::now(),
             event_type: SecurityEventType::ConfigurationChanged,
@@ -244,8 +305,9 @@ impl SecurityAuditLogger {
             ]),
             outcome: SecurityOutcome::Success,
             risk_score: 0,
-        })
-        .await;
+        };
+        self.update_stats(&event).await;
+        self.log_event_internal(event).await;
 
         Ok(())
     }
@@ -262,7 +324,7 @@ impl SecurityAuditLogger {
         self.update_stats(&event).await;
 
         // Check for real-time alerts
-        if self.config.enable_real_time_alerts && self.should_alert(&event) {
+        if self.config.enable_real_time_alerts {
             self.send_alert(&event).await;
         }
 
@@ -403,42 +465,174 @@ impl SecurityAuditLogger {
     }
 
     /// Buffer event for batch writing
+    ///
+    /// A full buffer stays locked until its events are written, so queries find
+    /// them either in the buffer or in the file.
     async fn buffer_event(&self, event: SecurityEvent) {
         let mut buffer = self.event_buffer.write().await;
         buffer.push(event);
 
         if buffer.len() >= self.buffer_size {
             let events = buffer.drain(..).collect::<Vec<_>>();
-            drop(buffer); // Release lock early
+            self.write_entries(events, false).await;
+        }
+    }
 
-            for event in events {
-                self.log_event_internal(event).await;
+    /// Refuse to keep the integrity key or recorded head inside the log directory
+    async fn check_integrity_paths(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let log_dir = match self.config.log_file_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => std::path::Path::new("."),
+        };
+        let log_dir = tokio::fs::canonicalize(log_dir).await?;
+
+        for path in [&self.config.integrity_key_path, &self.config.integrity_head_path] {
+            let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) else {
+                return Err(format!("{} must be kept outside the log directory", path.display()).into());
+            };
+            tokio::fs::create_dir_all(parent).await?;
+            if tokio::fs::canonicalize(parent).await?.starts_with(&log_dir) {
+                return Err(format!("{} must be kept outside the log directory", path.display()).into());
             }
         }
+
+        Ok(())
     }
 
-    /// Write event to log file
-    async fn log_event_internal(&self, event: SecurityEvent) {
-        // Serialize event as JSON
-        let json_line = match serde_json::to_string(&event) {
-            Ok(line) => format!("{}\n", line),
+    /// Head to continue the chain from
+    ///
+    /// When the log ends before the recorded head, records were removed from
+    /// its end. The chain then continues from the recorded head, so the gap
+    /// stays visible to verification instead of being papered over.
+    async fn resume_head(&self, key: &[u8]) -> std::io::Result<Option<ChainHead>> {
+        let log_head = audit_chain::read_chain_head(&self.config.log_file_path).await?;
+        let recorded = match audit_chain::load_recorded_head(&self.config.integrity_head_path).await {
+            Ok(Some(recorded)) if recorded.verify(key) => Some(recorded.head),
+            Ok(Some(_)) => {
+                error!(
+                    "Recorded audit chain head {} has an invalid signature",
+                    self.config.integrity_head_path.display()
+                );
+                None
+            }
+            Ok(None) => None,
             Err(e) => {
-                eprintln!("Failed to serialize audit event: {}", e);
-                return;
+                error!(
+                    "Failed to read recorded audit chain head {}: {}",
+                    self.config.integrity_head_path.display(),
+                    e
+                );
+                None
             }
         };
 
-        // Write to file
+        match (log_head, recorded) {
+            (log_head, Some(recorded))
+                if log_head.as_ref().map_or(true, |head| head.seq < recorded.seq) =>
+            {
+                error!(
+                    "Audit log {} ends at sequence {:?} before the recorded chain head at {}",
+                    self.config.log_file_path.display(),
+                    log_head.map(|head| head.seq),
+                    recorded.seq
+                );
+                Ok(Some(recorded))
+            }
+            (log_head, _) => Ok(log_head),
+        }
+    }
+
+    /// Write event to log file
+    async fn log_event_internal(&self, event: SecurityEvent) {
+        self.write_entries(vec![event], false).await;
+    }
+
+    /// Link events onto the chain and append them, with any checkpoints due
+    ///
+    /// `seal` adds a checkpoint after the events unless one already ends them.
+    /// The new head is then recorded outside the log.
+    async fn write_entries(&self, events: Vec<SecurityEvent>, seal: bool) {
+        // Hold the writer lock while linking so records hit the file in chain order
         let mut writer_guard = self.log_writer.write().await;
-        if let Some(writer) = writer_guard.as_mut() {
-            if let Err(e) = writer.write_all(json_line.as_bytes()).await {
-                eprintln!("Failed to write audit log: {}", e);
-                return;
+        let Some(writer) = writer_guard.as_mut() else {
+            return;
+        };
+        let mut chain_guard = self.chain.write().await;
+        let Some(chain) = chain_guard.as_mut() else {
+            return;
+        };
+
+        let mut records = Vec::new();
+        for event in events {
+            records.push(chain.append(AuditEntry::Event(event)));
+            if chain.checkpoint_due() {
+                if let Some(checkpoint) = chain.checkpoint() {
+                    records.push(chain.append(checkpoint));
+                }
             }
-            if let Err(e) = writer.flush().await {
-                eprintln!("Failed to flush audit log: {}", e);
+        }
+        let sealed = matches!(records.last(), Some(record) if matches!(record.entry, AuditEntry::Checkpoint(_)));
+        if seal && !sealed {
+            if let Some(checkpoint) = chain.checkpoint() {
+                records.push(chain.append(checkpoint));
+            }
+        }
+        if records.is_empty() {
+            return;
+        }
+
+        let mut lines = String::new();
+        for record in &records {
+            match serde_json::to_string(record) {
+                Ok(line) => {
+                    lines.push_str(&line);
+                    lines.push('\n');
+                }
+                Err(e) => {
+                    error!("Failed to serialize audit record: {}", e);
+                    return;
+                }
             }
         }
+
+        if let Err(e) = writer.write_all(lines.as_bytes()).await {
+            error!("Failed to write audit log: {}", e);
+            return;
+        }
+        if let Err(e) = writer.flush().await {
+            error!("Failed to flush audit log: {}", e);
+            return;
+        }
+
+        if let Some(head) = chain.recorded_head() {
+            if let Err(e) = audit_chain::store_recorded_head(&self.config.integrity_head_path, &head).await {
+                error!("Failed to record audit chain head: {}", e);
+            }
+        }
+    }
+
+    /// Walk the rotated and current log files and report the first broken link in their hash chain
+    pub async fn verify_integrity(&self) -> Result<AuditVerificationReport, String> {
+        self.flush().await;
+
+        let key = audit_chain::load_or_create_key(&self.config.integrity_key_path)
+            .await
+            .map_err(|e| format!("Failed to load audit integrity key: {}", e))?;
+        let recorded = audit_chain::load_recorded_head(&self.config.integrity_head_path)
+            .await
+            .map_err(|e| format!("Failed to read recorded audit chain head: {}", e))?;
+
+        let mut files: Vec<PathBuf> = rotated_log_files(&self.config.log_file_path)
+            .await
+            .map_err(|e| format!("Failed to list audit logs: {}", e))?
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        files.push(self.config.log_file_path.clone());
+
+        audit_chain::verify_chain(&files, &key, recorded.as_ref())
+            .await
+            .map_err(|e| format!("Failed to read audit log: {}", e))
     }
 
     /// Update audit statistics
@@ -474,8 +668,27 @@ impl SecurityAuditLogger {
     }
 
     /// Send real-time alert for high-priority events
+    ///
+    /// Each sink receives the events meeting its own `min_severity`, below the
+    /// global threshold too. Delivery runs in the background so retries never
+    /// hold up logging. Without sinks, events passing the global threshold are
+    /// printed to the console.
     async fn send_alert(&self, event: &SecurityEvent) {
-        // In a real implementation, this would send alerts via email, webhooks, etc.
+        if self.alert_dispatcher.has_sinks() {
+            if self.alert_dispatcher.accepts(event) {
+                let dispatcher = Arc::clone(&self.alert_dispatcher);
+                let event = event.clone();
+                tokio::spawn(async move {
+                    dispatcher.dispatch(&event).await;
+                });
+            }
+            return;
+        }
+
+        if !self.should_alert(event) {
+            return;
+        }
+
         println!(
             "🚨 SECURITY ALERT: {:?} - {} (Risk Score: {})",
             event.severity, event.event_type, event.risk_score
@@ -488,14 +701,12 @@ impl SecurityAuditLogger {
 
     /// Flush buffered events
     pub async fn flush(&self) {
+        // Locked until written, like a full buffer in `buffer_event`
         let mut buffer = self.event_buffer.write().await;
         let events = buffer.drain(..).collect::<Vec<_>>();
+        self.write_entries(events, false).await;
         drop(buffer);
 
-        for event in events {
-            self.log_event_internal(event).await;
-        }
-
         // Flush writer
         let mut writer_guard = self.log_writer.write().await;
         if let Some(writer) = writer_guard.as_mut() {
@@ -509,18 +720,23 @@ impl SecurityAuditLogger {
         stats.clone()
     }
 
-    /// Query events by criteria
-    pub async fn query_events(
-        &self,
-        start_time: Option<DateTime<Utc>>,
-        end_time: Option<DateTime<Utc>>,
-        event_types: Option<Vec<SecurityEventType>>,
-        severity_filter: Option<SecuritySeverity>,
-        session_id: Option<String>,
-    ) -> Vec<SecurityEvent> {
-        // In a production system, this would read from the log file or database
-        // For now, return empty vector as this would require parsing the log file
-        Vec::new()
+    /// Search the current and rotated log files plus unflushed events
+    pub async fn query_events(&self, query: &AuditQuery) -> Result<AuditQueryResult, String> {
+        let mut runner = AuditQueryRunner::new(query);
+
+        // Hold the buffer while scanning so no event is flushed between the two sources.
+        // The buffer holds the newest events, so it goes first.
+        let buffer = self.event_buffer.read().await;
+        for event in buffer.iter() {
+            runner.add_event(event.clone());
+        }
+        runner
+            .add_log_files(&self.config.log_file_path)
+            .await
+            .map_err(|e| format!("Failed to read audit logs: {}", e))?;
+        drop(buffer);
+
+        Ok(runner.finish())
     }
 
     /// Perform log rotation if needed
@@ -536,6 +752,13 @@ impl SecurityAuditLogger {
         let file_size_mb = metadata.len() / (1024 * 1024);
 
         if file_size_mb >= self.config.max_file_size_mb {
+            // Move buffered events into the file they were logged during and seal it,
+            // so its tail is covered by a signature and queries can prune by rotation time
+            let mut buffer = self.event_buffer.write().await;
+            let events = buffer.drain(..).collect::<Vec<_>>();
+            self.write_entries(events, true).await;
+            drop(buffer);
+
             // Close current writer
             {
                 let mut writer_guard = self.log_writer.write().await;
@@ -583,17 +806,39 @@ impl Default for AuditStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::security::alert_sinks::AlertSinkKind;
     use tempfile::TempDir;
 
+    /// Logs under `logs/`, with the integrity key and head kept apart in `integrity/`
+    fn test_config(temp_dir: &TempDir) -> AuditConfig {
+        AuditConfig {
+            log_file_path: temp_dir.path().join("logs").join("test_audit.log"),
+            alert_dead_letter_path: temp_dir.path().join("logs").join("dead_letter.jsonl"),
+            integrity_key_path: temp_dir.path().join("integrity").join("audit.key"),
+            integrity_head_path: temp_dir.path().join("integrity").join("audit_head.json"),
+            ..Default::default()
+        }
+    }
+
+    async fn log_logins(logger: &SecurityAuditLogger, users: &[&str]) {
+        for user in users {
+            logger
+                .log_authentication(
+                    SecurityEventType::LoginSuccess,
+                    None,
+                    Some(user.to_string()),
+                    HashMap::new(),
+                    SecurityOutcome::Success,
+                )
+                .await;
+        }
+    }
+
     #[tokio::test]
     async fn test_audit_logger_creation() {
         let temp_dir = TempDir::new().unwrap();
-        let log_path = temp_dir.path().join("test_audit.log");
-
-        let config = AuditConfig {
-            log_file_path: log_path.clone(),
-            ..Default::default()
-        };
+        let config = test_config(&temp_dir);
+        let log_path = config.log_file_path.clone();
 
         let logger = SecurityAuditLogger::with_config(config).await;
 
@@ -602,14 +847,24 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_event_logging() {
+    async fn test_integrity_key_must_stay_outside_the_log_directory() {
         let temp_dir = TempDir::new().unwrap();
-        let log_path = temp_dir.path().join("test_audit.log");
-
         let config = AuditConfig {
-            log_file_path: log_path.clone(),
-            ..Default::default()
+            integrity_key_path: temp_dir.path().join("logs").join("keys").join("audit.key"),
+            ..test_config(&temp_dir)
         };
+        let log_path = config.log_file_path.clone();
+
+        let _logger = SecurityAuditLogger::with_config(config).await;
+        assert!(!log_path.exists());
+        assert!(!temp_dir.path().join("logs").join("keys").join("audit.key").exists());
+    }
+
+    #[tokio::test]
+    async fn test_event_logging() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = test_config(&temp_dir);
+        let log_path = config.log_file_path.clone();
 
         let logger = SecurityAuditLogger::with_config(config).await;
 
@@ -638,9 +893,177 @@ mod tests {
         assert!(log_content.contains("session-123"));
     }
 
+    #[tokio::test]
+    async fn test_sinks_use_their_own_threshold() {
+        let temp_dir = TempDir::new().unwrap();
+        let syslog_path = temp_dir.path().join("alerts.log");
+
+        let config = AuditConfig {
+            alert_severity_threshold: SecuritySeverity::Critical,
+            alert_sinks: vec![AlertSinkConfig {
+                name: "syslog".to_string(),
+                kind: AlertSinkKind::SyslogFile {
+                    path: syslog_path.clone(),
+                    app_name: "test".to_string(),
+                },
+                min_severity: SecuritySeverity::Warning,
+            }],
+            ..test_config(&temp_dir)
+        };
+        let logger = SecurityAuditLogger::with_config(config).await;
+
+        for (severity, user) in [(SecuritySeverity::Info, "ignored"), (SecuritySeverity::Warning, "alerted")] {
+            logger
+                .log_event(SecurityEvent {
+                    id: String::new(),
+                    timestamp: Utc::now(),
+                    event_type: SecurityEventType::LoginFailure,
+                    severity,
+                    session_id: None,
+                    user_id: None,
+                    window_label: None,
+                    source_ip: None,
+                    command: None,
+                    details: HashMap::from([("user".to_string(), serde_json::json!(user))]),
+                    outcome: SecurityOutcome::Failure,
+                    risk_score: 10,
+                })
+                .await;
+        }
+
+        // Delivery runs in the background
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let content = loop {
+            let content = tokio::fs::read_to_string(&syslog_path).await.unwrap_or_default();
+            if !content.is_empty() || std::time::Instant::now() > deadline {
+                break content;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        };
+        assert_eq!(content.lines().count(), 1);
+        assert!(content.contains("alerted"));
+    }
+
+    #[tokio::test]
+    async fn test_integrity_verification_detects_tampering() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = AuditConfig {
+            checkpoint_interval: 2,
+            ..test_config(&temp_dir)
+        };
+        let log_path = config.log_file_path.clone();
+
+        let logger = SecurityAuditLogger::with_config(config.clone()).await;
+        log_logins(&logger, &["alice", "bob", "carol"]).await;
+
+        let report = logger.verify_integrity().await.unwrap();
+        assert!(report.valid);
+        assert_eq!(report.records_checked, 6);
+        assert_eq!(report.checkpoints_verified, 2);
+        drop(logger);
+
+        // Reopening continues the existing chain
+        let logger = SecurityAuditLogger::with_config(config).await;
+        assert!(logger.verify_integrity().await.unwrap().valid);
+
+        let content = tokio::fs::read_to_string(&log_path).await.unwrap();
+        tokio::fs::write(&log_path, content.replacen("bob", "eve", 1))
+            .await
+            .unwrap();
+
+        let report = logger.verify_integrity().await.unwrap();
+        assert!(!report.valid);
+        assert_eq!(report.first_broken_link.unwrap().line, 4);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_log_is_detected() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = test_config(&temp_dir);
+        let log_path = config.log_file_path.clone();
+
+        let logger = SecurityAuditLogger::with_config(config.clone()).await;
+        log_logins(&logger, &["alice", "bob", "carol"]).await;
+        assert!(logger.verify_integrity().await.unwrap().valid);
+
+        // No checkpoint covers the last events, but the recorded head does
+        let content = tokio::fs::read_to_string(&log_path).await.unwrap();
+        let kept: Vec<&str> = content.lines().take(2).collect();
+        tokio::fs::write(&log_path, kept.join("\n") + "\n")
+            .await
+            .unwrap();
+
+        let report = logger.verify_integrity().await.unwrap();
+        assert_eq!(report.first_broken_link.unwrap().seq, Some(3));
+        drop(logger);
+
+        // Restarting does not hide the gap
+        let logger = SecurityAuditLogger::with_config(config).await;
+        let broken = logger.verify_integrity().await.unwrap().first_broken_link.unwrap();
+        assert_eq!(broken.line, 3);
+        assert_eq!(broken.reason, "expected sequence 2, found 4");
+    }
+
+    #[tokio::test]
+    async fn test_rotated_logs_are_verified() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = AuditConfig {
+            max_file_size_mb: 0,
+            ..test_config(&temp_dir)
+        };
+        let log_path = config.log_file_path.clone();
+
+        let logger = SecurityAuditLogger::with_config(config).await;
+        log_logins(&logger, &["alice", "bob"]).await;
+        logger.rotate_logs_if_needed().await.unwrap();
+        log_logins(&logger, &["carol"]).await;
+
+        let report = logger.verify_integrity().await.unwrap();
+        assert!(report.valid, "{:?}", report.first_broken_link);
+        assert_eq!(report.files_checked, 2);
+
+        // Removing the rotated file breaks the chain at the start of the current one
+        let rotated = rotated_log_files(&log_path).await.unwrap();
+        tokio::fs::remove_file(&rotated[0].0).await.unwrap();
+        let broken = logger.verify_integrity().await.unwrap().first_broken_link.unwrap();
+        assert_eq!(broken.file, log_path);
+        assert_eq!(broken.line, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_queries_see_events_while_they_are_flushed() {
+        let temp_dir = TempDir::new().unwrap();
+        let logger = Arc::new(SecurityAuditLogger::with_config(test_config(&temp_dir)).await);
+        let query = AuditQuery {
+            event_types: Some(vec![SecurityEventType::LoginSuccess]),
+            aggregate: true,
+            ..Default::default()
+        };
+
+        // Several full buffers are written while the queries run
+        let users: Vec<String> = (0..350).map(|i| format!("user-{}", i)).collect();
+        let writer = {
+            let logger = logger.clone();
+            tokio::spawn(async move {
+                let users: Vec<&str> = users.iter().map(String::as_str).collect();
+                log_logins(&logger, &users).await;
+            })
+        };
+
+        let mut seen = 0;
+        while !writer.is_finished() {
+            let matches = logger.query_events(&query).await.unwrap().total_matches;
+            assert!(matches >= seen, "{} events found after {}", matches, seen);
+            seen = matches;
+        }
+        writer.await.unwrap();
+        assert_eq!(logger.query_events(&query).await.unwrap().total_matches, 350);
+    }
+
     #[tokio::test]
     async fn test_stats_update() {
-        let logger = SecurityAuditLogger::new().await;
+        let temp_dir = TempDir::new().unwrap();
+        let logger = SecurityAuditLogger::with_config(test_config(&temp_dir)).await;
 
         let event = SecurityEvent {
             id: "test-stats".to_string(),
@@ -0,0 +1,563 @@
+//! Audit Log Queries
+//!
+//! Filters, pagination and aggregation over security events stored in the
+//! current audit log, its rotated predecessors and the unflushed buffer.
+
+use super::audit_chain::{AuditEntry, AuditRecord};
+use super::audit_logger::{SecurityEvent, SecurityEventType, SecurityOutcome, SecuritySeverity};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashSet};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+/// Page size used when a query does not set a limit
+pub const DEFAULT_QUERY_LIMIT: usize = 100;
+
+/// Rotated file names only keep whole seconds, and an event is stamped just before it is written
+const ROTATION_SLACK_SECS: i64 = 2;
+
+/// Criteria for searching audit events; every set field must match
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditQuery {
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub event_types: Option<Vec<SecurityEventType>>,
+    pub min_severity: Option<SecuritySeverity>,
+    pub outcomes: Option<Vec<SecurityOutcome>>,
+    pub session_id: Option<String>,
+    pub user_id: Option<String>,
+    pub min_risk_score: Option<u8>,
+    pub max_risk_score: Option<u8>,
+    /// Case-insensitive substring searched for in the event details
+    pub text: Option<String>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+    /// Count every match by type, severity and hour; without this the scan stops
+    /// at the first older file once the page is filled
+    pub aggregate: bool,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, event: &SecurityEvent) -> bool {
+        if self.start_time.is_some_and(|start| event.timestamp < start)
+            || self.end_time.is_some_and(|end| event.timestamp > end)
+        {
+            return false;
+        }
+
+        if let Some(event_types) = &self.event_types {
+            if !event_types.contains(&event.event_type) {
+                return false;
+            }
+        }
+
+        if self
+            .min_severity
+            .is_some_and(|severity| event.severity < severity)
+        {
+            return false;
+        }
+
+        if let Some(outcomes) = &self.outcomes {
+            if !outcomes.contains(&event.outcome) {
+                return false;
+            }
+        }
+
+        if self.session_id.is_some() && event.session_id != self.session_id {
+            return false;
+        }
+        if self.user_id.is_some() && event.user_id != self.user_id {
+            return false;
+        }
+
+        if self
+            .min_risk_score
+            .is_some_and(|min| event.risk_score < min)
+            || self
+                .max_risk_score
+                .is_some_and(|max| event.risk_score > max)
+        {
+            return false;
+        }
+
+        if let Some(text) = &self.text {
+            let needle = text.to_lowercase();
+            let found = event.details.iter().any(|(key, value)| {
+                key.to_lowercase().contains(&needle)
+                    || value.to_string().to_lowercase().contains(&needle)
+            });
+            if !found {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Whether a file written between `opened_at` and `rotated_at` can hold matching events
+    fn may_contain(
+        &self,
+        opened_at: Option<DateTime<Utc>>,
+        rotated_at: Option<DateTime<Utc>>,
+    ) -> bool {
+        let slack = chrono::Duration::seconds(ROTATION_SLACK_SECS);
+        let ends_before_start = rotated_at
+            .zip(self.start_time)
+            .is_some_and(|(rotated_at, start)| rotated_at + slack < start);
+        let opens_after_end = opened_at
+            .zip(self.end_time)
+            .is_some_and(|(opened_at, end)| opened_at - slack > end);
+        !ends_before_start && !opens_after_end
+    }
+
+    fn page_end(&self) -> usize {
+        self.offset + self.limit.unwrap_or(DEFAULT_QUERY_LIMIT)
+    }
+}
+
+/// Counts over every event matching an aggregating query, not just the returned page
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditAggregations {
+    pub by_type: BTreeMap<String, u64>,
+    pub by_severity: BTreeMap<String, u64>,
+    /// Keyed by the start of each hour
+    pub by_hour: BTreeMap<DateTime<Utc>, u64>,
+}
+
+impl AuditAggregations {
+    fn add(&mut self, event: &SecurityEvent) {
+        *self
+            .by_type
+            .entry(format!("{:?}", event.event_type))
+            .or_insert(0) += 1;
+        *self
+            .by_severity
+            .entry(format!("{:?}", event.severity))
+            .or_insert(0) += 1;
+
+        let hour = event
+            .timestamp
+            .with_minute(0)
+            .and_then(|t| t.with_second(0))
+            .and_then(|t| t.with_nanosecond(0))
+            .unwrap_or(event.timestamp);
+        *self.by_hour.entry(hour).or_insert(0) += 1;
+    }
+}
+
+/// A page of matching events, newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditQueryResult {
+    pub events: Vec<SecurityEvent>,
+    /// Matches seen; a lower bound unless `complete`
+    pub total_matches: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub aggregations: AuditAggregations,
+    pub files_searched: usize,
+    /// Whether every file that could hold matches was searched
+    pub complete: bool,
+}
+
+/// Ranks newer events higher, then lower ids
+fn rank(event: &SecurityEvent) -> (DateTime<Utc>, Reverse<&str>) {
+    (event.timestamp, Reverse(event.id.as_str()))
+}
+
+#[derive(Debug)]
+struct Ranked(SecurityEvent);
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        rank(&self.0).cmp(&rank(&other.0))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+/// Collects matching events from any number of sources, newest sources first
+///
+/// Only the newest `offset + limit` matches are kept in memory.
+#[derive(Debug)]
+pub struct AuditQueryRunner<'a> {
+    query: &'a AuditQuery,
+    /// Min-heap, so the oldest kept match is evicted first
+    page: BinaryHeap<Reverse<Ranked>>,
+    page_ids: HashSet<String>,
+    total_matches: usize,
+    aggregations: AuditAggregations,
+    files_searched: usize,
+    complete: bool,
+}
+
+impl<'a> AuditQueryRunner<'a> {
+    pub fn new(query: &'a AuditQuery) -> Self {
+        Self {
+            query,
+            page: BinaryHeap::new(),
+            page_ids: HashSet::new(),
+            total_matches: 0,
+            aggregations: AuditAggregations::default(),
+            files_searched: 0,
+            complete: true,
+        }
+    }
+
+    pub fn add_event(&mut self, event: SecurityEvent) {
+        // Events are identified by id, so one read from two sources is only listed once
+        if !self.query.matches(&event) || self.page_ids.contains(&event.id) {
+            return;
+        }
+        self.total_matches += 1;
+        if self.query.aggregate {
+            self.aggregations.add(&event);
+        }
+
+        let capacity = self.query.page_end();
+        if self.page.len() >= capacity
+            && self
+                .page
+                .peek()
+                .is_none_or(|Reverse(oldest)| rank(&event) <= rank(&oldest.0))
+        {
+            return;
+        }
+        self.page_ids.insert(event.id.clone());
+        self.page.push(Reverse(Ranked(event)));
+        if self.page.len() > capacity {
+            if let Some(Reverse(Ranked(evicted))) = self.page.pop() {
+                self.page_ids.remove(&evicted.id);
+            }
+        }
+    }
+
+    /// Whether the page is filled with events newer than anything written before `rotated_at`
+    fn page_filled_after(&self, rotated_at: DateTime<Utc>) -> bool {
+        !self.query.aggregate
+            && self.page.len() >= self.query.page_end()
+            && self.page.peek().is_some_and(|Reverse(oldest)| {
+                oldest.0.timestamp > rotated_at + chrono::Duration::seconds(ROTATION_SLACK_SECS)
+            })
+    }
+
+    /// Scan a log file, skipping checkpoints and lines that do not parse
+    pub async fn add_file(&mut self, path: &Path) -> std::io::Result<()> {
+        let file = match tokio::fs::File::open(path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        self.files_searched += 1;
+
+        let mut lines = BufReader::new(file).lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // Records written before the log was hash-chained hold a bare event
+            let event = match serde_json::from_str::<AuditRecord>(&line) {
+                Ok(AuditRecord {
+                    entry: AuditEntry::Event(event),
+                    ..
+                }) => event,
+                Ok(_) => continue,
+                Err(_) => match serde_json::from_str::<SecurityEvent>(&line) {
+                    Ok(event) => event,
+                    Err(_) => continue,
+                },
+            };
+            self.add_event(event);
+        }
+
+        Ok(())
+    }
+
+    /// Scan the log, then whichever rotated files can hold matching events, newest first
+    ///
+    /// Each file covers the time between the rotation before it and its own rotation.
+    pub async fn add_log_files(&mut self, log_path: &Path) -> std::io::Result<()> {
+        let rotated = rotated_log_files(log_path).await?;
+        let newest_rotation = rotated.last().map(|(_, rotated_at)| *rotated_at);
+        if self.query.may_contain(newest_rotation, None) {
+            self.add_file(log_path).await?;
+        }
+
+        for (i, (path, rotated_at)) in rotated.iter().enumerate().rev() {
+            if self.page_filled_after(*rotated_at) {
+                self.complete = false;
+                break;
+            }
+            let opened_at = i.checked_sub(1).map(|prev| rotated[prev].1);
+            if self.query.may_contain(opened_at, Some(*rotated_at)) {
+                self.add_file(path).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> AuditQueryResult {
+        let limit = self.query.limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+        let events = self
+            .page
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(Ranked(event))| event)
+            .skip(self.query.offset)
+            .take(limit)
+            .collect();
+
+        AuditQueryResult {
+            events,
+            total_matches: self.total_matches,
+            offset: self.query.offset,
+            limit,
+            aggregations: self.aggregations,
+            files_searched: self.files_searched,
+            complete: self.complete,
+        }
+    }
+}
+
+/// Rotated siblings of a log file (`{stem}.{%Y%m%d_%H%M%S}.log`) with their rotation time, oldest first
+pub async fn rotated_log_files(log_path: &Path) -> std::io::Result<Vec<(PathBuf, DateTime<Utc>)>> {
+    let Some(stem) = log_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+    else {
+        return Ok(Vec::new());
+    };
+    let dir = match log_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let mut entries = match tokio::fs::read_dir(&dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let prefix = format!("{}.", stem);
+    let mut files = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let rotated_at = name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".log"))
+            .and_then(|stamp| NaiveDateTime::parse_from_str(stamp, "%Y%m%d_%H%M%S").ok())
+            .map(|naive| Utc.from_utc_datetime(&naive));
+        if let Some(rotated_at) = rotated_at {
+            files.push((entry.path(), rotated_at));
+        }
+    }
+
+    files.sort_by_key(|(_, rotated_at)| *rotated_at);
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn event(
+        id: &str,
+        minutes_ago: i64,
+        event_type: SecurityEventType,
+        risk_score: u8,
+    ) -> SecurityEvent {
+        SecurityEvent {
+            id: id.to_string(),
+            timestamp: Utc::now() - chrono::Duration::minutes(minutes_ago),
+            event_type,
+            severity: SecuritySeverity::Warning,
+            session_id: Some("session-1".to_string()),
+            user_id: None,
+            window_label: None,
+            source_ip: None,
+            command: None,
+            details: HashMap::from([(
+                "path".to_string(),
+                serde_json::json!(format!("/tmp/{}", id)),
+            )]),
+            outcome: SecurityOutcome::Blocked,
+            risk_score,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_spans_rotated_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("audit.log");
+        let rotated_path = temp_dir.path().join("audit.20240101_000000.log");
+
+        let old = event("old", 600, SecurityEventType::CommandBlocked, 40);
+        tokio::fs::write(
+            &rotated_path,
+            format!("{}\n", serde_json::to_string(&old).unwrap()),
+        )
+        .await
+        .unwrap();
+
+        let mut chain = crate::security::audit_chain::AuditChain::new(None, 0, b"key".to_vec());
+        let lines: Vec<String> = [
+            event("recent", 5, SecurityEventType::InjectionAttempt, 90),
+            event("other", 65, SecurityEventType::CommandBlocked, 60),
+        ]
+        .into_iter()
+        .map(|e| serde_json::to_string(&chain.append(AuditEntry::Event(e))).unwrap())
+        .collect();
+        tokio::fs::write(&log_path, lines.join("\n")).await.unwrap();
+
+        let query = AuditQuery {
+            min_risk_score: Some(50),
+            aggregate: true,
+            ..Default::default()
+        };
+        let mut runner = AuditQueryRunner::new(&query);
+        runner.add_log_files(&log_path).await.unwrap();
+        runner.add_event(event("buffered", 1, SecurityEventType::CommandBlocked, 70));
+        let result = runner.finish();
+
+        assert_eq!(result.files_searched, 2);
+        assert!(result.complete);
+        assert_eq!(result.total_matches, 3);
+        let ids: Vec<&str> = result.events.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["buffered", "recent", "other"]);
+        assert_eq!(result.aggregations.by_type["CommandBlocked"], 2);
+        assert_eq!(result.aggregations.by_type["InjectionAttempt"], 1);
+
+        // Old rotated file is skipped entirely once the range starts after its rotation
+        let query = AuditQuery {
+            start_time: Some(Utc::now() - chrono::Duration::hours(2)),
+            text: Some("/TMP/OTHER".to_string()),
+            ..Default::default()
+        };
+        let mut runner = AuditQueryRunner::new(&query);
+        runner.add_log_files(&log_path).await.unwrap();
+        let result = runner.finish();
+        assert_eq!(result.files_searched, 1);
+        assert_eq!(result.total_matches, 1);
+        assert_eq!(result.events[0].id, "other");
+    }
+
+    #[test]
+    fn test_pagination_and_hourly_counts() {
+        let query = AuditQuery {
+            offset: 1,
+            limit: Some(2),
+            aggregate: true,
+            ..Default::default()
+        };
+        let mut runner = AuditQueryRunner::new(&query);
+        for i in 0..5 {
+            runner.add_event(event(
+                &format!("e{}", i),
+                i * 10,
+                SecurityEventType::CommandBlocked,
+                10,
+            ));
+        }
+        let result = runner.finish();
+
+        assert_eq!(result.total_matches, 5);
+        let ids: Vec<&str> = result.events.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["e1", "e2"]);
+        assert_eq!(result.aggregations.by_hour.values().sum::<u64>(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_scan_is_limited_to_files_that_can_match() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("audit.log");
+        let write_events = |path: PathBuf, events: Vec<SecurityEvent>| async move {
+            let lines: Vec<String> = events
+                .iter()
+                .map(|e| serde_json::to_string(e).unwrap())
+                .collect();
+            tokio::fs::write(path, lines.join("\n")).await.unwrap();
+        };
+        let rotated_path = |hours_ago: i64| {
+            let rotated_at = Utc::now() - chrono::Duration::hours(hours_ago);
+            temp_dir
+                .path()
+                .join(format!("audit.{}.log", rotated_at.format("%Y%m%d_%H%M%S")))
+        };
+
+        write_events(
+            rotated_path(3),
+            vec![event("oldest", 240, SecurityEventType::CommandBlocked, 10)],
+        )
+        .await;
+        write_events(
+            rotated_path(1),
+            vec![event("older", 120, SecurityEventType::CommandBlocked, 10)],
+        )
+        .await;
+        write_events(
+            log_path.clone(),
+            vec![
+                event("new", 20, SecurityEventType::CommandBlocked, 10),
+                event("newest", 10, SecurityEventType::CommandBlocked, 10),
+            ],
+        )
+        .await;
+
+        // A filled page stops the scan before older files
+        let query = AuditQuery {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let mut runner = AuditQueryRunner::new(&query);
+        runner.add_log_files(&log_path).await.unwrap();
+        let result = runner.finish();
+        assert_eq!(result.files_searched, 1);
+        assert!(!result.complete);
+        let ids: Vec<&str> = result.events.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["newest", "new"]);
+
+        // Aggregating reads everything
+        let query = AuditQuery {
+            limit: Some(2),
+            aggregate: true,
+            ..Default::default()
+        };
+        let mut runner = AuditQueryRunner::new(&query);
+        runner.add_log_files(&log_path).await.unwrap();
+        let result = runner.finish();
+        assert_eq!(result.files_searched, 3);
+        assert!(result.complete);
+        assert_eq!(result.total_matches, 4);
+        assert_eq!(result.events.len(), 2);
+
+        // Files opened after the range ends are skipped
+        let query = AuditQuery {
+            end_time: Some(Utc::now() - chrono::Duration::minutes(210)),
+            ..Default::default()
+        };
+        let mut runner = AuditQueryRunner::new(&query);
+        runner.add_log_files(&log_path).await.unwrap();
+        let result = runner.finish();
+        assert_eq!(result.files_searched, 1);
+        assert_eq!(result.total_matches, 1);
+        assert_eq!(result.events[0].id, "oldest");
+    }
+}
@@ -4,12 +4,18 @@
 //! without breaking backward compatibility.
 
 use super::{
-    audit_logger::{SecurityAuditLogger, SecurityEventType, SecurityOutcome},
+    audit_chain::AuditVerificationReport,
+    audit_logger::{AuditConfig, SecurityAuditLogger, SecurityEventType, SecurityOutcome},
+    audit_query::{AuditQuery, AuditQueryResult},
     command_validator::{CommandValidationResult, CommandWhitelist},
     input_sanitizer::{InputSanitizer, ValidationResult as InputValidationResult},
     ipc_security::{CommandValidation, IpcSecurity, SecurityContext},
     rate_limiter::{EnhancedRateLimiter, RateLimitResult},
-    session_manager::{SecureSessionManager, SessionValidation},
+    session_manager::{
+        SecureSessionManager, SessionConfig, SessionSecurityLevel, SessionValidation,
+    },
+    session_store::SessionPersistence,
+    totp::{MfaEnrollmentSetup, MfaMethod},
 };
 
 use serde::{Deserialize, Serialize};
@@ -17,6 +23,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tauri::{State, Window};
 use tokio::sync::RwLock;
+use tracing::error;
 
 /// Enhanced IPC Security Manager
 /// This wraps the basic IPC security with additional features
@@ -31,15 +38,42 @@ pub struct EnhancedIpcSecurity {
 }
 
 impl EnhancedIpcSecurity {
-    /// Create a new enhanced IPC security manager
+    /// Create a new enhanced IPC security manager whose audit log, keys and
+    /// sessions are kept in the app's data directories
     pub async fn new() -> Self {
+        Self::with_config(
+            AuditConfig::default(),
+            SessionConfig {
+                key_store_path: SessionConfig::default_key_store_path(),
+                ..SessionConfig::default()
+            },
+            SessionPersistence::default_for_app(),
+        )
+        .await
+    }
+
+    /// Create with custom audit and session configuration; without `persistence`
+    /// sessions live in memory only
+    pub async fn with_config(
+        audit_config: AuditConfig,
+        session_config: SessionConfig,
+        persistence: Option<SessionPersistence>,
+    ) -> Self {
+        let session_manager = SecureSessionManager::with_config(session_config);
+        let session_manager = match persistence {
+            Some(persistence) => session_manager.with_persistence(persistence),
+            None => session_manager,
+        };
+
         Self {
             basic_security: IpcSecurity::default(),
             input_sanitizer: InputSanitizer::default(),
             command_validator: CommandWhitelist::default(),
-            audit_logger: Arc::new(RwLock::new(SecurityAuditLogger::new().await)),
+            audit_logger: Arc::new(RwLock::new(
+                SecurityAuditLogger::with_config(audit_config).await,
+            )),
             enhanced_rate_limiter: Arc::new(RwLock::new(EnhancedRateLimiter::new())),
-            session_manager: Arc::new(RwLock::new(SecureSessionManager::new())),
+            session_manager: Arc::new(RwLock::new(session_manager)),
         }
     }
 
@@ -111,12 +145,14 @@ impl EnhancedIpcSecurity {
 
         // 3. Command validation with permissions
         let user_permissions = &context.permissions;
-        let command_result = self.command_validator.validate_command(
-            command,
-            args,
-            user_permissions,
-            context.user_id.is_some(), // Simple MFA check
-        );
+        let mfa_verified = self
+            .session_manager
+            .read()
+            .await
+            .has_mfa_elevation(session_id);
+        let command_result =
+            self.command_validator
+                .validate_command(command, args, user_permissions, mfa_verified);
 
         match command_result {
             CommandValidationResult::Allowed { .. } => {
@@ -176,9 +212,82 @@ impl EnhancedIpcSecurity {
         }
     }
 
-    /// Create a new session (delegates to basic security)
-    pub fn create_session(&self, window_label: String, user_id: Option<String>) -> String {
-        self.basic_security.create_session(window_label, user_id)
+    /// Create a new session in basic security and track it in the session manager
+    pub async fn create_session(&self, window_label: String, user_id: Option<String>) -> String {
+        let session_id = self
+            .basic_security
+            .create_session(window_label.clone(), user_id.clone());
+
+        let mut session_manager = self.session_manager.write().await;
+        if let Err(e) = session_manager.create_session_with_id(
+            session_id.clone(),
+            window_label,
+            user_id,
+            None,
+            None,
+            SessionSecurityLevel::Enhanced,
+        ) {
+            error!("Failed to track session {}: {}", session_id, e);
+        }
+
+        session_id
+    }
+
+    /// Start TOTP enrollment for the session's user
+    pub async fn enroll_mfa(&self, session_id: &str) -> Result<MfaEnrollmentSetup, String> {
+        let mut session_manager = self.session_manager.write().await;
+        session_manager.enroll_mfa(session_id)
+    }
+
+    /// Confirm TOTP enrollment with a code from the authenticator
+    pub async fn confirm_mfa_enrollment(&self, session_id: &str, code: &str) -> Result<(), String> {
+        let result = {
+            let mut session_manager = self.session_manager.write().await;
+            session_manager.confirm_mfa_enrollment(session_id, code)
+        };
+        self.log_mfa_result(session_id, "mfa_enrollment", &result)
+            .await;
+        result
+    }
+
+    /// Verify a second factor, elevating the session for MFA-gated commands
+    pub async fn verify_mfa(&self, session_id: &str, code: &str) -> Result<MfaMethod, String> {
+        let result = {
+            let mut session_manager = self.session_manager.write().await;
+            session_manager.verify_mfa(session_id, code)
+        };
+        self.log_mfa_result(session_id, "mfa_verification", &result)
+            .await;
+        result
+    }
+
+    /// Audit the outcome of an MFA step
+    async fn log_mfa_result<T>(&self, session_id: &str, action: &str, result: &Result<T, String>) {
+        let mut details = HashMap::from([(
+            "action".to_string(),
+            serde_json::Value::String(action.to_string()),
+        )]);
+        let (event_type, outcome) = match result {
+            Ok(_) => (SecurityEventType::LoginSuccess, SecurityOutcome::Success),
+            Err(reason) => {
+                details.insert(
+                    "reason".to_string(),
+                    serde_json::Value::String(reason.clone()),
+                );
+                (SecurityEventType::LoginFailure, SecurityOutcome::Failure)
+            }
+        };
+
+        let audit_logger = self.audit_logger.read().await;
+        audit_logger
+            .log_authentication(
+                event_type,
+                Some(session_id.to_string()),
+                None,
+                details,
+                outcome,
+            )
+            .await;
     }
 
     /// Get comprehensive security statistics
@@ -229,6 +338,18 @@ impl EnhancedIpcSecurity {
         audit_logger.flush().await;
     }
 
+    /// Verify the hash chain of the audit log
+    pub async fn verify_audit_log(&self) -> Result<AuditVerificationReport, String> {
+        let audit_logger = self.audit_logger.read().await;
+        audit_logger.verify_integrity().await
+    }
+
+    /// Search audit events across current and rotated log files
+    pub async fn query_audit_events(&self, query: &AuditQuery) -> Result<AuditQueryResult, String> {
+        let audit_logger = self.audit_logger.read().await;
+        audit_logger.query_events(query).await
+    }
+
     /// Clean up expired data
     pub async fn cleanup_expired(&self) {
         self.basic_security.cleanup_expired_sessions();
@@ -291,17 +412,59 @@ pub async fn validate_ipc_command_enhanced(
         .await
 }
 
+/// The OS account running the app
+///
+/// Sessions created over IPC belong to it rather than to a user id sent by the
+/// webview, so a compromised page cannot start sessions, or MFA enrollments,
+/// for someone else.
+fn local_account() -> Option<String> {
+    ["USER", "USERNAME"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|name| !name.is_empty())
+}
+
 /// Tauri command for creating enhanced session
 #[tauri::command]
 pub async fn create_enhanced_security_session(
     window: Window,
     security: State<'_, EnhancedIpcSecurity>,
-    user_id: Option<String>,
 ) -> Result<String, String> {
-    let session_id = security.create_session(window.label().to_string(), user_id);
+    let session_id = security
+        .create_session(window.label().to_string(), local_account())
+        .await;
     Ok(session_id)
 }
 
+/// Tauri command for starting TOTP enrollment
+#[tauri::command]
+pub async fn enroll_mfa(
+    security: State<'_, EnhancedIpcSecurity>,
+    session_id: String,
+) -> Result<MfaEnrollmentSetup, String> {
+    security.enroll_mfa(&session_id).await
+}
+
+/// Tauri command for confirming TOTP enrollment
+#[tauri::command]
+pub async fn confirm_mfa_enrollment(
+    security: State<'_, EnhancedIpcSecurity>,
+    session_id: String,
+    code: String,
+) -> Result<(), String> {
+    security.confirm_mfa_enrollment(&session_id, &code).await
+}
+
+/// Tauri command for verifying a TOTP or recovery code
+#[tauri::command]
+pub async fn verify_mfa_code(
+    security: State<'_, EnhancedIpcSecurity>,
+    session_id: String,
+    code: String,
+) -> Result<MfaMethod, String> {
+    security.verify_mfa(&session_id, &code).await
+}
+
 /// Tauri command for enhanced security statistics
 #[tauri::command]
 pub async fn get_enhanced_security_stats(
@@ -317,6 +480,23 @@ pub async fn flush_security_logs(security: State<'_, EnhancedIpcSecurity>) -> Re
     Ok(())
 }
 
+/// Tauri command for verifying audit log integrity
+#[tauri::command]
+pub async fn verify_audit_log(
+    security: State<'_, EnhancedIpcSecurity>,
+) -> Result<AuditVerificationReport, String> {
+    security.verify_audit_log().await
+}
+
+/// Tauri command for searching the audit log
+#[tauri::command]
+pub async fn query_audit_events(
+    security: State<'_, EnhancedIpcSecurity>,
+    query: AuditQuery,
+) -> Result<AuditQueryResult, String> {
+    security.query_audit_events(&query).await
+}
+
 /// Tauri command for cleanup operations
 #[tauri::command]
 pub async fn cleanup_security_data(security: State<'_, EnhancedIpcSecurity>) -> Result<(), String> {
@@ -327,13 +507,39 @@ pub async fn cleanup_security_data(security: State<'_, EnhancedIpcSecurity>) ->
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::security::session_store::{FileSessionStore, SessionStateCipher};
+    use tempfile::TempDir;
+
+    /// Security manager keeping its logs, keys and sessions under `temp_dir`
+    async fn test_security(temp_dir: &TempDir) -> EnhancedIpcSecurity {
+        let path = temp_dir.path();
+        EnhancedIpcSecurity::with_config(
+            AuditConfig {
+                log_file_path: path.join("logs").join("security_audit.log"),
+                alert_dead_letter_path: path.join("logs").join("dead_letter.jsonl"),
+                integrity_key_path: path.join("audit").join("audit.key"),
+                integrity_head_path: path.join("audit").join("audit_head.json"),
+                ..AuditConfig::default()
+            },
+            SessionConfig {
+                key_store_path: Some(path.join("session_keys.json")),
+                ..SessionConfig::default()
+            },
+            Some(SessionPersistence::new(
+                Box::new(FileSessionStore::new(path.join("sessions"))),
+                SessionStateCipher::new(&[7; 32]),
+            )),
+        )
+        .await
+    }
 
     #[tokio::test]
     async fn test_enhanced_security_creation() {
-        let security = EnhancedIpcSecurity::new().await;
+        let temp_dir = TempDir::new().unwrap();
+        let security = test_security(&temp_dir).await;
 
         // Should be able to create sessions
-        let session_id = security.create_session("test".to_string(), None);
+        let session_id = security.create_session("test".to_string(), None).await;
         assert!(!session_id.is_empty());
 
         // Should be able to get stats
@@ -344,10 +550,13 @@ mod tests {
 
     #[tokio::test]
     async fn test_enhanced_command_validation() {
-        let security = EnhancedIpcSecurity::new().await;
+        let temp_dir = TempDir::new().unwrap();
+        let security = test_security(&temp_dir).await;
 
         // Create a session
-        let session_id = security.create_session("test".to_string(), Some("user1".to_string()));
+        let session_id = security
+            .create_session("test".to_string(), Some("user1".to_string()))
+            .await;
 
         // Test valid command
         let result = security
@@ -371,7 +580,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_security_statistics() {
-        let security = EnhancedIpcSecurity::new().await;
+        let temp_dir = TempDir::new().unwrap();
+        let security = test_security(&temp_dir).await;
         let stats = security.get_enhanced_stats().await;
 
         // Should contain all expected sections
@@ -384,11 +594,14 @@ mod tests {
 
     #[tokio::test]
     async fn test_cleanup_operations() {
-        let security = EnhancedIpcSecurity::new().await;
+        let temp_dir = TempDir::new().unwrap();
+        let security = test_security(&temp_dir).await;
 
         // Create some sessions
-        let _session1 = security.create_session("test1".to_string(), None);
-        let _session2 = security.create_session("test2".to_string(), Some("user1".to_string()));
+        let _session1 = security.create_session("test1".to_string(), None).await;
+        let _session2 = security
+            .create_session("test2".to_string(), Some("user1".to_string()))
+            .await;
 
         // Cleanup should not fail
         security.cleanup_expired().await;
@@ -399,8 +612,9 @@ mod tests {
 
     #[tokio::test]
     async fn test_rate_limiting() {
-        let security = EnhancedIpcSecurity::new().await;
-        let session_id = security.create_session("test".to_string(), None);
+        let temp_dir = TempDir::new().unwrap();
+        let security = test_security(&temp_dir).await;
+        let session_id = security.create_session("test".to_string(), None).await;
 
         // First few commands should work
         for i in 0..5 {
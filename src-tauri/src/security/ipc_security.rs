@@ -219,7 +219,7 @@ impl IpcSecurity {
 
         let mut state = match self.rate_limiter.lock() {
             Ok(state) => state,
-            Err(_) => return false, // Failed to acquire lock, deny request
+            Err(_) => return Err("Rate limiter unavailable".to_string()), // Failed to acquire lock, deny request
         };
         let now = Instant::now();
         let key = format!("{}:{}", session_id, command);
@@ -6,13 +6,19 @@
 //! This module provides both basic security (for backward compatibility) and
 //! enhanced security features for comprehensive protection.
 
+pub mod alert_sinks;
+pub mod audit_chain;
 pub mod audit_logger;
+pub mod audit_query;
 pub mod command_validator;
 pub mod enhanced_ipc_security;
 pub mod input_sanitizer;
 pub mod ipc_security;
 pub mod rate_limiter;
 pub mod session_manager;
+pub mod session_store;
+pub mod session_tokens;
+pub mod totp;
 
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -3,11 +3,20 @@
 //! Advanced session management with secure tokens, session validation,
 //! and comprehensive security controls.
 
+use super::session_store::{
+    DirtyRecords, SessionPersistence, SessionStateRef, ACTIVITY_RETENTION_DAYS,
+    PENDING_MFA_ENROLLMENT_HOURS,
+};
+use super::session_tokens::{
+    peek_claims, SessionTokenClaims, SessionTokenPair, TokenError, TokenKeySet, TokenType,
+};
+use super::totp::{MfaEnrollment, MfaEnrollmentSetup, MfaMethod};
 use chrono::{DateTime, Duration, Utc};
-use rand::Rng;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use tracing::error;
 use uuid::Uuid;
 
 /// Session security level
@@ -49,6 +58,9 @@ pub struct SessionSecurityContext {
     pub risk_score: u8,
     pub failed_attempts: u32,
     pub mfa_verified: bool,
+    /// Until when MFA-gated commands are allowed without verifying again
+    #[serde(default)]
+    pub mfa_elevated_until: Option<DateTime<Utc>>,
     pub session_token: String,
     pub refresh_token: Option<String>,
 }
@@ -82,7 +94,25 @@ pub struct SessionConfig {
     pub enable_device_tracking: bool,
     pub require_ip_validation: bool,
     pub session_rotation_minutes: i64,
+    /// How long a verified second factor elevates the session
     pub mfa_timeout_minutes: i64,
+    /// Issuer shown in authenticator apps
+    pub mfa_issuer: String,
+    /// TOTP steps of clock drift accepted either way
+    pub mfa_skew_steps: i64,
+    /// Where token signing keys are kept; without it keys live only in memory
+    pub key_store_path: Option<PathBuf>,
+    pub signing_key_rotation_hours: i64,
+    /// Keys kept for verifying tokens signed before a rotation
+    pub signing_keys_retained: usize,
+}
+
+impl SessionConfig {
+    /// Key store under the app data directory
+    pub fn default_key_store_path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("com", "autodev-ai", "neural-bridge-platform")
+            .map(|dirs| dirs.data_dir().join("session_keys.json"))
+    }
 }
 
 impl Default for SessionConfig {
@@ -95,6 +125,11 @@ impl Default for SessionConfig {
             require_ip_validation: false,
             session_rotation_minutes: 60,
             mfa_timeout_minutes: 5,
+            mfa_issuer: "NeuralBridge".to_string(),
+            mfa_skew_steps: 1,
+            key_store_path: None,
+            signing_key_rotation_hours: 24 * 7,
+            signing_keys_retained: 3,
         }
     }
 }
@@ -105,31 +140,113 @@ pub struct SecureSessionManager {
     sessions: HashMap<String, SessionSecurityContext>,
     session_activities: HashMap<String, Vec<SessionActivity>>,
     blacklisted_tokens: HashSet<String>,
+    /// Confirmed TOTP enrollments by user id
+    mfa_enrollments: HashMap<String, MfaEnrollment>,
+    /// Enrollments awaiting their first code; confirming one replaces the user's active enrollment
+    pending_mfa_enrollments: HashMap<String, MfaEnrollment>,
     config: SessionConfig,
-    token_salt: [u8; 32],
+    key_set: TokenKeySet,
+    /// Where sessions and the blacklist are saved; `None` keeps them in memory only
+    persistence: Option<SessionPersistence>,
+    /// Records changed since the last save
+    dirty: DirtyRecords,
 }
 
 impl SecureSessionManager {
     /// Create a new secure session manager
     pub fn new() -> Self {
-        let mut rng = rand::thread_rng();
-        let mut token_salt = [0u8; 32];
-        rng.fill(&mut token_salt);
+        Self::with_config(SessionConfig::default())
+    }
+
+    /// Create with custom configuration
+    pub fn with_config(config: SessionConfig) -> Self {
+        let key_set = match &config.key_store_path {
+            Some(path) => TokenKeySet::load_or_create(path).unwrap_or_else(|e| {
+                error!("{}; tokens will not survive a restart", e);
+                TokenKeySet::generate()
+            }),
+            None => TokenKeySet::generate(),
+        };
 
         Self {
             sessions: HashMap::new(),
             session_activities: HashMap::new(),
             blacklisted_tokens: HashSet::new(),
-            config: SessionConfig::default(),
-            token_salt,
+            mfa_enrollments: HashMap::new(),
+            pending_mfa_enrollments: HashMap::new(),
+            config,
+            key_set,
+            persistence: None,
+            dirty: DirtyRecords::default(),
         }
     }
 
-    /// Create with custom configuration
-    pub fn with_config(config: SessionConfig) -> Self {
-        let mut manager = Self::new();
-        manager.config = config;
-        manager
+    /// Restore saved sessions from `persistence` and keep saving changes to it
+    ///
+    /// Expired sessions and stale blacklist entries are dropped while loading.
+    /// Records that cannot be read are logged and skipped.
+    pub fn with_persistence(mut self, persistence: SessionPersistence) -> Self {
+        match persistence.load() {
+            Ok(mut state) => {
+                self.dirty = state.compact(Utc::now());
+                self.sessions = state.sessions;
+                self.session_activities = state.activities;
+                self.blacklisted_tokens = state.blacklisted_tokens;
+                self.mfa_enrollments = state.mfa_enrollments;
+                self.pending_mfa_enrollments = state.pending_mfa_enrollments;
+                self.persistence = Some(persistence);
+                self.persist();
+            }
+            Err(e) => {
+                error!("Failed to restore sessions: {}", e);
+                self.persistence = Some(persistence);
+            }
+        }
+        self
+    }
+
+    /// Save every session, activity log, blacklist entry and enrollment and wait until written
+    pub fn save_state(&mut self) -> Result<(), String> {
+        // Deleted records are only known from the pending changes
+        let pending = std::mem::take(&mut self.dirty);
+        let Some(persistence) = &self.persistence else {
+            return Ok(());
+        };
+
+        let state = self.state_ref();
+        let mut dirty = DirtyRecords::all(&state);
+        dirty.extend(pending);
+
+        persistence.save(state, &dirty)?;
+        persistence.flush()
+    }
+
+    /// Queue the records changed since the last save; failures are logged since
+    /// the in-memory state is still valid
+    fn persist(&mut self) {
+        let dirty = std::mem::take(&mut self.dirty);
+        let Some(persistence) = &self.persistence else {
+            return;
+        };
+        if let Err(e) = persistence.save(self.state_ref(), &dirty) {
+            error!("Failed to persist sessions: {}", e);
+        }
+    }
+
+    fn state_ref(&self) -> SessionStateRef<'_> {
+        SessionStateRef {
+            sessions: &self.sessions,
+            activities: &self.session_activities,
+            blacklisted_tokens: &self.blacklisted_tokens,
+            mfa_enrollments: &self.mfa_enrollments,
+            pending_mfa_enrollments: &self.pending_mfa_enrollments,
+        }
+    }
+
+    /// Revoke a token until it expires
+    fn blacklist_token(&mut self, token: String) {
+        self.dirty.blacklisted_tokens.insert(token.clone());
+        self.blacklisted_tokens.insert(token);
     }
 
     /// Create a new secure session
@@ -141,11 +258,32 @@ impl SecureSessionManager {
         user_agent: Option<String>,
         security_level: SessionSecurityLevel,
     ) -> Result<SessionSecurityContext, String> {
-        let session_id = Uuid::new_v4().to_string();
+        self.create_session_with_id(
+            Uuid::new_v4().to_string(),
+            window_label,
+            user_id,
+            ip_address,
+            user_agent,
+            security_level,
+        )
+    }
+
+    /// Create a session under an id issued elsewhere, e.g. by the basic IPC security layer
+    pub fn create_session_with_id(
+        &mut self,
+        session_id: String,
+        window_label: String,
+        user_id: Option<String>,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+        security_level: SessionSecurityLevel,
+    ) -> Result<SessionSecurityContext, String> {
+        if self.sessions.contains_key(&session_id) {
+            return Err("Session already exists".to_string());
+        }
         let now = Utc::now();
 
-        // Generate secure session token
-        let session_token = self.generate_secure_token(&session_id, &user_id);
+        self.rotate_signing_key_if_due();
 
         // Generate device fingerprint
         let device_fingerprint =
@@ -167,13 +305,16 @@ impl SecureSessionManager {
         let risk_score =
             self.calculate_initial_risk_score(&ip_address, &user_agent, security_level);
 
-        let session = SessionSecurityContext {
+        let mut session = SessionSecurityContext {
             session_id: session_id.clone(),
             user_id: user_id.clone(),
-            authentication_state: if user_id.is_some() {
-                AuthenticationState::Authenticated
-            } else {
-                AuthenticationState::Anonymous
+            authentication_state: match &user_id {
+                // Users with a confirmed authenticator must prove it first
+                Some(user_id) if self.has_confirmed_mfa(user_id) => {
+                    AuthenticationState::TwoFactorPending
+                }
+                Some(_) => AuthenticationState::Authenticated,
+                None => AuthenticationState::Anonymous,
             },
             security_level,
             permissions,
@@ -187,14 +328,17 @@ impl SecureSessionManager {
             risk_score,
             failed_attempts: 0,
             mfa_verified: false,
-            session_token: session_token.clone(),
-            refresh_token: if security_level >= SessionSecurityLevel::Enhanced {
-                Some(self.generate_refresh_token(&session_id))
-            } else {
-                None
-            },
+            mfa_elevated_until: None,
+            session_token: String::new(),
+            refresh_token: None,
         };
 
+        // Tokens embed the session's claims, so they are signed once it is complete
+        session.session_token = self.issue_token(&session, TokenType::Access);
+        if security_level >= SessionSecurityLevel::Enhanced {
+            session.refresh_token = Some(self.issue_token(&session, TokenType::Refresh));
+        }
+
         // Log session creation
         self.log_session_activity(
             &session_id,
@@ -217,6 +361,8 @@ impl SecureSessionManager {
         );
 
         self.sessions.insert(session_id.clone(), session.clone());
+        self.dirty.sessions.insert(session_id);
+        self.persist();
         Ok(session)
     }
 
@@ -246,6 +392,7 @@ impl SecureSessionManager {
             return SessionValidation::Expired;
         }
         
+        self.dirty.sessions.insert(session_id.to_string());
         let session = self.sessions.get_mut(session_id).unwrap();
 
         // Check authentication state
@@ -335,44 +482,146 @@ impl SecureSessionManager {
 
     /// Refresh session with new token
     pub fn refresh_session(&mut self, session_id: &str) -> Result<String, String> {
-        // First check if session exists and get user_id
-        let user_id = self
+        self.rotate_signing_key_if_due();
+
+        let mut session = self
             .sessions
             .get(session_id)
             .ok_or("Session not found")?
-            .user_id
             .clone();
 
-        // Generate new token before getting mutable reference
-        let new_token = self.generate_secure_token(session_id, &user_id);
-        
-        // Now get mutable reference to update session
-        let session = self
-            .sessions
-            .get_mut(session_id)
-            .ok_or("Session not found")?;
-
         // Store old token for blacklisting
         let old_token = session.session_token.clone();
-        
-        // Update session
-        session.session_token = new_token.clone();
+
+        // Extend expiry before signing so the new token carries it
         session.last_activity = Utc::now();
-        
-        // Extend expiry
-        let expiry_hours = self.config.default_expiry_hours;
-        session.expires_at = Utc::now() + Duration::hours(expiry_hours);
-        
-        drop(session); // Drop the mutable borrow
-        
+        session.expires_at = Utc::now() + Duration::hours(self.config.default_expiry_hours);
+        let new_token = self.issue_token(&session, TokenType::Access);
+        session.session_token = new_token.clone();
+        self.sessions.insert(session_id.to_string(), session);
+        self.dirty.sessions.insert(session_id.to_string());
+
         // Blacklist old token
-        self.blacklisted_tokens.insert(old_token);
-        
+        self.blacklist_token(old_token);
+
         self.log_session_activity(session_id, "session_refreshed", HashMap::new(), 5);
+        self.persist();
 
         Ok(new_token)
     }
 
+    /// Exchange a refresh token for a new access and refresh token pair
+    ///
+    /// Each refresh token is single-use. Presenting one that was already
+    /// exchanged means it leaked, so the whole session is revoked.
+    pub fn refresh_with_token(&mut self, refresh_token: &str) -> Result<SessionTokenPair, String> {
+        let claims = self
+            .key_set
+            .verify(refresh_token)
+            .map_err(|e| e.to_string())?;
+        if claims.token_type != TokenType::Refresh {
+            return Err(TokenError::WrongType.to_string());
+        }
+
+        let session_id = claims.session_id;
+        let current = self
+            .sessions
+            .get(&session_id)
+            .ok_or("Session not found")?
+            .refresh_token
+            .clone();
+        if current.as_deref() != Some(refresh_token) {
+            self.log_session_activity(
+                &session_id,
+                "refresh_token_reuse",
+                HashMap::from([(
+                    "token_id".to_string(),
+                    serde_json::Value::String(claims.jti),
+                )]),
+                90,
+            );
+            self.terminate_session(&session_id);
+            return Err("Refresh token reuse detected; session revoked".to_string());
+        }
+
+        self.refresh_session(&session_id)?;
+
+        let mut session = self
+            .sessions
+            .get(&session_id)
+            .ok_or("Session not found")?
+            .clone();
+        let new_refresh_token = self.issue_token(&session, TokenType::Refresh);
+        session.refresh_token = Some(new_refresh_token.clone());
+        self.blacklist_token(refresh_token.to_string());
+
+        let pair = SessionTokenPair {
+            session_id: session_id.clone(),
+            access_token: session.session_token.clone(),
+            refresh_token: new_refresh_token,
+            expires_at: session.expires_at,
+        };
+        self.sessions.insert(session_id.clone(), session);
+        self.dirty.sessions.insert(session_id.clone());
+
+        self.log_session_activity(&session_id, "refresh_token_rotated", HashMap::new(), 5);
+        self.persist();
+
+        Ok(pair)
+    }
+
+    /// Verify a token's signature, expiry and revocation and return its claims
+    ///
+    /// Works for sessions this manager has not seen, e.g. after a restart, as
+    /// long as the signing key is still in the key set.
+    pub fn validate_token(
+        &self,
+        token: &str,
+        expected_type: TokenType,
+    ) -> Result<SessionTokenClaims, String> {
+        let claims = self.key_set.verify(token).map_err(|e| e.to_string())?;
+        if claims.token_type != expected_type {
+            return Err(TokenError::WrongType.to_string());
+        }
+        if self.blacklisted_tokens.contains(token) {
+            return Err("Token has been revoked".to_string());
+        }
+
+        if let Some(session) = self.sessions.get(&claims.session_id) {
+            match session.authentication_state {
+                AuthenticationState::Revoked => return Err("Session revoked".to_string()),
+                AuthenticationState::Suspended => return Err("Session suspended".to_string()),
+                _ => {}
+            }
+        }
+
+        Ok(claims)
+    }
+
+    /// Start signing new tokens with a fresh key
+    pub fn rotate_signing_key(&mut self) -> Result<String, String> {
+        let kid = self
+            .key_set
+            .rotate(self.config.signing_keys_retained)
+            .kid
+            .clone();
+        if let Some(path) = &self.config.key_store_path {
+            self.key_set.save(path)?;
+        }
+        Ok(kid)
+    }
+
+    fn rotate_signing_key_if_due(&mut self) {
+        if self
+            .key_set
+            .rotation_due(Duration::hours(self.config.signing_key_rotation_hours))
+        {
+            if let Err(e) = self.rotate_signing_key() {
+                error!("Failed to rotate session signing key: {}", e);
+            }
+        }
+    }
+
     /// Update session permissions
     pub fn update_permissions(&mut self, session_id: &str, permissions: HashSet<String>) -> bool {
         // Check if session exists and get old permissions
@@ -382,9 +631,14 @@ impl SecureSessionManager {
             return false;
         };
         
-        // Update permissions
-        if let Some(session) = self.sessions.get_mut(session_id) {
+        // Update permissions and reissue the access token, which carries them
+        if let Some(mut session) = self.sessions.get(session_id).cloned() {
             session.permissions = permissions.clone();
+            let new_token = self.issue_token(&session, TokenType::Access);
+            let old_token = std::mem::replace(&mut session.session_token, new_token);
+            self.blacklist_token(old_token);
+            self.sessions.insert(session_id.to_string(), session);
+            self.dirty.sessions.insert(session_id.to_string());
         }
         
         // Log activity after releasing the mutable borrow
@@ -413,6 +667,7 @@ impl SecureSessionManager {
             ]),
             15,
         );
+        self.persist();
 
         true
     }
@@ -428,6 +683,7 @@ impl SecureSessionManager {
         } else {
             return false;
         };
+        self.dirty.sessions.insert(session_id.to_string());
         
         // Log activity after releasing the mutable borrow
         self.log_session_activity(
@@ -461,42 +717,204 @@ impl SecureSessionManager {
                 80,
             );
         }
+        self.persist();
 
         true
     }
 
-    /// Enable MFA for session
+    /// Start TOTP enrollment for the session's user
+    ///
+    /// The enrollment only takes effect once confirmed with a code; until then
+    /// a confirmed enrollment stays active. Replacing a confirmed enrollment
+    /// requires an MFA-elevated session.
+    pub fn enroll_mfa(&mut self, session_id: &str) -> Result<MfaEnrollmentSetup, String> {
+        let session = self.sessions.get(session_id).ok_or("Session not found")?;
+        let user_id = session
+            .user_id
+            .clone()
+            .ok_or("MFA requires an authenticated user")?;
+        if session.authentication_state == AuthenticationState::TwoFactorPending {
+            return Err("Complete MFA verification first".to_string());
+        }
+        if self.has_confirmed_mfa(&user_id) && !self.has_mfa_elevation(session_id) {
+            return Err("MFA is already enrolled; verify a code before re-enrolling".to_string());
+        }
+
+        let (enrollment, setup) = MfaEnrollment::generate(&self.config.mfa_issuer, &user_id);
+        self.dirty.pending_mfa_enrollments.insert(user_id.clone());
+        self.pending_mfa_enrollments.insert(user_id, enrollment);
+
+        self.log_session_activity(session_id, "mfa_enrollment_started", HashMap::new(), 10);
+        self.persist();
+
+        Ok(setup)
+    }
+
+    /// Confirm a pending enrollment with a code from the authenticator, making it the active one
+    pub fn confirm_mfa_enrollment(&mut self, session_id: &str, code: &str) -> Result<(), String> {
+        let user_id = self.session_user(session_id)?;
+        let skew_steps = self.config.mfa_skew_steps;
+        let cutoff = Utc::now() - Duration::hours(PENDING_MFA_ENROLLMENT_HOURS);
+        let enrollment = self
+            .pending_mfa_enrollments
+            .get_mut(&user_id)
+            .filter(|enrollment| enrollment.created_at > cutoff)
+            .ok_or("No MFA enrollment is pending")?;
+
+        if let Err(e) = enrollment.verify_totp(code, Utc::now(), skew_steps) {
+            self.record_failed_attempt(session_id);
+            return Err(e.to_string());
+        }
+
+        if let Some(mut enrollment) = self.pending_mfa_enrollments.remove(&user_id) {
+            enrollment.confirmed = true;
+            self.mfa_enrollments.insert(user_id.clone(), enrollment);
+        }
+        self.dirty.pending_mfa_enrollments.insert(user_id.clone());
+        self.dirty.mfa_enrollments.insert(user_id);
+
+        self.elevate_session(session_id, MfaMethod::Totp);
+        self.log_session_activity(session_id, "mfa_enabled", HashMap::new(), 10);
+        self.persist();
+
+        Ok(())
+    }
+
+    /// Require the second factor again before MFA-gated commands are allowed
     pub fn enable_mfa(&mut self, session_id: &str) -> bool {
+        let enrolled = match self.sessions.get(session_id) {
+            Some(session) => session
+                .user_id
+                .as_deref()
+                .is_some_and(|user_id| self.has_confirmed_mfa(user_id)),
+            None => return false,
+        };
+        if !enrolled {
+            return false;
+        }
+
         if let Some(session) = self.sessions.get_mut(session_id) {
             session.authentication_state = AuthenticationState::TwoFactorPending;
-            self.log_session_activity(session_id, "mfa_enabled", HashMap::new(), 10);
-            true
-        } else {
-            false
+            session.mfa_verified = false;
+            session.mfa_elevated_until = None;
+        }
+        self.dirty.sessions.insert(session_id.to_string());
+        self.log_session_activity(session_id, "mfa_challenge_started", HashMap::new(), 10);
+        self.persist();
+        true
+    }
+
+    /// Verify a TOTP or recovery code and elevate the session
+    pub fn verify_mfa(&mut self, session_id: &str, code: &str) -> Result<MfaMethod, String> {
+        let user_id = self.session_user(session_id)?;
+        let skew_steps = self.config.mfa_skew_steps;
+        let enrollment = self
+            .mfa_enrollments
+            .get_mut(&user_id)
+            .filter(|enrollment| enrollment.confirmed)
+            .ok_or("MFA is not enrolled")?;
+
+        let method = match enrollment.verify(code, Utc::now(), skew_steps) {
+            Ok(method) => method,
+            Err(e) => {
+                self.log_session_activity(
+                    session_id,
+                    "mfa_failed",
+                    HashMap::from([(
+                        "reason".to_string(),
+                        serde_json::Value::String(e.to_string()),
+                    )]),
+                    50,
+                );
+                self.record_failed_attempt(session_id);
+                return Err(e.to_string());
+            }
+        };
+        let remaining_recovery_codes = enrollment.remaining_recovery_codes();
+        self.dirty.mfa_enrollments.insert(user_id);
+
+        self.elevate_session(session_id, method);
+        if method == MfaMethod::RecoveryCode {
+            self.log_session_activity(
+                session_id,
+                "mfa_recovery_code_used",
+                HashMap::from([(
+                    "remaining".to_string(),
+                    serde_json::Value::Number(remaining_recovery_codes.into()),
+                )]),
+                30,
+            );
         }
+        self.persist();
+
+        Ok(method)
+    }
+
+    /// Whether the session verified a second factor recently enough for MFA-gated commands
+    pub fn has_mfa_elevation(&self, session_id: &str) -> bool {
+        self.sessions.get(session_id).is_some_and(|session| {
+            session.mfa_verified
+                && session
+                    .mfa_elevated_until
+                    .is_some_and(|until| Utc::now() < until)
+        })
     }
 
-    /// Verify MFA and complete authentication
-    pub fn verify_mfa(&mut self, session_id: &str) -> bool {
+    fn has_confirmed_mfa(&self, user_id: &str) -> bool {
+        self.mfa_enrollments
+            .get(user_id)
+            .is_some_and(|enrollment| enrollment.confirmed)
+    }
+
+    fn session_user(&self, session_id: &str) -> Result<String, String> {
+        self.sessions
+            .get(session_id)
+            .ok_or("Session not found")?
+            .user_id
+            .clone()
+            .ok_or_else(|| "MFA requires an authenticated user".to_string())
+    }
+
+    /// Mark the session as MFA-verified for the configured time
+    fn elevate_session(&mut self, session_id: &str, method: MfaMethod) {
+        let elevated_until = Utc::now() + Duration::minutes(self.config.mfa_timeout_minutes);
         if let Some(session) = self.sessions.get_mut(session_id) {
             session.mfa_verified = true;
+            session.mfa_elevated_until = Some(elevated_until);
             session.authentication_state = AuthenticationState::TwoFactorAuthenticated;
             session.risk_score = session.risk_score.saturating_sub(20); // Reduce risk after MFA
-
-            self.log_session_activity(session_id, "mfa_verified", HashMap::new(), 5);
-            true
-        } else {
-            false
         }
+        self.dirty.sessions.insert(session_id.to_string());
+
+        self.log_session_activity(
+            session_id,
+            "mfa_verified",
+            HashMap::from([(
+                "method".to_string(),
+                serde_json::to_value(method).unwrap_or_default(),
+            )]),
+            5,
+        );
     }
 
     /// Terminate session
     pub fn terminate_session(&mut self, session_id: &str) -> bool {
+        let terminated = self.remove_session(session_id);
+        if terminated {
+            self.persist();
+        }
+        terminated
+    }
+
+    /// Remove a session and blacklist its tokens
+    fn remove_session(&mut self, session_id: &str) -> bool {
         if let Some(session) = self.sessions.remove(session_id) {
+            self.dirty.sessions.insert(session_id.to_string());
+
             // Blacklist token
-            self.blacklisted_tokens.insert(session.session_token);
+            self.blacklist_token(session.session_token);
             if let Some(refresh_token) = session.refresh_token {
-                self.blacklisted_tokens.insert(refresh_token);
+                self.blacklist_token(refresh_token);
             }
 
             self.log_session_activity(session_id, "session_terminated", HashMap::new(), 10);
@@ -515,35 +933,28 @@ impl SecureSessionManager {
         self.sessions.get(session_id)
     }
 
-    /// Generate secure token with salt and hash
-    fn generate_secure_token(&self, session_id: &str, user_id: &Option<String>) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(&self.token_salt);
-        hasher.update(session_id.as_bytes());
-        if let Some(uid) = user_id {
-            hasher.update(uid.as_bytes());
-        }
-        hasher.update(Utc::now().timestamp().to_be_bytes());
-
-        // Add some randomness
-        let mut rng = rand::thread_rng();
-        let random_bytes: [u8; 16] = rng.gen();
-        hasher.update(&random_bytes);
-
-        let result = hasher.finalize();
-        hex::encode(result)
-    }
+    /// Sign a token carrying the session's current claims
+    fn issue_token(&self, session: &SessionSecurityContext, token_type: TokenType) -> String {
+        let now = Utc::now();
+        let expires_at = match token_type {
+            TokenType::Access => (now + Duration::minutes(self.config.session_rotation_minutes))
+                .min(session.expires_at),
+            TokenType::Refresh => session.expires_at,
+        };
 
-    /// Generate refresh token
-    fn generate_refresh_token(&self, session_id: &str) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(b"REFRESH");
-        hasher.update(&self.token_salt);
-        hasher.update(session_id.as_bytes());
-        hasher.update(Utc::now().timestamp().to_be_bytes());
+        let mut permissions: Vec<String> = session.permissions.iter().cloned().collect();
+        permissions.sort();
 
-        let result = hasher.finalize();
-        hex::encode(result)
+        self.key_set.sign(&SessionTokenClaims {
+            jti: Uuid::new_v4().to_string(),
+            token_type,
+            session_id: session.session_id.clone(),
+            user_id: session.user_id.clone(),
+            security_level: session.security_level,
+            permissions,
+            issued_at: now,
+            expires_at,
+        })
     }
 
     /// Generate device fingerprint
@@ -611,6 +1022,7 @@ impl SecureSessionManager {
             .entry(session_id.to_string())
             .or_insert_with(Vec::new)
             .push(activity);
+        self.dirty.activities.insert(session_id.to_string());
     }
 
     /// Clean up expired sessions and old activities
@@ -626,20 +1038,31 @@ impl SecureSessionManager {
             .collect();
 
         for session_id in expired_sessions {
-            self.terminate_session(&session_id);
+            self.remove_session(&session_id);
         }
 
-        // Clean up old blacklisted tokens (keep for 24 hours)
-        // In a real implementation, you'd want to track token timestamps
-        if self.blacklisted_tokens.len() > 10000 {
-            self.blacklisted_tokens.clear(); // Simple cleanup
-        }
+        // Expired tokens fail verification anyway, so they no longer need blacklisting
+        let dirty = &mut self.dirty;
+        self.blacklisted_tokens.retain(|token| {
+            let keep = peek_claims(token).is_some_and(|claims| claims.expires_at > now);
+            if !keep {
+                dirty.blacklisted_tokens.insert(token.clone());
+            }
+            keep
+        });
 
-        // Clean up old activities (keep for 7 days)
-        let cutoff = now - Duration::days(7);
-        for activities in self.session_activities.values_mut() {
+        // Clean up old activities
+        let cutoff = now - Duration::days(ACTIVITY_RETENTION_DAYS);
+        self.session_activities.retain(|session_id, activities| {
+            let before = activities.len();
             activities.retain(|activity| activity.timestamp > cutoff);
-        }
+            if activities.len() != before {
+                dirty.activities.insert(session_id.clone());
+            }
+            !activities.is_empty()
+        });
+
+        self.persist();
     }
 
     /// Get session statistics
@@ -799,23 +1222,146 @@ mod tests {
 
     #[test]
     fn test_token_generation() {
-        let manager = SecureSessionManager::new();
+        let mut manager = SecureSessionManager::new();
+
+        let session = manager
+            .create_session(
+                "main".to_string(),
+                Some("user1".to_string()),
+                None,
+                None,
+                SessionSecurityLevel::Enhanced,
+            )
+            .unwrap();
 
-        let token1 = manager.generate_secure_token("session1", &Some("user1".to_string()));
-        let token2 = manager.generate_secure_token("session2", &Some("user2".to_string()));
-        let token3 = manager.generate_secure_token("session1", &Some("user1".to_string()));
+        let claims = manager
+            .validate_token(&session.session_token, TokenType::Access)
+            .unwrap();
+        assert_eq!(claims.session_id, session.session_id);
+        assert_eq!(claims.user_id.as_deref(), Some("user1"));
+        assert_eq!(claims.security_level, SessionSecurityLevel::Enhanced);
+        assert!(claims
+            .permissions
+            .contains(&"user.authenticated".to_string()));
+
+        // Refresh tokens are not accepted as access tokens
+        let refresh_token = session.refresh_token.unwrap();
+        assert!(manager
+            .validate_token(&refresh_token, TokenType::Access)
+            .is_err());
+
+        // A manager sharing the key set, e.g. after a restart, accepts the token
+        let restarted = SecureSessionManager {
+            key_set: manager.key_set.clone(),
+            ..SecureSessionManager::new()
+        };
+        assert!(restarted
+            .validate_token(&session.session_token, TokenType::Access)
+            .is_ok());
+    }
 
-        // Tokens should be different
-        assert_ne!(token1, token2);
-        assert_ne!(token1, token3); // Different due to timestamp
+    #[test]
+    fn test_refresh_token_rotation_and_reuse() {
+        let mut manager = SecureSessionManager::new();
 
-        // Tokens should be hex encoded (64 chars for SHA256)
-        assert_eq!(token1.len(), 64);
-        assert!(token1.chars().all(|c| c.is_ascii_hexdigit()));
+        let session = manager
+            .create_session(
+                "main".to_string(),
+                Some("user".to_string()),
+                None,
+                None,
+                SessionSecurityLevel::Enhanced,
+            )
+            .unwrap();
+        let first_refresh = session.refresh_token.unwrap();
+
+        let pair = manager.refresh_with_token(&first_refresh).unwrap();
+        assert_ne!(pair.refresh_token, first_refresh);
+        assert!(manager
+            .validate_token(&pair.access_token, TokenType::Access)
+            .is_ok());
+        assert!(manager
+            .validate_token(&session.session_token, TokenType::Access)
+            .is_err());
+
+        // Replaying the exchanged token revokes the session and its newer tokens
+        assert!(manager.refresh_with_token(&first_refresh).is_err());
+        assert!(manager.get_session(&session.session_id).is_none());
+        assert!(manager
+            .validate_token(&pair.access_token, TokenType::Access)
+            .is_err());
+        assert!(manager.refresh_with_token(&pair.refresh_token).is_err());
+    }
+
+    #[test]
+    fn test_sessions_survive_restart() {
+        use crate::security::session_store::{FileSessionStore, SessionStateCipher};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let persistence = || {
+            SessionPersistence::new(
+                Box::new(FileSessionStore::new(temp_dir.path().join("sessions"))),
+                SessionStateCipher::new(&[3u8; 32]),
+            )
+        };
+        let config = SessionConfig {
+            key_store_path: Some(temp_dir.path().join("session_keys.json")),
+            ..SessionConfig::default()
+        };
+
+        let mut manager =
+            SecureSessionManager::with_config(config.clone()).with_persistence(persistence());
+        let kept = manager
+            .create_session(
+                "main".to_string(),
+                Some("user".to_string()),
+                None,
+                None,
+                SessionSecurityLevel::Enhanced,
+            )
+            .unwrap();
+        let ended = manager
+            .create_session(
+                "main".to_string(),
+                None,
+                None,
+                None,
+                SessionSecurityLevel::Basic,
+            )
+            .unwrap();
+        manager.terminate_session(&ended.session_id);
+
+        // Expire a session behind the manager's back; it is dropped on load
+        let mut expired = manager.get_session(&kept.session_id).unwrap().clone();
+        expired.session_id = "expired".to_string();
+        expired.expires_at = Utc::now() - Duration::minutes(1);
+        manager.sessions.insert(expired.session_id.clone(), expired);
+        manager.save_state().unwrap();
+        drop(manager);
+
+        let restarted = SecureSessionManager::with_config(config).with_persistence(persistence());
+        assert!(restarted.get_session(&kept.session_id).is_some());
+        assert!(restarted.get_session("expired").is_none());
+        assert!(restarted
+            .validate_token(&kept.session_token, TokenType::Access)
+            .is_ok());
+        assert!(restarted.is_token_blacklisted(&ended.session_token));
+        assert!(restarted
+            .get_session_activities(&kept.session_id)
+            .is_some_and(|activities| !activities.is_empty()));
+        drop(restarted);
+
+        // Only the kept session's record is left; the expired one was deleted on load
+        let session_files = std::fs::read_dir(temp_dir.path().join("sessions").join("session"))
+            .unwrap()
+            .count();
+        assert_eq!(session_files, 1);
     }
 
     #[test]
     fn test_mfa_workflow() {
+        use crate::security::totp::{time_step, totp_code};
+
         let mut manager = SecureSessionManager::new();
 
         let session = manager
@@ -830,15 +1376,40 @@ mod tests {
 
         let session_id = session.session_id;
 
+        // Nothing to challenge before an authenticator is enrolled
+        assert!(!manager.enable_mfa(&session_id));
+
+        let setup = manager.enroll_mfa(&session_id).unwrap();
+        let secret = data_encoding::BASE32_NOPAD
+            .decode(setup.secret.as_bytes())
+            .unwrap();
+        let step = time_step(Utc::now());
+        assert!(manager
+            .confirm_mfa_enrollment(&session_id, "000000x")
+            .is_err());
+        manager
+            .confirm_mfa_enrollment(&session_id, &totp_code(&secret, step))
+            .unwrap();
+        assert!(manager.has_mfa_elevation(&session_id));
+
         // Enable MFA
         assert!(manager.enable_mfa(&session_id));
+        assert!(!manager.has_mfa_elevation(&session_id));
         assert_eq!(
             manager.validate_session(&session_id, None),
             SessionValidation::RequiresMFA
         );
 
+        // The code used for enrollment cannot be replayed
+        assert!(manager
+            .verify_mfa(&session_id, &totp_code(&secret, step))
+            .is_err());
+
         // Verify MFA
-        assert!(manager.verify_mfa(&session_id));
+        assert_eq!(
+            manager.verify_mfa(&session_id, &totp_code(&secret, step + 1)),
+            Ok(MfaMethod::Totp)
+        );
         assert_eq!(
             manager.validate_session(&session_id, None),
             SessionValidation::Valid
@@ -850,5 +1421,86 @@ mod tests {
             AuthenticationState::TwoFactorAuthenticated
         );
         assert!(session.mfa_verified);
+        assert!(manager.has_mfa_elevation(&session_id));
+
+        // Elevation lapses after the MFA timeout
+        manager
+            .sessions
+            .get_mut(&session_id)
+            .unwrap()
+            .mfa_elevated_until = Some(Utc::now() - Duration::seconds(1));
+        assert!(!manager.has_mfa_elevation(&session_id));
+
+        // New sessions of an enrolled user start with a challenge; recovery codes work once
+        let second = manager
+            .create_session(
+                "other".to_string(),
+                Some("user".to_string()),
+                None,
+                None,
+                SessionSecurityLevel::Enhanced,
+            )
+            .unwrap();
+        assert_eq!(
+            second.authentication_state,
+            AuthenticationState::TwoFactorPending
+        );
+        assert_eq!(
+            manager.verify_mfa(&second.session_id, &setup.recovery_codes[0]),
+            Ok(MfaMethod::RecoveryCode)
+        );
+        assert!(manager
+            .verify_mfa(&second.session_id, &setup.recovery_codes[0])
+            .is_err());
+    }
+
+    #[test]
+    fn test_reenrollment_keeps_the_active_authenticator() {
+        use crate::security::totp::{time_step, totp_code};
+
+        let decode = |secret: &str| data_encoding::BASE32_NOPAD.decode(secret.as_bytes()).unwrap();
+        let mut manager = SecureSessionManager::new();
+        let session_id = manager
+            .create_session(
+                "test".to_string(),
+                Some("user".to_string()),
+                None,
+                None,
+                SessionSecurityLevel::Enhanced,
+            )
+            .unwrap()
+            .session_id;
+
+        let step = time_step(Utc::now());
+        let first = decode(&manager.enroll_mfa(&session_id).unwrap().secret);
+        manager
+            .confirm_mfa_enrollment(&session_id, &totp_code(&first, step - 1))
+            .unwrap();
+
+        // Starting over leaves the confirmed authenticator in force
+        let second = decode(&manager.enroll_mfa(&session_id).unwrap().secret);
+        assert!(manager.has_confirmed_mfa("user"));
+        assert_eq!(
+            manager.verify_mfa(&session_id, &totp_code(&first, step)),
+            Ok(MfaMethod::Totp)
+        );
+        assert!(manager
+            .verify_mfa(&session_id, &totp_code(&second, step))
+            .is_err());
+
+        // Confirming switches over to the new one
+        manager
+            .confirm_mfa_enrollment(&session_id, &totp_code(&second, step))
+            .unwrap();
+        assert!(manager
+            .verify_mfa(&session_id, &totp_code(&first, step + 1))
+            .is_err());
+        assert_eq!(
+            manager.verify_mfa(&session_id, &totp_code(&second, step + 1)),
+            Ok(MfaMethod::Totp)
+        );
+        assert!(manager
+            .confirm_mfa_enrollment(&session_id, &totp_code(&second, step + 1))
+            .is_err());
     }
 }
@@ -0,0 +1,746 @@
+//! Session Persistence
+//!
+//! Storage backends for the secure session manager's sessions, activity logs,
+//! token blacklist and MFA enrollments. Each of them is stored as its own
+//! AES-256-GCM encrypted record, either as a file (the default) or as a row of
+//! the application database when one is available, so a change only rewrites
+//! the records it touched. Writes happen on a background thread, and stale
+//! entries are compacted away when the state is loaded.
+
+use super::session_manager::{AuthenticationState, SessionActivity, SessionSecurityContext};
+use super::session_tokens::peek_claims;
+use super::totp::MfaEnrollment;
+use crate::database::Database;
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use chrono::{DateTime, Duration, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use tracing::{error, warn};
+
+/// Prefix of every encrypted record, also bound into the authentication tag
+const RECORD_MAGIC: &[u8; 6] = b"NBSES2";
+const NONCE_LEN: usize = 12;
+
+/// How long activity logs are kept
+pub const ACTIVITY_RETENTION_DAYS: i64 = 7;
+
+/// How long an enrollment can wait for its confirming code
+pub const PENDING_MFA_ENROLLMENT_HOURS: i64 = 24;
+
+/// Everything the session manager persists
+#[derive(Debug, Clone, Default)]
+pub struct PersistedSessionState {
+    pub sessions: HashMap<String, SessionSecurityContext>,
+    pub activities: HashMap<String, Vec<SessionActivity>>,
+    pub blacklisted_tokens: HashSet<String>,
+    pub mfa_enrollments: HashMap<String, MfaEnrollment>,
+    pub pending_mfa_enrollments: HashMap<String, MfaEnrollment>,
+}
+
+impl PersistedSessionState {
+    /// Drop expired or revoked sessions, old activities, unconfirmed enrollments
+    /// and blacklist entries for tokens that have expired anyway; returns the
+    /// records that were removed
+    pub fn compact(&mut self, now: DateTime<Utc>) -> DirtyRecords {
+        let mut removed = DirtyRecords::default();
+
+        self.sessions.retain(|session_id, session| {
+            let keep = session.expires_at > now
+                && !matches!(
+                    session.authentication_state,
+                    AuthenticationState::Expired | AuthenticationState::Revoked
+                );
+            if !keep {
+                removed.sessions.insert(session_id.clone());
+            }
+            keep
+        });
+
+        self.blacklisted_tokens.retain(|token| {
+            let keep = peek_claims(token).is_some_and(|claims| claims.expires_at > now);
+            if !keep {
+                removed.blacklisted_tokens.insert(token.clone());
+            }
+            keep
+        });
+
+        let cutoff = now - Duration::days(ACTIVITY_RETENTION_DAYS);
+        for (session_id, activities) in self.activities.iter_mut() {
+            let before = activities.len();
+            activities.retain(|activity| activity.timestamp > cutoff);
+            if activities.len() != before {
+                removed.activities.insert(session_id.clone());
+            }
+        }
+        self.activities
+            .retain(|_, activities| !activities.is_empty());
+
+        let enrollment_cutoff = now - Duration::hours(PENDING_MFA_ENROLLMENT_HOURS);
+        self.pending_mfa_enrollments.retain(|user_id, enrollment| {
+            let keep = enrollment.created_at > enrollment_cutoff;
+            if !keep {
+                removed.pending_mfa_enrollments.insert(user_id.clone());
+            }
+            keep
+        });
+
+        removed
+    }
+}
+
+/// Keys of records changed since they were last saved
+#[derive(Debug, Clone, Default)]
+pub struct DirtyRecords {
+    pub sessions: HashSet<String>,
+    /// Session ids whose activity log changed
+    pub activities: HashSet<String>,
+    /// Tokens added to or removed from the blacklist
+    pub blacklisted_tokens: HashSet<String>,
+    /// User ids whose enrollment changed
+    pub mfa_enrollments: HashSet<String>,
+    pub pending_mfa_enrollments: HashSet<String>,
+}
+
+impl DirtyRecords {
+    /// Every record in `state`
+    pub fn all(state: &SessionStateRef<'_>) -> Self {
+        Self {
+            sessions: state.sessions.keys().cloned().collect(),
+            activities: state.activities.keys().cloned().collect(),
+            blacklisted_tokens: state.blacklisted_tokens.clone(),
+            mfa_enrollments: state.mfa_enrollments.keys().cloned().collect(),
+            pending_mfa_enrollments: state.pending_mfa_enrollments.keys().cloned().collect(),
+        }
+    }
+
+    /// Add the keys in `other`
+    pub fn extend(&mut self, other: DirtyRecords) {
+        self.sessions.extend(other.sessions);
+        self.activities.extend(other.activities);
+        self.blacklisted_tokens.extend(other.blacklisted_tokens);
+        self.mfa_enrollments.extend(other.mfa_enrollments);
+        self.pending_mfa_enrollments
+            .extend(other.pending_mfa_enrollments);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+            && self.activities.is_empty()
+            && self.blacklisted_tokens.is_empty()
+            && self.mfa_enrollments.is_empty()
+            && self.pending_mfa_enrollments.is_empty()
+    }
+}
+
+/// The session manager's current state, borrowed for saving
+#[derive(Debug, Clone, Copy)]
+pub struct SessionStateRef<'a> {
+    pub sessions: &'a HashMap<String, SessionSecurityContext>,
+    pub activities: &'a HashMap<String, Vec<SessionActivity>>,
+    pub blacklisted_tokens: &'a HashSet<String>,
+    pub mfa_enrollments: &'a HashMap<String, MfaEnrollment>,
+    pub pending_mfa_enrollments: &'a HashMap<String, MfaEnrollment>,
+}
+
+/// What a stored record holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SessionRecordKind {
+    Session,
+    Activities,
+    BlacklistedToken,
+    MfaEnrollment,
+    PendingMfaEnrollment,
+}
+
+impl SessionRecordKind {
+    pub const ALL: [Self; 5] = [
+        Self::Session,
+        Self::Activities,
+        Self::BlacklistedToken,
+        Self::MfaEnrollment,
+        Self::PendingMfaEnrollment,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Session => "session",
+            Self::Activities => "activities",
+            Self::BlacklistedToken => "blacklisted_token",
+            Self::MfaEnrollment => "mfa_enrollment",
+            Self::PendingMfaEnrollment => "pending_mfa_enrollment",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == value)
+    }
+}
+
+/// Identifies a stored record
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SessionRecordKey {
+    pub kind: SessionRecordKind,
+    pub key: String,
+}
+
+impl SessionRecordKey {
+    pub fn new(kind: SessionRecordKind, key: impl Into<String>) -> Self {
+        Self {
+            kind,
+            key: key.into(),
+        }
+    }
+
+    /// Blacklisted tokens are stored under their hash, so the key does not reveal them
+    pub fn blacklisted_token(token: &str) -> Self {
+        Self::new(
+            SessionRecordKind::BlacklistedToken,
+            hex::encode(Sha256::digest(token.as_bytes())),
+        )
+    }
+}
+
+/// A record to write, or `None` to delete it
+pub type SessionRecordChange = (SessionRecordKey, Option<Vec<u8>>);
+
+/// Backend holding the encrypted session records
+pub trait SessionStore: Send + Sync + std::fmt::Debug {
+    /// Every stored record
+    fn load(&self) -> Result<Vec<(SessionRecordKey, Vec<u8>)>, String>;
+
+    /// Write and delete records, all at once where the backend allows it
+    fn apply(&self, changes: &[SessionRecordChange]) -> Result<(), String>;
+}
+
+/// Stores each record in its own file under `{dir}/{kind}/`, replaced atomically on save
+#[derive(Debug, Clone)]
+pub struct FileSessionStore {
+    dir: PathBuf,
+}
+
+impl FileSessionStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Keys are hex encoded, since user ids may not be valid file names
+    fn record_path(&self, key: &SessionRecordKey) -> PathBuf {
+        self.dir
+            .join(key.kind.as_str())
+            .join(format!("{}.bin", hex::encode(key.key.as_bytes())))
+    }
+}
+
+impl SessionStore for FileSessionStore {
+    fn load(&self) -> Result<Vec<(SessionRecordKey, Vec<u8>)>, String> {
+        let mut records = Vec::new();
+        for kind in SessionRecordKind::ALL {
+            let kind_dir = self.dir.join(kind.as_str());
+            let entries = match std::fs::read_dir(&kind_dir) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(format!("Failed to read {}: {}", kind_dir.display(), e)),
+            };
+
+            for entry in entries {
+                let path = entry
+                    .map_err(|e| format!("Failed to read {}: {}", kind_dir.display(), e))?
+                    .path();
+                let key = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| name.strip_suffix(".bin"))
+                    .and_then(|name| hex::decode(name).ok())
+                    .and_then(|key| String::from_utf8(key).ok());
+                let Some(key) = key else {
+                    continue;
+                };
+
+                let payload = std::fs::read(&path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                records.push((SessionRecordKey::new(kind, key), payload));
+            }
+        }
+        Ok(records)
+    }
+
+    fn apply(&self, changes: &[SessionRecordChange]) -> Result<(), String> {
+        for (key, payload) in changes {
+            let path = self.record_path(key);
+            match payload {
+                Some(payload) => write_private_file(&path, payload)?,
+                None => match std::fs::remove_file(&path) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(format!("Failed to remove {}: {}", path.display(), e)),
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Stores records in the application database's `security_session_records` table
+#[derive(Clone)]
+pub struct DatabaseSessionStore {
+    database: Database,
+}
+
+impl std::fmt::Debug for DatabaseSessionStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DatabaseSessionStore")
+            .field("database_url", &self.database.config().database_url)
+            .finish()
+    }
+}
+
+impl DatabaseSessionStore {
+    /// The database must be initialized, so the table's migration has been applied
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    /// Store backed by the global database, if it is initialized
+    pub fn from_global_database() -> Option<Self> {
+        crate::database::global_database().ok().map(Self::new)
+    }
+}
+
+impl SessionStore for DatabaseSessionStore {
+    fn load(&self) -> Result<Vec<(SessionRecordKey, Vec<u8>)>, String> {
+        let rows = self
+            .database
+            .with_connection_blocking(|conn| {
+                let mut stmt =
+                    conn.prepare("SELECT kind, record_key, payload FROM security_session_records")?;
+                let rows = stmt.query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Vec<u8>>(2)?,
+                    ))
+                })?;
+                rows.collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .map_err(|e| format!("Failed to load sessions: {}", e))?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(kind, key, payload)| {
+                SessionRecordKind::parse(&kind).map(|kind| (SessionRecordKey::new(kind, key), payload))
+            })
+            .collect())
+    }
+
+    fn apply(&self, changes: &[SessionRecordChange]) -> Result<(), String> {
+        let changes = changes.to_vec();
+        self.database
+            .with_connection_blocking(move |conn| {
+                let tx = conn.transaction()?;
+                let updated_at = Utc::now().to_rfc3339();
+                for (key, payload) in &changes {
+                    match payload {
+                        Some(payload) => tx.execute(
+                            "INSERT INTO security_session_records (kind, record_key, payload, updated_at)
+                             VALUES (?1, ?2, ?3, ?4)
+                             ON CONFLICT(kind, record_key) DO UPDATE
+                             SET payload = excluded.payload, updated_at = excluded.updated_at",
+                            rusqlite::params![key.kind.as_str(), key.key, payload, updated_at],
+                        )?,
+                        None => tx.execute(
+                            "DELETE FROM security_session_records WHERE kind = ?1 AND record_key = ?2",
+                            rusqlite::params![key.kind.as_str(), key.key],
+                        )?,
+                    };
+                }
+                tx.commit()
+            })
+            .map_err(|e| format!("Failed to save sessions: {}", e))
+    }
+}
+
+/// Encrypts records at rest with AES-256-GCM
+pub struct SessionStateCipher {
+    cipher: Aes256Gcm,
+}
+
+impl std::fmt::Debug for SessionStateCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionStateCipher").finish_non_exhaustive()
+    }
+}
+
+impl SessionStateCipher {
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+        }
+    }
+
+    /// Load the encryption key, creating it on first use
+    pub fn load_or_create(key_path: &Path) -> Result<Self, String> {
+        match std::fs::read(key_path) {
+            Ok(bytes) => {
+                let key: [u8; 32] = bytes
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| format!("Session key file {} is corrupt", key_path.display()))?;
+                Ok(Self::new(&key))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let key: [u8; 32] = rand::random();
+                write_private_file(key_path, &key)?;
+                Ok(Self::new(&key))
+            }
+            Err(e) => Err(format!("Failed to read {}: {}", key_path.display(), e)),
+        }
+    }
+
+    /// The record's key is bound into the tag, so a record cannot be moved to another slot
+    fn aad(key: &SessionRecordKey) -> Vec<u8> {
+        [
+            RECORD_MAGIC.as_slice(),
+            key.kind.as_str().as_bytes(),
+            &[0],
+            key.key.as_bytes(),
+        ]
+        .concat()
+    }
+
+    pub fn encrypt<T: Serialize>(&self, key: &SessionRecordKey, value: &T) -> Result<Vec<u8>, String> {
+        let plaintext = serde_json::to_vec(value)
+            .map_err(|e| format!("Failed to serialize sessions: {}", e))?;
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &Self::aad(key),
+                },
+            )
+            .map_err(|_| "Failed to encrypt sessions".to_string())?;
+
+        let mut record = Vec::with_capacity(RECORD_MAGIC.len() + NONCE_LEN + ciphertext.len());
+        record.extend_from_slice(RECORD_MAGIC);
+        record.extend_from_slice(&nonce);
+        record.extend_from_slice(&ciphertext);
+        Ok(record)
+    }
+
+    pub fn decrypt<T: DeserializeOwned>(
+        &self,
+        key: &SessionRecordKey,
+        record: &[u8],
+    ) -> Result<T, String> {
+        let body = record
+            .strip_prefix(RECORD_MAGIC.as_slice())
+            .filter(|body| body.len() > NONCE_LEN)
+            .ok_or("Session record is not in a known format")?;
+        let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+
+        let plaintext = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &Self::aad(key),
+                },
+            )
+            .map_err(|_| "Session record failed authentication".to_string())?;
+
+        serde_json::from_slice(&plaintext)
+            .map_err(|e| format!("Failed to parse session record: {}", e))
+    }
+}
+
+#[derive(Debug)]
+enum WriterMessage {
+    Apply(Vec<SessionRecordChange>),
+    /// Reply once everything queued before has been written, with the first failure since the last flush
+    Flush(mpsc::Sender<Result<(), String>>),
+}
+
+/// A store together with the cipher protecting it
+///
+/// Changes are encrypted on the caller's thread and written in order by a
+/// background thread, which drains its queue when this is dropped.
+#[derive(Debug)]
+pub struct SessionPersistence {
+    store: std::sync::Arc<dyn SessionStore>,
+    cipher: SessionStateCipher,
+    writer: Option<(mpsc::Sender<WriterMessage>, JoinHandle<()>)>,
+}
+
+impl SessionPersistence {
+    pub fn new(store: Box<dyn SessionStore>, cipher: SessionStateCipher) -> Self {
+        let store: std::sync::Arc<dyn SessionStore> = std::sync::Arc::from(store);
+        let (sender, receiver) = mpsc::channel();
+        let writer_store = store.clone();
+        let handle = std::thread::Builder::new()
+            .name("session-store-writer".to_string())
+            .spawn(move || run_writer(writer_store.as_ref(), receiver))
+            .map_err(|e| error!("Failed to start the session writer: {}", e))
+            .ok();
+
+        Self {
+            store,
+            cipher,
+            writer: handle.map(|handle| (sender, handle)),
+        }
+    }
+
+    /// Database-backed persistence when the app database is available, otherwise
+    /// files in the app data directory
+    ///
+    /// The key lives in the app's preference directory, apart from the data it protects.
+    pub fn default_for_app() -> Option<Self> {
+        let dirs = directories::ProjectDirs::from("com", "autodev-ai", "neural-bridge-platform")?;
+
+        let cipher =
+            SessionStateCipher::load_or_create(&dirs.preference_dir().join("session_state.key"))
+                .map_err(|e| error!("Session persistence disabled: {}", e))
+                .ok()?;
+        let store: Box<dyn SessionStore> = match DatabaseSessionStore::from_global_database() {
+            Some(store) => Box::new(store),
+            None => Box::new(FileSessionStore::new(dirs.data_dir().join("sessions"))),
+        };
+
+        Some(Self::new(store, cipher))
+    }
+
+    /// Read every record; ones that cannot be decrypted are logged and skipped
+    pub fn load(&self) -> Result<PersistedSessionState, String> {
+        let mut state = PersistedSessionState::default();
+        for (key, record) in self.store.load()? {
+            match key.kind {
+                SessionRecordKind::Session => {
+                    if let Some(session) = self.open(&key, &record) {
+                        state.sessions.insert(key.key, session);
+                    }
+                }
+                SessionRecordKind::Activities => {
+                    if let Some(activities) = self.open(&key, &record) {
+                        state.activities.insert(key.key, activities);
+                    }
+                }
+                SessionRecordKind::BlacklistedToken => {
+                    if let Some(token) = self.open(&key, &record) {
+                        state.blacklisted_tokens.insert(token);
+                    }
+                }
+                SessionRecordKind::MfaEnrollment => {
+                    if let Some(enrollment) = self.open(&key, &record) {
+                        state.mfa_enrollments.insert(key.key, enrollment);
+                    }
+                }
+                SessionRecordKind::PendingMfaEnrollment => {
+                    if let Some(enrollment) = self.open(&key, &record) {
+                        state.pending_mfa_enrollments.insert(key.key, enrollment);
+                    }
+                }
+            }
+        }
+        Ok(state)
+    }
+
+    /// Queue the dirty records of `state` for writing, deleting those no longer present
+    pub fn save(&self, state: SessionStateRef<'_>, dirty: &DirtyRecords) -> Result<(), String> {
+        if dirty.is_empty() {
+            return Ok(());
+        }
+
+        let mut changes = Vec::new();
+        for session_id in &dirty.sessions {
+            let key = SessionRecordKey::new(SessionRecordKind::Session, session_id.as_str());
+            changes.push(self.change(key, state.sessions.get(session_id))?);
+        }
+        for session_id in &dirty.activities {
+            let key = SessionRecordKey::new(SessionRecordKind::Activities, session_id.as_str());
+            changes.push(self.change(key, state.activities.get(session_id))?);
+        }
+        for token in &dirty.blacklisted_tokens {
+            let key = SessionRecordKey::blacklisted_token(token);
+            changes.push(self.change(key, state.blacklisted_tokens.get(token))?);
+        }
+        for user_id in &dirty.mfa_enrollments {
+            let key = SessionRecordKey::new(SessionRecordKind::MfaEnrollment, user_id.as_str());
+            changes.push(self.change(key, state.mfa_enrollments.get(user_id))?);
+        }
+        for user_id in &dirty.pending_mfa_enrollments {
+            let key =
+                SessionRecordKey::new(SessionRecordKind::PendingMfaEnrollment, user_id.as_str());
+            changes.push(self.change(key, state.pending_mfa_enrollments.get(user_id))?);
+        }
+
+        match &self.writer {
+            Some((sender, _)) => sender
+                .send(WriterMessage::Apply(changes))
+                .map_err(|_| "Session writer has stopped".to_string()),
+            None => self.store.apply(&changes),
+        }
+    }
+
+    /// Wait until everything queued so far has been written
+    pub fn flush(&self) -> Result<(), String> {
+        let Some((sender, _)) = &self.writer else {
+            return Ok(());
+        };
+        let (reply, result) = mpsc::channel();
+        sender
+            .send(WriterMessage::Flush(reply))
+            .map_err(|_| "Session writer has stopped".to_string())?;
+        result
+            .recv()
+            .map_err(|_| "Session writer has stopped".to_string())?
+    }
+
+    fn open<T: DeserializeOwned>(&self, key: &SessionRecordKey, record: &[u8]) -> Option<T> {
+        self.cipher
+            .decrypt(key, record)
+            .map_err(|e| warn!("Skipping {} record {}: {}", key.kind.as_str(), key.key, e))
+            .ok()
+    }
+
+    fn change<T: Serialize>(
+        &self,
+        key: SessionRecordKey,
+        value: Option<&T>,
+    ) -> Result<SessionRecordChange, String> {
+        let record = value
+            .map(|value| self.cipher.encrypt(&key, value))
+            .transpose()?;
+        Ok((key, record))
+    }
+}
+
+impl Drop for SessionPersistence {
+    fn drop(&mut self) {
+        if let Some((sender, handle)) = self.writer.take() {
+            drop(sender);
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run_writer(store: &dyn SessionStore, receiver: mpsc::Receiver<WriterMessage>) {
+    let mut failure = None;
+    for message in receiver {
+        match message {
+            WriterMessage::Apply(changes) => {
+                if let Err(e) = store.apply(&changes) {
+                    error!("Failed to persist sessions: {}", e);
+                    failure.get_or_insert(e);
+                }
+            }
+            WriterMessage::Flush(reply) => {
+                let _ = reply.send(failure.take().map_or(Ok(()), Err));
+            }
+        }
+    }
+}
+
+/// Write a file atomically, readable by the current user only
+fn write_private_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, contents)
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to protect {}: {}", tmp_path.display(), e))?;
+    }
+
+    std::fs::rename(&tmp_path, path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConfig;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_is_encrypted_and_authenticated() {
+        let cipher = SessionStateCipher::new(&[7u8; 32]);
+        let key = SessionRecordKey::blacklisted_token("secret-token-value");
+
+        let record = cipher.encrypt(&key, &"secret-token-value").unwrap();
+        assert!(!String::from_utf8_lossy(&record).contains("secret-token-value"));
+        assert!(!key.key.contains("secret-token-value"));
+
+        let restored: String = cipher.decrypt(&key, &record).unwrap();
+        assert_eq!(restored, "secret-token-value");
+
+        let mut tampered = record.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(cipher.decrypt::<String>(&key, &tampered).is_err());
+        assert!(SessionStateCipher::new(&[8u8; 32])
+            .decrypt::<String>(&key, &record)
+            .is_err());
+
+        // A record copied into another slot does not authenticate
+        let other = SessionRecordKey::new(SessionRecordKind::Session, "session-1");
+        assert!(cipher.decrypt::<String>(&other, &record).is_err());
+    }
+
+    fn assert_round_trip(store: &dyn SessionStore) {
+        let session = SessionRecordKey::new(SessionRecordKind::Session, "session-1");
+        let enrollment = SessionRecordKey::new(SessionRecordKind::MfaEnrollment, "user/with:odd chars");
+
+        assert!(store.load().unwrap().is_empty());
+        store
+            .apply(&[
+                (session.clone(), Some(b"first".to_vec())),
+                (enrollment.clone(), Some(b"totp".to_vec())),
+            ])
+            .unwrap();
+        store
+            .apply(&[(session.clone(), Some(b"second".to_vec()))])
+            .unwrap();
+
+        let mut records = store.load().unwrap();
+        records.sort_by(|a, b| a.0.key.cmp(&b.0.key));
+        assert_eq!(
+            records,
+            vec![
+                (session.clone(), b"second".to_vec()),
+                (enrollment.clone(), b"totp".to_vec()),
+            ]
+        );
+
+        store.apply(&[(session, None)]).unwrap();
+        assert_eq!(store.load().unwrap(), vec![(enrollment, b"totp".to_vec())]);
+    }
+
+    #[test]
+    fn test_file_store_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_round_trip(&FileSessionStore::new(temp_dir.path().join("sessions")));
+    }
+
+    #[tokio::test]
+    async fn test_database_store_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let database = Database::new(DatabaseConfig::for_path(temp_dir.path().join("app.db")));
+        database.initialize().await.unwrap();
+
+        assert_round_trip(&DatabaseSessionStore::new(database));
+    }
+}
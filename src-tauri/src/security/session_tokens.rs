@@ -0,0 +1,334 @@
+//! Signed Session Tokens
+//!
+//! Stateless, HMAC-signed tokens for the secure session manager. A token
+//! carries its session id, expiry, security level and permissions, and names
+//! the signing key by id so keys can be rotated while tokens signed with
+//! recent keys stay valid. The key set can be persisted, which lets tokens
+//! be validated after an application restart.
+//!
+//! Token layout: `nbt1.<key id>.<hex claims JSON>.<hex HMAC-SHA256>`, where the
+//! signature covers everything before the last dot.
+
+use super::session_manager::SessionSecurityLevel;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::path::Path;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const TOKEN_VERSION: &str = "nbt1";
+
+/// What a token may be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenType {
+    Access,
+    Refresh,
+}
+
+/// Claims carried inside a session token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionTokenClaims {
+    /// Unique token id
+    pub jti: String,
+    pub token_type: TokenType,
+    pub session_id: String,
+    pub user_id: Option<String>,
+    pub security_level: SessionSecurityLevel,
+    /// Sorted so equal permission sets produce identical claims
+    pub permissions: Vec<String>,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Access and refresh token issued together
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTokenPair {
+    pub session_id: String,
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Why a token was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenError {
+    Malformed,
+    UnknownKey(String),
+    BadSignature,
+    Expired,
+    WrongType,
+}
+
+impl std::fmt::Display for TokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenError::Malformed => write!(f, "Token is malformed"),
+            TokenError::UnknownKey(kid) => write!(f, "Token signed with unknown key {}", kid),
+            TokenError::BadSignature => write!(f, "Token signature is invalid"),
+            TokenError::Expired => write!(f, "Token has expired"),
+            TokenError::WrongType => write!(f, "Token cannot be used here"),
+        }
+    }
+}
+
+/// One HMAC key and its id
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SigningKey {
+    pub kid: String,
+    #[serde(with = "hex_bytes")]
+    pub secret: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("kid", &self.kid)
+            .field("secret", &"<redacted>")
+            .field("created_at", &self.created_at)
+            .finish()
+    }
+}
+
+impl SigningKey {
+    fn generate() -> Self {
+        let secret: [u8; 32] = rand::random();
+        Self {
+            kid: Uuid::new_v4().simple().to_string()[..12].to_string(),
+            secret: secret.to_vec(),
+            created_at: Utc::now(),
+        }
+    }
+
+    fn mac(&self, data: &[u8]) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(data);
+        mac
+    }
+}
+
+/// Signing keys, newest last; the newest signs and all of them verify
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenKeySet {
+    keys: Vec<SigningKey>,
+}
+
+impl TokenKeySet {
+    pub fn generate() -> Self {
+        Self {
+            keys: vec![SigningKey::generate()],
+        }
+    }
+
+    /// Load a persisted key set, creating and saving a new one if none exists
+    pub fn load_or_create(path: &Path) -> Result<Self, String> {
+        match std::fs::read(path) {
+            Ok(bytes) => {
+                let key_set: Self = serde_json::from_slice(&bytes)
+                    .map_err(|e| format!("Invalid session key file {}: {}", path.display(), e))?;
+                if key_set.keys.is_empty() {
+                    return Err(format!("Session key file {} holds no keys", path.display()));
+                }
+                Ok(key_set)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let key_set = Self::generate();
+                key_set.save(path)?;
+                Ok(key_set)
+            }
+            Err(e) => Err(format!(
+                "Failed to read session key file {}: {}",
+                path.display(),
+                e
+            )),
+        }
+    }
+
+    /// Write the key set atomically, readable by the current user only
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| format!("Failed to serialize session keys: {}", e))?;
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, json)
+            .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o600))
+                .map_err(|e| format!("Failed to protect {}: {}", tmp_path.display(), e))?;
+        }
+
+        std::fs::rename(&tmp_path, path)
+            .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+    }
+
+    pub fn active(&self) -> &SigningKey {
+        self.keys.last().expect("key set is never empty")
+    }
+
+    pub fn key_ids(&self) -> Vec<&str> {
+        self.keys.iter().map(|key| key.kid.as_str()).collect()
+    }
+
+    /// Whether the active key is older than `max_age`
+    pub fn rotation_due(&self, max_age: Duration) -> bool {
+        Utc::now() - self.active().created_at > max_age
+    }
+
+    /// Start signing with a fresh key, keeping at most `retain` keys for verification
+    pub fn rotate(&mut self, retain: usize) -> &SigningKey {
+        self.keys.push(SigningKey::generate());
+        let excess = self.keys.len().saturating_sub(retain.max(1));
+        self.keys.drain(..excess);
+        self.active()
+    }
+
+    /// Sign claims with the active key
+    pub fn sign(&self, claims: &SessionTokenClaims) -> String {
+        let key = self.active();
+        let payload = hex::encode(serde_json::to_vec(claims).unwrap_or_default());
+        let signed = format!("{}.{}.{}", TOKEN_VERSION, key.kid, payload);
+        let signature = hex::encode(key.mac(signed.as_bytes()).finalize().into_bytes());
+        format!("{}.{}", signed, signature)
+    }
+
+    /// Check a token's signature and expiry and return its claims
+    pub fn verify(&self, token: &str) -> Result<SessionTokenClaims, TokenError> {
+        let (signed, signature) = token.rsplit_once('.').ok_or(TokenError::Malformed)?;
+        let mut parts = signed.splitn(3, '.');
+        let (Some(TOKEN_VERSION), Some(kid), Some(payload)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(TokenError::Malformed);
+        };
+
+        let key = self
+            .keys
+            .iter()
+            .find(|key| key.kid == kid)
+            .ok_or_else(|| TokenError::UnknownKey(kid.to_string()))?;
+        let signature = hex::decode(signature).map_err(|_| TokenError::Malformed)?;
+        key.mac(signed.as_bytes())
+            .verify_slice(&signature)
+            .map_err(|_| TokenError::BadSignature)?;
+
+        let payload = hex::decode(payload).map_err(|_| TokenError::Malformed)?;
+        let claims: SessionTokenClaims =
+            serde_json::from_slice(&payload).map_err(|_| TokenError::Malformed)?;
+        if Utc::now() > claims.expires_at {
+            return Err(TokenError::Expired);
+        }
+
+        Ok(claims)
+    }
+}
+
+/// Claims of a token without verifying it, for blacklist bookkeeping only
+pub fn peek_claims(token: &str) -> Option<SessionTokenClaims> {
+    let payload = token.split('.').nth(2)?;
+    serde_json::from_slice(&hex::decode(payload).ok()?).ok()
+}
+
+mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        hex::decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(expires_in: Duration) -> SessionTokenClaims {
+        let now = Utc::now();
+        SessionTokenClaims {
+            jti: Uuid::new_v4().to_string(),
+            token_type: TokenType::Access,
+            session_id: "session-1".to_string(),
+            user_id: Some("user-1".to_string()),
+            security_level: SessionSecurityLevel::Enhanced,
+            permissions: vec!["basic.read".to_string()],
+            issued_at: now,
+            expires_at: now + expires_in,
+        }
+    }
+
+    #[test]
+    fn test_debug_output_hides_key_material() {
+        let key_set = TokenKeySet::generate();
+        let secret = hex::encode(&key_set.keys[0].secret);
+        let debug = format!("{:?}", key_set);
+        assert!(debug.contains(&key_set.keys[0].kid));
+        assert!(!debug.contains(&secret));
+        assert!(!debug.contains(&format!("{:?}", key_set.keys[0].secret)));
+    }
+
+    #[test]
+    fn test_sign_verify_and_tamper() {
+        let key_set = TokenKeySet::generate();
+        let original = claims(Duration::hours(1));
+        let token = key_set.sign(&original);
+
+        assert_eq!(key_set.verify(&token).unwrap(), original);
+        assert_eq!(peek_claims(&token), Some(original.clone()));
+
+        let mut forged = original.clone();
+        forged.permissions.push("admin".to_string());
+        let forged_payload = hex::encode(serde_json::to_vec(&forged).unwrap());
+        let parts: Vec<&str> = token.split('.').collect();
+        let tampered = format!("{}.{}.{}.{}", parts[0], parts[1], forged_payload, parts[3]);
+        assert_eq!(key_set.verify(&tampered), Err(TokenError::BadSignature));
+
+        let expired = key_set.sign(&claims(Duration::seconds(-1)));
+        assert_eq!(key_set.verify(&expired), Err(TokenError::Expired));
+    }
+
+    #[test]
+    fn test_rotation_keeps_recent_keys() {
+        let mut key_set = TokenKeySet::generate();
+        let first = key_set.sign(&claims(Duration::hours(1)));
+        let first_kid = key_set.active().kid.clone();
+
+        key_set.rotate(2);
+        let second = key_set.sign(&claims(Duration::hours(1)));
+        assert!(key_set.verify(&first).is_ok());
+        assert!(key_set.verify(&second).is_ok());
+
+        key_set.rotate(2);
+        assert_eq!(
+            key_set.verify(&first),
+            Err(TokenError::UnknownKey(first_kid))
+        );
+        assert!(key_set.verify(&second).is_ok());
+    }
+
+    #[test]
+    fn test_key_set_survives_reload() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("keys").join("session_keys.json");
+
+        let key_set = TokenKeySet::load_or_create(&path).unwrap();
+        let token = key_set.sign(&claims(Duration::hours(1)));
+
+        let reloaded = TokenKeySet::load_or_create(&path).unwrap();
+        assert!(reloaded.verify(&token).is_ok());
+    }
+}
@@ -0,0 +1,326 @@
+//! TOTP Second Factor
+//!
+//! RFC 6238 time-based one-time passwords with the parameters authenticator
+//! apps assume by default (HMAC-SHA1, 6 digits, 30 second steps), provisioning
+//! URIs for enrolling via QR code, and single-use recovery codes. Recovery
+//! codes are only kept as SHA-256 hashes.
+
+use chrono::{DateTime, Utc};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+type HmacSha1 = Hmac<Sha1>;
+
+pub const TOTP_DIGITS: u32 = 6;
+pub const TOTP_STEP_SECONDS: i64 = 30;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// 160-bit secrets, as recommended by RFC 4226
+const SECRET_BYTES: usize = 20;
+
+/// How a second factor was proven
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MfaMethod {
+    Totp,
+    RecoveryCode,
+}
+
+/// Why a code was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MfaError {
+    InvalidCode,
+    /// The code's time step was already used
+    Replayed,
+}
+
+impl std::fmt::Display for MfaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MfaError::InvalidCode => write!(f, "Invalid verification code"),
+            MfaError::Replayed => write!(f, "Verification code was already used"),
+        }
+    }
+}
+
+/// What the user needs to set up their authenticator, shown once at enrollment
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MfaEnrollmentSetup {
+    /// Base32 secret for manual entry
+    pub secret: String,
+    /// `otpauth://` URI for QR codes
+    pub provisioning_uri: String,
+    pub recovery_codes: Vec<String>,
+}
+
+impl std::fmt::Debug for MfaEnrollmentSetup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MfaEnrollmentSetup")
+            .field("secret", &"<redacted>")
+            .field("provisioning_uri", &"<redacted>")
+            .field("recovery_codes", &self.recovery_codes.len())
+            .finish()
+    }
+}
+
+/// A user's TOTP enrollment
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MfaEnrollment {
+    /// Base32 encoded shared secret
+    secret: String,
+    /// Set once the user proved their authenticator works
+    pub confirmed: bool,
+    pub created_at: DateTime<Utc>,
+    recovery_code_hashes: Vec<String>,
+    /// Last accepted time step; codes from it or earlier steps are replays
+    last_used_step: Option<i64>,
+}
+
+impl std::fmt::Debug for MfaEnrollment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MfaEnrollment")
+            .field("secret", &"<redacted>")
+            .field("confirmed", &self.confirmed)
+            .field("created_at", &self.created_at)
+            .field("recovery_codes", &self.recovery_code_hashes.len())
+            .field("last_used_step", &self.last_used_step)
+            .finish()
+    }
+}
+
+impl MfaEnrollment {
+    /// Create an unconfirmed enrollment for `account`
+    pub fn generate(issuer: &str, account: &str) -> (Self, MfaEnrollmentSetup) {
+        let secret_bytes: [u8; SECRET_BYTES] = rand::random();
+        let secret = BASE32_NOPAD.encode(&secret_bytes);
+        let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| generate_recovery_code())
+            .collect();
+
+        let enrollment = Self {
+            secret: secret.clone(),
+            confirmed: false,
+            created_at: Utc::now(),
+            recovery_code_hashes: recovery_codes
+                .iter()
+                .map(|code| hash_recovery_code(code))
+                .collect(),
+            last_used_step: None,
+        };
+        let setup = MfaEnrollmentSetup {
+            provisioning_uri: provisioning_uri(issuer, account, &secret),
+            secret,
+            recovery_codes,
+        };
+
+        (enrollment, setup)
+    }
+
+    /// Check a TOTP code, accepting up to `skew_steps` steps of clock drift either way
+    pub fn verify_totp(
+        &mut self,
+        code: &str,
+        now: DateTime<Utc>,
+        skew_steps: i64,
+    ) -> Result<MfaMethod, MfaError> {
+        let code = normalize_code(code);
+        let secret = BASE32_NOPAD
+            .decode(self.secret.as_bytes())
+            .map_err(|_| MfaError::InvalidCode)?;
+
+        let current = time_step(now);
+        let step = (current - skew_steps..=current + skew_steps)
+            .find(|&step| constant_time_eq(totp_code(&secret, step).as_bytes(), code.as_bytes()))
+            .ok_or(MfaError::InvalidCode)?;
+
+        if self.last_used_step.is_some_and(|last| step <= last) {
+            return Err(MfaError::Replayed);
+        }
+        self.last_used_step = Some(step);
+        Ok(MfaMethod::Totp)
+    }
+
+    /// Check a recovery code, consuming it on success
+    pub fn use_recovery_code(&mut self, code: &str) -> Result<MfaMethod, MfaError> {
+        let hash = hash_recovery_code(code);
+        let index = self
+            .recovery_code_hashes
+            .iter()
+            .position(|stored| constant_time_eq(stored.as_bytes(), hash.as_bytes()))
+            .ok_or(MfaError::InvalidCode)?;
+
+        self.recovery_code_hashes.remove(index);
+        Ok(MfaMethod::RecoveryCode)
+    }
+
+    /// Check either a TOTP code or a recovery code
+    pub fn verify(
+        &mut self,
+        code: &str,
+        now: DateTime<Utc>,
+        skew_steps: i64,
+    ) -> Result<MfaMethod, MfaError> {
+        match self.verify_totp(code, now, skew_steps) {
+            Err(MfaError::InvalidCode) => self.use_recovery_code(code),
+            result => result,
+        }
+    }
+
+    pub fn remaining_recovery_codes(&self) -> usize {
+        self.recovery_code_hashes.len()
+    }
+}
+
+/// Index of the 30 second window containing `time`
+pub fn time_step(time: DateTime<Utc>) -> i64 {
+    time.timestamp().div_euclid(TOTP_STEP_SECONDS)
+}
+
+/// The code an authenticator shows during `step`
+pub fn totp_code(secret: &[u8], step: i64) -> String {
+    let mut mac = HmacSha1::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&(step as u64).to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation (RFC 4226 section 5.3)
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_DIGITS),
+        width = TOTP_DIGITS as usize
+    )
+}
+
+/// `otpauth://totp/` URI understood by common authenticator apps
+pub fn provisioning_uri(issuer: &str, account: &str, secret: &str) -> String {
+    let label = format!("{}:{}", issuer, account);
+    let mut uri = url::Url::parse("otpauth://totp/").expect("static URI is valid");
+    uri.set_path(&format!("/{}", encode_component(&label)));
+    uri.query_pairs_mut()
+        .append_pair("secret", secret)
+        .append_pair("issuer", issuer)
+        .append_pair("algorithm", "SHA1")
+        .append_pair("digits", &TOTP_DIGITS.to_string())
+        .append_pair("period", &TOTP_STEP_SECONDS.to_string());
+    uri.to_string()
+}
+
+fn encode_component(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes())
+        .collect::<String>()
+        .replace('+', "%20")
+}
+
+/// Ten base32 characters grouped as `XXXXX-XXXXX`
+fn generate_recovery_code() -> String {
+    let bytes: [u8; 8] = rand::random();
+    let encoded = BASE32_NOPAD.encode(&bytes);
+    format!("{}-{}", &encoded[..5], &encoded[5..10])
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = normalize_code(code).replace('-', "").to_uppercase();
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+fn normalize_code(code: &str) -> String {
+    code.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        // SHA1 test vectors from RFC 6238 appendix B, truncated to 6 digits
+        let secret = b"12345678901234567890";
+        let vectors = [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+        ];
+        for (timestamp, expected) in vectors {
+            let time = Utc.timestamp_opt(timestamp, 0).unwrap();
+            assert_eq!(totp_code(secret, time_step(time)), expected);
+        }
+    }
+
+    #[test]
+    fn test_debug_output_hides_the_secret() {
+        let (enrollment, setup) = MfaEnrollment::generate("NeuralBridge", "user@example.com");
+        for debug in [format!("{:?}", enrollment), format!("{:?}", setup)] {
+            assert!(!debug.contains(&setup.secret));
+            assert!(!debug.contains(&setup.recovery_codes[0]));
+        }
+    }
+
+    #[test]
+    fn test_skew_window_and_replay() {
+        let (mut enrollment, setup) = MfaEnrollment::generate("NeuralBridge", "user@example.com");
+        let secret = BASE32_NOPAD.decode(setup.secret.as_bytes()).unwrap();
+        let now = Utc::now();
+        let step = time_step(now);
+
+        // One step behind is tolerated, two are not
+        assert_eq!(
+            enrollment.verify_totp(&totp_code(&secret, step - 2), now, 1),
+            Err(MfaError::InvalidCode)
+        );
+        assert_eq!(
+            enrollment.verify_totp(&totp_code(&secret, step - 1), now, 1),
+            Ok(MfaMethod::Totp)
+        );
+
+        // The same or an older step cannot be used again
+        assert_eq!(
+            enrollment.verify_totp(&totp_code(&secret, step - 1), now, 1),
+            Err(MfaError::Replayed)
+        );
+        assert_eq!(
+            enrollment.verify_totp(&totp_code(&secret, step), now, 1),
+            Ok(MfaMethod::Totp)
+        );
+
+        assert!(setup
+            .provisioning_uri
+            .starts_with("otpauth://totp/NeuralBridge%3Auser%40example.com?secret="));
+    }
+
+    #[test]
+    fn test_recovery_codes_are_single_use() {
+        let (mut enrollment, setup) = MfaEnrollment::generate("NeuralBridge", "user");
+        assert_eq!(setup.recovery_codes.len(), RECOVERY_CODE_COUNT);
+
+        let code = setup.recovery_codes[0].to_lowercase();
+        assert_eq!(
+            enrollment.verify(&code, Utc::now(), 1),
+            Ok(MfaMethod::RecoveryCode)
+        );
+        assert_eq!(
+            enrollment.verify(&code, Utc::now(), 1),
+            Err(MfaError::InvalidCode)
+        );
+        assert_eq!(
+            enrollment.remaining_recovery_codes(),
+            RECOVERY_CODE_COUNT - 1
+        );
+    }
+}
@@ -9,6 +9,8 @@ use tauri::{
 };
 use tracing::{error, info, warn};
 
+use crate::plugins::global_shortcuts::{register_shortcut_action, ShortcutAction, ShortcutScope};
+
 /// System tray configuration with comprehensive options
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrayConfig {
@@ -81,6 +83,10 @@ pub fn create_system_tray(app: &AppHandle) -> tauri::Result<TrayIcon> {
     // Initialize tray state
     initialize_tray_state(&config);
 
+    if let Err(e) = register_shortcut_actions(app) {
+        warn!("Failed to register tray shortcut actions: {}", e);
+    }
+
     info!(
         "System tray created successfully with GTK integration: {}",
         config.gtk_integration
@@ -88,6 +94,25 @@ pub fn create_system_tray(app: &AppHandle) -> tauri::Result<TrayIcon> {
     Ok(tray)
 }
 
+/// Offers the tray's window toggle for keyboard shortcuts
+fn register_shortcut_actions(app: &AppHandle) -> Result<(), String> {
+    let handle = app.clone();
+    register_shortcut_action(
+        app,
+        ShortcutAction {
+            id: "app.toggle_window".to_string(),
+            label: "Show or hide the main window".to_string(),
+            source: "tray".to_string(),
+            default_accelerator: Some("CmdOrCtrl+Alt+Space".to_string()),
+            default_scope: ShortcutScope::Global,
+        },
+        Some(Arc::new(move || {
+            toggle_main_window_visibility(&handle);
+            Ok(())
+        })),
+    )
+}
+
 /// Creates the tray context menu with comprehensive options
 fn create_tray_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
     info!("Creating comprehensive tray menu");